@@ -62,18 +62,20 @@ async fn run_loopback_demo() -> Result<(), Box<dyn std::error::Error>> {
     // Extract channels for the viewer
     let frame_rx = client_transport.frames.rx;
     let input_tx = client_transport.input.tx;
+    let control_tx = client_transport.control.tx;
 
     // Keep the remaining transport parts
     let _client_frame_tx = client_transport.frames.tx;
     let _client_input_rx = client_transport.input.rx;
     let _client_clipboard = client_transport.clipboard;
-    let _client_control = client_transport.control;
+    let _client_control_rx = client_transport.control.rx;
 
     // Configure host session
     let host_config = HostSessionConfig {
         capture: CaptureConfig::new(30, 80), // 30 FPS, 80% quality
         allow_input: true,
         session_id: "loopback-host".to_string(),
+        ..Default::default()
     };
 
     // Create and start host session
@@ -100,6 +102,7 @@ async fn run_loopback_demo() -> Result<(), Box<dyn std::error::Error>> {
         height: 720,
         show_overlay: true,
         capture_input: true, // Enable input capture
+        ..Default::default()
     };
 
     info!("Opening viewer window...");
@@ -117,7 +120,8 @@ async fn run_loopback_demo() -> Result<(), Box<dyn std::error::Error>> {
 
     // Run the viewer (this blocks until the window is closed)
     // Note: eframe must run on the main thread
-    let viewer = ViewerWindow::new(viewer_config, frame_rx, input_tx);
+    let viewer =
+        ViewerWindow::new(viewer_config, frame_rx, input_tx).with_control_sender(control_tx);
 
     // Stop the host session when viewer exits
     running.store(false, Ordering::SeqCst);
@@ -3,6 +3,14 @@
 //! This module handles cross-platform clipboard synchronization between
 //! host and client sessions.
 
+pub mod policy;
 pub mod sync;
+pub mod transfer;
 
-pub use sync::{ClipboardContent, ClipboardMonitor, ClipboardSync};
+pub use policy::{ClipboardMode, ClipboardPolicy, ContentFilter};
+
+pub use sync::{ClipboardContent, ClipboardFile, ClipboardMonitor, ClipboardSync, HtmlContent};
+pub use transfer::{
+    Assembled, ChunkAssembler, ClipboardTransferEvent, Rejection, TransferDirection,
+    CLIPBOARD_CHUNK_SIZE, MAX_CLIPBOARD_MESSAGE_SIZE,
+};
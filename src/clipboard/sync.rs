@@ -3,23 +3,73 @@
 //! This module provides clipboard monitoring and synchronization between
 //! host and client sessions.
 
-use arboard::Clipboard;
+use arboard::{Clipboard, ImageData};
+use image::io::Reader as ImageReader;
+use image::{ImageBuffer, ImageFormat, Rgba};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use tokio::sync::mpsc;
 use tokio::sync::RwLock;
+use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, error, info, warn};
 
+use crate::clipboard::policy::{ClipboardMode, ClipboardPolicy, ContentFilter};
+use crate::clipboard::transfer::{
+    split_item, Assembled, ChunkAssembler, ClipboardTransferEvent, TransferDirection,
+    CLIPBOARD_CHUNK_SIZE,
+};
+use crate::config::ClipboardConfig;
 use crate::error::{RemoteDeskError, Result};
-use crate::session::transport::{ClipboardContentType, TransportClipboard};
+use crate::files::{FileTransfers, SharedFiles};
+use crate::network::codec::decode;
+use crate::network::ConnectionRole;
+use crate::session::transport::{
+    ClipboardCancelReason, ClipboardContentType, ClipboardMessage, TransportClipboard,
+};
 
 /// Default polling interval for clipboard changes
 const DEFAULT_POLL_INTERVAL_MS: u64 = 500;
 
+/// Capacity of the transfer event channel
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Largest clipboard image accepted from a peer, in pixels
+///
+/// PNG compresses a blank image to almost nothing, so the size of the
+/// received data says little about what decoding it would allocate.
+const MAX_IMAGE_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// HTML clipboard payload
+///
+/// Rich text is sent as HTML together with a plain-text rendering so
+/// applications that don't understand HTML can still paste.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HtmlContent {
+    /// HTML markup
+    pub html: String,
+    /// Plain-text alternative (empty if the source provided none)
+    pub plain_text: String,
+}
+
+/// A file advertised by a clipboard file list
+///
+/// Only the name and size are sent when files are copied; the contents are
+/// transferred when the other side pastes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipboardFile {
+    /// File name without any directory
+    pub name: String,
+    /// File size in bytes
+    pub size: u64,
+}
+
 /// Clipboard content with metadata
 #[derive(Debug, Clone)]
 pub struct ClipboardContent {
@@ -31,6 +81,8 @@ pub struct ClipboardContent {
     pub hash: u64,
     /// Timestamp when content was captured
     pub timestamp: Instant,
+    /// Local paths behind a file list (empty for remote content)
+    pub local_files: Vec<PathBuf>,
 }
 
 impl ClipboardContent {
@@ -43,6 +95,25 @@ impl ClipboardContent {
             data,
             hash,
             timestamp: Instant::now(),
+            local_files: Vec::new(),
+        }
+    }
+
+    /// Creates new HTML content with a plain-text alternative
+    pub fn html(html: &str, plain_text: &str) -> Self {
+        let payload = HtmlContent {
+            html: html.to_string(),
+            plain_text: plain_text.to_string(),
+        };
+        // Serializing two strings into memory cannot fail
+        let data = bincode::serialize(&payload).unwrap_or_default();
+        let hash = Self::compute_hash(&data);
+        Self {
+            content_type: ClipboardContentType::Html,
+            data,
+            hash,
+            timestamp: Instant::now(),
+            local_files: Vec::new(),
         }
     }
 
@@ -54,11 +125,97 @@ impl ClipboardContent {
             data: png_data,
             hash,
             timestamp: Instant::now(),
+            local_files: Vec::new(),
+        }
+    }
+
+    /// Creates a file list from local paths
+    ///
+    /// Directories and unreadable paths are skipped. Returns None if no
+    /// regular file is left.
+    pub fn file_list(paths: &[PathBuf]) -> Option<Self> {
+        let (files, local_files): (Vec<ClipboardFile>, Vec<PathBuf>) = paths
+            .iter()
+            .filter_map(|path| {
+                let metadata = std::fs::metadata(path).ok().filter(|m| m.is_file())?;
+                let name = path.file_name()?.to_string_lossy().into_owned();
+                let file = ClipboardFile {
+                    name,
+                    size: metadata.len(),
+                };
+                Some((file, path.clone()))
+            })
+            .unzip();
+
+        if files.is_empty() {
+            return None;
+        }
+
+        // Serializing names and sizes into memory cannot fail
+        let data = bincode::serialize(&files).unwrap_or_default();
+        let hash = Self::compute_hash(&data);
+        Some(Self {
+            content_type: ClipboardContentType::FileList,
+            data,
+            hash,
+            timestamp: Instant::now(),
+            local_files,
+        })
+    }
+
+    /// Creates image content by PNG-encoding raw RGBA pixels
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the pixel buffer doesn't match the dimensions or
+    /// encoding fails
+    pub fn from_rgba(width: usize, height: usize, rgba: &[u8]) -> Result<Self> {
+        let img: ImageBuffer<Rgba<u8>, Vec<u8>> =
+            ImageBuffer::from_raw(width as u32, height as u32, rgba.to_vec()).ok_or_else(|| {
+                RemoteDeskError::Generic("Clipboard image size doesn't match its data".to_string())
+            })?;
+
+        let mut buffer = Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(img)
+            .write_to(&mut buffer, ImageFormat::Png)
+            .map_err(|e| RemoteDeskError::Generic(format!("PNG encoding failed: {}", e)))?;
+
+        Ok(Self::image(buffer.into_inner()))
+    }
+
+    /// Decodes image content to its width, height and RGBA pixels
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the content is not an image, is not valid PNG or
+    /// has more than `MAX_IMAGE_PIXELS` pixels
+    pub fn to_rgba(&self) -> Result<(usize, usize, Vec<u8>)> {
+        if self.content_type != ClipboardContentType::Image {
+            return Err(RemoteDeskError::Generic(
+                "Clipboard content is not an image".to_string(),
+            ));
         }
+
+        // Check the size in the header before decoding allocates it
+        let (width, height) = ImageReader::with_format(Cursor::new(&self.data), ImageFormat::Png)
+            .into_dimensions()
+            .map_err(|e| RemoteDeskError::Generic(format!("PNG decoding failed: {}", e)))?;
+        if u64::from(width) * u64::from(height) > MAX_IMAGE_PIXELS {
+            return Err(RemoteDeskError::Generic(format!(
+                "Clipboard image is too large ({}x{})",
+                width, height
+            )));
+        }
+
+        let img = image::load_from_memory_with_format(&self.data, ImageFormat::Png)
+            .map_err(|e| RemoteDeskError::Generic(format!("PNG decoding failed: {}", e)))?
+            .to_rgba8();
+
+        Ok((img.width() as usize, img.height() as usize, img.into_raw()))
     }
 
     /// Computes hash for content
-    fn compute_hash(data: &[u8]) -> u64 {
+    pub(crate) fn compute_hash(data: &[u8]) -> u64 {
         let mut hasher = DefaultHasher::new();
         data.hash(&mut hasher);
         hasher.finish()
@@ -81,23 +238,126 @@ impl ClipboardContent {
             data: transport.data.clone(),
             hash: transport.content_hash,
             timestamp: Instant::now(),
+            local_files: Vec::new(),
         }
     }
 
     /// Returns content as string if it's text
+    ///
+    /// For HTML content this is the plain-text alternative.
     pub fn as_text(&self) -> Option<String> {
-        if self.content_type == ClipboardContentType::Text {
-            String::from_utf8(self.data.clone()).ok()
+        match self.content_type {
+            ClipboardContentType::Text => String::from_utf8(self.data.clone()).ok(),
+            ClipboardContentType::Html => self.as_html().map(|html| html.plain_text),
+            ClipboardContentType::Image | ClipboardContentType::FileList => None,
+        }
+    }
+
+    /// Returns the advertised files if this is a file list
+    pub fn as_file_list(&self) -> Option<Vec<ClipboardFile>> {
+        if self.content_type == ClipboardContentType::FileList {
+            decode(&self.data).ok()
         } else {
             None
         }
     }
+
+    /// Returns the HTML payload if this is HTML content
+    pub fn as_html(&self) -> Option<HtmlContent> {
+        if self.content_type == ClipboardContentType::Html {
+            decode(&self.data).ok()
+        } else {
+            None
+        }
+    }
+}
+
+/// Hashes raw clipboard image pixels
+///
+/// Images are compared before PNG encoding so an unchanged clipboard image
+/// isn't re-encoded on every poll.
+fn pixel_hash(width: usize, height: usize, rgba: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (width, height).hash(&mut hasher);
+    rgba.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Reads the clipboard and returns its content if it changed since the
+/// last call
+///
+/// Copied files take priority, since file managers also put their paths on
+/// the clipboard as text. HTML is preferred over plain text, since rich
+/// text copies offer both. An image is only read when there is no changed
+/// text.
+fn poll_change(
+    clipboard: &mut Clipboard,
+    last_hash: &AtomicU64,
+    last_image_hash: &AtomicU64,
+) -> Option<ClipboardContent> {
+    if let Some(content) = read_file_list(clipboard) {
+        if content.hash == last_hash.swap(content.hash, Ordering::SeqCst) {
+            return None;
+        }
+        debug!("Clipboard changed: {} files", content.local_files.len());
+        return Some(content);
+    }
+
+    let text_content = match clipboard.get().html() {
+        Ok(html) => {
+            let plain_text = clipboard.get_text().unwrap_or_default();
+            Some(ClipboardContent::html(&html, &plain_text))
+        }
+        Err(_) => clipboard
+            .get_text()
+            .ok()
+            .map(|text| ClipboardContent::text(&text)),
+    };
+
+    if let Some(content) = text_content {
+        if content.hash != last_hash.swap(content.hash, Ordering::SeqCst) {
+            debug!(
+                "Clipboard changed: {:?}, {} bytes",
+                content.content_type,
+                content.data.len()
+            );
+            return Some(content);
+        }
+    }
+
+    if let Ok(image) = clipboard.get_image() {
+        let hash = pixel_hash(image.width, image.height, &image.bytes);
+        if hash != last_image_hash.swap(hash, Ordering::SeqCst) {
+            match ClipboardContent::from_rgba(image.width, image.height, &image.bytes) {
+                Ok(content) => {
+                    debug!(
+                        "Clipboard changed: {}x{} image ({} bytes PNG)",
+                        image.width,
+                        image.height,
+                        content.data.len()
+                    );
+                    return Some(content);
+                }
+                Err(e) => warn!("Failed to encode clipboard image: {}", e),
+            }
+        }
+    }
+
+    None
+}
+
+/// Reads copied files from the clipboard
+fn read_file_list(clipboard: &mut Clipboard) -> Option<ClipboardContent> {
+    let paths = clipboard.get().file_list().ok()?;
+    ClipboardContent::file_list(&paths)
 }
 
 /// Clipboard monitor for detecting changes
 pub struct ClipboardMonitor {
-    /// Last content hash
+    /// Last text content hash
     last_hash: Arc<AtomicU64>,
+    /// Last image pixel hash
+    last_image_hash: Arc<AtomicU64>,
     /// Whether monitoring is active
     is_running: Arc<AtomicBool>,
     /// Poll interval
@@ -117,6 +377,7 @@ impl ClipboardMonitor {
     pub fn new() -> Self {
         Self {
             last_hash: Arc::new(AtomicU64::new(0)),
+            last_image_hash: Arc::new(AtomicU64::new(0)),
             is_running: Arc::new(AtomicBool::new(false)),
             poll_interval: Duration::from_millis(DEFAULT_POLL_INTERVAL_MS),
             sequence: Arc::new(AtomicU64::new(0)),
@@ -148,6 +409,7 @@ impl ClipboardMonitor {
 
         let is_running = Arc::clone(&self.is_running);
         let last_hash = Arc::clone(&self.last_hash);
+        let last_image_hash = Arc::clone(&self.last_image_hash);
         let poll_interval = self.poll_interval;
         let sequence = Arc::clone(&self.sequence);
 
@@ -163,27 +425,15 @@ impl ClipboardMonitor {
             };
 
             while is_running.load(Ordering::SeqCst) {
-                // Check for text content
-                if let Ok(text) = clipboard.get_text() {
-                    let content = ClipboardContent::text(&text);
-                    let current_hash = last_hash.load(Ordering::SeqCst);
-
-                    if content.hash != current_hash {
-                        last_hash.store(content.hash, Ordering::SeqCst);
-                        sequence.fetch_add(1, Ordering::SeqCst);
+                if let Some(content) = poll_change(&mut clipboard, &last_hash, &last_image_hash) {
+                    sequence.fetch_add(1, Ordering::SeqCst);
 
-                        debug!("Clipboard changed: {} chars", text.len());
-
-                        if tx.blocking_send(content).is_err() {
-                            debug!("Clipboard receiver closed");
-                            break;
-                        }
+                    if tx.blocking_send(content).is_err() {
+                        debug!("Clipboard receiver closed");
+                        break;
                     }
                 }
 
-                // Note: Image clipboard support would require platform-specific handling
-                // arboard supports get_image() but the API differs
-
                 std::thread::sleep(poll_interval);
             }
 
@@ -209,11 +459,24 @@ impl ClipboardMonitor {
             RemoteDeskError::Generic(format!("Failed to access clipboard: {}", e))
         })?;
 
-        let text = clipboard.get_text().map_err(|e| {
-            RemoteDeskError::Generic(format!("Failed to get clipboard text: {}", e))
+        if let Some(content) = read_file_list(&mut clipboard) {
+            return Ok(content);
+        }
+
+        if let Ok(html) = clipboard.get().html() {
+            let plain_text = clipboard.get_text().unwrap_or_default();
+            return Ok(ClipboardContent::html(&html, &plain_text));
+        }
+
+        if let Ok(text) = clipboard.get_text() {
+            return Ok(ClipboardContent::text(&text));
+        }
+
+        let image = clipboard.get_image().map_err(|e| {
+            RemoteDeskError::Generic(format!("Failed to get clipboard content: {}", e))
         })?;
 
-        Ok(ClipboardContent::text(&text))
+        ClipboardContent::from_rgba(image.width, image.height, &image.bytes)
     }
 
     /// Sets clipboard content
@@ -237,9 +500,62 @@ impl ClipboardMonitor {
 
                 debug!("Set clipboard text: {} chars", text.len());
             }
-            ClipboardContentType::Html | ClipboardContentType::Image => {
-                // HTML and Image handling would require more complex implementation
-                warn!("HTML/Image clipboard not yet fully supported");
+            ClipboardContentType::Image => {
+                let (width, height, rgba) = content.to_rgba()?;
+
+                // Update hash first so the monitor doesn't echo the image back
+                self.last_image_hash
+                    .store(pixel_hash(width, height, &rgba), Ordering::SeqCst);
+
+                clipboard
+                    .set_image(ImageData {
+                        width,
+                        height,
+                        bytes: Cow::Owned(rgba),
+                    })
+                    .map_err(|e| {
+                        RemoteDeskError::Generic(format!("Failed to set clipboard image: {}", e))
+                    })?;
+
+                debug!("Set clipboard image: {}x{}", width, height);
+            }
+            ClipboardContentType::Html => {
+                let payload = content.as_html().ok_or_else(|| {
+                    RemoteDeskError::Generic("Invalid HTML clipboard payload".to_string())
+                })?;
+                let alt_text =
+                    (!payload.plain_text.is_empty()).then_some(payload.plain_text.as_str());
+
+                clipboard
+                    .set_html(payload.html.as_str(), alt_text)
+                    .map_err(|e| {
+                        RemoteDeskError::Generic(format!("Failed to set clipboard HTML: {}", e))
+                    })?;
+
+                // Update hash to prevent echo
+                self.last_hash.store(content.hash, Ordering::SeqCst);
+
+                debug!("Set clipboard HTML: {} bytes", payload.html.len());
+            }
+            ClipboardContentType::FileList => {
+                // Remote file lists have no local files until they are fetched
+                if content.local_files.is_empty() {
+                    return Err(RemoteDeskError::Generic(
+                        "Clipboard files must be downloaded before they can be pasted".to_string(),
+                    ));
+                }
+
+                clipboard
+                    .set()
+                    .file_list(&content.local_files)
+                    .map_err(|e| {
+                        RemoteDeskError::Generic(format!("Failed to set clipboard files: {}", e))
+                    })?;
+
+                // Update hash to prevent echo
+                self.last_hash.store(content.hash, Ordering::SeqCst);
+
+                debug!("Set clipboard files: {}", content.local_files.len());
             }
         }
 
@@ -247,16 +563,152 @@ impl ClipboardMonitor {
     }
 }
 
+/// Outgoing chunked transfer shared between the sync and its send task
+#[derive(Debug, Default)]
+struct OutgoingTransfer {
+    /// Sequence number of the item being sent, plus one (0 when idle)
+    active: AtomicU64,
+    /// Set when the transfer should stop
+    cancel: std::sync::Mutex<Option<ClipboardCancelReason>>,
+}
+
+impl OutgoingTransfer {
+    fn begin(&self, sequence: u64) {
+        *self.cancel.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.active.store(sequence + 1, Ordering::SeqCst);
+    }
+
+    fn finish(&self) {
+        self.active.store(0, Ordering::SeqCst);
+    }
+
+    /// Requests cancellation of the item being sent
+    ///
+    /// With a sequence number, only that item is cancelled. Returns false
+    /// if nothing matching is being sent.
+    fn request_cancel(&self, sequence: Option<u64>, reason: ClipboardCancelReason) -> bool {
+        let active = self.active.load(Ordering::SeqCst);
+        if active == 0 || sequence.is_some_and(|seq| seq + 1 != active) {
+            return false;
+        }
+        *self.cancel.lock().unwrap_or_else(|e| e.into_inner()) = Some(reason);
+        true
+    }
+
+    fn take_cancel(&self) -> Option<ClipboardCancelReason> {
+        self.cancel.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+/// Sends one clipboard item, splitting it into chunks if needed
+///
+/// Items over `max_size` are dropped with a notice. Fails only if the
+/// outgoing channel is closed.
+async fn send_item(
+    outgoing_tx: &mpsc::Sender<ClipboardMessage>,
+    item: TransportClipboard,
+    max_size: u64,
+    transfer: &OutgoingTransfer,
+    events: &broadcast::Sender<ClipboardTransferEvent>,
+) -> std::result::Result<(), mpsc::error::SendError<ClipboardMessage>> {
+    let sequence = item.sequence;
+    let size = item.data.len() as u64;
+    let direction = TransferDirection::Outgoing;
+
+    if size > max_size {
+        let reason = ClipboardCancelReason::TooLarge {
+            size,
+            limit: max_size,
+        };
+        warn!("Clipboard not synced: {}", reason);
+        let _ = events.send(ClipboardTransferEvent::Cancelled {
+            sequence,
+            direction,
+            reason,
+        });
+        return Ok(());
+    }
+
+    let messages = split_item(item, CLIPBOARD_CHUNK_SIZE);
+    let chunked = messages.len() > 1;
+    transfer.begin(sequence);
+
+    let mut sent = 0;
+    for message in messages {
+        if let Some(reason) = transfer.take_cancel() {
+            transfer.finish();
+            info!("Clipboard transfer {} stopped: {}", sequence, reason);
+            outgoing_tx
+                .send(ClipboardMessage::Cancel { sequence, reason })
+                .await?;
+            let _ = events.send(ClipboardTransferEvent::Cancelled {
+                sequence,
+                direction,
+                reason,
+            });
+            return Ok(());
+        }
+
+        let chunk_len = match &message {
+            ClipboardMessage::Chunk { data, .. } => Some(data.len() as u64),
+            _ => None,
+        };
+
+        if let Err(e) = outgoing_tx.send(message).await {
+            transfer.finish();
+            return Err(e);
+        }
+
+        if let Some(len) = chunk_len {
+            sent += len;
+            let _ = events.send(ClipboardTransferEvent::Progress {
+                sequence,
+                direction,
+                transferred: sent,
+                total: size,
+            });
+        }
+    }
+
+    transfer.finish();
+    if chunked {
+        debug!("Sent clipboard item {} in chunks: {} bytes", sequence, size);
+    }
+    let _ = events.send(ClipboardTransferEvent::Completed {
+        sequence,
+        direction,
+        size,
+    });
+
+    Ok(())
+}
+
 /// Clipboard synchronizer for bidirectional sync
 pub struct ClipboardSync {
     /// Local clipboard monitor
     monitor: ClipboardMonitor,
     /// Send channel for outgoing changes
-    outgoing_tx: Option<mpsc::Sender<TransportClipboard>>,
+    outgoing_tx: Option<mpsc::Sender<ClipboardMessage>>,
     /// Whether sync is active
     is_running: Arc<AtomicBool>,
     /// Sequence counter
     sequence: Arc<AtomicU64>,
+    /// Largest item sent or accepted, in bytes
+    max_size: u64,
+    /// Chunked item being sent
+    outgoing: Arc<OutgoingTransfer>,
+    /// Reassembles incoming chunked items
+    assembler: ChunkAssembler,
+    /// Transfer progress and notices
+    events: broadcast::Sender<ClipboardTransferEvent>,
+    /// Directions and content types allowed
+    policy: Arc<std::sync::Mutex<ClipboardPolicy>>,
+    /// Blocks outgoing secrets
+    filter: Arc<ContentFilter>,
+    /// Files the peer may fetch from the local clipboard
+    shared_files: SharedFiles,
+    /// Latest file list copied on the remote side, with its sequence number
+    remote_files: Option<(u64, Vec<ClipboardFile>)>,
 }
 
 impl Default for ClipboardSync {
@@ -267,13 +719,114 @@ impl Default for ClipboardSync {
 
 impl ClipboardSync {
     /// Creates a new clipboard synchronizer
+    ///
+    /// The default configuration syncs everything both ways, so the role
+    /// makes no difference.
     pub fn new() -> Self {
-        Self {
+        Self::with_config(&ClipboardConfig::default(), ConnectionRole::Host)
+            .expect("default clipboard config must be valid")
+    }
+
+    /// Creates a synchronizer enforcing the configured size limit, mode
+    /// and content filters for one side of a session
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a configured filter pattern is invalid
+    pub fn with_config(config: &ClipboardConfig, role: ConnectionRole) -> Result<Self> {
+        let max_size = config.max_size_bytes();
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+
+        let mut policy = ClipboardPolicy::new(config.mode, role);
+        if !config.enabled {
+            policy.restrict(ClipboardMode::Disabled);
+        }
+        let filter = ContentFilter::from_config(config)?;
+
+        Ok(Self {
             monitor: ClipboardMonitor::new(),
             outgoing_tx: None,
             is_running: Arc::new(AtomicBool::new(false)),
             sequence: Arc::new(AtomicU64::new(0)),
+            max_size,
+            outgoing: Arc::new(OutgoingTransfer::default()),
+            assembler: ChunkAssembler::new(max_size),
+            events,
+            policy: Arc::new(std::sync::Mutex::new(policy)),
+            filter: Arc::new(filter),
+            shared_files: SharedFiles::default(),
+            remote_files: None,
+        })
+    }
+
+    /// Returns the current clipboard policy
+    pub fn policy(&self) -> ClipboardPolicy {
+        *self.policy.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Narrows the policy for this session, e.g. with the peer's permissions
+    ///
+    /// Takes effect immediately, including while syncing.
+    pub fn restrict(&self, mode: ClipboardMode) {
+        self.policy
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .restrict(mode);
+    }
+
+    /// Returns the files the peer may fetch, for the session's
+    /// [`FileTransfers`] service
+    pub fn shared_files(&self) -> SharedFiles {
+        self.shared_files.clone()
+    }
+
+    /// Returns the files most recently copied on the remote side
+    pub fn remote_files(&self) -> Option<&[ClipboardFile]> {
+        self.remote_files
+            .as_ref()
+            .map(|(_, files)| files.as_slice())
+    }
+
+    /// Downloads the files copied on the remote side into `dir` and puts
+    /// them on the local clipboard, ready to paste in a file manager
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the remote clipboard holds no files or a download
+    /// fails
+    pub async fn paste_remote_files(
+        &self,
+        transfers: &FileTransfers,
+        dir: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        let (sequence, files) = self.remote_files.as_ref().ok_or_else(|| {
+            RemoteDeskError::Generic("The remote clipboard holds no files".to_string())
+        })?;
+
+        let mut paths = Vec::with_capacity(files.len());
+        for index in 0..files.len() {
+            paths.push(
+                transfers
+                    .fetch_clipboard_file(*sequence, index as u32, dir)
+                    .await?,
+            );
         }
+
+        if let Some(content) = ClipboardContent::file_list(&paths) {
+            self.monitor.set_content(&content)?;
+        }
+
+        Ok(paths)
+    }
+
+    /// Returns the size limit in bytes
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// Subscribes to transfer progress and notices
+    pub fn subscribe(&self) -> broadcast::Receiver<ClipboardTransferEvent> {
+        self.events.subscribe()
     }
 
     /// Starts clipboard synchronization
@@ -281,8 +834,8 @@ impl ClipboardSync {
     /// Returns a receiver for changes from the remote side
     pub fn start(
         &mut self,
-        outgoing_tx: mpsc::Sender<TransportClipboard>,
-    ) -> mpsc::Receiver<TransportClipboard> {
+        outgoing_tx: mpsc::Sender<ClipboardMessage>,
+    ) -> mpsc::Receiver<ClipboardMessage> {
         let (incoming_tx, incoming_rx) = mpsc::channel(32);
 
         self.outgoing_tx = Some(outgoing_tx.clone());
@@ -292,6 +845,12 @@ impl ClipboardSync {
         let local_changes = self.monitor.start_monitoring();
         let sequence = Arc::clone(&self.sequence);
         let is_running = Arc::clone(&self.is_running);
+        let max_size = self.max_size;
+        let outgoing = Arc::clone(&self.outgoing);
+        let events = self.events.clone();
+        let policy = Arc::clone(&self.policy);
+        let filter = Arc::clone(&self.filter);
+        let shared_files = self.shared_files.clone();
 
         // Forward local changes to outgoing channel
         tokio::spawn(async move {
@@ -300,10 +859,37 @@ impl ClipboardSync {
             while is_running.load(Ordering::SeqCst) {
                 tokio::select! {
                     Some(content) = local_changes.recv() => {
+                        // Files copied earlier can no longer be fetched
+                        shared_files.clear();
+
+                        let current = *policy.lock().unwrap_or_else(|e| e.into_inner());
+                        let Some(content) = current.outgoing(content) else {
+                            debug!("Clipboard change not sent: not allowed by policy");
+                            continue;
+                        };
+
                         let seq = sequence.fetch_add(1, Ordering::SeqCst);
+
+                        if let Some(pattern) = filter.blocked_by(&content) {
+                            warn!("Clipboard not synced: content matches {}", pattern);
+                            let _ = events.send(ClipboardTransferEvent::Cancelled {
+                                sequence: seq,
+                                direction: TransferDirection::Outgoing,
+                                reason: ClipboardCancelReason::Blocked,
+                            });
+                            continue;
+                        }
+
+                        if content.content_type == ClipboardContentType::FileList {
+                            shared_files.share(seq, content.local_files.clone());
+                        }
+
                         let transport = content.to_transport(seq);
 
-                        if outgoing_tx.send(transport).await.is_err() {
+                        if send_item(&outgoing_tx, transport, max_size, &outgoing, &events)
+                            .await
+                            .is_err()
+                        {
                             debug!("Outgoing clipboard channel closed");
                             break;
                         }
@@ -323,6 +909,127 @@ impl ClipboardSync {
         self.outgoing_tx = None;
     }
 
+    /// Cancels the item currently being sent
+    ///
+    /// Returns false if no chunked transfer is in progress.
+    pub fn cancel_outgoing(&self) -> bool {
+        self.outgoing
+            .request_cancel(None, ClipboardCancelReason::Cancelled)
+    }
+
+    /// Cancels the item currently being received and tells the sender
+    ///
+    /// Returns false if no chunked transfer is in progress.
+    pub async fn cancel_incoming(&mut self) -> bool {
+        let Some(sequence) = self.assembler.cancel() else {
+            return false;
+        };
+        self.reject(sequence, ClipboardCancelReason::Cancelled).await;
+        true
+    }
+
+    /// Handles a message from the remote side
+    ///
+    /// Complete items are applied to the local clipboard. Items over the
+    /// size limit or with corrupt chunks are refused and the sender told.
+    pub async fn handle_remote(&mut self, message: ClipboardMessage) -> Result<()> {
+        if let ClipboardMessage::Reject { sequence, reason } = message {
+            if self.outgoing.request_cancel(Some(sequence), reason) {
+                warn!("Remote refused clipboard item {}: {}", sequence, reason);
+            }
+            return Ok(());
+        }
+
+        let direction = TransferDirection::Incoming;
+        let announced = match &message {
+            ClipboardMessage::Item(item) => Some((item.sequence, item.content_type)),
+            ClipboardMessage::ChunkStart {
+                sequence,
+                content_type,
+                ..
+            } => Some((*sequence, *content_type)),
+            _ => None,
+        };
+        if let Some((sequence, content_type)) = announced {
+            if !self.policy().allows(direction, content_type) {
+                debug!("Remote clipboard item {} not allowed by policy", sequence);
+                self.assembler.cancel();
+                self.reject(sequence, ClipboardCancelReason::Blocked).await;
+                return Ok(());
+            }
+        }
+
+        match self.assembler.accept(message) {
+            Ok(Assembled::Complete(item)) => {
+                let _ = self.events.send(ClipboardTransferEvent::Completed {
+                    sequence: item.sequence,
+                    direction,
+                    size: item.data.len() as u64,
+                });
+
+                // File contents are only fetched when the user pastes
+                if item.content_type == ClipboardContentType::FileList {
+                    let files = ClipboardContent::from_transport(&item).as_file_list();
+                    if let Some(files) = &files {
+                        info!("Remote clipboard holds {} files", files.len());
+                    }
+                    self.remote_files = files.map(|files| (item.sequence, files));
+                    return Ok(());
+                }
+
+                self.remote_files = None;
+                self.apply_remote(&item)
+            }
+            Ok(Assembled::Progress {
+                sequence,
+                received,
+                total,
+            }) => {
+                let _ = self.events.send(ClipboardTransferEvent::Progress {
+                    sequence,
+                    direction,
+                    transferred: received,
+                    total,
+                });
+                Ok(())
+            }
+            Ok(Assembled::Cancelled { sequence, reason }) => {
+                info!("Remote stopped clipboard transfer {}: {}", sequence, reason);
+                let _ = self.events.send(ClipboardTransferEvent::Cancelled {
+                    sequence,
+                    direction,
+                    reason,
+                });
+                Ok(())
+            }
+            Ok(Assembled::Ignored) => Ok(()),
+            Err(rejection) => {
+                warn!("Clipboard not synced: {}", rejection.reason);
+                self.reject(rejection.sequence, rejection.reason).await;
+                Ok(())
+            }
+        }
+    }
+
+    /// Tells the sender an incoming item was refused
+    async fn reject(&self, sequence: u64, reason: ClipboardCancelReason) {
+        let _ = self.events.send(ClipboardTransferEvent::Cancelled {
+            sequence,
+            direction: TransferDirection::Incoming,
+            reason,
+        });
+
+        if let Some(tx) = &self.outgoing_tx {
+            if tx
+                .send(ClipboardMessage::Reject { sequence, reason })
+                .await
+                .is_err()
+            {
+                debug!("Outgoing clipboard channel closed");
+            }
+        }
+    }
+
     /// Applies remote clipboard content locally
     pub fn apply_remote(&self, transport: &TransportClipboard) -> Result<()> {
         let content = ClipboardContent::from_transport(transport);
@@ -363,6 +1070,124 @@ mod tests {
         assert_eq!(restored.as_text(), content.as_text());
     }
 
+    #[test]
+    fn test_clipboard_content_html() {
+        let content = ClipboardContent::html("<b>Bold</b> text", "Bold text");
+        assert_eq!(content.content_type, ClipboardContentType::Html);
+
+        let restored = ClipboardContent::from_transport(&content.to_transport(7));
+        let html = restored.as_html().unwrap();
+        assert_eq!(html.html, "<b>Bold</b> text");
+        assert_eq!(html.plain_text, "Bold text");
+
+        // Plain-text consumers get the fallback
+        assert_eq!(restored.as_text(), Some("Bold text".to_string()));
+        assert_eq!(ClipboardContent::text("plain").as_html(), None);
+
+        // Formatting changes are detected even when the text is the same
+        let italic = ClipboardContent::html("<i>Bold</i> text", "Bold text");
+        assert_ne!(italic.hash, content.hash);
+    }
+
+    #[test]
+    fn test_crafted_html_length_is_refused() {
+        // HTML claiming to be an exabyte long, followed by a few bytes
+        let mut data = u64::MAX.to_le_bytes().to_vec();
+        data.extend_from_slice(b"<b>hi</b>");
+        let crafted = ClipboardContent::from_transport(&TransportClipboard {
+            content_type: ClipboardContentType::Html,
+            content_hash: ClipboardContent::compute_hash(&data),
+            data,
+            sequence: 1,
+        });
+
+        assert_eq!(crafted.as_html(), None);
+        assert_eq!(crafted.as_text(), None);
+    }
+
+    #[test]
+    fn test_clipboard_content_file_list() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("notes.txt");
+        std::fs::write(&file, b"twelve bytes").unwrap();
+
+        // Directories and missing paths are skipped
+        let paths = vec![
+            file.clone(),
+            dir.path().to_path_buf(),
+            dir.path().join("gone"),
+        ];
+        let content = ClipboardContent::file_list(&paths).unwrap();
+        assert_eq!(content.content_type, ClipboardContentType::FileList);
+        assert_eq!(content.local_files, vec![file]);
+        assert_eq!(content.as_text(), None);
+
+        // Only names and sizes cross the wire
+        let restored = ClipboardContent::from_transport(&content.to_transport(3));
+        assert!(restored.local_files.is_empty());
+        assert_eq!(
+            restored.as_file_list(),
+            Some(vec![ClipboardFile {
+                name: "notes.txt".to_string(),
+                size: 12,
+            }])
+        );
+
+        assert!(ClipboardContent::file_list(&[dir.path().to_path_buf()]).is_none());
+    }
+
+    #[test]
+    fn test_clipboard_content_image_roundtrip() {
+        // 2x1 image: one red pixel, one half-transparent blue pixel
+        let rgba = vec![255, 0, 0, 255, 0, 0, 255, 128];
+        let content = ClipboardContent::from_rgba(2, 1, &rgba).unwrap();
+
+        assert_eq!(content.content_type, ClipboardContentType::Image);
+        assert!(content.data.starts_with(b"\x89PNG"));
+        assert_eq!(content.as_text(), None);
+
+        let restored = ClipboardContent::from_transport(&content.to_transport(1));
+        assert_eq!(restored.hash, content.hash);
+        assert_eq!(restored.to_rgba().unwrap(), (2, 1, rgba.clone()));
+
+        // Same pixels encode to the same hash
+        let again = ClipboardContent::from_rgba(2, 1, &rgba).unwrap();
+        assert_eq!(again.hash, content.hash);
+    }
+
+    #[test]
+    fn test_clipboard_image_errors() {
+        assert!(ClipboardContent::from_rgba(2, 2, &[0; 4]).is_err());
+        assert!(ClipboardContent::text("not an image").to_rgba().is_err());
+        assert!(ClipboardContent::image(vec![1, 2, 3]).to_rgba().is_err());
+    }
+
+    /// CRC-32 as used by PNG chunks
+    fn png_crc(bytes: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &byte in bytes {
+            crc ^= u32::from(byte);
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+
+    #[test]
+    fn test_oversized_clipboard_image_is_refused() {
+        let mut png = ClipboardContent::from_rgba(1, 1, &[0; 4]).unwrap().data;
+
+        // Claim 100000x100000 pixels in the IHDR chunk, keeping its CRC valid
+        png[16..20].copy_from_slice(&100_000u32.to_be_bytes());
+        png[20..24].copy_from_slice(&100_000u32.to_be_bytes());
+        let crc = png_crc(&png[12..29]);
+        png[29..33].copy_from_slice(&crc.to_be_bytes());
+
+        let err = ClipboardContent::image(png).to_rgba().unwrap_err();
+        assert!(err.to_string().contains("too large"), "{}", err);
+    }
+
     #[test]
     fn test_clipboard_monitor_creation() {
         let monitor = ClipboardMonitor::new();
@@ -384,4 +1209,69 @@ mod tests {
         let retrieved = monitor.get_content().unwrap();
         assert_eq!(retrieved.as_text(), content.as_text());
     }
+
+    #[tokio::test]
+    async fn test_send_item_enforces_limit_and_chunks() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let (events, mut events_rx) = broadcast::channel(16);
+        let transfer = OutgoingTransfer::default();
+        let data = vec![7u8; CLIPBOARD_CHUNK_SIZE * 2 + 10];
+
+        // Over the limit: nothing is sent and the user is told why
+        let oversized = ClipboardContent::image(data.clone()).to_transport(1);
+        send_item(&tx, oversized, 1024, &transfer, &events)
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+        assert!(matches!(
+            events_rx.try_recv().unwrap(),
+            ClipboardTransferEvent::Cancelled {
+                reason: ClipboardCancelReason::TooLarge { .. },
+                ..
+            }
+        ));
+
+        // Under the limit but larger than a chunk
+        let large = ClipboardContent::image(data.clone()).to_transport(2);
+        send_item(&tx, large, u64::MAX, &transfer, &events)
+            .await
+            .unwrap();
+
+        let mut assembler = ChunkAssembler::new(u64::MAX);
+        let mut count = 0;
+        let mut complete = None;
+        while let Ok(message) = rx.try_recv() {
+            count += 1;
+            if let Assembled::Complete(item) = assembler.accept(message).unwrap() {
+                complete = Some(item);
+            }
+        }
+        assert_eq!(count, 4);
+        assert_eq!(complete.unwrap().data, data);
+
+        let mut last = None;
+        while let Ok(event) = events_rx.try_recv() {
+            last = Some(event);
+        }
+        assert_eq!(
+            last,
+            Some(ClipboardTransferEvent::Completed {
+                sequence: 2,
+                direction: TransferDirection::Outgoing,
+                size: data.len() as u64,
+            })
+        );
+    }
+
+    #[test]
+    fn test_outgoing_cancel_matches_sequence() {
+        let transfer = OutgoingTransfer::default();
+        assert!(!transfer.request_cancel(None, ClipboardCancelReason::Cancelled));
+
+        transfer.begin(5);
+        assert!(!transfer.request_cancel(Some(4), ClipboardCancelReason::Invalid));
+        assert!(transfer.request_cancel(Some(5), ClipboardCancelReason::Invalid));
+        assert_eq!(transfer.take_cancel(), Some(ClipboardCancelReason::Invalid));
+        assert_eq!(transfer.take_cancel(), None);
+    }
 }
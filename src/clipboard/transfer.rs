@@ -0,0 +1,417 @@
+//! Size limits and chunking for clipboard transfers
+//!
+//! The clipboard stream refuses messages larger than
+//! [`MAX_CLIPBOARD_MESSAGE_SIZE`], which leaves room for one
+//! [`CLIPBOARD_CHUNK_SIZE`] chunk. Larger items are therefore split into
+//! chunks on the sending side and reassembled by a [`ChunkAssembler`] on the
+//! receiving side. Both sides enforce their own `max_size_mb` limit.
+
+use crate::clipboard::sync::ClipboardContent;
+use crate::session::transport::{ClipboardCancelReason, ClipboardMessage, TransportClipboard};
+
+/// Size of each chunk of a large clipboard item
+pub const CLIPBOARD_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Largest message accepted on the clipboard stream: a chunk plus the
+/// fields sent with it
+pub const MAX_CLIPBOARD_MESSAGE_SIZE: usize = CLIPBOARD_CHUNK_SIZE + 1024;
+
+/// Direction of a clipboard transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferDirection {
+    /// Local clipboard sent to the remote side
+    Outgoing,
+    /// Remote clipboard received locally
+    Incoming,
+}
+
+/// Progress and outcome of clipboard transfers
+///
+/// Front-ends subscribe to these to show progress and to tell the user when
+/// an item was not synced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardTransferEvent {
+    /// A chunk of a large item was sent or received
+    Progress {
+        /// Sequence number of the item
+        sequence: u64,
+        /// Transfer direction
+        direction: TransferDirection,
+        /// Bytes transferred so far
+        transferred: u64,
+        /// Total item size in bytes
+        total: u64,
+    },
+    /// An item was transferred completely
+    Completed {
+        /// Sequence number of the item
+        sequence: u64,
+        /// Transfer direction
+        direction: TransferDirection,
+        /// Item size in bytes
+        size: u64,
+    },
+    /// An item was not transferred
+    Cancelled {
+        /// Sequence number of the item
+        sequence: u64,
+        /// Transfer direction
+        direction: TransferDirection,
+        /// Why the transfer stopped
+        reason: ClipboardCancelReason,
+    },
+}
+
+/// Returns the messages needed to send an item
+///
+/// Items up to `chunk_size` bytes are sent as a single `Item`; larger ones
+/// as a `ChunkStart` followed by their chunks.
+pub fn split_item(item: TransportClipboard, chunk_size: usize) -> Vec<ClipboardMessage> {
+    if item.data.len() <= chunk_size {
+        return vec![ClipboardMessage::Item(item)];
+    }
+
+    let chunks: Vec<&[u8]> = item.data.chunks(chunk_size).collect();
+    let mut messages = Vec::with_capacity(chunks.len() + 1);
+
+    messages.push(ClipboardMessage::ChunkStart {
+        sequence: item.sequence,
+        content_type: item.content_type,
+        content_hash: item.content_hash,
+        total_size: item.data.len() as u64,
+        chunk_count: chunks.len() as u32,
+    });
+    messages.extend(
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, data)| ClipboardMessage::Chunk {
+                sequence: item.sequence,
+                index: index as u32,
+                data: data.to_vec(),
+            }),
+    );
+
+    messages
+}
+
+/// Result of passing a message to a [`ChunkAssembler`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assembled {
+    /// An item is complete
+    Complete(TransportClipboard),
+    /// A chunked item is still being received
+    Progress {
+        /// Sequence number of the item
+        sequence: u64,
+        /// Bytes received so far
+        received: u64,
+        /// Total item size in bytes
+        total: u64,
+    },
+    /// The sender abandoned the item being received
+    Cancelled {
+        /// Sequence number of the item
+        sequence: u64,
+        /// Why the sender stopped
+        reason: ClipboardCancelReason,
+    },
+    /// Message didn't belong to a transfer in progress
+    Ignored,
+}
+
+/// An incoming item the assembler refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rejection {
+    /// Sequence number of the item
+    pub sequence: u64,
+    /// Why the item was refused
+    pub reason: ClipboardCancelReason,
+}
+
+/// Chunked item being received
+#[derive(Debug)]
+struct PendingItem {
+    item: TransportClipboard,
+    total_size: u64,
+    chunk_count: u32,
+    next_index: u32,
+}
+
+/// Reassembles chunked clipboard items and enforces the size limit
+#[derive(Debug)]
+pub struct ChunkAssembler {
+    max_size: u64,
+    pending: Option<PendingItem>,
+}
+
+impl ChunkAssembler {
+    /// Creates an assembler accepting items up to `max_size` bytes
+    pub fn new(max_size: u64) -> Self {
+        Self {
+            max_size,
+            pending: None,
+        }
+    }
+
+    /// Returns the sequence number of the item being received, if any
+    pub fn pending_sequence(&self) -> Option<u64> {
+        self.pending.as_ref().map(|p| p.item.sequence)
+    }
+
+    /// Drops the item being received and returns its sequence number
+    pub fn cancel(&mut self) -> Option<u64> {
+        self.pending.take().map(|p| p.item.sequence)
+    }
+
+    /// Handles a message from the remote side
+    ///
+    /// `Reject` messages concern outgoing transfers and are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a rejection if the item is over the size limit or its chunks
+    /// don't match the announcement; the caller should tell the sender.
+    pub fn accept(&mut self, message: ClipboardMessage) -> Result<Assembled, Rejection> {
+        match message {
+            ClipboardMessage::Item(item) => {
+                self.check_size(item.sequence, item.data.len() as u64)?;
+                Ok(Assembled::Complete(item))
+            }
+            ClipboardMessage::ChunkStart {
+                sequence,
+                content_type,
+                content_hash,
+                total_size,
+                chunk_count,
+            } => {
+                // A new item replaces any the sender abandoned
+                self.pending = None;
+                self.check_size(sequence, total_size)?;
+
+                // Every chunk carries at least one byte
+                if chunk_count == 0 || chunk_count as u64 > total_size {
+                    return Err(Rejection {
+                        sequence,
+                        reason: ClipboardCancelReason::Invalid,
+                    });
+                }
+
+                self.pending = Some(PendingItem {
+                    item: TransportClipboard {
+                        content_type,
+                        data: Vec::with_capacity(total_size as usize),
+                        content_hash,
+                        sequence,
+                    },
+                    total_size,
+                    chunk_count,
+                    next_index: 0,
+                });
+
+                Ok(Assembled::Progress {
+                    sequence,
+                    received: 0,
+                    total: total_size,
+                })
+            }
+            ClipboardMessage::Chunk {
+                sequence,
+                index,
+                data,
+            } => self.accept_chunk(sequence, index, data),
+            ClipboardMessage::Cancel { sequence, reason } => {
+                if self.pending_sequence() != Some(sequence) {
+                    return Ok(Assembled::Ignored);
+                }
+                self.pending = None;
+                Ok(Assembled::Cancelled { sequence, reason })
+            }
+            ClipboardMessage::Reject { .. } => Ok(Assembled::Ignored),
+        }
+    }
+
+    fn accept_chunk(
+        &mut self,
+        sequence: u64,
+        index: u32,
+        data: Vec<u8>,
+    ) -> Result<Assembled, Rejection> {
+        let mut pending = match self.pending.take() {
+            Some(pending) if pending.item.sequence == sequence => pending,
+            other => {
+                // Chunks of a rejected or cancelled item still in flight
+                self.pending = other;
+                return Ok(Assembled::Ignored);
+            }
+        };
+
+        let received = (pending.item.data.len() + data.len()) as u64;
+        if index != pending.next_index || received > pending.total_size {
+            return Err(Rejection {
+                sequence,
+                reason: ClipboardCancelReason::Invalid,
+            });
+        }
+
+        pending.item.data.extend_from_slice(&data);
+        pending.next_index += 1;
+
+        if pending.next_index < pending.chunk_count {
+            let total = pending.total_size;
+            self.pending = Some(pending);
+            return Ok(Assembled::Progress {
+                sequence,
+                received,
+                total,
+            });
+        }
+
+        let PendingItem {
+            item, total_size, ..
+        } = pending;
+        if received != total_size || ClipboardContent::compute_hash(&item.data) != item.content_hash {
+            return Err(Rejection {
+                sequence,
+                reason: ClipboardCancelReason::Invalid,
+            });
+        }
+
+        Ok(Assembled::Complete(item))
+    }
+
+    fn check_size(&self, sequence: u64, size: u64) -> Result<(), Rejection> {
+        if size > self.max_size {
+            return Err(Rejection {
+                sequence,
+                reason: ClipboardCancelReason::TooLarge {
+                    size,
+                    limit: self.max_size,
+                },
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::transport::ClipboardContentType;
+
+    fn item(sequence: u64, size: usize) -> TransportClipboard {
+        let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+        TransportClipboard {
+            content_type: ClipboardContentType::Text,
+            content_hash: ClipboardContent::compute_hash(&data),
+            data,
+            sequence,
+        }
+    }
+
+    #[test]
+    fn test_small_item_is_not_chunked() {
+        let messages = split_item(item(1, 100), 64 * 1024);
+        assert!(matches!(messages.as_slice(), [ClipboardMessage::Item(_)]));
+
+        let mut assembler = ChunkAssembler::new(1024);
+        let result = assembler.accept(messages[0].clone()).unwrap();
+        assert_eq!(result, Assembled::Complete(item(1, 100)));
+    }
+
+    #[test]
+    fn test_chunked_roundtrip() {
+        let original = item(7, 2500);
+        let messages = split_item(original.clone(), 1000);
+        assert_eq!(messages.len(), 4);
+
+        let mut assembler = ChunkAssembler::new(10_000);
+        let results: Vec<Assembled> = messages
+            .into_iter()
+            .map(|m| assembler.accept(m).unwrap())
+            .collect();
+
+        let progress: Vec<u64> = results
+            .iter()
+            .filter_map(|r| match r {
+                Assembled::Progress { received, .. } => Some(*received),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(progress, vec![0, 1000, 2000]);
+        assert_eq!(results.last(), Some(&Assembled::Complete(original)));
+        assert_eq!(assembler.pending_sequence(), None);
+    }
+
+    #[test]
+    fn test_oversized_item_rejected() {
+        let mut assembler = ChunkAssembler::new(1000);
+
+        let rejection = assembler
+            .accept(ClipboardMessage::Item(item(1, 1001)))
+            .unwrap_err();
+        assert_eq!(
+            rejection.reason,
+            ClipboardCancelReason::TooLarge {
+                size: 1001,
+                limit: 1000
+            }
+        );
+
+        // Chunks of a refused item that are already in flight are ignored
+        let messages = split_item(item(2, 3000), 1000);
+        assert!(assembler.accept(messages[0].clone()).is_err());
+        assert_eq!(
+            assembler.accept(messages[1].clone()).unwrap(),
+            Assembled::Ignored
+        );
+    }
+
+    #[test]
+    fn test_corrupt_chunks_rejected() {
+        let mut assembler = ChunkAssembler::new(10_000);
+        let mut messages = split_item(item(3, 2500), 1000);
+
+        // Out of order
+        assembler.accept(messages[0].clone()).unwrap();
+        let rejection = assembler.accept(messages[2].clone()).unwrap_err();
+        assert_eq!(rejection.reason, ClipboardCancelReason::Invalid);
+
+        // Data that doesn't match the announced hash
+        if let ClipboardMessage::Chunk { data, .. } = &mut messages[3] {
+            data[0] ^= 0xFF;
+        }
+        let last = messages.pop().unwrap();
+        for message in messages {
+            assembler.accept(message).unwrap();
+        }
+        let rejection = assembler.accept(last).unwrap_err();
+        assert_eq!(rejection.reason, ClipboardCancelReason::Invalid);
+    }
+
+    #[test]
+    fn test_sender_and_receiver_cancel() {
+        let mut assembler = ChunkAssembler::new(10_000);
+        let messages = split_item(item(4, 2500), 1000);
+        assembler.accept(messages[0].clone()).unwrap();
+        assembler.accept(messages[1].clone()).unwrap();
+
+        let result = assembler
+            .accept(ClipboardMessage::Cancel {
+                sequence: 4,
+                reason: ClipboardCancelReason::Cancelled,
+            })
+            .unwrap();
+        assert_eq!(
+            result,
+            Assembled::Cancelled {
+                sequence: 4,
+                reason: ClipboardCancelReason::Cancelled
+            }
+        );
+
+        assembler.accept(messages[0].clone()).unwrap();
+        assert_eq!(assembler.cancel(), Some(4));
+        assert_eq!(assembler.cancel(), None);
+    }
+}
@@ -6,8 +6,10 @@
 //! - Providing sensible defaults
 //! - Configuration validation
 
+use crate::clipboard::{ClipboardMode, ContentFilter};
 use crate::error::{ConfigError, ConfigResult};
-use directories::ProjectDirs;
+use crate::input::MacroConfig;
+use directories::{ProjectDirs, UserDirs};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -17,6 +19,7 @@ const CONFIG_FILE_NAME: &str = "config.toml";
 const DEVICE_ID_FILE_NAME: &str = "device_id";
 const PASSWORD_HASH_FILE_NAME: &str = "password.hash";
 const CONNECTION_LOG_FILE_NAME: &str = "connections.log";
+const POLICY_FILE_NAME: &str = "policy.toml";
 
 const DEFAULT_LISTEN_PORT: u16 = 0; // 0 = random port
 const DEFAULT_MAX_CONNECTIONS: u8 = 1;
@@ -27,8 +30,20 @@ const DEFAULT_FPS: u8 = 30;
 const DEFAULT_COMPRESSION_LEVEL: u8 = 3;
 const DEFAULT_MAX_PASSWORD_ATTEMPTS: u32 = 5;
 const DEFAULT_LOCKOUT_DURATION_MINUTES: u32 = 15;
+const DEFAULT_CONNECTIONS_PER_MINUTE: u32 = 10;
+const DEFAULT_CONNECTION_BURST: u32 = 5;
+const DEFAULT_DEVICE_ATTEMPTS_PER_MINUTE: u32 = 6;
+const DEFAULT_REJECTIONS_BEFORE_BAN: u32 = 3;
+const DEFAULT_BASE_BAN_SECONDS: u64 = 60;
+const DEFAULT_MAX_BAN_MINUTES: u32 = 60;
+const DEFAULT_MAX_PENDING_HANDSHAKES: usize = 16;
+const DEFAULT_MAX_PENDING_REQUESTS: usize = 16;
+const DEFAULT_AUDIT_LOG_MAX_SIZE_MB: u32 = 10;
+const DEFAULT_AUDIT_LOG_MAX_FILES: u32 = 5;
 const DEFAULT_CLIPBOARD_MAX_SIZE_MB: u32 = 10;
 const DEFAULT_CLIPBOARD_SYNC_DELAY_MS: u64 = 500;
+const BYTES_PER_MB: u64 = 1024 * 1024;
+const DEFAULT_DOWNLOAD_DIR_NAME: &str = "Downloads";
 
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +62,22 @@ pub struct Config {
 
     /// UI configuration
     pub ui: UiConfig,
+
+    /// Input configuration
+    #[serde(default)]
+    pub input: InputConfig,
+
+    /// File transfer configuration
+    #[serde(default)]
+    pub files: FilesConfig,
+
+    /// Remote terminal configuration
+    #[serde(default)]
+    pub terminal: TerminalConfig,
+
+    /// Port forwarding configuration
+    #[serde(default)]
+    pub forwarding: ForwardingConfig,
 }
 
 /// Network-related configuration
@@ -93,11 +124,66 @@ pub struct SecurityConfig {
     /// Idle timeout in minutes
     pub idle_timeout_minutes: u32,
 
+    /// Allow clients to request a session timeout extension
+    #[serde(default)]
+    pub allow_session_extend: bool,
+
     /// Maximum password attempts before lockout
     pub max_password_attempts: u32,
 
     /// Lockout duration in minutes
     pub lockout_duration_minutes: u32,
+
+    /// Connection rate limiting
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+
+    /// Connection audit log
+    #[serde(default)]
+    pub audit: AuditConfig,
+}
+
+/// Connection rate limiting configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Connection attempts allowed per minute from one IP address
+    pub connections_per_minute: u32,
+
+    /// Connection attempts allowed in a burst before throttling
+    pub connection_burst: u32,
+
+    /// Connection attempts allowed per minute for one claimed device ID
+    pub device_attempts_per_minute: u32,
+
+    /// Rejections before a peer is temporarily banned
+    pub rejections_before_ban: u32,
+
+    /// Duration of the first ban in seconds (doubles on each repeat)
+    pub base_ban_seconds: u64,
+
+    /// Maximum ban duration in minutes
+    pub max_ban_minutes: u32,
+
+    /// Maximum concurrent handshakes still waiting for the peer's request
+    pub max_pending_handshakes: usize,
+
+    /// Maximum connection requests waiting to be accepted or rejected
+    pub max_pending_requests: usize,
+}
+
+/// Connection audit log configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Record connection events to the audit log
+    pub enabled: bool,
+
+    /// Rotate the log once it reaches this size in MB
+    pub max_size_mb: u32,
+
+    /// Number of rotated log files to keep
+    pub max_files: u32,
 }
 
 /// Clipboard configuration
@@ -111,6 +197,91 @@ pub struct ClipboardConfig {
 
     /// Delay before syncing clipboard (debounce) in milliseconds
     pub sync_delay_ms: u64,
+
+    /// Directions and content types to sync
+    #[serde(default)]
+    pub mode: ClipboardMode,
+
+    /// Never send content that looks like a private key or access token
+    #[serde(default)]
+    pub block_secrets: bool,
+
+    /// Regular expressions for additional content that is never sent
+    #[serde(default)]
+    pub blocked_patterns: Vec<String>,
+}
+
+impl ClipboardConfig {
+    /// Returns the clipboard size limit in bytes
+    pub fn max_size_bytes(&self) -> u64 {
+        self.max_size_mb as u64 * BYTES_PER_MB
+    }
+}
+
+/// File transfer configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilesConfig {
+    /// Directory received files are saved in (the user's download
+    /// directory if unset)
+    pub download_dir: Option<PathBuf>,
+
+    /// Directory files from connected clients are saved in without asking,
+    /// such as files dropped onto their viewer (offers wait to be accepted
+    /// and go to the download directory if unset)
+    pub drop_dir: Option<PathBuf>,
+
+    /// Directories connected clients may browse and download from, if
+    /// their permissions allow it (none by default)
+    pub browse_roots: Vec<PathBuf>,
+}
+
+impl FilesConfig {
+    /// Returns the directory received files are saved in
+    pub fn download_dir(&self) -> PathBuf {
+        if let Some(dir) = &self.download_dir {
+            return dir.clone();
+        }
+
+        UserDirs::new()
+            .map(|dirs| {
+                dirs.download_dir()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| dirs.home_dir().join(DEFAULT_DOWNLOAD_DIR_NAME))
+            })
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DOWNLOAD_DIR_NAME))
+    }
+}
+
+/// Remote terminal configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalConfig {
+    /// Let connected clients open a shell on this machine, if their
+    /// permissions allow it (off by default)
+    pub enabled: bool,
+
+    /// Shell to run (`$SHELL`, or `/bin/sh`, if unset)
+    pub shell: Option<PathBuf>,
+}
+
+/// Port forwarding configuration
+///
+/// Clients can always forward ports to targets reachable from their own
+/// machine; this only limits what they may reach through this one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForwardingConfig {
+    /// Let connected clients forward ports through this machine, if their
+    /// permissions allow it (off by default)
+    pub enabled: bool,
+
+    /// Targets clients may connect to, as `host:port` with `*` for any
+    /// host or port (e.g. `localhost:80`, `db.lan:*`)
+    pub allowed_targets: Vec<String>,
+
+    /// Loopback ports clients may have this machine listen on
+    pub listen_ports: Vec<u16>,
 }
 
 /// UI configuration
@@ -123,6 +294,14 @@ pub struct UiConfig {
     pub minimize_to_tray: bool,
 }
 
+/// Input configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputConfig {
+    /// User-defined key macros, shown after the built-in ones
+    #[serde(default)]
+    pub macros: Vec<MacroConfig>,
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -131,6 +310,10 @@ impl Default for Config {
             security: SecurityConfig::default(),
             clipboard: ClipboardConfig::default(),
             ui: UiConfig::default(),
+            input: InputConfig::default(),
+            files: FilesConfig::default(),
+            terminal: TerminalConfig::default(),
+            forwarding: ForwardingConfig::default(),
         }
     }
 }
@@ -166,8 +349,36 @@ impl Default for SecurityConfig {
             min_password_length: 6,
             session_timeout_minutes: DEFAULT_SESSION_TIMEOUT_MINUTES,
             idle_timeout_minutes: DEFAULT_IDLE_TIMEOUT_MINUTES,
+            allow_session_extend: false,
             max_password_attempts: DEFAULT_MAX_PASSWORD_ATTEMPTS,
             lockout_duration_minutes: DEFAULT_LOCKOUT_DURATION_MINUTES,
+            rate_limit: RateLimitConfig::default(),
+            audit: AuditConfig::default(),
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            connections_per_minute: DEFAULT_CONNECTIONS_PER_MINUTE,
+            connection_burst: DEFAULT_CONNECTION_BURST,
+            device_attempts_per_minute: DEFAULT_DEVICE_ATTEMPTS_PER_MINUTE,
+            rejections_before_ban: DEFAULT_REJECTIONS_BEFORE_BAN,
+            base_ban_seconds: DEFAULT_BASE_BAN_SECONDS,
+            max_ban_minutes: DEFAULT_MAX_BAN_MINUTES,
+            max_pending_handshakes: DEFAULT_MAX_PENDING_HANDSHAKES,
+            max_pending_requests: DEFAULT_MAX_PENDING_REQUESTS,
+        }
+    }
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_size_mb: DEFAULT_AUDIT_LOG_MAX_SIZE_MB,
+            max_files: DEFAULT_AUDIT_LOG_MAX_FILES,
         }
     }
 }
@@ -178,6 +389,9 @@ impl Default for ClipboardConfig {
             enabled: true,
             max_size_mb: DEFAULT_CLIPBOARD_MAX_SIZE_MB,
             sync_delay_ms: DEFAULT_CLIPBOARD_SYNC_DELAY_MS,
+            mode: ClipboardMode::default(),
+            block_secrets: false,
+            blocked_patterns: Vec::new(),
         }
     }
 }
@@ -319,6 +533,88 @@ impl ConfigManager {
             ));
         }
 
+        // Validate rate limits
+        let rate_limit = &config.security.rate_limit;
+        if rate_limit.connections_per_minute == 0
+            || rate_limit.connection_burst == 0
+            || rate_limit.device_attempts_per_minute == 0
+        {
+            return Err(ConfigError::InvalidValue(
+                "Connection rate limits must be greater than 0".to_string(),
+            ));
+        }
+
+        if rate_limit.max_pending_handshakes == 0 {
+            return Err(ConfigError::InvalidValue(
+                "Maximum pending handshakes must be greater than 0".to_string(),
+            ));
+        }
+
+        if rate_limit.max_pending_requests == 0 {
+            return Err(ConfigError::InvalidValue(
+                "Maximum pending requests must be greater than 0".to_string(),
+            ));
+        }
+
+        if config.clipboard.max_size_mb == 0 {
+            return Err(ConfigError::InvalidValue(
+                "Clipboard size limit must be greater than 0".to_string(),
+            ));
+        }
+
+        // Validate clipboard filters
+        ContentFilter::from_config(&config.clipboard)
+            .map_err(|e| ConfigError::InvalidValue(e.to_string()))?;
+
+        // Validate download and drop directories
+        if let Some(dir) = &config.files.download_dir {
+            if dir.exists() && !dir.is_dir() {
+                return Err(ConfigError::InvalidValue(format!(
+                    "Download directory {:?} is not a directory",
+                    dir
+                )));
+            }
+        }
+        if let Some(dir) = &config.files.drop_dir {
+            if dir.exists() && !dir.is_dir() {
+                return Err(ConfigError::InvalidValue(format!(
+                    "Drop directory {:?} is not a directory",
+                    dir
+                )));
+            }
+        }
+
+        // Validate browse roots
+        for dir in &config.files.browse_roots {
+            if !dir.is_dir() {
+                return Err(ConfigError::InvalidValue(format!(
+                    "Browse root {:?} is not a directory",
+                    dir
+                )));
+            }
+        }
+
+        // Validate terminal shell
+        if let Some(shell) = &config.terminal.shell {
+            if !shell.is_file() {
+                return Err(ConfigError::InvalidValue(format!(
+                    "Terminal shell {:?} is not a file",
+                    shell
+                )));
+            }
+        }
+
+        // Validate forwarding targets
+        crate::network::ForwardPolicy::from_config(&config.forwarding)
+            .map_err(|e| ConfigError::InvalidValue(e.to_string()))?;
+
+        // Validate key macros
+        for key_macro in &config.input.macros {
+            key_macro
+                .to_macro()
+                .map_err(|e| ConfigError::InvalidValue(e.to_string()))?;
+        }
+
         Ok(())
     }
 
@@ -337,6 +633,11 @@ impl ConfigManager {
         self.config_dir.join(CONNECTION_LOG_FILE_NAME)
     }
 
+    /// Gets the path to the access policy file
+    pub fn policy_path(&self) -> PathBuf {
+        self.config_dir.join(POLICY_FILE_NAME)
+    }
+
     /// Gets the configuration directory path
     pub fn config_directory(&self) -> &PathBuf {
         &self.config_dir
@@ -365,6 +666,79 @@ mod tests {
         config.desktop.default_quality = 150; // Invalid
 
         assert!(manager.validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.security.rate_limit.max_pending_handshakes = 0; // Invalid
+
+        assert!(manager.validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.security.rate_limit.max_pending_requests = 0; // Invalid
+
+        assert!(manager.validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.clipboard.max_size_mb = 0; // Invalid
+
+        assert!(manager.validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.clipboard.blocked_patterns.push("[a-".to_string()); // Invalid
+
+        assert!(manager.validate(&config).is_err());
+
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("not-a-dir");
+        fs::write(&file, "").unwrap();
+        let mut config = Config::default();
+        config.files.download_dir = Some(file.clone()); // Invalid
+
+        assert!(manager.validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.files.drop_dir = Some(file.clone()); // Invalid
+
+        assert!(manager.validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.files.browse_roots.push(file); // Invalid
+
+        assert!(manager.validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.terminal.shell = Some(temp_dir.path().to_path_buf()); // Invalid
+
+        assert!(manager.validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.forwarding.allowed_targets.push("localhost".to_string()); // Invalid
+
+        assert!(manager.validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.input.macros.push(MacroConfig {
+            name: "Broken".to_string(),
+            keys: "Ctrl+Nope".to_string(), // Invalid
+        });
+
+        assert!(manager.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_config_without_rate_limit_section() {
+        // Config files written before rate limiting existed still load
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        value["security"]
+            .as_table_mut()
+            .unwrap()
+            .remove("rate_limit");
+
+        let deserialized: Config = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+
+        assert_eq!(
+            deserialized.security.rate_limit.connection_burst,
+            DEFAULT_CONNECTION_BURST
+        );
     }
 
     #[test]
@@ -375,4 +749,64 @@ mod tests {
 
         assert_eq!(config.network.listen_port, deserialized.network.listen_port);
     }
+
+    #[test]
+    fn test_config_with_custom_macros() {
+        let mut config = Config::default();
+        config.input.macros.push(MacroConfig {
+            name: "Reopen tab".to_string(),
+            keys: "Ctrl+Shift+T".to_string(),
+        });
+
+        let toml_str = toml::to_string(&config).unwrap();
+        let deserialized: Config = toml::from_str(&toml_str).unwrap();
+        assert_eq!(deserialized.input.macros, config.input.macros);
+
+        // Config files without an [input] section still load
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        value.as_table_mut().unwrap().remove("input");
+        let deserialized: Config = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+        assert!(deserialized.input.macros.is_empty());
+    }
+
+    #[test]
+    fn test_download_dir() {
+        let mut config = Config::default();
+        assert!(!config.files.download_dir().as_os_str().is_empty());
+
+        config.files.download_dir = Some(PathBuf::from("/srv/incoming"));
+        assert_eq!(config.files.download_dir(), PathBuf::from("/srv/incoming"));
+
+        // Config files without a [files] section still load
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        value.as_table_mut().unwrap().remove("files");
+        let deserialized: Config = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+        assert!(deserialized.files.download_dir.is_none());
+        assert!(deserialized.files.drop_dir.is_none());
+        assert!(deserialized.files.browse_roots.is_empty());
+    }
+
+    #[test]
+    fn test_terminal_disabled_by_default() {
+        assert!(!Config::default().terminal.enabled);
+
+        // Config files without a [terminal] section still load
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        value.as_table_mut().unwrap().remove("terminal");
+        let deserialized: Config = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+        assert!(!deserialized.terminal.enabled);
+        assert!(deserialized.terminal.shell.is_none());
+    }
+
+    #[test]
+    fn test_forwarding_disabled_by_default() {
+        assert!(!Config::default().forwarding.enabled);
+
+        // Config files without a [forwarding] section still load
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        value.as_table_mut().unwrap().remove("forwarding");
+        let deserialized: Config = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+        assert!(!deserialized.forwarding.enabled);
+        assert!(deserialized.forwarding.allowed_targets.is_empty());
+    }
 }
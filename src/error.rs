@@ -26,6 +26,26 @@ pub enum RemoteDeskError {
     #[error("Session error: {0}")]
     Session(#[from] SessionError),
 
+    /// File transfer errors
+    #[error("File transfer error: {0}")]
+    FileTransfer(#[from] FileTransferError),
+
+    /// Remote terminal errors
+    #[error("Terminal error: {0}")]
+    Terminal(#[from] TerminalError),
+
+    /// Port forwarding errors
+    #[error("Port forwarding error: {0}")]
+    Forward(#[from] ForwardError),
+
+    /// Custom channel errors
+    #[error("Custom channel error: {0}")]
+    CustomChannel(#[from] CustomChannelError),
+
+    /// Request/response errors
+    #[error("Request error: {0}")]
+    Rpc(#[from] RpcError),
+
     /// I/O errors
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
@@ -82,6 +102,9 @@ pub enum SecurityError {
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
 
+    #[error("Temporarily banned for {seconds} more seconds")]
+    TemporarilyBanned { seconds: u64 },
+
     #[error("Encryption error: {0}")]
     EncryptionError(String),
 
@@ -146,6 +169,210 @@ pub enum SessionError {
 
     #[error("Transport error: {0}")]
     TransportError(String),
+
+    /// Chat message is empty or too long
+    #[error("Invalid chat message: {0}")]
+    InvalidChatMessage(String),
+}
+
+/// File transfer errors
+#[derive(Error, Debug)]
+pub enum FileTransferError {
+    /// File name is empty or tries to leave the download directory
+    #[error("Invalid file name: {0}")]
+    InvalidFileName(String),
+
+    /// Peer asked for a file that isn't shared
+    #[error("File not available: {0}")]
+    NotAvailable(String),
+
+    /// Received data doesn't match the announced size
+    #[error("Size mismatch for {name}: expected {expected} bytes, got {actual}")]
+    SizeMismatch {
+        /// File name
+        name: String,
+        /// Announced size in bytes
+        expected: u64,
+        /// Received size in bytes
+        actual: u64,
+    },
+
+    /// Received data doesn't match the announced SHA-256 digest
+    #[error("Checksum mismatch for {0}")]
+    ChecksumMismatch(String),
+
+    /// Transfer was cancelled by either side
+    #[error("Transfer cancelled: {0}")]
+    Cancelled(String),
+
+    /// No transfer with this ID is waiting for that action
+    #[error("Unknown file transfer: {0}")]
+    UnknownTransfer(u64),
+
+    /// The same file is already being received into the same directory
+    #[error("Already receiving {0}")]
+    AlreadyReceiving(String),
+
+    /// The peer doesn't let this side browse its files
+    #[error("File browsing is not enabled")]
+    BrowsingDisabled,
+
+    /// The peer couldn't carry out a request
+    #[error("Peer error: {0}")]
+    Remote(String),
+
+    /// The peer didn't answer a request in time
+    #[error("Request timed out")]
+    Timeout,
+
+    /// File transfer channel is closed
+    #[error("File transfer channel closed")]
+    ChannelClosed,
+
+    /// Reading or writing a file failed
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Remote terminal errors
+#[derive(Error, Debug)]
+pub enum TerminalError {
+    /// The host doesn't let this peer open terminals
+    #[error("Terminal access is disabled")]
+    Disabled,
+
+    /// Terminals can't be started on this platform
+    #[error("Terminals are not supported on this platform")]
+    Unsupported,
+
+    /// The session already has as many terminals as allowed
+    #[error("Too many open terminals (limit {0})")]
+    TooManyTerminals(usize),
+
+    /// No open terminal has this ID
+    #[error("Unknown terminal: {0}")]
+    UnknownTerminal(u32),
+
+    /// The peer couldn't carry out a request
+    #[error("Peer error: {0}")]
+    Remote(String),
+
+    /// The peer didn't answer a request in time
+    #[error("Request timed out")]
+    Timeout,
+
+    /// Terminal channel is closed
+    #[error("Terminal channel closed")]
+    ChannelClosed,
+
+    /// Starting or talking to the shell failed
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Port forwarding errors
+#[derive(Error, Debug)]
+pub enum ForwardError {
+    /// A forwarding spec couldn't be parsed
+    #[error("Invalid forward {0:?} (expected bind_port:host:port)")]
+    InvalidSpec(String),
+
+    /// The peer refused to open a tunnel
+    #[error("Peer refused: {0}")]
+    Refused(String),
+
+    /// No forward with this ID is active
+    #[error("Unknown forward: {0}")]
+    UnknownForward(u64),
+
+    /// The peer didn't answer in time
+    #[error("Request timed out")]
+    Timeout,
+
+    /// Opening or using a tunnel stream failed
+    #[error("Tunnel stream error: {0}")]
+    Stream(String),
+
+    /// Listening or connecting locally failed
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Custom channel errors
+#[derive(Error, Debug)]
+pub enum CustomChannelError {
+    /// The channel wasn't agreed with the peer in the handshake
+    #[error("Channel {0:?} was not agreed with the peer")]
+    UnknownChannel(String),
+
+    /// The channel is already open in this session
+    #[error("Channel {0:?} is already open")]
+    AlreadyOpen(String),
+
+    /// The peer dropped data sent on the channel
+    #[error("Peer rejected channel {channel:?}: {reason}")]
+    Rejected {
+        /// Channel name
+        channel: String,
+        /// Reason given by the peer
+        reason: String,
+    },
+
+    /// A message is too large to send
+    #[error("Message is {size} bytes, the limit is {max}")]
+    TooLarge {
+        /// Message size
+        size: usize,
+        /// Largest size allowed
+        max: usize,
+    },
+
+    /// A message couldn't be serialized or deserialized
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    /// The session has ended
+    #[error("Channel closed")]
+    ChannelClosed,
+}
+
+/// Request/response errors
+///
+/// The peer's answers to failed requests arrive as an `ErrorMessage` and
+/// are mapped to a variant by their error code.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RpcError {
+    /// The peer didn't answer in time
+    #[error("Request timed out")]
+    Timeout,
+
+    /// The session ended before the peer answered
+    #[error("Channel closed")]
+    ChannelClosed,
+
+    /// The peer doesn't answer this request
+    #[error("Unsupported request: {0}")]
+    Unsupported(String),
+
+    /// The peer refused the request
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    /// The peer found the request malformed
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    /// The peer is out of resources for the request
+    #[error("Peer is busy: {0}")]
+    Busy(String),
+
+    /// The peer failed for another reason
+    #[error("Peer error: {0}")]
+    Remote(String),
+
+    /// The peer answered with a response of the wrong kind
+    #[error("Unexpected response: {0}")]
+    UnexpectedResponse(String),
 }
 
 /// Type alias for Results using RemoteDeskError
@@ -163,6 +390,21 @@ pub type NetworkResult<T> = std::result::Result<T, NetworkError>;
 /// Type alias for Session Results
 pub type SessionResult<T> = std::result::Result<T, SessionError>;
 
+/// Type alias for File Transfer Results
+pub type FileTransferResult<T> = std::result::Result<T, FileTransferError>;
+
+/// Type alias for Terminal Results
+pub type TerminalResult<T> = std::result::Result<T, TerminalError>;
+
+/// Type alias for Port Forwarding Results
+pub type ForwardResult<T> = std::result::Result<T, ForwardError>;
+
+/// Type alias for Custom Channel Results
+pub type CustomChannelResult<T> = std::result::Result<T, CustomChannelError>;
+
+/// Type alias for Request/Response Results
+pub type RpcResult<T> = std::result::Result<T, RpcError>;
+
 impl From<bincode::Error> for RemoteDeskError {
     fn from(err: bincode::Error) -> Self {
         RemoteDeskError::Serialization(err.to_string())
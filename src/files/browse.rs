@@ -0,0 +1,525 @@
+//! Browsing the host's files
+//!
+//! A host can make a set of directories, its browse roots, available to
+//! clients. [`FileBrowser`] serves list and stat requests for them on the
+//! session's browse stream and sends this side's own requests to the peer.
+//! Files are downloaded with [`FileTransfers::fetch_remote_file`], which
+//! resolves the remote path through the same [`BrowseRoots`].
+//!
+//! Remote paths start with a root's name, so `/Documents/report.pdf` is
+//! `report.pdf` in the root named `Documents`. A peer can never reach a
+//! file outside the roots, whether through `..` or a symbolic link.
+//!
+//! [`FileTransfers::fetch_remote_file`]: crate::files::FileTransfers::fetch_remote_file
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use tokio::fs;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::error::{FileTransferError, FileTransferResult, RpcError};
+use crate::files::store::sanitize_file_name;
+use crate::session::rpc::RpcCalls;
+use crate::session::transport::{BrowseMessage, ChannelPair, RemoteEntry};
+
+/// How long to wait for the peer to answer a request
+pub const BROWSE_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Most entries returned for one directory
+///
+/// Keeps a listing well within the stream's message size limit.
+pub const MAX_LISTING_ENTRIES: usize = 4096;
+
+/// Returns the remote path of `name` inside the remote directory `dir`
+pub fn join_remote_path(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+/// Returns the remote path of the directory containing `path`
+///
+/// The parent of a root, and of `/` itself, is `/`.
+pub fn parent_remote_path(path: &str) -> String {
+    match path.trim_end_matches('/').rsplit_once('/') {
+        Some((parent, _)) if !parent.is_empty() => parent.to_string(),
+        _ => "/".to_string(),
+    }
+}
+
+/// Splits a remote path into checked components
+fn split_remote_path(path: &str) -> FileTransferResult<Vec<String>> {
+    path.split('/')
+        .filter(|part| !part.is_empty())
+        .map(sanitize_file_name)
+        .collect()
+}
+
+/// A directory the peer may browse
+#[derive(Debug)]
+struct BrowseRoot {
+    /// Name the root is shown under
+    name: String,
+    path: PathBuf,
+}
+
+/// Directories this side lets the peer browse and download from
+///
+/// Empty by default, in which case every request is refused.
+#[derive(Debug, Clone, Default)]
+pub struct BrowseRoots {
+    roots: Arc<Vec<BrowseRoot>>,
+}
+
+impl BrowseRoots {
+    /// Shares the given directories
+    ///
+    /// Each root is named after its last path component; clashing names
+    /// get a numeric suffix.
+    pub fn new<I: IntoIterator<Item = PathBuf>>(dirs: I) -> Self {
+        let mut roots: Vec<BrowseRoot> = Vec::new();
+
+        for path in dirs {
+            let base = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string().replace(['/', '\\'], "_"));
+            let mut name = base.clone();
+            let mut suffix = 1;
+            while roots.iter().any(|root| root.name == name) {
+                suffix += 1;
+                name = format!("{} ({})", base, suffix);
+            }
+            roots.push(BrowseRoot { name, path });
+        }
+
+        Self {
+            roots: Arc::new(roots),
+        }
+    }
+
+    /// Returns true if nothing is shared
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Returns the names the roots are shown under
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.roots.iter().map(|root| root.name.as_str())
+    }
+
+    /// Lists a remote directory, directories first
+    ///
+    /// # Errors
+    ///
+    /// Returns error if nothing is shared, or the path is invalid, outside
+    /// the roots or not a directory
+    pub async fn list(&self, path: &str) -> FileTransferResult<Vec<RemoteEntry>> {
+        let Some(dir) = self.resolve(path).await? else {
+            let mut entries = Vec::with_capacity(self.roots.len());
+            for root in self.roots.iter() {
+                entries.push(match fs::metadata(&root.path).await {
+                    Ok(metadata) => remote_entry(root.name.clone(), &metadata),
+                    Err(_) => RemoteEntry {
+                        name: root.name.clone(),
+                        is_dir: true,
+                        size: 0,
+                        modified: None,
+                    },
+                });
+            }
+            return Ok(entries);
+        };
+
+        if !fs::metadata(&dir).await?.is_dir() {
+            return Err(FileTransferError::NotAvailable(format!(
+                "{} is not a directory",
+                path
+            )));
+        }
+
+        let mut entries = Vec::new();
+        let mut read_dir = fs::read_dir(&dir).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            // Names that aren't valid UTF-8 couldn't be requested back
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            // Broken links are left out
+            let Ok(metadata) = fs::metadata(entry.path()).await else {
+                continue;
+            };
+            entries.push(remote_entry(name, &metadata));
+        }
+
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        entries.truncate(MAX_LISTING_ENTRIES);
+        Ok(entries)
+    }
+
+    /// Describes a remote file or directory
+    ///
+    /// # Errors
+    ///
+    /// Returns error if nothing is shared, or the path is invalid or
+    /// outside the roots
+    pub async fn stat(&self, path: &str) -> FileTransferResult<RemoteEntry> {
+        let Some(local) = self.resolve(path).await? else {
+            return Ok(RemoteEntry {
+                name: "/".to_string(),
+                is_dir: true,
+                size: 0,
+                modified: None,
+            });
+        };
+
+        let metadata = fs::metadata(&local).await?;
+        let name = split_remote_path(path)?.pop().unwrap_or_default();
+        Ok(remote_entry(name, &metadata))
+    }
+
+    /// Returns the local path of a remote file
+    ///
+    /// # Errors
+    ///
+    /// Returns error if nothing is shared, or the path is invalid, outside
+    /// the roots or not a file
+    pub async fn resolve_file(&self, path: &str) -> FileTransferResult<PathBuf> {
+        match self.resolve(path).await? {
+            Some(local) if fs::metadata(&local).await?.is_file() => Ok(local),
+            _ => Err(FileTransferError::NotAvailable(path.to_string())),
+        }
+    }
+
+    /// Returns the local path of a remote path, or None for `/`
+    async fn resolve(&self, path: &str) -> FileTransferResult<Option<PathBuf>> {
+        if self.is_empty() {
+            return Err(FileTransferError::BrowsingDisabled);
+        }
+
+        let components = split_remote_path(path)?;
+        let Some((root_name, rest)) = components.split_first() else {
+            return Ok(None);
+        };
+        let not_available = || FileTransferError::NotAvailable(path.to_string());
+
+        let root = self
+            .roots
+            .iter()
+            .find(|root| &root.name == root_name)
+            .ok_or_else(not_available)?;
+        let root_path = fs::canonicalize(&root.path)
+            .await
+            .map_err(|_| not_available())?;
+
+        let local = rest
+            .iter()
+            .fold(root_path.clone(), |local, part| local.join(part));
+        // Links may point anywhere, so check where the path really leads
+        let local = fs::canonicalize(&local).await.map_err(|_| not_available())?;
+        if !local.starts_with(&root_path) {
+            debug!("Refused path outside browse roots: {}", path);
+            return Err(not_available());
+        }
+
+        Ok(Some(local))
+    }
+}
+
+/// Describes a local file or directory for the peer
+fn remote_entry(name: String, metadata: &std::fs::Metadata) -> RemoteEntry {
+    RemoteEntry {
+        name,
+        is_dir: metadata.is_dir(),
+        size: if metadata.is_dir() { 0 } else { metadata.len() },
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_secs()),
+    }
+}
+
+struct Inner {
+    tx: mpsc::Sender<BrowseMessage>,
+    roots: BrowseRoots,
+    /// Requests waiting for the peer's response
+    calls: RpcCalls<BrowseMessage>,
+}
+
+/// File browsing service for a session
+pub struct FileBrowser {
+    inner: Arc<Inner>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl FileBrowser {
+    /// Starts serving the session's browse stream
+    ///
+    /// The peer may browse `roots`; pass empty roots to refuse every
+    /// request.
+    pub fn start(channel: ChannelPair<BrowseMessage>, roots: BrowseRoots) -> Self {
+        let ChannelPair { tx, mut rx } = channel;
+        let inner = Arc::new(Inner {
+            tx,
+            roots,
+            calls: RpcCalls::new().with_timeout(BROWSE_REQUEST_TIMEOUT),
+        });
+
+        let task_inner = Arc::clone(&inner);
+        let task = tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                handle_message(&task_inner, message).await;
+            }
+            debug!("File browsing channel closed");
+            task_inner.calls.close();
+        });
+
+        Self { inner, task }
+    }
+
+    /// Returns the directories the peer may browse
+    pub fn roots(&self) -> &BrowseRoots {
+        &self.inner.roots
+    }
+
+    /// Lists a directory on the peer
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the peer refuses or doesn't answer in time
+    pub async fn list(&self, path: &str) -> FileTransferResult<Vec<RemoteEntry>> {
+        let request = |request_id| BrowseMessage::List {
+            request_id,
+            path: path.to_string(),
+        };
+
+        match self.request(request).await? {
+            BrowseMessage::Entries { entries, .. } => Ok(entries),
+            other => Err(unexpected_response(other)),
+        }
+    }
+
+    /// Describes a file or directory on the peer
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the peer refuses or doesn't answer in time
+    pub async fn stat(&self, path: &str) -> FileTransferResult<RemoteEntry> {
+        let request = |request_id| BrowseMessage::Stat {
+            request_id,
+            path: path.to_string(),
+        };
+
+        match self.request(request).await? {
+            BrowseMessage::Entry { entry, .. } => Ok(entry),
+            other => Err(unexpected_response(other)),
+        }
+    }
+
+    /// Sends a request and waits for its response
+    async fn request(
+        &self,
+        request: impl FnOnce(u64) -> BrowseMessage,
+    ) -> FileTransferResult<BrowseMessage> {
+        let response = self
+            .inner
+            .calls
+            .call(&self.inner.tx, |request_id| request(request_id.into()))
+            .await
+            .map_err(|e| match e {
+                RpcError::Timeout => FileTransferError::Timeout,
+                RpcError::ChannelClosed => FileTransferError::ChannelClosed,
+                other => FileTransferError::Remote(other.to_string()),
+            })?;
+
+        match response {
+            BrowseMessage::Error { message, .. } => Err(FileTransferError::Remote(message)),
+            response => Ok(response),
+        }
+    }
+}
+
+impl Drop for FileBrowser {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Handles one message from the peer
+async fn handle_message(inner: &Arc<Inner>, message: BrowseMessage) {
+    match message {
+        BrowseMessage::List { request_id, path } => {
+            let inner = Arc::clone(inner);
+            tokio::spawn(async move {
+                let response = match inner.roots.list(&path).await {
+                    Ok(entries) => BrowseMessage::Entries {
+                        request_id,
+                        entries,
+                    },
+                    Err(e) => error_response(request_id, &path, e),
+                };
+                let _ = send(&inner, response).await;
+            });
+        }
+        BrowseMessage::Stat { request_id, path } => {
+            let inner = Arc::clone(inner);
+            tokio::spawn(async move {
+                let response = match inner.roots.stat(&path).await {
+                    Ok(entry) => BrowseMessage::Entry { request_id, entry },
+                    Err(e) => error_response(request_id, &path, e),
+                };
+                let _ = send(&inner, response).await;
+            });
+        }
+        BrowseMessage::Entries { request_id, .. }
+        | BrowseMessage::Entry { request_id, .. }
+        | BrowseMessage::Error { request_id, .. } => match u32::try_from(request_id) {
+            Ok(request_id) => {
+                inner.calls.resolve(request_id, Ok(message));
+            }
+            // This side never sends such IDs
+            Err(_) => debug!("Response to unknown browse request {}", request_id),
+        },
+    }
+}
+
+/// Builds the response for a request that failed
+fn error_response(request_id: u64, path: &str, error: FileTransferError) -> BrowseMessage {
+    let message = match error {
+        // Local I/O details stay on this side
+        FileTransferError::Io(_) => FileTransferError::NotAvailable(path.to_string()).to_string(),
+        other => other.to_string(),
+    };
+    BrowseMessage::Error {
+        request_id,
+        message,
+    }
+}
+
+fn unexpected_response(response: BrowseMessage) -> FileTransferError {
+    FileTransferError::Remote(format!("unexpected response: {:?}", response))
+}
+
+async fn send(inner: &Inner, message: BrowseMessage) -> FileTransferResult<()> {
+    inner
+        .tx
+        .send(message)
+        .await
+        .map_err(|_| FileTransferError::ChannelClosed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::transport::create_loopback_transport;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn shared_tree() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("photos")).unwrap();
+        std::fs::write(dir.path().join("photos").join("cat.jpg"), b"meow").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"remember the milk").unwrap();
+        dir
+    }
+
+    fn root_name(dir: &Path) -> String {
+        dir.file_name().unwrap().to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_remote_paths() {
+        assert_eq!(join_remote_path("/", "Documents"), "/Documents");
+        assert_eq!(join_remote_path("/Documents/", "a.txt"), "/Documents/a.txt");
+        assert_eq!(parent_remote_path("/Documents/a.txt"), "/Documents");
+        assert_eq!(parent_remote_path("/Documents"), "/");
+        assert_eq!(parent_remote_path("/"), "/");
+    }
+
+    #[test]
+    fn test_root_names_are_unique() {
+        let roots = BrowseRoots::new([
+            PathBuf::from("/home/alice/Documents"),
+            PathBuf::from("/mnt/backup/Documents"),
+        ]);
+        let names: Vec<&str> = roots.names().collect();
+        assert_eq!(names, vec!["Documents", "Documents (2)"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_and_resolve() {
+        let tree = shared_tree();
+        let name = root_name(tree.path());
+        let roots = BrowseRoots::new([tree.path().to_path_buf()]);
+
+        let top = roots.list("/").await.unwrap();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].name, name);
+        assert!(top[0].is_dir);
+
+        let entries = roots.list(&format!("/{}", name)).await.unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["photos", "notes.txt"]);
+        assert_eq!(entries[1].size, 17);
+
+        let path = format!("/{}/photos/cat.jpg", name);
+        let local = roots.resolve_file(&path).await.unwrap();
+        assert_eq!(std::fs::read(local).unwrap(), b"meow");
+        assert_eq!(roots.stat(&path).await.unwrap().name, "cat.jpg");
+
+        // Directories can't be downloaded
+        assert!(roots.resolve_file(&format!("/{}/photos", name)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_paths_outside_roots_refused() {
+        let tree = shared_tree();
+        let name = root_name(tree.path());
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret"), b"hunter2").unwrap();
+        let roots = BrowseRoots::new([tree.path().to_path_buf()]);
+
+        assert!(roots.list(&format!("/{}/..", name)).await.is_err());
+        assert!(roots.list("/elsewhere").await.is_err());
+        assert!(roots
+            .resolve_file(&format!("/{}/photos/../../secret", name))
+            .await
+            .is_err());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(outside.path(), tree.path().join("escape")).unwrap();
+            assert!(roots
+                .resolve_file(&format!("/{}/escape/secret", name))
+                .await
+                .is_err());
+        }
+
+        assert!(matches!(
+            BrowseRoots::default().list("/").await,
+            Err(FileTransferError::BrowsingDisabled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_browse_over_stream() {
+        let tree = shared_tree();
+        let name = root_name(tree.path());
+        let (host, client) = create_loopback_transport();
+        let _host = FileBrowser::start(host.browse, BrowseRoots::new([tree.path().to_path_buf()]));
+        let client = FileBrowser::start(client.browse, BrowseRoots::default());
+
+        let entries = client.list(&format!("/{}/photos", name)).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "cat.jpg");
+        assert_eq!(entries[0].size, 4);
+
+        let entry = client.stat(&format!("/{}/notes.txt", name)).await.unwrap();
+        assert!(!entry.is_dir);
+
+        let error = client.list(&format!("/{}/missing", name)).await.unwrap_err();
+        assert!(matches!(error, FileTransferError::Remote(_)));
+    }
+}
@@ -0,0 +1,14 @@
+//! File transfer module for RemoteDesk
+//!
+//! This module moves files between peers over a session's file stream:
+//! offers the receiver accepts or declines, chunked transfers checked
+//! against a SHA-256 digest, and resuming after a lost connection. The host
+//! can also let clients browse and download from chosen directories.
+
+pub mod browse;
+pub mod store;
+pub mod transfers;
+
+pub use browse::{BrowseRoots, FileBrowser};
+pub use store::{sanitize_file_name, sha256_file, IncomingFile, FILE_CHUNK_SIZE};
+pub use transfers::{FileTransferEvent, FileTransfers, SharedFiles, TransferInfo, TransferStatus};
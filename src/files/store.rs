@@ -0,0 +1,385 @@
+//! Reading and writing transferred files on disk
+//!
+//! Incoming files are written to a `.part` file next to their destination
+//! and only renamed into place once their size and SHA-256 digest match
+//! what the sender announced. The partial file is named after the digest, so
+//! an interrupted transfer of the same file can pick up where it stopped.
+
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::warn;
+
+use crate::error::{FileTransferError, FileTransferResult};
+
+/// Size of each chunk sent over the file stream
+pub const FILE_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Extension appended to files while they are being received
+const PART_EXTENSION: &str = "part";
+
+/// Highest suffix tried when picking a name that doesn't exist yet
+const MAX_NAME_SUFFIX: u32 = 9999;
+
+/// Digest bytes included in partial file names
+const PART_DIGEST_BYTES: usize = 8;
+
+/// Returns the file name a peer sent, if it is safe to create
+///
+/// Only a single normal path component is accepted, so a peer can't write
+/// outside the download directory.
+pub fn sanitize_file_name(name: &str) -> FileTransferResult<String> {
+    let invalid = || FileTransferError::InvalidFileName(name.to_string());
+
+    if name
+        .chars()
+        .any(|c| c.is_control() || c == '/' || c == '\\')
+    {
+        return Err(invalid());
+    }
+
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(part)), None) => {
+            part.to_str().map(str::to_string).ok_or_else(invalid)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Returns a path in `dir` for `name` that doesn't exist yet
+///
+/// Existing files are never overwritten; `report.pdf` becomes
+/// `report (1).pdf` and so on.
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let path = Path::new(name);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path.extension().map(|e| e.to_string_lossy().into_owned());
+
+    (1..=MAX_NAME_SUFFIX)
+        .map(|n| match &extension {
+            Some(ext) => dir.join(format!("{} ({}).{}", stem, n, ext)),
+            None => dir.join(format!("{} ({})", stem, n)),
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(candidate)
+}
+
+/// Returns the partial file a file is received into
+///
+/// `name` must already be sanitized.
+pub fn part_path(dir: &Path, name: &str, sha256: &[u8; 32]) -> PathBuf {
+    let digest: String = sha256[..PART_DIGEST_BYTES]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    dir.join(format!("{}.{}.{}", name, digest, PART_EXTENSION))
+}
+
+/// Computes the SHA-256 digest of a file
+pub async fn sha256_file(path: &Path) -> FileTransferResult<[u8; 32]> {
+    let mut file = File::open(path).await?;
+    let mut hasher = Sha256::new();
+    hash_reader(&mut file, &mut hasher).await?;
+    Ok(hasher.finalize().into())
+}
+
+/// Feeds everything left in `file` to `hasher`
+async fn hash_reader(file: &mut File, hasher: &mut Sha256) -> FileTransferResult<()> {
+    let mut buffer = vec![0u8; FILE_CHUNK_SIZE];
+
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+/// A file being received
+#[derive(Debug)]
+pub struct IncomingFile {
+    /// Sanitized file name
+    name: String,
+    /// Directory the file is saved in
+    dir: PathBuf,
+    /// Temporary path the data is written to
+    part_path: PathBuf,
+    file: File,
+    hasher: Sha256,
+    written: u64,
+    size: u64,
+    sha256: [u8; 32],
+}
+
+impl IncomingFile {
+    /// Starts receiving a file into `dir`, discarding any partial data
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the name is unsafe or the file can't be created
+    pub async fn create(
+        dir: &Path,
+        name: &str,
+        size: u64,
+        sha256: [u8; 32],
+    ) -> FileTransferResult<Self> {
+        let name = sanitize_file_name(name)?;
+        fs::create_dir_all(dir).await?;
+
+        let part_path = part_path(dir, &name, &sha256);
+        let file = File::create(&part_path).await?;
+
+        Ok(Self {
+            name,
+            dir: dir.to_path_buf(),
+            part_path,
+            file,
+            hasher: Sha256::new(),
+            written: 0,
+            size,
+            sha256,
+        })
+    }
+
+    /// Continues receiving a file into `dir`
+    ///
+    /// Data left by an interrupted transfer of the same file is kept, and
+    /// [`written`](Self::written) tells the sender where to continue.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the name is unsafe or the file can't be opened
+    pub async fn resume(
+        dir: &Path,
+        name: &str,
+        size: u64,
+        sha256: [u8; 32],
+    ) -> FileTransferResult<Self> {
+        let name = sanitize_file_name(name)?;
+        fs::create_dir_all(dir).await?;
+
+        let part_path = part_path(dir, &name, &sha256);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&part_path)
+            .await?;
+
+        // More data than the file holds can't be a prefix of it
+        if file.metadata().await?.len() > size {
+            file.set_len(0).await?;
+        }
+
+        let mut hasher = Sha256::new();
+        hash_reader(&mut file, &mut hasher).await?;
+        let written = file.metadata().await?.len();
+
+        Ok(Self {
+            name,
+            dir: dir.to_path_buf(),
+            part_path,
+            file,
+            hasher,
+            written,
+            size,
+            sha256,
+        })
+    }
+
+    /// Returns the sanitized file name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the announced size in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the number of bytes received so far
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Returns the partial file the data is written to
+    pub fn part_path(&self) -> &Path {
+        &self.part_path
+    }
+
+    /// Appends a chunk and returns the total bytes received
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the data goes past the announced size or can't be
+    /// written
+    pub async fn write_chunk(&mut self, data: &[u8]) -> FileTransferResult<u64> {
+        let written = self.written + data.len() as u64;
+        if written > self.size {
+            return Err(FileTransferError::SizeMismatch {
+                name: self.name.clone(),
+                expected: self.size,
+                actual: written,
+            });
+        }
+
+        self.file.write_all(data).await?;
+        self.hasher.update(data);
+        self.written = written;
+
+        Ok(written)
+    }
+
+    /// Verifies the file and moves it into place, returning its final path
+    ///
+    /// The partial file is removed if verification fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the size or digest doesn't match
+    pub async fn finish(mut self) -> FileTransferResult<PathBuf> {
+        self.file.flush().await?;
+
+        if self.written != self.size {
+            let error = FileTransferError::SizeMismatch {
+                name: self.name.clone(),
+                expected: self.size,
+                actual: self.written,
+            };
+            self.discard().await;
+            return Err(error);
+        }
+
+        let digest: [u8; 32] = self.hasher.clone().finalize().into();
+        if digest != self.sha256 {
+            let error = FileTransferError::ChecksumMismatch(self.name.clone());
+            self.discard().await;
+            return Err(error);
+        }
+
+        let path = unique_path(&self.dir, &self.name);
+        fs::rename(&self.part_path, &path).await?;
+
+        Ok(path)
+    }
+
+    /// Stops receiving but keeps the partial file for a later resume
+    pub async fn keep(mut self) {
+        if let Err(e) = self.file.flush().await {
+            warn!("Failed to flush {}: {}", self.part_path.display(), e);
+        }
+    }
+
+    /// Stops receiving and removes the partial file
+    pub async fn discard(self) {
+        drop(self.file);
+        // The file may already be gone
+        let _ = fs::remove_file(&self.part_path).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn digest(data: &[u8]) -> [u8; 32] {
+        Sha256::digest(data).into()
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("report.pdf").unwrap(), "report.pdf");
+        assert!(sanitize_file_name("").is_err());
+        assert!(sanitize_file_name("..").is_err());
+        assert!(sanitize_file_name("../etc/passwd").is_err());
+        assert!(sanitize_file_name("dir/file").is_err());
+        assert!(sanitize_file_name("C:\\Windows\\evil.dll").is_err());
+        assert!(sanitize_file_name("/etc/passwd").is_err());
+        assert!(sanitize_file_name("bad\nname").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_incoming_file_verified_and_renamed() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("log.txt"), b"existing").unwrap();
+
+        let data = b"hello world".repeat(100);
+        let mut incoming =
+            IncomingFile::create(dir.path(), "log.txt", data.len() as u64, digest(&data))
+                .await
+                .unwrap();
+        for chunk in data.chunks(300) {
+            incoming.write_chunk(chunk).await.unwrap();
+        }
+
+        let path = incoming.finish().await.unwrap();
+        assert_eq!(path, dir.path().join("log (1).txt"));
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        assert_eq!(sha256_file(&path).await.unwrap(), digest(&data));
+
+        // The existing file is untouched and no partial file is left
+        assert_eq!(
+            std::fs::read(dir.path().join("log.txt")).unwrap(),
+            b"existing"
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_incoming_file_rejects_bad_data() {
+        let dir = TempDir::new().unwrap();
+
+        let mut incoming = IncomingFile::create(dir.path(), "a.bin", 4, digest(b"abcd"))
+            .await
+            .unwrap();
+        assert!(incoming.write_chunk(b"abcde").await.is_err());
+        incoming.write_chunk(b"abce").await.unwrap();
+        assert!(matches!(
+            incoming.finish().await,
+            Err(FileTransferError::ChecksumMismatch(_))
+        ));
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_incoming_file_resumes_partial_data() {
+        let dir = TempDir::new().unwrap();
+        let data = b"0123456789".repeat(50);
+        let size = data.len() as u64;
+
+        let mut first = IncomingFile::create(dir.path(), "setup.exe", size, digest(&data))
+            .await
+            .unwrap();
+        first.write_chunk(&data[..120]).await.unwrap();
+        first.keep().await;
+
+        let mut resumed = IncomingFile::resume(dir.path(), "setup.exe", size, digest(&data))
+            .await
+            .unwrap();
+        assert_eq!(resumed.written(), 120);
+        resumed.write_chunk(&data[120..]).await.unwrap();
+
+        let path = resumed.finish().await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+
+        // A different file with the same name starts from scratch
+        let other = b"other".to_vec();
+        let fresh = IncomingFile::resume(dir.path(), "setup.exe", 5, digest(&other))
+            .await
+            .unwrap();
+        assert_eq!(fresh.written(), 0);
+    }
+}
@@ -0,0 +1,1429 @@
+//! File transfer service for one session
+//!
+//! [`FileTransfers`] owns the session's file stream and runs several
+//! transfers over it at once. Files reach the peer in two ways:
+//!
+//! - The peer fetches a file from the local clipboard's file list.
+//! - The local side offers a file with [`FileTransfers::send_file`], and the
+//!   peer accepts or declines the offer.
+//! - The peer fetches a file from the local browse roots.
+//!
+//! Offers can also be accepted automatically with
+//! [`FileTransfers::set_auto_accept`], which hosts use for a configured drop
+//! directory. Hosts whose peer may not transfer files refuse every offer and
+//! fetch with [`FileTransfers::set_peer_allowed`].
+//!
+//! Every file is checked against its SHA-256 digest. A download cut off by a
+//! lost connection keeps its partial data; accepting a later offer of the
+//! same file continues where it stopped.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex};
+use tracing::{debug, info, warn};
+
+use crate::clipboard::TransferDirection;
+use crate::error::{FileTransferError, FileTransferResult};
+use crate::files::browse::BrowseRoots;
+use crate::files::store::{
+    part_path, sanitize_file_name, sha256_file, IncomingFile, FILE_CHUNK_SIZE,
+};
+use crate::network::ConnectionRole;
+use crate::session::transport::{ChannelPair, FileMessage};
+
+/// Capacity of the file transfer event channel
+pub const FILE_EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Finished transfers kept for [`FileTransfers::list`]
+const MAX_FINISHED_TRANSFERS: usize = 64;
+
+/// Reason sent when an offer is declined
+const DECLINED_REASON: &str = "declined";
+
+/// Reason sent when a running transfer is cancelled
+const CANCELLED_REASON: &str = "cancelled";
+
+/// Progress and outcome of file transfers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileTransferEvent {
+    /// The peer offered a file; accept or cancel it
+    Offered {
+        /// Transfer ID
+        transfer_id: u64,
+        /// File name
+        name: String,
+        /// File size in bytes
+        size: u64,
+    },
+    /// Part of a file was sent or received
+    Progress {
+        /// Transfer ID
+        transfer_id: u64,
+        /// Transfer direction
+        direction: TransferDirection,
+        /// File name
+        name: String,
+        /// Bytes transferred so far, including resumed data
+        transferred: u64,
+        /// File size in bytes
+        total: u64,
+    },
+    /// A file was sent, or received and verified
+    Completed {
+        /// Transfer ID
+        transfer_id: u64,
+        /// Transfer direction
+        direction: TransferDirection,
+        /// Local path of the file
+        path: PathBuf,
+    },
+    /// A transfer failed, was cancelled or was interrupted
+    Failed {
+        /// Transfer ID
+        transfer_id: u64,
+        /// Error description
+        message: String,
+    },
+}
+
+/// State of a transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferStatus {
+    /// Waiting for the receiver to accept the offer
+    Offered,
+    /// Data is being transferred
+    Active,
+    /// The file was transferred and verified
+    Completed,
+    /// The connection was lost; sending the file again resumes it
+    Interrupted,
+    /// The transfer failed or was cancelled
+    Failed(String),
+}
+
+impl TransferStatus {
+    /// Returns true once the transfer has ended
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            TransferStatus::Completed | TransferStatus::Interrupted | TransferStatus::Failed(_)
+        )
+    }
+}
+
+impl fmt::Display for TransferStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferStatus::Offered => write!(f, "offered"),
+            TransferStatus::Active => write!(f, "active"),
+            TransferStatus::Completed => write!(f, "completed"),
+            TransferStatus::Interrupted => write!(f, "interrupted"),
+            TransferStatus::Failed(reason) => write!(f, "failed: {}", reason),
+        }
+    }
+}
+
+/// Snapshot of a transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferInfo {
+    /// Transfer ID
+    pub transfer_id: u64,
+    /// Transfer direction
+    pub direction: TransferDirection,
+    /// File name
+    pub name: String,
+    /// File size in bytes
+    pub size: u64,
+    /// Bytes transferred so far
+    pub transferred: u64,
+    /// Current state
+    pub status: TransferStatus,
+}
+
+/// Files the peer may fetch
+///
+/// Only the latest clipboard file list and the files under the browse
+/// roots can be fetched, so a peer can never read a file the user didn't
+/// copy or share.
+#[derive(Debug, Clone, Default)]
+pub struct SharedFiles {
+    inner: Arc<std::sync::Mutex<Option<SharedItem>>>,
+    /// Directories the peer may browse and download from
+    roots: BrowseRoots,
+}
+
+/// Files of one clipboard item
+#[derive(Debug)]
+struct SharedItem {
+    sequence: u64,
+    paths: Vec<PathBuf>,
+}
+
+impl SharedFiles {
+    /// Also lets the peer download files under the given directories
+    pub fn with_roots(mut self, roots: BrowseRoots) -> Self {
+        self.roots = roots;
+        self
+    }
+
+    /// Returns the directories the peer may download from
+    pub fn roots(&self) -> &BrowseRoots {
+        &self.roots
+    }
+
+    /// Shares the files of a clipboard item, replacing earlier ones
+    pub fn share(&self, sequence: u64, paths: Vec<PathBuf>) {
+        *self.inner.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(SharedItem { sequence, paths });
+    }
+
+    /// Stops sharing files
+    pub fn clear(&self) {
+        *self.inner.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Returns the path of a shared file
+    pub fn get(&self, sequence: u64, index: u32) -> Option<PathBuf> {
+        match &*self.inner.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(item) if item.sequence == sequence => item.paths.get(index as usize).cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// What this side does for a transfer
+enum TransferKind {
+    /// Sending a local file
+    Upload { path: PathBuf },
+    /// Offer from the peer waiting for an answer
+    Offer { sha256: [u8; 32] },
+    /// Receiving a file
+    Download {
+        dir: PathBuf,
+        file: Option<Box<IncomingFile>>,
+        /// Caller waiting for a clipboard file
+        done: Option<oneshot::Sender<FileTransferResult<PathBuf>>>,
+    },
+    /// Nothing left to do
+    Finished,
+}
+
+struct Transfer {
+    info: TransferInfo,
+    kind: TransferKind,
+}
+
+impl Transfer {
+    fn new(
+        transfer_id: u64,
+        direction: TransferDirection,
+        name: String,
+        size: u64,
+        status: TransferStatus,
+        kind: TransferKind,
+    ) -> Self {
+        Self {
+            info: TransferInfo {
+                transfer_id,
+                direction,
+                name,
+                size,
+                transferred: 0,
+                status,
+            },
+            kind,
+        }
+    }
+}
+
+struct Inner {
+    tx: mpsc::Sender<FileMessage>,
+    shared: SharedFiles,
+    /// Directory offered files are saved in
+    download_dir: PathBuf,
+    /// Offers are accepted as soon as they arrive
+    auto_accept: AtomicBool,
+    /// The peer may offer and fetch files
+    peer_allowed: AtomicBool,
+    transfers: Mutex<HashMap<u64, Transfer>>,
+    events: broadcast::Sender<FileTransferEvent>,
+}
+
+/// File transfer service for a session
+pub struct FileTransfers {
+    inner: Arc<Inner>,
+    next_id: AtomicU64,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl FileTransfers {
+    /// Starts serving the session's file stream
+    ///
+    /// Offered files are saved in `download_dir`, and `shared` holds the
+    /// clipboard files the peer may fetch.
+    pub fn start(
+        channel: ChannelPair<FileMessage>,
+        role: ConnectionRole,
+        download_dir: PathBuf,
+        shared: SharedFiles,
+    ) -> Self {
+        let ChannelPair { tx, mut rx } = channel;
+        let (events, _) = broadcast::channel(FILE_EVENT_CHANNEL_CAPACITY);
+
+        let inner = Arc::new(Inner {
+            tx,
+            shared,
+            download_dir,
+            auto_accept: AtomicBool::new(false),
+            peer_allowed: AtomicBool::new(true),
+            transfers: Mutex::new(HashMap::new()),
+            events,
+        });
+
+        let task_inner = Arc::clone(&inner);
+        let task = tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                handle_message(&task_inner, message).await;
+            }
+            debug!("File transfer channel closed");
+            interrupt_all(&task_inner).await;
+        });
+
+        let first_id = match role {
+            ConnectionRole::Host => 1,
+            ConnectionRole::Client => 2,
+        };
+
+        Self {
+            inner,
+            next_id: AtomicU64::new(first_id),
+            task,
+        }
+    }
+
+    /// Returns the directory offered files are saved in
+    pub fn download_dir(&self) -> &Path {
+        &self.inner.download_dir
+    }
+
+    /// Sets whether offers are accepted without asking
+    ///
+    /// Accepted files are saved in the download directory, as with
+    /// [`accept`](Self::accept). Off by default.
+    pub fn set_auto_accept(&self, enabled: bool) {
+        self.inner.auto_accept.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Returns true if offers are accepted without asking
+    pub fn auto_accept(&self) -> bool {
+        self.inner.auto_accept.load(Ordering::SeqCst)
+    }
+
+    /// Sets whether the peer may offer and fetch files
+    ///
+    /// When not allowed, every offer and fetch from the peer is cancelled.
+    /// Files sent from this side are unaffected. Allowed by default.
+    pub fn set_peer_allowed(&self, allowed: bool) {
+        self.inner.peer_allowed.store(allowed, Ordering::SeqCst);
+    }
+
+    /// Subscribes to transfer progress
+    pub fn subscribe(&self) -> broadcast::Receiver<FileTransferEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// Returns current and recently finished transfers, oldest first
+    pub async fn list(&self) -> Vec<TransferInfo> {
+        let mut transfers: Vec<TransferInfo> = self
+            .inner
+            .transfers
+            .lock()
+            .await
+            .values()
+            .map(|t| t.info.clone())
+            .collect();
+        transfers.sort_by_key(|t| t.transfer_id);
+        transfers
+    }
+
+    /// Offers a local file to the peer and returns the transfer ID
+    ///
+    /// The file is sent once the peer accepts. If an earlier transfer of
+    /// the same file was interrupted, only the missing part is sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the path isn't a readable file or the stream is
+    /// closed
+    pub async fn send_file(&self, path: &Path) -> FileTransferResult<u64> {
+        let metadata = tokio::fs::metadata(path).await?;
+        if !metadata.is_file() {
+            return Err(FileTransferError::NotAvailable(path.display().to_string()));
+        }
+
+        let name = file_name(path)?;
+        let size = metadata.len();
+        let sha256 = sha256_file(path).await?;
+        let transfer_id = self.next_transfer_id();
+
+        self.inner.transfers.lock().await.insert(
+            transfer_id,
+            Transfer::new(
+                transfer_id,
+                TransferDirection::Outgoing,
+                name.clone(),
+                size,
+                TransferStatus::Offered,
+                TransferKind::Upload {
+                    path: path.to_path_buf(),
+                },
+            ),
+        );
+
+        info!("Offering {} ({} bytes)", name, size);
+        let offer = FileMessage::Offer {
+            transfer_id,
+            name,
+            size,
+            sha256,
+        };
+        if let Err(e) = send(&self.inner, offer).await {
+            self.inner.transfers.lock().await.remove(&transfer_id);
+            return Err(e);
+        }
+
+        Ok(transfer_id)
+    }
+
+    /// Accepts a file the peer offered
+    ///
+    /// # Errors
+    ///
+    /// Returns error if there is no such offer, the file is already being
+    /// received or can't be created
+    pub async fn accept(&self, transfer_id: u64) -> FileTransferResult<()> {
+        accept_offer(&self.inner, transfer_id).await
+    }
+
+    /// Declines an offer or cancels a transfer in progress
+    ///
+    /// # Errors
+    ///
+    /// Returns error if there is no such transfer or it has already ended
+    pub async fn cancel(&self, transfer_id: u64) -> FileTransferResult<()> {
+        let reason = match self.inner.transfers.lock().await.get(&transfer_id) {
+            Some(transfer) if !transfer.info.status.is_finished() => match transfer.kind {
+                TransferKind::Offer { .. } => DECLINED_REASON,
+                _ => CANCELLED_REASON,
+            },
+            _ => return Err(FileTransferError::UnknownTransfer(transfer_id)),
+        };
+
+        end_transfer(
+            &self.inner,
+            transfer_id,
+            FileTransferError::Cancelled(reason.to_string()),
+            false,
+        )
+        .await;
+
+        send(
+            &self.inner,
+            FileMessage::Cancel {
+                transfer_id,
+                reason: reason.to_string(),
+            },
+        )
+        .await
+    }
+
+    /// Downloads a file from the peer's clipboard file list into `dir`
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the peer no longer shares the file, the transfer is
+    /// cancelled or the data fails verification
+    pub async fn fetch_clipboard_file(
+        &self,
+        sequence: u64,
+        index: u32,
+        dir: &Path,
+    ) -> FileTransferResult<PathBuf> {
+        self.fetch(dir, |transfer_id| FileMessage::Request {
+            transfer_id,
+            sequence,
+            index,
+        })
+        .await
+    }
+
+    /// Downloads a file from the peer's browse roots into `dir`
+    ///
+    /// `path` is a remote path as listed by
+    /// [`FileBrowser`](crate::files::FileBrowser).
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the peer doesn't share the file, the transfer is
+    /// cancelled or the data fails verification
+    pub async fn fetch_remote_file(&self, path: &str, dir: &Path) -> FileTransferResult<PathBuf> {
+        self.fetch(dir, |transfer_id| FileMessage::Fetch {
+            transfer_id,
+            path: path.to_string(),
+        })
+        .await
+    }
+
+    /// Sends the request built by `request` and receives the file into `dir`
+    async fn fetch(
+        &self,
+        dir: &Path,
+        request: impl FnOnce(u64) -> FileMessage,
+    ) -> FileTransferResult<PathBuf> {
+        let transfer_id = self.next_transfer_id();
+        let (done_tx, done_rx) = oneshot::channel();
+
+        // Name and size arrive with the peer's Start message
+        self.inner.transfers.lock().await.insert(
+            transfer_id,
+            Transfer::new(
+                transfer_id,
+                TransferDirection::Incoming,
+                String::new(),
+                0,
+                TransferStatus::Active,
+                TransferKind::Download {
+                    dir: dir.to_path_buf(),
+                    file: None,
+                    done: Some(done_tx),
+                },
+            ),
+        );
+
+        if let Err(e) = send(&self.inner, request(transfer_id)).await {
+            self.inner.transfers.lock().await.remove(&transfer_id);
+            return Err(e);
+        }
+
+        done_rx
+            .await
+            .unwrap_or(Err(FileTransferError::ChannelClosed))
+    }
+
+    fn next_transfer_id(&self) -> u64 {
+        // Host and client IDs differ in parity
+        self.next_id.fetch_add(2, Ordering::SeqCst)
+    }
+}
+
+impl Drop for FileTransfers {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Handles one message from the peer
+async fn handle_message(inner: &Arc<Inner>, message: FileMessage) {
+    if !inner.peer_allowed.load(Ordering::SeqCst) {
+        if let FileMessage::Request { transfer_id, .. }
+        | FileMessage::Fetch { transfer_id, .. }
+        | FileMessage::Offer { transfer_id, .. } = message
+        {
+            debug!("Refused file transfer {}: not permitted", transfer_id);
+            let _ = send(
+                inner,
+                FileMessage::Cancel {
+                    transfer_id,
+                    reason: "file transfer not permitted".to_string(),
+                },
+            )
+            .await;
+            return;
+        }
+    }
+
+    match message {
+        FileMessage::Request {
+            transfer_id,
+            sequence,
+            index,
+        } => {
+            let path = inner.shared.get(sequence, index).ok_or_else(|| {
+                FileTransferError::NotAvailable(format!(
+                    "clipboard item {} file {}",
+                    sequence, index
+                ))
+            });
+            serve_request(inner, transfer_id, path).await;
+        }
+        FileMessage::Fetch { transfer_id, path } => {
+            let path = inner.shared.roots().resolve_file(&path).await;
+            serve_request(inner, transfer_id, path).await;
+        }
+        FileMessage::Offer {
+            transfer_id,
+            name,
+            size,
+            sha256,
+        } => {
+            let mut transfers = inner.transfers.lock().await;
+            let name = match sanitize_file_name(&name) {
+                Ok(name) if !transfers.contains_key(&transfer_id) => name,
+                result => {
+                    drop(transfers);
+                    let reason = match result {
+                        Err(e) => e.to_string(),
+                        Ok(_) => format!("duplicate transfer ID {}", transfer_id),
+                    };
+                    warn!("Refused file offer: {}", reason);
+                    let _ = send(
+                        inner,
+                        FileMessage::Cancel {
+                            transfer_id,
+                            reason,
+                        },
+                    )
+                    .await;
+                    return;
+                }
+            };
+
+            info!("Peer offers {} ({} bytes)", name, size);
+            transfers.insert(
+                transfer_id,
+                Transfer::new(
+                    transfer_id,
+                    TransferDirection::Incoming,
+                    name.clone(),
+                    size,
+                    TransferStatus::Offered,
+                    TransferKind::Offer { sha256 },
+                ),
+            );
+            drop(transfers);
+
+            let _ = inner.events.send(FileTransferEvent::Offered {
+                transfer_id,
+                name,
+                size,
+            });
+
+            if inner.auto_accept.load(Ordering::SeqCst) {
+                if let Err(e) = accept_offer(inner, transfer_id).await {
+                    end_transfer(inner, transfer_id, e, true).await;
+                }
+            }
+        }
+        FileMessage::Accept {
+            transfer_id,
+            offset,
+        } => {
+            let mut transfers = inner.transfers.lock().await;
+            let Some(transfer) = transfers
+                .get_mut(&transfer_id)
+                .filter(|t| t.info.status == TransferStatus::Offered)
+            else {
+                return;
+            };
+            let TransferKind::Upload { path } = &transfer.kind else {
+                return;
+            };
+            let path = path.clone();
+
+            if offset > transfer.info.size {
+                let error = FileTransferError::SizeMismatch {
+                    name: transfer.info.name.clone(),
+                    expected: transfer.info.size,
+                    actual: offset,
+                };
+                drop(transfers);
+                end_transfer(inner, transfer_id, error, true).await;
+                return;
+            }
+
+            if offset > 0 {
+                info!(
+                    "Resuming {} at {} of {} bytes",
+                    transfer.info.name, offset, transfer.info.size
+                );
+            }
+            transfer.info.status = TransferStatus::Active;
+            transfer.info.transferred = offset;
+            drop(transfers);
+
+            let inner = Arc::clone(inner);
+            tokio::spawn(async move {
+                if let Err(e) = serve_file(&inner, transfer_id, &path, offset).await {
+                    warn!("Failed to send {}: {}", path.display(), e);
+                    end_transfer(&inner, transfer_id, e, true).await;
+                }
+            });
+        }
+        FileMessage::Start {
+            transfer_id,
+            name,
+            size,
+            sha256,
+        } => {
+            let mut transfers = inner.transfers.lock().await;
+            let dir = match transfers.get(&transfer_id).map(|t| &t.kind) {
+                Some(TransferKind::Download {
+                    dir, file: None, ..
+                }) => dir.clone(),
+                _ => return,
+            };
+
+            let busy = sanitize_file_name(&name)
+                .map(|name| is_receiving_into(&transfers, &part_path(&dir, &name, &sha256)))
+                .unwrap_or(false);
+            let result = if busy {
+                Err(FileTransferError::AlreadyReceiving(name))
+            } else {
+                IncomingFile::create(&dir, &name, size, sha256).await
+            };
+
+            match result {
+                Ok(file) => {
+                    info!("Receiving {} ({} bytes)", file.name(), size);
+                    if let Some(transfer) = transfers.get_mut(&transfer_id) {
+                        transfer.info.name = file.name().to_string();
+                        transfer.info.size = size;
+                        if let TransferKind::Download { file: slot, .. } = &mut transfer.kind {
+                            *slot = Some(Box::new(file));
+                        }
+                    }
+                }
+                Err(e) => {
+                    drop(transfers);
+                    end_transfer(inner, transfer_id, e, true).await;
+                }
+            }
+        }
+        FileMessage::Chunk { transfer_id, data } => {
+            let mut transfers = inner.transfers.lock().await;
+            let Some(transfer) = transfers.get_mut(&transfer_id) else {
+                return;
+            };
+            let TransferKind::Download {
+                file: Some(file), ..
+            } = &mut transfer.kind
+            else {
+                return;
+            };
+
+            match file.write_chunk(&data).await {
+                Ok(transferred) => {
+                    transfer.info.transferred = transferred;
+                    let _ = inner.events.send(FileTransferEvent::Progress {
+                        transfer_id,
+                        direction: TransferDirection::Incoming,
+                        name: file.name().to_string(),
+                        transferred,
+                        total: file.size(),
+                    });
+                }
+                Err(e) => {
+                    drop(transfers);
+                    end_transfer(inner, transfer_id, e, true).await;
+                }
+            }
+        }
+        FileMessage::End { transfer_id } => {
+            let mut transfers = inner.transfers.lock().await;
+            let Some(transfer) = transfers.get_mut(&transfer_id) else {
+                return;
+            };
+            if !matches!(transfer.kind, TransferKind::Download { .. }) {
+                return;
+            }
+            let TransferKind::Download { file, done, .. } =
+                std::mem::replace(&mut transfer.kind, TransferKind::Finished)
+            else {
+                return;
+            };
+
+            let result = match file {
+                Some(file) => file.finish().await,
+                None => Err(FileTransferError::NotAvailable(
+                    "file ended before it started".to_string(),
+                )),
+            };
+
+            match &result {
+                Ok(path) => {
+                    info!("Received {}", path.display());
+                    transfer.info.status = TransferStatus::Completed;
+                    let _ = inner.events.send(FileTransferEvent::Completed {
+                        transfer_id,
+                        direction: TransferDirection::Incoming,
+                        path: path.clone(),
+                    });
+                }
+                Err(e) => {
+                    warn!("File transfer {} failed: {}", transfer_id, e);
+                    transfer.info.status = TransferStatus::Failed(e.to_string());
+                    let _ = inner.events.send(FileTransferEvent::Failed {
+                        transfer_id,
+                        message: e.to_string(),
+                    });
+                }
+            }
+            prune(&mut transfers);
+            drop(transfers);
+
+            if let Some(done) = done {
+                let _ = done.send(result);
+            }
+        }
+        FileMessage::Cancel {
+            transfer_id,
+            reason,
+        } => {
+            end_transfer(
+                inner,
+                transfer_id,
+                FileTransferError::Cancelled(reason),
+                false,
+            )
+            .await;
+        }
+    }
+}
+
+/// Starts sending a file the peer requested, or refuses the request
+async fn serve_request(inner: &Arc<Inner>, transfer_id: u64, path: FileTransferResult<PathBuf>) {
+    let mut transfers = inner.transfers.lock().await;
+    let path = match path {
+        Ok(path) if !transfers.contains_key(&transfer_id) => path,
+        result => {
+            drop(transfers);
+            let reason = match result {
+                Err(e) => e.to_string(),
+                Ok(_) => format!("duplicate transfer ID {}", transfer_id),
+            };
+            debug!("Refused file request: {}", reason);
+            let _ = send(
+                inner,
+                FileMessage::Cancel {
+                    transfer_id,
+                    reason,
+                },
+            )
+            .await;
+            return;
+        }
+    };
+
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    transfers.insert(
+        transfer_id,
+        Transfer::new(
+            transfer_id,
+            TransferDirection::Outgoing,
+            name,
+            0,
+            TransferStatus::Active,
+            TransferKind::Upload { path: path.clone() },
+        ),
+    );
+    drop(transfers);
+
+    let inner = Arc::clone(inner);
+    tokio::spawn(async move {
+        if let Err(e) = send_requested_file(&inner, transfer_id, &path).await {
+            warn!("Failed to send {}: {}", path.display(), e);
+            end_transfer(&inner, transfer_id, e, true).await;
+        }
+    });
+}
+
+/// Announces and sends a file the peer requested
+async fn send_requested_file(
+    inner: &Inner,
+    transfer_id: u64,
+    path: &Path,
+) -> FileTransferResult<()> {
+    let name = file_name(path)?;
+    let size = tokio::fs::metadata(path).await?.len();
+    let sha256 = sha256_file(path).await?;
+
+    if let Some(transfer) = inner.transfers.lock().await.get_mut(&transfer_id) {
+        transfer.info.size = size;
+    }
+
+    info!("Sending {} ({} bytes)", name, size);
+    send(
+        inner,
+        FileMessage::Start {
+            transfer_id,
+            name,
+            size,
+            sha256,
+        },
+    )
+    .await?;
+
+    serve_file(inner, transfer_id, path, 0).await
+}
+
+/// Sends a file from `offset` on, then marks the transfer completed
+///
+/// Stops quietly once the transfer is no longer active, for example after
+/// the peer cancelled it.
+async fn serve_file(
+    inner: &Inner,
+    transfer_id: u64,
+    path: &Path,
+    offset: u64,
+) -> FileTransferResult<()> {
+    let mut file = File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut buffer = vec![0u8; FILE_CHUNK_SIZE];
+    let mut transferred = offset;
+
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        transferred += read as u64;
+
+        {
+            let mut transfers = inner.transfers.lock().await;
+            let Some(transfer) = transfers
+                .get_mut(&transfer_id)
+                .filter(|t| t.info.status == TransferStatus::Active)
+            else {
+                debug!("File transfer {} stopped", transfer_id);
+                return Ok(());
+            };
+
+            transfer.info.transferred = transferred;
+            let _ = inner.events.send(FileTransferEvent::Progress {
+                transfer_id,
+                direction: TransferDirection::Outgoing,
+                name: transfer.info.name.clone(),
+                transferred,
+                total: transfer.info.size,
+            });
+        }
+
+        send(
+            inner,
+            FileMessage::Chunk {
+                transfer_id,
+                data: buffer[..read].to_vec(),
+            },
+        )
+        .await?;
+    }
+
+    {
+        let mut transfers = inner.transfers.lock().await;
+        let Some(transfer) = transfers
+            .get_mut(&transfer_id)
+            .filter(|t| t.info.status == TransferStatus::Active)
+        else {
+            return Ok(());
+        };
+        transfer.info.status = TransferStatus::Completed;
+        transfer.kind = TransferKind::Finished;
+        prune(&mut transfers);
+    }
+
+    send(inner, FileMessage::End { transfer_id }).await?;
+
+    info!("Sent {}", path.display());
+    let _ = inner.events.send(FileTransferEvent::Completed {
+        transfer_id,
+        direction: TransferDirection::Outgoing,
+        path: path.to_path_buf(),
+    });
+
+    Ok(())
+}
+
+/// Accepts an offer from the peer and starts receiving the file
+async fn accept_offer(inner: &Inner, transfer_id: u64) -> FileTransferResult<()> {
+    let mut transfers = inner.transfers.lock().await;
+
+    let (name, size, sha256) = match transfers.get(&transfer_id) {
+        Some(Transfer {
+            info,
+            kind: TransferKind::Offer { sha256 },
+        }) if info.status == TransferStatus::Offered => (info.name.clone(), info.size, *sha256),
+        _ => return Err(FileTransferError::UnknownTransfer(transfer_id)),
+    };
+
+    let part = part_path(&inner.download_dir, &name, &sha256);
+    if is_receiving_into(&transfers, &part) {
+        return Err(FileTransferError::AlreadyReceiving(name));
+    }
+
+    let file = IncomingFile::resume(&inner.download_dir, &name, size, sha256).await?;
+    let offset = file.written();
+    if offset > 0 {
+        info!("Resuming {} at {} of {} bytes", name, offset, size);
+    } else {
+        info!("Receiving {} ({} bytes)", name, size);
+    }
+
+    if let Some(transfer) = transfers.get_mut(&transfer_id) {
+        transfer.info.status = TransferStatus::Active;
+        transfer.info.transferred = offset;
+        transfer.kind = TransferKind::Download {
+            dir: inner.download_dir.clone(),
+            file: Some(Box::new(file)),
+            done: None,
+        };
+    }
+    drop(transfers);
+
+    send(
+        inner,
+        FileMessage::Accept {
+            transfer_id,
+            offset,
+        },
+    )
+    .await
+}
+
+async fn send(inner: &Inner, message: FileMessage) -> FileTransferResult<()> {
+    inner
+        .tx
+        .send(message)
+        .await
+        .map_err(|_| FileTransferError::ChannelClosed)
+}
+
+/// Returns the name a local file is sent under
+fn file_name(path: &Path) -> FileTransferResult<String> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| FileTransferError::InvalidFileName(path.display().to_string()))
+}
+
+/// Returns true if a download is already writing to `part`
+fn is_receiving_into(transfers: &HashMap<u64, Transfer>, part: &Path) -> bool {
+    transfers.values().any(|t| {
+        matches!(&t.kind, TransferKind::Download { file: Some(file), .. } if file.part_path() == part)
+    })
+}
+
+/// Ends a transfer that didn't complete, optionally telling the peer
+///
+/// A closed stream marks the transfer interrupted and keeps the partial
+/// download so a later offer of the same file can resume it; any other
+/// error removes the partial file.
+async fn end_transfer(
+    inner: &Inner,
+    transfer_id: u64,
+    error: FileTransferError,
+    notify_peer: bool,
+) {
+    let mut transfers = inner.transfers.lock().await;
+    let Some(transfer) = transfers
+        .get_mut(&transfer_id)
+        .filter(|t| !t.info.status.is_finished())
+    else {
+        return;
+    };
+
+    let interrupted = matches!(error, FileTransferError::ChannelClosed);
+    transfer.info.status = if interrupted {
+        TransferStatus::Interrupted
+    } else {
+        TransferStatus::Failed(error.to_string())
+    };
+    let kind = std::mem::replace(&mut transfer.kind, TransferKind::Finished);
+    prune(&mut transfers);
+    drop(transfers);
+
+    warn!("File transfer {} failed: {}", transfer_id, error);
+    let _ = inner.events.send(FileTransferEvent::Failed {
+        transfer_id,
+        message: error.to_string(),
+    });
+
+    if notify_peer {
+        let _ = send(
+            inner,
+            FileMessage::Cancel {
+                transfer_id,
+                reason: error.to_string(),
+            },
+        )
+        .await;
+    }
+
+    if let TransferKind::Download { file, done, .. } = kind {
+        match file {
+            Some(file) if interrupted => file.keep().await,
+            Some(file) => file.discard().await,
+            None => {}
+        }
+        if let Some(done) = done {
+            let _ = done.send(Err(error));
+        }
+    }
+}
+
+/// Interrupts every unfinished transfer once the stream has closed
+async fn interrupt_all(inner: &Inner) {
+    let unfinished: Vec<u64> = inner
+        .transfers
+        .lock()
+        .await
+        .values()
+        .filter(|t| !t.info.status.is_finished())
+        .map(|t| t.info.transfer_id)
+        .collect();
+
+    for transfer_id in unfinished {
+        end_transfer(inner, transfer_id, FileTransferError::ChannelClosed, false).await;
+    }
+}
+
+/// Forgets the oldest finished transfers beyond the history limit
+fn prune(transfers: &mut HashMap<u64, Transfer>) {
+    let mut finished: Vec<u64> = transfers
+        .values()
+        .filter(|t| t.info.status.is_finished())
+        .map(|t| t.info.transfer_id)
+        .collect();
+    if finished.len() <= MAX_FINISHED_TRANSFERS {
+        return;
+    }
+
+    finished.sort_unstable();
+    for transfer_id in &finished[..finished.len() - MAX_FINISHED_TRANSFERS] {
+        transfers.remove(transfer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::transport::create_loopback_transport;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// Waits for the first event `f` maps to a value
+    async fn wait_for<T>(
+        events: &mut broadcast::Receiver<FileTransferEvent>,
+        mut f: impl FnMut(FileTransferEvent) -> Option<T>,
+    ) -> T {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(value) = f(events.recv().await.unwrap()) {
+                    return value;
+                }
+            }
+        })
+        .await
+        .expect("timed out waiting for a file transfer event")
+    }
+
+    fn start_pair(
+        host_shared: SharedFiles,
+        host_dir: &Path,
+        client_dir: &Path,
+    ) -> (FileTransfers, FileTransfers) {
+        let (host, client) = create_loopback_transport();
+        (
+            FileTransfers::start(
+                host.files,
+                ConnectionRole::Host,
+                host_dir.to_path_buf(),
+                host_shared,
+            ),
+            FileTransfers::start(
+                client.files,
+                ConnectionRole::Client,
+                client_dir.to_path_buf(),
+                SharedFiles::default(),
+            ),
+        )
+    }
+
+    async fn wait_for_offer(events: &mut broadcast::Receiver<FileTransferEvent>) -> u64 {
+        wait_for(events, |event| match event {
+            FileTransferEvent::Offered { transfer_id, .. } => Some(transfer_id),
+            _ => None,
+        })
+        .await
+    }
+
+    async fn wait_for_completion(events: &mut broadcast::Receiver<FileTransferEvent>) -> PathBuf {
+        wait_for(events, |event| match event {
+            FileTransferEvent::Completed { path, .. } => Some(path),
+            FileTransferEvent::Failed { message, .. } => panic!("transfer failed: {}", message),
+            _ => None,
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_fetch_shared_file() {
+        let source = TempDir::new().unwrap();
+        let downloads = TempDir::new().unwrap();
+
+        let data: Vec<u8> = (0..FILE_CHUNK_SIZE * 2 + 17).map(|i| i as u8).collect();
+        let path = source.path().join("installer.bin");
+        std::fs::write(&path, &data).unwrap();
+
+        let host_shared = SharedFiles::default();
+        host_shared.share(3, vec![path]);
+        let (_host, client) = start_pair(host_shared, source.path(), downloads.path());
+        let mut events = client.subscribe();
+
+        let saved = client
+            .fetch_clipboard_file(3, 0, downloads.path())
+            .await
+            .unwrap();
+        assert_eq!(saved, downloads.path().join("installer.bin"));
+        assert_eq!(std::fs::read(&saved).unwrap(), data);
+
+        let mut progress = 0;
+        while let Ok(event) = events.try_recv() {
+            if let FileTransferEvent::Progress { transferred, .. } = event {
+                progress = transferred;
+            }
+        }
+        assert_eq!(progress, data.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_unshared_file_fails() {
+        let downloads = TempDir::new().unwrap();
+
+        let host_shared = SharedFiles::default();
+        host_shared.share(1, vec![PathBuf::from("/etc/hostname")]);
+        let (_host, client) = start_pair(host_shared, downloads.path(), downloads.path());
+
+        // Wrong sequence and wrong index are both refused
+        assert!(matches!(
+            client.fetch_clipboard_file(2, 0, downloads.path()).await,
+            Err(FileTransferError::Cancelled(_))
+        ));
+        assert!(client
+            .fetch_clipboard_file(1, 5, downloads.path())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_remote_file() {
+        let shared = TempDir::new().unwrap();
+        let downloads = TempDir::new().unwrap();
+        std::fs::write(shared.path().join("manual.pdf"), b"%PDF-1.7").unwrap();
+        let root = shared.path().file_name().unwrap().to_string_lossy().into_owned();
+
+        let host_shared =
+            SharedFiles::default().with_roots(BrowseRoots::new([shared.path().to_path_buf()]));
+        let (_host, client) = start_pair(host_shared, shared.path(), downloads.path());
+
+        let path = client
+            .fetch_remote_file(&format!("/{}/manual.pdf", root), downloads.path())
+            .await
+            .unwrap();
+        assert_eq!(path, downloads.path().join("manual.pdf"));
+        assert_eq!(std::fs::read(path).unwrap(), b"%PDF-1.7");
+
+        assert!(matches!(
+            client
+                .fetch_remote_file(&format!("/{}/../etc/passwd", root), downloads.path())
+                .await,
+            Err(FileTransferError::Cancelled(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_offer_accept_concurrent_transfers() {
+        let source = TempDir::new().unwrap();
+        let downloads = TempDir::new().unwrap();
+        let (host, client) = start_pair(SharedFiles::default(), source.path(), downloads.path());
+        let mut client_events = client.subscribe();
+
+        let logs: Vec<u8> = (0..FILE_CHUNK_SIZE * 3).map(|i| (i % 13) as u8).collect();
+        let setup: Vec<u8> = (0..FILE_CHUNK_SIZE + 5).map(|i| (i % 7) as u8).collect();
+        std::fs::write(source.path().join("logs.zip"), &logs).unwrap();
+        std::fs::write(source.path().join("setup.msi"), &setup).unwrap();
+
+        let first = host
+            .send_file(&source.path().join("logs.zip"))
+            .await
+            .unwrap();
+        let second = host
+            .send_file(&source.path().join("setup.msi"))
+            .await
+            .unwrap();
+        assert_ne!(first, second);
+        assert_eq!(first % 2, 1, "host transfer IDs are odd");
+
+        // Both offers are accepted before either file arrives
+        for _ in 0..2 {
+            let transfer_id = wait_for_offer(&mut client_events).await;
+            client.accept(transfer_id).await.unwrap();
+        }
+        for _ in 0..2 {
+            wait_for_completion(&mut client_events).await;
+        }
+
+        assert_eq!(
+            std::fs::read(downloads.path().join("logs.zip")).unwrap(),
+            logs
+        );
+        assert_eq!(
+            std::fs::read(downloads.path().join("setup.msi")).unwrap(),
+            setup
+        );
+        assert!(client
+            .list()
+            .await
+            .iter()
+            .all(|t| t.status == TransferStatus::Completed));
+        assert!(client.accept(first).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_declined_offer() {
+        let source = TempDir::new().unwrap();
+        let downloads = TempDir::new().unwrap();
+        let (host, client) = start_pair(SharedFiles::default(), source.path(), downloads.path());
+        let mut host_events = host.subscribe();
+        let mut client_events = client.subscribe();
+
+        std::fs::write(source.path().join("big.iso"), b"not wanted").unwrap();
+        host.send_file(&source.path().join("big.iso"))
+            .await
+            .unwrap();
+
+        let transfer_id = wait_for_offer(&mut client_events).await;
+        client.cancel(transfer_id).await.unwrap();
+
+        let message = wait_for(&mut host_events, |event| match event {
+            FileTransferEvent::Failed { message, .. } => Some(message),
+            _ => None,
+        })
+        .await;
+        assert!(message.contains(DECLINED_REASON));
+        assert_eq!(std::fs::read_dir(downloads.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_auto_accepted_offer() {
+        let drop_dir = TempDir::new().unwrap();
+        let source = TempDir::new().unwrap();
+        let (host, client) = start_pair(SharedFiles::default(), drop_dir.path(), source.path());
+        host.set_auto_accept(true);
+        let mut host_events = host.subscribe();
+
+        std::fs::write(source.path().join("photo.jpg"), b"jpeg data").unwrap();
+        client
+            .send_file(&source.path().join("photo.jpg"))
+            .await
+            .unwrap();
+
+        let saved = wait_for_completion(&mut host_events).await;
+        assert_eq!(saved, drop_dir.path().join("photo.jpg"));
+        assert_eq!(std::fs::read(saved).unwrap(), b"jpeg data");
+    }
+
+    #[tokio::test]
+    async fn test_peer_not_allowed_is_refused() {
+        let drop_dir = TempDir::new().unwrap();
+        let source = TempDir::new().unwrap();
+        let (host, client) = start_pair(SharedFiles::default(), drop_dir.path(), source.path());
+        host.set_auto_accept(true);
+        host.set_peer_allowed(false);
+        let mut client_events = client.subscribe();
+
+        std::fs::write(source.path().join("photo.jpg"), b"jpeg data").unwrap();
+        client
+            .send_file(&source.path().join("photo.jpg"))
+            .await
+            .unwrap();
+
+        let message = wait_for(&mut client_events, |event| match event {
+            FileTransferEvent::Failed { message, .. } => Some(message),
+            _ => None,
+        })
+        .await;
+        assert!(message.contains("not permitted"));
+        assert_eq!(std::fs::read_dir(drop_dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_download_resumes() {
+        let source = TempDir::new().unwrap();
+        let downloads = TempDir::new().unwrap();
+
+        let data: Vec<u8> = (0..FILE_CHUNK_SIZE * 4).map(|i| (i % 251) as u8).collect();
+        let path = source.path().join("dump.bin");
+        std::fs::write(&path, &data).unwrap();
+        let sha256 = sha256_file(&path).await.unwrap();
+
+        // The first chunk is left over from a transfer that was cut off
+        let mut partial =
+            IncomingFile::create(downloads.path(), "dump.bin", data.len() as u64, sha256)
+                .await
+                .unwrap();
+        partial.write_chunk(&data[..FILE_CHUNK_SIZE]).await.unwrap();
+        partial.keep().await;
+
+        let (host, client) = start_pair(SharedFiles::default(), source.path(), downloads.path());
+        let mut host_events = host.subscribe();
+        let mut client_events = client.subscribe();
+
+        host.send_file(&path).await.unwrap();
+        let transfer_id = wait_for_offer(&mut client_events).await;
+        client.accept(transfer_id).await.unwrap();
+
+        // Sending starts after the data the receiver already has
+        let first_progress = wait_for(&mut host_events, |event| match event {
+            FileTransferEvent::Progress { transferred, .. } => Some(transferred),
+            _ => None,
+        })
+        .await;
+        assert_eq!(first_progress, FILE_CHUNK_SIZE as u64 * 2);
+
+        let saved = wait_for_completion(&mut client_events).await;
+        assert_eq!(std::fs::read(saved).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_closed_stream_interrupts_transfers() {
+        let source = TempDir::new().unwrap();
+        let downloads = TempDir::new().unwrap();
+        let (host, client) = start_pair(SharedFiles::default(), source.path(), downloads.path());
+        let mut client_events = client.subscribe();
+
+        std::fs::write(source.path().join("notes.txt"), b"notes").unwrap();
+        host.send_file(&source.path().join("notes.txt"))
+            .await
+            .unwrap();
+        let transfer_id = wait_for_offer(&mut client_events).await;
+
+        drop(host);
+        wait_for(&mut client_events, |event| {
+            matches!(event, FileTransferEvent::Failed { .. }).then_some(())
+        })
+        .await;
+
+        let transfers = client.list().await;
+        assert_eq!(transfers[0].transfer_id, transfer_id);
+        assert_eq!(transfers[0].status, TransferStatus::Interrupted);
+    }
+}
@@ -0,0 +1,376 @@
+//! Key mapping table
+//!
+//! This is the single source of truth for translating keys between the
+//! viewer (egui), the wire format (`u16` key codes) and input simulation
+//! (rdev). Wire codes are Windows virtual-key codes, so every entry in the
+//! table must use a distinct code.
+
+use egui::Key as E;
+use rdev::Key as RdevKey;
+
+use crate::input::types::Key;
+
+/// How a key is simulated with rdev
+#[derive(Debug, Clone, Copy)]
+enum RdevMapping {
+    /// Named rdev key
+    Key(RdevKey),
+    /// Key rdev has no name for, given as the platform's native code
+    Native {
+        /// X11 keycode
+        x11: u32,
+        /// macOS virtual keycode, if the key exists there
+        macos: Option<u32>,
+    },
+}
+
+/// One row of the key mapping table
+#[derive(Debug, Clone, Copy)]
+struct KeyMapping {
+    /// Our key
+    key: Key,
+    /// egui keys that produce this key
+    egui: &'static [egui::Key],
+    /// How the key is simulated
+    rdev: RdevMapping,
+}
+
+const fn map(key: Key, egui: &'static [egui::Key], rdev: RdevKey) -> KeyMapping {
+    KeyMapping {
+        key,
+        egui,
+        rdev: RdevMapping::Key(rdev),
+    }
+}
+
+const fn native(key: Key, egui: &'static [egui::Key], x11: u32, macos: Option<u32>) -> KeyMapping {
+    KeyMapping {
+        key,
+        egui,
+        rdev: RdevMapping::Native { x11, macos },
+    }
+}
+
+/// Every key except `Key::Unknown`, with its egui and rdev equivalents
+const KEY_TABLE: &[KeyMapping] = &[
+    // Letters
+    map(Key::A, &[E::A], RdevKey::KeyA),
+    map(Key::B, &[E::B], RdevKey::KeyB),
+    map(Key::C, &[E::C], RdevKey::KeyC),
+    map(Key::D, &[E::D], RdevKey::KeyD),
+    map(Key::E, &[E::E], RdevKey::KeyE),
+    map(Key::F, &[E::F], RdevKey::KeyF),
+    map(Key::G, &[E::G], RdevKey::KeyG),
+    map(Key::H, &[E::H], RdevKey::KeyH),
+    map(Key::I, &[E::I], RdevKey::KeyI),
+    map(Key::J, &[E::J], RdevKey::KeyJ),
+    map(Key::K, &[E::K], RdevKey::KeyK),
+    map(Key::L, &[E::L], RdevKey::KeyL),
+    map(Key::M, &[E::M], RdevKey::KeyM),
+    map(Key::N, &[E::N], RdevKey::KeyN),
+    map(Key::O, &[E::O], RdevKey::KeyO),
+    map(Key::P, &[E::P], RdevKey::KeyP),
+    map(Key::Q, &[E::Q], RdevKey::KeyQ),
+    map(Key::R, &[E::R], RdevKey::KeyR),
+    map(Key::S, &[E::S], RdevKey::KeyS),
+    map(Key::T, &[E::T], RdevKey::KeyT),
+    map(Key::U, &[E::U], RdevKey::KeyU),
+    map(Key::V, &[E::V], RdevKey::KeyV),
+    map(Key::W, &[E::W], RdevKey::KeyW),
+    map(Key::X, &[E::X], RdevKey::KeyX),
+    map(Key::Y, &[E::Y], RdevKey::KeyY),
+    map(Key::Z, &[E::Z], RdevKey::KeyZ),
+    // Numbers
+    map(Key::Num0, &[E::Num0], RdevKey::Num0),
+    map(Key::Num1, &[E::Num1], RdevKey::Num1),
+    map(Key::Num2, &[E::Num2], RdevKey::Num2),
+    map(Key::Num3, &[E::Num3], RdevKey::Num3),
+    map(Key::Num4, &[E::Num4], RdevKey::Num4),
+    map(Key::Num5, &[E::Num5], RdevKey::Num5),
+    map(Key::Num6, &[E::Num6], RdevKey::Num6),
+    map(Key::Num7, &[E::Num7], RdevKey::Num7),
+    map(Key::Num8, &[E::Num8], RdevKey::Num8),
+    map(Key::Num9, &[E::Num9], RdevKey::Num9),
+    // Function keys
+    map(Key::F1, &[E::F1], RdevKey::F1),
+    map(Key::F2, &[E::F2], RdevKey::F2),
+    map(Key::F3, &[E::F3], RdevKey::F3),
+    map(Key::F4, &[E::F4], RdevKey::F4),
+    map(Key::F5, &[E::F5], RdevKey::F5),
+    map(Key::F6, &[E::F6], RdevKey::F6),
+    map(Key::F7, &[E::F7], RdevKey::F7),
+    map(Key::F8, &[E::F8], RdevKey::F8),
+    map(Key::F9, &[E::F9], RdevKey::F9),
+    map(Key::F10, &[E::F10], RdevKey::F10),
+    map(Key::F11, &[E::F11], RdevKey::F11),
+    map(Key::F12, &[E::F12], RdevKey::F12),
+    native(Key::F13, &[E::F13], 191, Some(105)),
+    native(Key::F14, &[E::F14], 192, Some(107)),
+    native(Key::F15, &[E::F15], 193, Some(113)),
+    native(Key::F16, &[E::F16], 194, Some(106)),
+    native(Key::F17, &[E::F17], 195, Some(64)),
+    native(Key::F18, &[E::F18], 196, Some(79)),
+    native(Key::F19, &[E::F19], 197, Some(80)),
+    native(Key::F20, &[E::F20], 198, Some(90)),
+    native(Key::F21, &[E::F21], 199, None),
+    native(Key::F22, &[E::F22], 200, None),
+    native(Key::F23, &[E::F23], 201, None),
+    native(Key::F24, &[E::F24], 202, None),
+    // Modifiers (egui reports these as modifier state, not key events)
+    map(Key::Shift, &[], RdevKey::ShiftLeft),
+    map(Key::Control, &[], RdevKey::ControlLeft),
+    map(Key::Alt, &[], RdevKey::Alt),
+    map(Key::Meta, &[], RdevKey::MetaLeft),
+    map(Key::ShiftRight, &[], RdevKey::ShiftRight),
+    map(Key::ControlRight, &[], RdevKey::ControlRight),
+    map(Key::AltRight, &[], RdevKey::AltGr),
+    map(Key::MetaRight, &[], RdevKey::MetaRight),
+    // Navigation
+    map(Key::Up, &[E::ArrowUp], RdevKey::UpArrow),
+    map(Key::Down, &[E::ArrowDown], RdevKey::DownArrow),
+    map(Key::Left, &[E::ArrowLeft], RdevKey::LeftArrow),
+    map(Key::Right, &[E::ArrowRight], RdevKey::RightArrow),
+    map(Key::Home, &[E::Home], RdevKey::Home),
+    map(Key::End, &[E::End], RdevKey::End),
+    map(Key::PageUp, &[E::PageUp], RdevKey::PageUp),
+    map(Key::PageDown, &[E::PageDown], RdevKey::PageDown),
+    // Special keys
+    map(Key::Return, &[E::Enter], RdevKey::Return),
+    map(Key::Escape, &[E::Escape], RdevKey::Escape),
+    map(Key::Backspace, &[E::Backspace], RdevKey::Backspace),
+    map(Key::Tab, &[E::Tab], RdevKey::Tab),
+    map(Key::Space, &[E::Space], RdevKey::Space),
+    map(Key::Delete, &[E::Delete], RdevKey::Delete),
+    map(Key::Insert, &[E::Insert], RdevKey::Insert),
+    map(Key::CapsLock, &[], RdevKey::CapsLock),
+    map(Key::NumLock, &[], RdevKey::NumLock),
+    map(Key::ScrollLock, &[], RdevKey::ScrollLock),
+    map(Key::PrintScreen, &[], RdevKey::PrintScreen),
+    map(Key::Pause, &[], RdevKey::Pause),
+    native(Key::ContextMenu, &[], 135, Some(110)),
+    // Numpad
+    map(Key::Numpad0, &[], RdevKey::Kp0),
+    map(Key::Numpad1, &[], RdevKey::Kp1),
+    map(Key::Numpad2, &[], RdevKey::Kp2),
+    map(Key::Numpad3, &[], RdevKey::Kp3),
+    map(Key::Numpad4, &[], RdevKey::Kp4),
+    map(Key::Numpad5, &[], RdevKey::Kp5),
+    map(Key::Numpad6, &[], RdevKey::Kp6),
+    map(Key::Numpad7, &[], RdevKey::Kp7),
+    map(Key::Numpad8, &[], RdevKey::Kp8),
+    map(Key::Numpad9, &[], RdevKey::Kp9),
+    map(Key::NumpadMultiply, &[], RdevKey::KpMultiply),
+    map(Key::NumpadAdd, &[], RdevKey::KpPlus),
+    map(Key::NumpadSubtract, &[], RdevKey::KpMinus),
+    map(Key::NumpadDecimal, &[], RdevKey::KpDelete),
+    map(Key::NumpadDivide, &[], RdevKey::KpDivide),
+    map(Key::NumpadEnter, &[], RdevKey::KpReturn),
+    // Punctuation (shifted egui symbols map to their unshifted key)
+    map(Key::Minus, &[E::Minus], RdevKey::Minus),
+    map(Key::Equal, &[E::Equals, E::Plus], RdevKey::Equal),
+    map(Key::LeftBracket, &[E::OpenBracket], RdevKey::LeftBracket),
+    map(Key::RightBracket, &[E::CloseBracket], RdevKey::RightBracket),
+    map(Key::Semicolon, &[E::Semicolon, E::Colon], RdevKey::SemiColon),
+    map(Key::Quote, &[], RdevKey::Quote),
+    map(Key::Backslash, &[E::Backslash, E::Pipe], RdevKey::BackSlash),
+    map(Key::Comma, &[E::Comma], RdevKey::Comma),
+    map(Key::Period, &[E::Period], RdevKey::Dot),
+    map(Key::Slash, &[E::Slash, E::Questionmark], RdevKey::Slash),
+    map(Key::Grave, &[E::Backtick], RdevKey::BackQuote),
+    map(Key::IntlBackslash, &[], RdevKey::IntlBackslash),
+    // Media keys
+    native(Key::VolumeMute, &[], 121, Some(74)),
+    native(Key::VolumeDown, &[], 122, Some(73)),
+    native(Key::VolumeUp, &[], 123, Some(72)),
+    native(Key::MediaNextTrack, &[], 171, None),
+    native(Key::MediaPrevTrack, &[], 173, None),
+    native(Key::MediaStop, &[], 174, None),
+    native(Key::MediaPlayPause, &[], 172, None),
+];
+
+impl Key {
+    /// Returns every known key (excluding `Key::Unknown`)
+    pub fn all() -> impl Iterator<Item = Key> {
+        KEY_TABLE.iter().map(|mapping| mapping.key)
+    }
+
+    /// Returns the wire code for this key
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+
+    /// Looks up a key by its wire code, returning `Key::Unknown` if unmapped
+    pub fn from_code(code: u16) -> Key {
+        KEY_TABLE
+            .iter()
+            .find(|mapping| mapping.key.code() == code)
+            .map_or(Key::Unknown, |mapping| mapping.key)
+    }
+
+    /// Converts an egui key to our key
+    pub fn from_egui(key: egui::Key) -> Option<Key> {
+        KEY_TABLE
+            .iter()
+            .find(|mapping| mapping.egui.contains(&key))
+            .map(|mapping| mapping.key)
+    }
+
+    /// Returns true if the key types a character when pressed without
+    /// Control, Alt or Meta
+    pub fn is_printable(self) -> bool {
+        let code = self.code();
+        matches!(
+            self,
+            Key::Space
+                | Key::Minus
+                | Key::Equal
+                | Key::LeftBracket
+                | Key::RightBracket
+                | Key::Semicolon
+                | Key::Quote
+                | Key::Backslash
+                | Key::Comma
+                | Key::Period
+                | Key::Slash
+                | Key::Grave
+                | Key::IntlBackslash
+        ) || (Key::A.code()..=Key::Z.code()).contains(&code)
+            || (Key::Num0.code()..=Key::Num9.code()).contains(&code)
+            || (Key::Numpad0.code()..=Key::NumpadDivide.code()).contains(&code)
+    }
+
+    /// Returns true if the key is a modifier (Shift, Control, Alt or Meta)
+    pub fn is_modifier(self) -> bool {
+        matches!(
+            self,
+            Key::Shift
+                | Key::ShiftRight
+                | Key::Control
+                | Key::ControlRight
+                | Key::Alt
+                | Key::AltRight
+                | Key::Meta
+                | Key::MetaRight
+        )
+    }
+
+    /// Converts this key to the rdev key used for simulation
+    ///
+    /// Returns None for `Key::Unknown` and for keys that do not exist on
+    /// the current platform.
+    pub fn to_rdev(self) -> Option<RdevKey> {
+        let mapping = KEY_TABLE.iter().find(|mapping| mapping.key == self)?;
+
+        match mapping.rdev {
+            RdevMapping::Key(key) => Some(key),
+            RdevMapping::Native { x11, macos } => native_rdev_key(self, x11, macos),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn native_rdev_key(key: Key, _x11: u32, _macos: Option<u32>) -> Option<RdevKey> {
+    // Wire codes are Windows virtual-key codes
+    Some(RdevKey::Unknown(key.code() as u32))
+}
+
+#[cfg(target_os = "macos")]
+fn native_rdev_key(_key: Key, _x11: u32, macos: Option<u32>) -> Option<RdevKey> {
+    macos.map(RdevKey::Unknown)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn native_rdev_key(_key: Key, x11: u32, _macos: Option<u32>) -> Option<RdevKey> {
+    Some(RdevKey::Unknown(x11))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_code_round_trip() {
+        for key in Key::all() {
+            assert_eq!(Key::from_code(key.code()), key, "{:?}", key);
+        }
+        assert_eq!(Key::from_code(Key::Unknown.code()), Key::Unknown);
+        assert_eq!(Key::from_code(0x0E), Key::Unknown);
+    }
+
+    #[test]
+    fn test_table_entries_are_unique() {
+        let mut keys = HashSet::new();
+        let mut codes = HashSet::new();
+        let mut egui_keys = HashSet::new();
+
+        for mapping in KEY_TABLE {
+            assert!(keys.insert(mapping.key), "duplicate key {:?}", mapping.key);
+            assert!(codes.insert(mapping.key.code()), "duplicate code for {:?}", mapping.key);
+            assert_ne!(mapping.key, Key::Unknown);
+            for egui_key in mapping.egui {
+                assert!(egui_keys.insert(*egui_key), "duplicate egui key {:?}", egui_key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_key_has_rdev_mapping() {
+        for key in Key::all() {
+            let mapping = KEY_TABLE.iter().find(|m| m.key == key).unwrap();
+            if let RdevMapping::Key(_) = mapping.rdev {
+                assert!(key.to_rdev().is_some(), "{:?}", key);
+            }
+        }
+        assert!(Key::Unknown.to_rdev().is_none());
+    }
+
+    #[test]
+    fn test_egui_round_trip() {
+        // Every egui key we accept maps back to a key with the same egui name
+        for mapping in KEY_TABLE {
+            for egui_key in mapping.egui {
+                assert_eq!(Key::from_egui(*egui_key), Some(mapping.key));
+            }
+        }
+
+        assert_eq!(Key::from_egui(egui::Key::ArrowUp), Some(Key::Up));
+        assert_eq!(Key::from_egui(egui::Key::F24), Some(Key::F24));
+        assert_eq!(Key::from_egui(egui::Key::Questionmark), Some(Key::Slash));
+        assert_eq!(Key::from_egui(egui::Key::Copy), None);
+    }
+
+    #[test]
+    fn test_printable_keys() {
+        for key in [Key::A, Key::Z, Key::Num5, Key::Space, Key::Slash, Key::Numpad7] {
+            assert!(key.is_printable(), "{:?}", key);
+        }
+        for key in [Key::Return, Key::Tab, Key::F1, Key::Left, Key::Shift, Key::Unknown] {
+            assert!(!key.is_printable(), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn test_coverage() {
+        for key in [
+            Key::F13,
+            Key::F24,
+            Key::PageDown,
+            Key::Numpad0,
+            Key::NumpadEnter,
+            Key::NumpadDecimal,
+            Key::Grave,
+            Key::IntlBackslash,
+            Key::ShiftRight,
+            Key::ControlRight,
+            Key::AltRight,
+            Key::Meta,
+            Key::MetaRight,
+            Key::VolumeUp,
+            Key::MediaPlayPause,
+            Key::PrintScreen,
+        ] {
+            assert!(Key::all().any(|k| k == key), "{:?} missing from table", key);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! Key combination macros
+//!
+//! Some shortcuts never reach the viewer because the local OS handles them
+//! first (Ctrl+Alt+Del, Alt+Tab, the Super key). Macros describe those
+//! shortcuts as key sequences the viewer or CLI can send to the host as a
+//! single `InputEvent::Batch`.
+//!
+//! Sequences are written as `+`-joined combinations separated by commas,
+//! for example `Ctrl+Alt+Delete` or `Super+R, Escape`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{RemoteDeskError, Result};
+use crate::input::types::{BatchEvent, InputEvent, Key, KeyboardEvent};
+
+/// Separator between keys held together
+const KEY_SEPARATOR: char = '+';
+
+/// Separator between combinations pressed one after another
+const STEP_SEPARATOR: char = ',';
+
+/// Built-in macros as (name, sequence) pairs
+const BUILTIN_MACROS: &[(&str, &str)] = &[
+    ("Ctrl+Alt+Del", "Ctrl+Alt+Delete"),
+    ("Alt+Tab", "Alt+Tab"),
+    ("Alt+F4", "Alt+F4"),
+    ("Ctrl+Shift+Esc", "Ctrl+Shift+Escape"),
+    ("Super", "Super"),
+    ("Lock screen", "Super+L"),
+    ("Print Screen", "PrintScreen"),
+];
+
+/// A sequence of key combinations
+///
+/// Each combination is pressed in order and released in reverse order
+/// before the next one starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySequence {
+    steps: Vec<Vec<Key>>,
+}
+
+impl KeySequence {
+    /// Creates a sequence from key combinations
+    pub fn new(steps: Vec<Vec<Key>>) -> Self {
+        Self { steps }
+    }
+
+    /// Returns the key combinations in order
+    pub fn steps(&self) -> &[Vec<Key>] {
+        &self.steps
+    }
+
+    /// Returns the press and release events for the whole sequence
+    pub fn to_events(&self) -> Vec<BatchEvent> {
+        let mut events = Vec::new();
+
+        for combo in &self.steps {
+            events.extend(
+                combo
+                    .iter()
+                    .map(|key| BatchEvent::Keyboard(KeyboardEvent::key_press(*key))),
+            );
+            events.extend(
+                combo
+                    .iter()
+                    .rev()
+                    .map(|key| BatchEvent::Keyboard(KeyboardEvent::key_release(*key))),
+            );
+        }
+
+        events
+    }
+
+    /// Returns the sequence as a single batch event
+    pub fn to_batch(&self) -> InputEvent {
+        InputEvent::Batch(self.to_events())
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = RemoteDeskError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let steps = s
+            .split(STEP_SEPARATOR)
+            .map(|step| {
+                step.split(KEY_SEPARATOR)
+                    .map(|name| parse_key(name.trim()))
+                    .collect::<Result<Vec<Key>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self::new(steps))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, combo) in self.steps.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{} ", STEP_SEPARATOR)?;
+            }
+            for (j, key) in combo.iter().enumerate() {
+                if j > 0 {
+                    write!(f, "{}", KEY_SEPARATOR)?;
+                }
+                write!(f, "{}", key_name(*key))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A named key sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMacro {
+    /// Name shown in menus and used by the CLI
+    pub name: String,
+    /// Keys to send
+    pub sequence: KeySequence,
+}
+
+impl KeyMacro {
+    /// Creates a macro
+    pub fn new(name: impl Into<String>, sequence: KeySequence) -> Self {
+        Self {
+            name: name.into(),
+            sequence,
+        }
+    }
+
+    /// Returns the built-in macros
+    pub fn builtin() -> Vec<KeyMacro> {
+        BUILTIN_MACROS
+            .iter()
+            .map(|(name, keys)| {
+                let sequence = keys.parse().expect("built-in macro must parse");
+                KeyMacro::new(*name, sequence)
+            })
+            .collect()
+    }
+
+    /// Returns the built-in macros followed by the configured ones
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a configured macro contains an unknown key
+    pub fn with_custom(custom: &[MacroConfig]) -> Result<Vec<KeyMacro>> {
+        let mut macros = Self::builtin();
+        for config in custom {
+            macros.push(config.to_macro()?);
+        }
+        Ok(macros)
+    }
+
+    /// Looks up a macro by name, or parses the input as a key sequence
+    ///
+    /// Names are matched case-insensitively so `ctrl+alt+del` finds the
+    /// built-in macro.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no macro matches and the input is not a valid
+    /// sequence
+    pub fn resolve(input: &str, macros: &[KeyMacro]) -> Result<KeySequence> {
+        match macros
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(input.trim()))
+        {
+            Some(found) => Ok(found.sequence.clone()),
+            None => input.parse(),
+        }
+    }
+}
+
+/// User-defined macro as stored in the configuration file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroConfig {
+    /// Macro name
+    pub name: String,
+    /// Key sequence, e.g. "Ctrl+Shift+T" or "Super+R, Escape"
+    pub keys: String,
+}
+
+impl MacroConfig {
+    /// Parses the configured keys into a macro
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the sequence contains an unknown key
+    pub fn to_macro(&self) -> Result<KeyMacro> {
+        let sequence = self.keys.parse().map_err(|e| {
+            RemoteDeskError::Generic(format!("Invalid macro '{}': {}", self.name, e))
+        })?;
+        Ok(KeyMacro::new(self.name.clone(), sequence))
+    }
+}
+
+/// Parses a single key name
+///
+/// Accepts the `Key` variant names plus common aliases, ignoring case.
+fn parse_key(name: &str) -> Result<Key> {
+    let lower = name.to_ascii_lowercase();
+    let alias = match lower.as_str() {
+        "ctrl" | "control" => Some(Key::Control),
+        "alt" | "option" => Some(Key::Alt),
+        "shift" => Some(Key::Shift),
+        "super" | "win" | "windows" | "cmd" | "command" | "meta" => Some(Key::Meta),
+        "del" => Some(Key::Delete),
+        "esc" => Some(Key::Escape),
+        "enter" => Some(Key::Return),
+        "ins" => Some(Key::Insert),
+        "pgup" => Some(Key::PageUp),
+        "pgdn" => Some(Key::PageDown),
+        "prtsc" | "print" => Some(Key::PrintScreen),
+        "menu" => Some(Key::ContextMenu),
+        _ => None,
+    };
+
+    if let Some(key) = alias {
+        return Ok(key);
+    }
+
+    // Digits name the number row keys
+    if let [digit @ b'0'..=b'9'] = lower.as_bytes() {
+        return Ok(Key::from_code(Key::Num0.code() + (digit - b'0') as u16));
+    }
+
+    Key::all()
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+        .ok_or_else(|| RemoteDeskError::Generic(format!("Unknown key: '{}'", name)))
+}
+
+/// Returns the display name of a key
+fn key_name(key: Key) -> String {
+    match key {
+        Key::Control => "Ctrl".to_string(),
+        Key::Meta => "Super".to_string(),
+        _ => format!("{:?}", key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::types::KeyboardEventType;
+
+    fn key_events(events: &[BatchEvent]) -> Vec<(KeyboardEventType, Key)> {
+        events
+            .iter()
+            .map(|event| match event {
+                BatchEvent::Keyboard(kb) => (kb.event_type, kb.key),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_sequence() {
+        let sequence: KeySequence = "ctrl+ALT+del, Win+r, 5".parse().unwrap();
+        assert_eq!(
+            sequence.steps(),
+            &[
+                vec![Key::Control, Key::Alt, Key::Delete],
+                vec![Key::Meta, Key::R],
+                vec![Key::Num5],
+            ]
+        );
+        assert_eq!(sequence.to_string(), "Ctrl+Alt+Delete, Super+R, Num5");
+    }
+
+    #[test]
+    fn test_parse_unknown_key() {
+        assert!("Ctrl+Hyper".parse::<KeySequence>().is_err());
+        assert!("".parse::<KeySequence>().is_err());
+    }
+
+    #[test]
+    fn test_events_release_in_reverse_order() {
+        let sequence: KeySequence = "Alt+Tab, Escape".parse().unwrap();
+        let events = key_events(&sequence.to_events());
+
+        use KeyboardEventType::{KeyPress, KeyRelease};
+        assert_eq!(
+            events,
+            vec![
+                (KeyPress, Key::Alt),
+                (KeyPress, Key::Tab),
+                (KeyRelease, Key::Tab),
+                (KeyRelease, Key::Alt),
+                (KeyPress, Key::Escape),
+                (KeyRelease, Key::Escape),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_builtin_and_custom() {
+        let custom = vec![MacroConfig {
+            name: "Reopen tab".to_string(),
+            keys: "Ctrl+Shift+T".to_string(),
+        }];
+        let macros = KeyMacro::with_custom(&custom).unwrap();
+
+        let sequence = KeyMacro::resolve("ctrl+alt+del", &macros).unwrap();
+        assert_eq!(sequence.steps(), &[vec![Key::Control, Key::Alt, Key::Delete]]);
+
+        let sequence = KeyMacro::resolve("Reopen tab", &macros).unwrap();
+        assert_eq!(sequence.steps(), &[vec![Key::Control, Key::Shift, Key::T]]);
+
+        // Not a macro name, but a valid sequence
+        let sequence = KeyMacro::resolve("Super+E", &macros).unwrap();
+        assert_eq!(sequence.steps(), &[vec![Key::Meta, Key::E]]);
+
+        let bad = vec![MacroConfig {
+            name: "Broken".to_string(),
+            keys: "Ctrl+Nope".to_string(),
+        }];
+        assert!(KeyMacro::with_custom(&bad).is_err());
+    }
+}
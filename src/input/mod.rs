@@ -6,11 +6,19 @@
 //! - Cross-platform input handling
 //! - Event serialization for network transmission
 
+pub mod keymap;
+pub mod macros;
+pub mod pressed;
+mod relative;
 pub mod simulator;
+mod text;
 pub mod types;
 
 // Re-export commonly used types
+pub use macros::{KeyMacro, KeySequence, MacroConfig};
+pub use pressed::PressedInputs;
 pub use simulator::InputSimulator;
 pub use types::{
-    InputEvent, Key, KeyboardEvent, KeyboardEventType, MouseButton, MouseEvent, MouseEventType,
+    BatchEvent, InputEvent, Key, KeyboardEvent, KeyboardEventType, KeyboardMode, MouseButton,
+    MouseEvent, MouseEventType, TextEvent,
 };
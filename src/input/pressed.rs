@@ -0,0 +1,141 @@
+//! Pressed key and button tracking
+//!
+//! The host records which keys and mouse buttons a client currently holds
+//! down so they can be released if the client goes away mid-press. Without
+//! this a dropped connection can leave a modifier stuck on the host.
+
+use std::collections::HashSet;
+
+use crate::input::types::{
+    InputEvent, Key, KeyboardEvent, KeyboardEventType, MouseButton, MouseEvent, MouseEventType,
+};
+
+/// Keys and mouse buttons currently held down by a client
+#[derive(Debug, Clone, Default)]
+pub struct PressedInputs {
+    keys: HashSet<Key>,
+    buttons: HashSet<MouseButton>,
+}
+
+impl PressedInputs {
+    /// Creates an empty pressed state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the state after an event has been simulated
+    ///
+    /// Text events press and release their own keys and are not tracked.
+    pub fn record(&mut self, event: &InputEvent) {
+        match event {
+            InputEvent::Keyboard(kb) => match kb.event_type {
+                KeyboardEventType::KeyPress => {
+                    self.keys.insert(kb.key);
+                }
+                KeyboardEventType::KeyRelease => {
+                    self.keys.remove(&kb.key);
+                }
+            },
+            InputEvent::Mouse(mouse) => match mouse.event_type {
+                MouseEventType::ButtonPress { button } => {
+                    self.buttons.insert(button);
+                }
+                MouseEventType::ButtonRelease { button } => {
+                    self.buttons.remove(&button);
+                }
+                _ => {}
+            },
+            InputEvent::Text(_) => {}
+            InputEvent::Batch(events) => {
+                for event in events {
+                    self.record(&event.clone().into());
+                }
+            }
+        }
+    }
+
+    /// Returns true if the key is held down
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    /// Returns true if the mouse button is held down
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    /// Returns true if nothing is held down
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.buttons.is_empty()
+    }
+
+    /// Clears the state and returns the events that release everything
+    ///
+    /// Ordinary keys are released before modifiers so the host never sees
+    /// a bare key press turn into a shortcut, and mouse buttons go last.
+    pub fn release_all(&mut self) -> Vec<InputEvent> {
+        let mut keys: Vec<Key> = self.keys.drain().collect();
+        keys.sort_by_key(|key| (key.is_modifier(), key.code()));
+
+        let mut buttons: Vec<MouseButton> = self.buttons.drain().collect();
+        buttons.sort_by_key(|button| *button as u8);
+
+        keys.into_iter()
+            .map(|key| InputEvent::Keyboard(KeyboardEvent::key_release(key)))
+            .chain(
+                buttons
+                    .into_iter()
+                    .map(|button| InputEvent::Mouse(MouseEvent::button_release(button))),
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_press_and_release_tracking() {
+        let mut pressed = PressedInputs::new();
+        assert!(pressed.is_empty());
+
+        pressed.record(&KeyboardEvent::key_press(Key::Control).into());
+        pressed.record(&MouseEvent::button_press(MouseButton::Left).into());
+        assert!(pressed.is_key_pressed(Key::Control));
+        assert!(pressed.is_button_pressed(MouseButton::Left));
+
+        pressed.record(&KeyboardEvent::key_release(Key::Control).into());
+        pressed.record(&MouseEvent::button_release(MouseButton::Left).into());
+        assert!(pressed.is_empty());
+    }
+
+    #[test]
+    fn test_release_all_order() {
+        let mut pressed = PressedInputs::new();
+        pressed.record(&KeyboardEvent::key_press(Key::Shift).into());
+        pressed.record(&KeyboardEvent::key_press(Key::C).into());
+        pressed.record(&MouseEvent::button_press(MouseButton::Right).into());
+        pressed.record(&MouseEvent::move_to(10, 10).into());
+
+        let released: Vec<String> = pressed
+            .release_all()
+            .iter()
+            .map(|event| match event {
+                InputEvent::Keyboard(kb) => {
+                    assert_eq!(kb.event_type, KeyboardEventType::KeyRelease);
+                    format!("{:?}", kb.key)
+                }
+                InputEvent::Mouse(mouse) => format!("{:?}", mouse.event_type),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(
+            released,
+            vec!["C", "Shift", "ButtonRelease { button: Right }"]
+        );
+        assert!(pressed.is_empty());
+        assert!(pressed.release_all().is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+//! Relative pointer motion
+//!
+//! rdev can only move the pointer to an absolute position, which
+//! applications that grab and re-center the pointer ignore. These helpers
+//! inject raw motion deltas through each platform's native input API.
+
+/// Screen number XTest interprets as "the current screen"
+#[cfg(target_os = "linux")]
+const CURRENT_SCREEN: std::os::raw::c_int = -1;
+
+/// Injects relative pointer motion
+///
+/// On Linux this keeps one X display connection, opened on first use and
+/// closed on drop, rather than connecting for every event.
+#[derive(Default)]
+pub(crate) struct RelativePointer {
+    #[cfg(target_os = "linux")]
+    display: std::sync::Mutex<Option<XDisplay>>,
+}
+
+impl RelativePointer {
+    /// Moves the pointer by the given delta
+    pub(crate) fn move_by(&self, dx: i32, dy: i32) -> Result<(), String> {
+        #[cfg(target_os = "linux")]
+        {
+            let mut display = self.display.lock().unwrap_or_else(|e| e.into_inner());
+            move_relative(&mut display, dx, dy)
+        }
+        #[cfg(not(target_os = "linux"))]
+        move_relative(dx, dy)
+    }
+}
+
+/// An open X display connection
+#[cfg(target_os = "linux")]
+pub(super) struct XDisplay(pub(super) *mut x11::xlib::Display);
+
+// SAFETY: each connection is only used while holding the lock of the
+// `RelativePointer` or `UnicodeTyper` that owns it, so Xlib never sees two
+// threads at once.
+#[cfg(target_os = "linux")]
+unsafe impl Send for XDisplay {}
+
+#[cfg(target_os = "linux")]
+impl XDisplay {
+    pub(super) fn open() -> Result<Self, String> {
+        // SAFETY: a null name opens the default display; the result is
+        // checked before use.
+        let display = unsafe { x11::xlib::XOpenDisplay(std::ptr::null()) };
+        if display.is_null() {
+            Err("cannot open X display".to_string())
+        } else {
+            Ok(Self(display))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for XDisplay {
+    fn drop(&mut self) {
+        // SAFETY: the connection was opened by `open` and is closed once
+        unsafe {
+            x11::xlib::XCloseDisplay(self.0);
+        }
+    }
+}
+
+/// Moves the pointer by the given delta, opening the display if needed
+#[cfg(target_os = "linux")]
+fn move_relative(display: &mut Option<XDisplay>, dx: i32, dy: i32) -> Result<(), String> {
+    use x11::{xlib, xtest};
+
+    let display = match display {
+        Some(display) => display,
+        None => display.insert(XDisplay::open()?),
+    };
+
+    // SAFETY: the display connection is open and not used by anyone else
+    // while we hold it.
+    let status = unsafe {
+        let status = xtest::XTestFakeRelativeMotionEvent(display.0, CURRENT_SCREEN, dx, dy, 0);
+        xlib::XFlush(display.0);
+        status
+    };
+
+    if status == 0 {
+        Err("XTest rejected relative motion".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Moves the pointer by the given delta
+///
+/// Windows applies the user's pointer acceleration to relative input.
+#[cfg(target_os = "windows")]
+fn move_relative(dx: i32, dy: i32) -> Result<(), String> {
+    use std::mem;
+    use winapi::um::winuser::{SendInput, INPUT, INPUT_MOUSE, MOUSEEVENTF_MOVE};
+
+    // SAFETY: INPUT is a plain C struct for which all-zero is a valid value,
+    // and SendInput only reads the single element we pass.
+    unsafe {
+        let mut input: INPUT = mem::zeroed();
+        input.type_ = INPUT_MOUSE;
+        let mouse = input.u.mi_mut();
+        mouse.dx = dx;
+        mouse.dy = dy;
+        mouse.dwFlags = MOUSEEVENTF_MOVE;
+
+        if SendInput(1, &mut input, mem::size_of::<INPUT>() as i32) == 1 {
+            Ok(())
+        } else {
+            Err("SendInput rejected relative motion".to_string())
+        }
+    }
+}
+
+/// Moves the pointer by the given delta
+#[cfg(target_os = "macos")]
+fn move_relative(dx: i32, dy: i32) -> Result<(), String> {
+    use core_graphics::event::{
+        CGEvent, CGEventTapLocation, CGEventType, CGMouseButton, EventField,
+    };
+    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+    use core_graphics::geometry::CGPoint;
+
+    let source = || {
+        CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+            .map_err(|_| "cannot create event source".to_string())
+    };
+
+    let current = CGEvent::new(source()?)
+        .map_err(|_| "cannot read pointer location".to_string())?
+        .location();
+    let target = CGPoint::new(current.x + dx as f64, current.y + dy as f64);
+
+    // Games read the delta fields rather than the new location
+    let event = CGEvent::new_mouse_event(
+        source()?,
+        CGEventType::MouseMoved,
+        target,
+        CGMouseButton::Left,
+    )
+    .map_err(|_| "cannot create mouse event".to_string())?;
+    event.set_integer_value_field(EventField::MOUSE_EVENT_DELTA_X, dx as i64);
+    event.set_integer_value_field(EventField::MOUSE_EVENT_DELTA_Y, dy as i64);
+    event.post(CGEventTapLocation::HID);
+
+    Ok(())
+}
+
+/// Moves the pointer by the given delta
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn move_relative(_dx: i32, _dy: i32) -> Result<(), String> {
+    Err("relative motion is not supported on this platform".to_string())
+}
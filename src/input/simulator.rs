@@ -3,8 +3,12 @@
 //! This module provides cross-platform input simulation for keyboard and mouse.
 
 use crate::error::{RemoteDeskError, Result};
+use crate::input::pressed::PressedInputs;
+use crate::input::relative::RelativePointer;
+use crate::input::text::UnicodeTyper;
 use crate::input::types::{
-    InputEvent, Key, KeyboardEvent, KeyboardEventType, MouseButton, MouseEvent, MouseEventType,
+    BatchEvent, InputEvent, Key, KeyboardEvent, KeyboardEventType, MouseButton, MouseEvent,
+    MouseEventType,
 };
 use rdev::{simulate, Button, EventType, Key as RdevKey};
 use std::sync::{
@@ -24,6 +28,10 @@ pub struct InputSimulator {
     events_failed: Arc<AtomicU64>,
     /// Delay between events (milliseconds)
     event_delay_ms: u64,
+    /// Injects relative motion, keeping its display connection open
+    pointer: RelativePointer,
+    /// Types characters that have no key on a US layout
+    typer: UnicodeTyper,
 }
 
 impl InputSimulator {
@@ -33,6 +41,8 @@ impl InputSimulator {
             events_simulated: Arc::new(AtomicU64::new(0)),
             events_failed: Arc::new(AtomicU64::new(0)),
             event_delay_ms: DEFAULT_EVENT_DELAY_MS,
+            pointer: RelativePointer::default(),
+            typer: UnicodeTyper::default(),
         }
     }
 
@@ -42,6 +52,8 @@ impl InputSimulator {
             events_simulated: Arc::new(AtomicU64::new(0)),
             events_failed: Arc::new(AtomicU64::new(0)),
             event_delay_ms,
+            pointer: RelativePointer::default(),
+            typer: UnicodeTyper::default(),
         }
     }
 
@@ -54,9 +66,33 @@ impl InputSimulator {
         match event {
             InputEvent::Keyboard(kb_event) => self.simulate_keyboard(kb_event),
             InputEvent::Mouse(mouse_event) => self.simulate_mouse(mouse_event),
+            InputEvent::Text(text_event) => self.type_string(&text_event.text),
+            InputEvent::Batch(events) => self.simulate_batch(events),
         }
     }
 
+    /// Simulates a batch of events in order
+    ///
+    /// If an event fails, anything the batch pressed is released before the
+    /// error is returned so a half-sent shortcut never leaves keys held.
+    fn simulate_batch(&self, events: &[BatchEvent]) -> Result<()> {
+        let mut pressed = PressedInputs::new();
+
+        for event in events {
+            let event = InputEvent::from(event.clone());
+            if let Err(e) = self.simulate(&event) {
+                for release in pressed.release_all() {
+                    let _ = self.simulate(&release);
+                }
+                return Err(e);
+            }
+            pressed.record(&event);
+        }
+
+        debug!("Simulated batch of {} events", events.len());
+        Ok(())
+    }
+
     /// Simulates a keyboard event
     fn simulate_keyboard(&self, event: &KeyboardEvent) -> Result<()> {
         let rdev_key = self.convert_key(event.key)?;
@@ -107,6 +143,10 @@ impl InputSimulator {
                 self.send_event(event_type)?;
                 debug!("Simulated mouse wheel: ({}, {})", delta_x, delta_y);
             }
+            MouseEventType::MoveRelative { dx, dy } => {
+                self.send_relative_motion(*dx, *dy)?;
+                debug!("Simulated relative mouse move by ({}, {})", dx, dy);
+            }
         }
 
         Ok(())
@@ -135,109 +175,48 @@ impl InputSimulator {
         }
     }
 
-    /// Converts our Key enum to rdev Key
-    fn convert_key(&self, key: Key) -> Result<RdevKey> {
-        let rdev_key = match key {
-            // Letters
-            Key::A => RdevKey::KeyA,
-            Key::B => RdevKey::KeyB,
-            Key::C => RdevKey::KeyC,
-            Key::D => RdevKey::KeyD,
-            Key::E => RdevKey::KeyE,
-            Key::F => RdevKey::KeyF,
-            Key::G => RdevKey::KeyG,
-            Key::H => RdevKey::KeyH,
-            Key::I => RdevKey::KeyI,
-            Key::J => RdevKey::KeyJ,
-            Key::K => RdevKey::KeyK,
-            Key::L => RdevKey::KeyL,
-            Key::M => RdevKey::KeyM,
-            Key::N => RdevKey::KeyN,
-            Key::O => RdevKey::KeyO,
-            Key::P => RdevKey::KeyP,
-            Key::Q => RdevKey::KeyQ,
-            Key::R => RdevKey::KeyR,
-            Key::S => RdevKey::KeyS,
-            Key::T => RdevKey::KeyT,
-            Key::U => RdevKey::KeyU,
-            Key::V => RdevKey::KeyV,
-            Key::W => RdevKey::KeyW,
-            Key::X => RdevKey::KeyX,
-            Key::Y => RdevKey::KeyY,
-            Key::Z => RdevKey::KeyZ,
-
-            // Numbers
-            Key::Num0 => RdevKey::Num0,
-            Key::Num1 => RdevKey::Num1,
-            Key::Num2 => RdevKey::Num2,
-            Key::Num3 => RdevKey::Num3,
-            Key::Num4 => RdevKey::Num4,
-            Key::Num5 => RdevKey::Num5,
-            Key::Num6 => RdevKey::Num6,
-            Key::Num7 => RdevKey::Num7,
-            Key::Num8 => RdevKey::Num8,
-            Key::Num9 => RdevKey::Num9,
-
-            // Function keys
-            Key::F1 => RdevKey::F1,
-            Key::F2 => RdevKey::F2,
-            Key::F3 => RdevKey::F3,
-            Key::F4 => RdevKey::F4,
-            Key::F5 => RdevKey::F5,
-            Key::F6 => RdevKey::F6,
-            Key::F7 => RdevKey::F7,
-            Key::F8 => RdevKey::F8,
-            Key::F9 => RdevKey::F9,
-            Key::F10 => RdevKey::F10,
-            Key::F11 => RdevKey::F11,
-            Key::F12 => RdevKey::F12,
-
-            // Modifiers
-            Key::Shift => RdevKey::ShiftLeft,
-            Key::Control => RdevKey::ControlLeft,
-            Key::Alt => RdevKey::Alt,
-            Key::Meta => RdevKey::MetaLeft,
-
-            // Navigation
-            Key::Up => RdevKey::UpArrow,
-            Key::Down => RdevKey::DownArrow,
-            Key::Left => RdevKey::LeftArrow,
-            Key::Right => RdevKey::RightArrow,
-            Key::Home => RdevKey::Home,
-            Key::End => RdevKey::End,
-            Key::PageUp => RdevKey::PageUp,
-            Key::PageDown => RdevKey::PageDown,
-
-            // Special keys
-            Key::Return => RdevKey::Return,
-            Key::Escape => RdevKey::Escape,
-            Key::Backspace => RdevKey::Backspace,
-            Key::Tab => RdevKey::Tab,
-            Key::Space => RdevKey::Space,
-            Key::Delete => RdevKey::Delete,
-            Key::Insert => RdevKey::Insert,
-            Key::CapsLock => RdevKey::CapsLock,
-
-            // Punctuation
-            Key::Minus => RdevKey::Minus,
-            Key::Equal => RdevKey::Equal,
-            Key::LeftBracket => RdevKey::LeftBracket,
-            Key::RightBracket => RdevKey::RightBracket,
-            Key::Semicolon => RdevKey::SemiColon,
-            Key::Quote => RdevKey::Quote,
-            Key::Backslash => RdevKey::BackSlash,
-            Key::Comma => RdevKey::Comma,
-            Key::Period => RdevKey::Dot,
-            Key::Slash => RdevKey::Slash,
-            Key::Grave => RdevKey::BackQuote,
-
-            Key::Unknown => {
-                warn!("Attempted to simulate unknown key");
-                return Err(RemoteDeskError::Generic("Unknown key".to_string()));
+    /// Moves the pointer by a delta using the platform's native input API
+    fn send_relative_motion(&self, dx: i32, dy: i32) -> Result<()> {
+        match self.pointer.move_by(dx, dy) {
+            Ok(()) => {
+                self.events_simulated.fetch_add(1, Ordering::Relaxed);
+                Ok(())
             }
-        };
+            Err(e) => {
+                self.events_failed.fetch_add(1, Ordering::Relaxed);
+                Err(RemoteDeskError::Generic(format!(
+                    "Failed to simulate relative mouse move: {}",
+                    e
+                )))
+            }
+        }
+    }
+
+    /// Types a character through the platform's native Unicode input
+    fn send_unicode(&self, c: char) -> Result<()> {
+        match self.typer.type_char(c) {
+            Ok(()) => {
+                self.events_simulated.fetch_add(1, Ordering::Relaxed);
 
-        Ok(rdev_key)
+                if self.event_delay_ms > 0 {
+                    std::thread::sleep(std::time::Duration::from_millis(self.event_delay_ms));
+                }
+
+                Ok(())
+            }
+            Err(e) => {
+                self.events_failed.fetch_add(1, Ordering::Relaxed);
+                Err(RemoteDeskError::Generic(format!("Failed to type '{}': {}", c, e)))
+            }
+        }
+    }
+
+    /// Converts our Key enum to rdev Key
+    fn convert_key(&self, key: Key) -> Result<RdevKey> {
+        key.to_rdev().ok_or_else(|| {
+            warn!("Attempted to simulate unsupported key {:?}", key);
+            RemoteDeskError::Generic(format!("Unsupported key: {:?}", key))
+        })
     }
 
     /// Converts our MouseButton to rdev Button
@@ -251,13 +230,21 @@ impl InputSimulator {
         }
     }
 
-    /// Types a text string by simulating key presses
+    /// Types a text string
+    ///
+    /// ASCII characters are typed as key presses; any other character is
+    /// sent as Unicode through the platform's native input API.
     ///
     /// # Errors
     ///
     /// Returns error if any key simulation fails
     pub fn type_string(&self, text: &str) -> Result<()> {
         for c in text.chars() {
+            if !c.is_ascii() {
+                self.send_unicode(c)?;
+                continue;
+            }
+
             let key = self.char_to_key(c)?;
 
             // Check if we need to hold shift
@@ -0,0 +1,223 @@
+//! Unicode text input
+//!
+//! Typing text as key presses only covers characters on a US keyboard
+//! layout. These helpers send any Unicode character through each
+//! platform's native input API instead.
+
+#[cfg(target_os = "linux")]
+use super::relative::XDisplay;
+
+/// Keysym that leaves a keycode unbound
+#[cfg(target_os = "linux")]
+const NO_SYMBOL: x11::xlib::KeySym = 0;
+
+/// Time the focused application gets to look up a remapped key before the
+/// mapping is restored
+#[cfg(target_os = "linux")]
+const KEYMAP_SETTLE_DELAY: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// Types Unicode characters
+///
+/// On Linux this keeps one X display connection, opened on first use and
+/// closed on drop, and types each character by briefly binding its keysym
+/// to an unused keycode.
+#[derive(Default)]
+pub(crate) struct UnicodeTyper {
+    #[cfg(target_os = "linux")]
+    display: std::sync::Mutex<Option<XDisplay>>,
+}
+
+impl UnicodeTyper {
+    /// Types a single character
+    pub(crate) fn type_char(&self, c: char) -> Result<(), String> {
+        #[cfg(target_os = "linux")]
+        {
+            let mut display = self.display.lock().unwrap_or_else(|e| e.into_inner());
+            type_unicode(&mut display, c)
+        }
+        #[cfg(not(target_os = "linux"))]
+        type_unicode(c)
+    }
+}
+
+/// Returns the X keysym for a character
+///
+/// Printable Latin-1 characters use their code point; every other
+/// character uses the Unicode keysym range.
+#[cfg(any(target_os = "linux", test))]
+fn keysym_for_char(c: char) -> u32 {
+    let code = c as u32;
+    if (0x20..=0x7e).contains(&code) || (0xa0..=0xff).contains(&code) {
+        code
+    } else {
+        0x0100_0000 + code
+    }
+}
+
+/// Returns the UTF-16 code units Windows expects for a character
+#[cfg(any(target_os = "windows", test))]
+fn utf16_units(c: char) -> Vec<u16> {
+    let mut buf = [0u16; 2];
+    c.encode_utf16(&mut buf).to_vec()
+}
+
+/// Types a character through XTest, opening the display if needed
+#[cfg(target_os = "linux")]
+fn type_unicode(display: &mut Option<XDisplay>, c: char) -> Result<(), String> {
+    use std::os::raw::c_uint;
+    use x11::{xlib, xtest};
+
+    let display = match display {
+        Some(display) => display,
+        None => display.insert(XDisplay::open()?),
+    };
+
+    // SAFETY: the display connection is open and not used by anyone else
+    // while we hold it, and Xlib only reads the two keysyms we pass.
+    unsafe {
+        let keycode = spare_keycode(display.0).ok_or("no unused keycode to type with")?;
+
+        // Both levels, so Xlib doesn't derive a case for letters
+        let mut keysyms = [keysym_for_char(c) as xlib::KeySym; 2];
+        xlib::XChangeKeyboardMapping(display.0, keycode, 2, keysyms.as_mut_ptr(), 1);
+        xlib::XSync(display.0, xlib::False);
+
+        let typed = xtest::XTestFakeKeyEvent(display.0, keycode as c_uint, xlib::True, 0) != 0
+            && xtest::XTestFakeKeyEvent(display.0, keycode as c_uint, xlib::False, 0) != 0;
+        xlib::XSync(display.0, xlib::False);
+
+        std::thread::sleep(KEYMAP_SETTLE_DELAY);
+        let mut unbound = [NO_SYMBOL; 2];
+        xlib::XChangeKeyboardMapping(display.0, keycode, 2, unbound.as_mut_ptr(), 1);
+        xlib::XFlush(display.0);
+
+        if typed {
+            Ok(())
+        } else {
+            Err("XTest rejected key event".to_string())
+        }
+    }
+}
+
+/// Finds a keycode with no keysyms bound, searching from the top of the range
+///
+/// # Safety
+///
+/// `display` must be an open connection not used by another thread.
+#[cfg(target_os = "linux")]
+unsafe fn spare_keycode(display: *mut x11::xlib::Display) -> Option<std::os::raw::c_int> {
+    use x11::xlib;
+
+    let (mut min, mut max) = (0, 0);
+    xlib::XDisplayKeycodes(display, &mut min, &mut max);
+    let count = max - min + 1;
+
+    let mut per_keycode = 0;
+    let mapping = xlib::XGetKeyboardMapping(display, min as u8, count, &mut per_keycode);
+    if mapping.is_null() {
+        return None;
+    }
+
+    let per_keycode = per_keycode as usize;
+    let keysyms = std::slice::from_raw_parts(mapping, count as usize * per_keycode);
+    let spare = keysyms
+        .chunks(per_keycode)
+        .rposition(|bound| bound.iter().all(|&keysym| keysym == NO_SYMBOL))
+        .map(|index| min + index as std::os::raw::c_int);
+    xlib::XFree(mapping.cast());
+
+    spare
+}
+
+/// Types a character as Unicode key events
+#[cfg(target_os = "windows")]
+fn type_unicode(c: char) -> Result<(), String> {
+    use std::mem;
+    use winapi::um::winuser::{
+        SendInput, INPUT, INPUT_KEYBOARD, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE,
+    };
+
+    let mut inputs = Vec::new();
+    for unit in utf16_units(c) {
+        for flags in [KEYEVENTF_UNICODE, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP] {
+            // SAFETY: INPUT is a plain C struct for which all-zero is a
+            // valid value.
+            let mut input: INPUT = unsafe { mem::zeroed() };
+            input.type_ = INPUT_KEYBOARD;
+            // SAFETY: the union holds keyboard input, as `type_` says
+            let key = unsafe { input.u.ki_mut() };
+            key.wScan = unit;
+            key.dwFlags = flags;
+            inputs.push(input);
+        }
+    }
+
+    // SAFETY: SendInput only reads the elements we pass
+    let sent = unsafe {
+        SendInput(
+            inputs.len() as u32,
+            inputs.as_mut_ptr(),
+            mem::size_of::<INPUT>() as i32,
+        )
+    };
+
+    if sent as usize == inputs.len() {
+        Ok(())
+    } else {
+        Err("SendInput rejected key events".to_string())
+    }
+}
+
+/// Types a character as a keyboard event carrying its text
+#[cfg(target_os = "macos")]
+fn type_unicode(c: char) -> Result<(), String> {
+    use core_graphics::event::{CGEvent, CGEventTapLocation};
+    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+
+    let mut buf = [0u8; 4];
+    let text = c.encode_utf8(&mut buf);
+
+    for key_down in [true, false] {
+        let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+            .map_err(|_| "cannot create event source".to_string())?;
+        // The attached string replaces whatever the key code would type
+        let event = CGEvent::new_keyboard_event(source, 0, key_down)
+            .map_err(|_| "cannot create keyboard event".to_string())?;
+        event.set_string(text);
+        event.post(CGEventTapLocation::HID);
+    }
+
+    Ok(())
+}
+
+/// Types a character as Unicode key events
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn type_unicode(_c: char) -> Result<(), String> {
+    Err("Unicode typing is not supported on this platform".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keysym_for_char() {
+        assert_eq!(keysym_for_char('a'), 0x61);
+        assert_eq!(keysym_for_char('~'), 0x7e);
+        assert_eq!(keysym_for_char('é'), 0xe9);
+        assert_eq!(keysym_for_char('ÿ'), 0xff);
+        assert_eq!(keysym_for_char('€'), 0x0100_20ac);
+        assert_eq!(keysym_for_char('日'), 0x0100_65e5);
+        assert_eq!(keysym_for_char('😀'), 0x0101_f600);
+        // C1 controls fall outside the Latin-1 keysyms
+        assert_eq!(keysym_for_char('\u{85}'), 0x0100_0085);
+    }
+
+    #[test]
+    fn test_utf16_units() {
+        assert_eq!(utf16_units('a'), vec![0x61]);
+        assert_eq!(utf16_units('é'), vec![0xe9]);
+        assert_eq!(utf16_units('€'), vec![0x20ac]);
+        assert_eq!(utf16_units('😀'), vec![0xd83d, 0xde00]);
+    }
+}
@@ -5,7 +5,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Mouse button identifier
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum MouseButton {
     /// Left mouse button
@@ -47,6 +47,13 @@ pub enum MouseEventType {
         /// Vertical scroll delta
         delta_y: i32,
     },
+    /// Mouse moved by a relative amount (pointer lock)
+    MoveRelative {
+        /// Horizontal movement in pixels
+        dx: i32,
+        /// Vertical movement in pixels
+        dy: i32,
+    },
 }
 
 /// Mouse input event
@@ -67,6 +74,14 @@ impl MouseEvent {
         }
     }
 
+    /// Creates a new relative mouse move event
+    pub fn move_by(dx: i32, dy: i32) -> Self {
+        Self {
+            event_type: MouseEventType::MoveRelative { dx, dy },
+            timestamp: Self::current_timestamp(),
+        }
+    }
+
     /// Creates a new mouse button press event
     pub fn button_press(button: MouseButton) -> Self {
         Self {
@@ -102,8 +117,9 @@ impl MouseEvent {
 
 /// Keyboard key identifier
 ///
-/// Represents common keyboard keys in a platform-independent way
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Represents common keyboard keys in a platform-independent way. The
+/// discriminants are the wire codes; see `input::keymap` for conversions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(u16)]
 pub enum Key {
     // Letters (A-Z)
@@ -207,16 +223,48 @@ pub enum Key {
     F11 = 0x7A,
     /// F12 key
     F12 = 0x7B,
+    /// F13 key
+    F13 = 0x7C,
+    /// F14 key
+    F14 = 0x7D,
+    /// F15 key
+    F15 = 0x7E,
+    /// F16 key
+    F16 = 0x7F,
+    /// F17 key
+    F17 = 0x80,
+    /// F18 key
+    F18 = 0x81,
+    /// F19 key
+    F19 = 0x82,
+    /// F20 key
+    F20 = 0x83,
+    /// F21 key
+    F21 = 0x84,
+    /// F22 key
+    F22 = 0x85,
+    /// F23 key
+    F23 = 0x86,
+    /// F24 key
+    F24 = 0x87,
 
     // Modifier keys
-    /// Shift key
+    /// Shift key (left, or side unknown)
     Shift = 0x10,
-    /// Control key
+    /// Control key (left, or side unknown)
     Control = 0x11,
-    /// Alt key
+    /// Alt key (left, or side unknown)
     Alt = 0x12,
-    /// Meta/Windows/Command key
+    /// Meta/Super/Windows/Command key (left, or side unknown)
     Meta = 0x5B,
+    /// Right Shift key
+    ShiftRight = 0xA1,
+    /// Right Control key
+    ControlRight = 0xA3,
+    /// Right Alt/AltGr key
+    AltRight = 0xA5,
+    /// Right Meta/Super/Windows/Command key
+    MetaRight = 0x5C,
 
     // Navigation keys
     /// Up arrow
@@ -253,6 +301,50 @@ pub enum Key {
     Insert = 0x2D,
     /// Caps Lock
     CapsLock = 0x14,
+    /// Num Lock
+    NumLock = 0x90,
+    /// Scroll Lock
+    ScrollLock = 0x91,
+    /// Print Screen
+    PrintScreen = 0x2C,
+    /// Pause/Break
+    Pause = 0x13,
+    /// Context menu key
+    ContextMenu = 0x5D,
+
+    // Numpad
+    /// Numpad 0
+    Numpad0 = 0x60,
+    /// Numpad 1
+    Numpad1 = 0x61,
+    /// Numpad 2
+    Numpad2 = 0x62,
+    /// Numpad 3
+    Numpad3 = 0x63,
+    /// Numpad 4
+    Numpad4 = 0x64,
+    /// Numpad 5
+    Numpad5 = 0x65,
+    /// Numpad 6
+    Numpad6 = 0x66,
+    /// Numpad 7
+    Numpad7 = 0x67,
+    /// Numpad 8
+    Numpad8 = 0x68,
+    /// Numpad 9
+    Numpad9 = 0x69,
+    /// Numpad multiply
+    NumpadMultiply = 0x6A,
+    /// Numpad add
+    NumpadAdd = 0x6B,
+    /// Numpad subtract
+    NumpadSubtract = 0x6D,
+    /// Numpad decimal point
+    NumpadDecimal = 0x6E,
+    /// Numpad divide
+    NumpadDivide = 0x6F,
+    /// Numpad Enter (Return with the extended-key bit set)
+    NumpadEnter = 0x10D,
 
     // Punctuation and symbols
     /// Minus/Underscore key
@@ -277,6 +369,24 @@ pub enum Key {
     Slash = 0xBF,
     /// Backtick/Grave
     Grave = 0xC0,
+    /// Extra backslash key on ISO keyboards
+    IntlBackslash = 0xE2,
+
+    // Media keys
+    /// Mute volume
+    VolumeMute = 0xAD,
+    /// Volume down
+    VolumeDown = 0xAE,
+    /// Volume up
+    VolumeUp = 0xAF,
+    /// Next track
+    MediaNextTrack = 0xB0,
+    /// Previous track
+    MediaPrevTrack = 0xB1,
+    /// Stop media
+    MediaStop = 0xB2,
+    /// Play/Pause media
+    MediaPlayPause = 0xB3,
 
     /// Unknown key
     Unknown = 0xFFFF,
@@ -330,13 +440,91 @@ impl KeyboardEvent {
     }
 }
 
-/// Generic input event (keyboard or mouse)
+/// Text input event
+///
+/// Carries characters as produced by the client's keyboard layout, so the
+/// host types the same text regardless of its own layout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEvent {
+    /// Text that was typed
+    pub text: String,
+    /// Timestamp when event occurred (milliseconds since epoch)
+    pub timestamp: u64,
+}
+
+impl TextEvent {
+    /// Creates a new text event
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            timestamp: Self::current_timestamp(),
+        }
+    }
+
+    /// Gets current timestamp in milliseconds
+    fn current_timestamp() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
+/// How the viewer sends keyboard input to the host
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyboardMode {
+    /// Send key codes for every key; the host's layout decides the characters
+    #[default]
+    Keycode,
+    /// Send typed characters as text and key codes only for shortcuts and
+    /// non-printing keys; works across different keyboard layouts
+    Text,
+}
+
+/// Generic input event (keyboard, mouse or text)
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum InputEvent {
     /// Keyboard event
     Keyboard(KeyboardEvent),
     /// Mouse event
     Mouse(MouseEvent),
+    /// Text event
+    Text(TextEvent),
+    /// Events injected together, in order, as one unit (key macros)
+    Batch(Vec<BatchEvent>),
+}
+
+/// Keyboard or mouse event injected as part of a batch
+///
+/// Batches hold only these, so a batch can never contain another batch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BatchEvent {
+    /// Keyboard event
+    Keyboard(KeyboardEvent),
+    /// Mouse event
+    Mouse(MouseEvent),
+}
+
+impl From<KeyboardEvent> for BatchEvent {
+    fn from(event: KeyboardEvent) -> Self {
+        BatchEvent::Keyboard(event)
+    }
+}
+
+impl From<MouseEvent> for BatchEvent {
+    fn from(event: MouseEvent) -> Self {
+        BatchEvent::Mouse(event)
+    }
+}
+
+impl From<BatchEvent> for InputEvent {
+    fn from(event: BatchEvent) -> Self {
+        match event {
+            BatchEvent::Keyboard(event) => InputEvent::Keyboard(event),
+            BatchEvent::Mouse(event) => InputEvent::Mouse(event),
+        }
+    }
 }
 
 impl From<KeyboardEvent> for InputEvent {
@@ -351,6 +539,12 @@ impl From<MouseEvent> for InputEvent {
     }
 }
 
+impl From<TextEvent> for InputEvent {
+    fn from(event: TextEvent) -> Self {
+        InputEvent::Text(event)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -376,6 +570,12 @@ mod tests {
                 delta_y: -10
             }
         ));
+
+        let event = MouseEvent::move_by(-3, 7);
+        assert!(matches!(
+            event.event_type,
+            MouseEventType::MoveRelative { dx: -3, dy: 7 }
+        ));
     }
 
     #[test]
@@ -398,6 +598,17 @@ mod tests {
         let mouse_event = MouseEvent::move_to(50, 50);
         let input_event: InputEvent = mouse_event.into();
         assert!(matches!(input_event, InputEvent::Mouse(_)));
+
+        let input_event: InputEvent = TextEvent::new("ä").into();
+        assert!(matches!(input_event, InputEvent::Text(_)));
+    }
+
+    #[test]
+    fn test_text_event_serialization() {
+        let event = InputEvent::Text(TextEvent::new("Grüße, 世界"));
+        let serialized = bincode::serialize(&event).unwrap();
+        let deserialized: InputEvent = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(event, deserialized);
     }
 
     #[test]
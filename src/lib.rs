@@ -33,11 +33,13 @@ pub mod clipboard;
 pub mod config;
 pub mod desktop;
 pub mod error;
+pub mod files;
 pub mod input;
 pub mod logging;
 pub mod network;
 pub mod security;
 pub mod session;
+pub mod terminal;
 pub mod ui;
 
 // Re-export commonly used types at crate root
@@ -3,12 +3,22 @@
 //! This is the main entry point for the RemoteDesk application.
 
 use remote_desk::{
+    clipboard::TransferDirection,
     config::ConfigManager,
     error::Result,
+    files::{BrowseRoots, TransferInfo},
+    input::KeyMacro,
     logging::{init_logging, LogLevel},
-    network::{ConnectionManager, ManagerConfig},
-    security::{DeviceIdManager, PasswordManager},
+    network::{
+        ConnectionEvent, ConnectionManager, ForwardDirection, ForwardInfo, ForwardPolicy,
+        ForwardSpec, ManagerConfig, RejectReason,
+    },
+    security::{AuditFilter, AuditRecord, DeviceIdManager, PasswordManager},
+    session::{ChatEntry, RemoteEntry, SessionManager},
+    terminal::TerminalEvent,
 };
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 use tracing::{error, info};
 
 /// Application state
@@ -16,6 +26,8 @@ struct App {
     config_manager: ConfigManager,
     device_id: remote_desk::security::DeviceId,
     connection_manager: ConnectionManager,
+    session_manager: SessionManager,
+    macros: Vec<KeyMacro>,
 }
 
 impl App {
@@ -57,21 +69,53 @@ impl App {
         info!("Desktop - FPS: {}", config.desktop.default_fps);
         info!("Security - Session timeout: {} minutes", config.security.session_timeout_minutes);
         info!("Clipboard - Enabled: {}", config.clipboard.enabled);
+        info!("Files - Download directory: {:?}", config.files.download_dir());
+        if let Some(dir) = &config.files.drop_dir {
+            info!("Files - Drop directory: {:?}", dir);
+        }
+        if !config.files.browse_roots.is_empty() {
+            info!("Files - Browse roots: {:?}", config.files.browse_roots);
+        }
+        info!("Terminal - Enabled: {}", config.terminal.enabled);
+        info!("Forwarding - Enabled: {}", config.forwarding.enabled);
+
+        let device_name = hostname::get()
+            .ok()
+            .and_then(|h| h.into_string().ok())
+            .unwrap_or_else(|| format!("RemoteDesk-{}", device_id.as_u32()));
 
         // Create connection manager
         let manager_config = ManagerConfig {
             device_id,
-            device_name: hostname::get()
-                .ok()
-                .and_then(|h| h.into_string().ok())
-                .unwrap_or_else(|| format!("RemoteDesk-{}", device_id.as_u32())),
+            device_name: device_name.clone(),
             service_port: config.network.listen_port,
             config_dir: config_manager.config_directory().clone(),
             password_hash_path: config_manager.password_hash_path(),
+            policy_path: config_manager.policy_path(),
+            audit_log_path: config_manager.connection_log_path(),
             max_connections: config.network.max_connections as usize,
+            rate_limit: config.security.rate_limit.clone(),
+            audit: config.security.audit.clone(),
+            // The CLI itself uses no custom channels
+            channels: Vec::new(),
         };
 
         let mut connection_manager = ConnectionManager::new(manager_config)?;
+        let mut session_manager = SessionManager::with_local_id(device_id.as_u32().to_string())
+            .with_download_dir(config.files.download_dir())
+            .with_device_name(device_name)
+            .with_audit_log(connection_manager.audit_log().clone())
+            .with_security_config(&config.security)
+            .with_clipboard(&config.clipboard);
+        if let Some(dir) = config.files.drop_dir.clone() {
+            session_manager = session_manager.with_drop_dir(dir);
+        }
+        session_manager =
+            session_manager.with_browse_roots(BrowseRoots::new(config.files.browse_roots.clone()));
+        session_manager = session_manager.with_terminal(&config.terminal);
+        session_manager =
+            session_manager.with_forward_policy(ForwardPolicy::from_config(&config.forwarding)?);
+        let macros = KeyMacro::with_custom(&config.input.macros)?;
 
         // Start connection manager
         if let Err(e) = connection_manager.start().await {
@@ -83,6 +127,8 @@ impl App {
             config_manager,
             device_id,
             connection_manager,
+            session_manager,
+            macros,
         })
     }
 
@@ -137,6 +183,24 @@ impl App {
         Ok(())
     }
 
+    /// Prints chat messages from the peer of a session as they arrive
+    async fn watch_chat(&self, session_id: &str) {
+        let Ok(chat) = self.session_manager.chat(session_id).await else {
+            return;
+        };
+
+        let mut entries = chat.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match entries.recv().await {
+                    Ok(entry) if !entry.outgoing => info!("{}", format_chat_entry(&entry)),
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
     /// Handles CLI input for basic commands
     async fn handle_cli_input(&mut self) -> Result<()> {
         use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -156,6 +220,10 @@ impl App {
                     println!();
                     break;
                 }
+                Some(event) = self.connection_manager.recv_event() => {
+                    println!();
+                    show_connection_event(event);
+                }
                 line = reader.next_line() => {
                     match line {
                         Ok(Some(input)) => {
@@ -164,6 +232,17 @@ impl App {
                                 continue;
                             }
 
+                            // The shell reads further lines itself
+                            let parts: Vec<&str> = input.split_whitespace().collect();
+                            if parts[0] == "shell" {
+                                if parts.len() < 2 {
+                                    error!("Usage: shell <session>");
+                                } else if let Err(e) = self.run_shell(parts[1], &mut reader).await {
+                                    error!("Shell error: {}", e);
+                                }
+                                continue;
+                            }
+
                             if let Err(e) = self.handle_command(input).await {
                                 error!("Command error: {}", e);
                             }
@@ -186,6 +265,55 @@ impl App {
         Ok(())
     }
 
+    /// Runs a shell on the host of a session until it exits or the user
+    /// types `~.` on a line of its own
+    ///
+    /// Input is sent a line at a time; Ctrl+C is passed on to the shell.
+    async fn run_shell(
+        &self,
+        session_id: &str,
+        reader: &mut tokio::io::Lines<tokio::io::BufReader<tokio::io::Stdin>>,
+    ) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let terminals = self.session_manager.terminals(session_id).await?;
+        let (cols, rows) = shell_size();
+        let mut terminal = terminals.open(cols, rows).await?;
+        info!("Connected to the remote shell. Type ~. on a line of its own to leave.");
+
+        let mut stdout = tokio::io::stdout();
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => terminal.write(&[0x03]).await?,
+                event = terminal.recv() => match event {
+                    Some(TerminalEvent::Output(data)) => {
+                        let _ = stdout.write_all(&data).await;
+                        let _ = stdout.flush().await;
+                    }
+                    Some(TerminalEvent::Closed(reason)) => {
+                        println!();
+                        info!("Remote shell closed: {}", reason);
+                        break;
+                    }
+                    None => break,
+                },
+                line = reader.next_line() => match line {
+                    Ok(Some(line)) if line.trim() == "~." => break,
+                    Ok(Some(line)) => terminal.write(format!("{}\n", line).as_bytes()).await?,
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Failed to read input: {}", e);
+                        break;
+                    }
+                },
+            }
+        }
+
+        // Dropping the terminal ends the remote shell
+        println!();
+        Ok(())
+    }
+
     /// Handles a single command
     async fn handle_command(&mut self, input: &str) -> Result<()> {
         let parts: Vec<&str> = input.split_whitespace().collect();
@@ -205,8 +333,31 @@ impl App {
                 info!("                             Example: disconnect 123 456 789");
                 info!("  password <new_password>  - Set a password for this device");
                 info!("  remove-password          - Remove the password (use manual accept)");
+                info!("  allow <request>          - Accept an incoming connection request");
+                info!("  deny <request>           - Reject an incoming connection request");
                 info!("  id                       - Show your device ID");
                 info!("  status                   - Show current status and connections");
+                info!("  history [filters]        - Show connection audit history");
+                info!("                             --peer <ID>  --event <type>");
+                info!("                             --since <30m|24h|7d>  --limit <N>");
+                info!("  macros                   - List key macros");
+                info!("  keys <session> <macro>   - Send a key macro or sequence to a session");
+                info!("                             Example: keys <session> Ctrl+Alt+Del");
+                info!("                             Example: keys <session> Super+R, Escape");
+                info!("  send <session> <path>    - Offer a file to the peer of a session");
+                info!("  transfers                - List file transfers");
+                info!("  accept <session> <ID>    - Accept a file the peer offered");
+                info!("  decline <session> <ID>   - Decline an offer or cancel a transfer");
+                info!("  ls <session> [path]      - List a directory the host shares");
+                info!("  get <session> <path>     - Download a file the host shares");
+                info!("  chat <session> [message] - Send a chat message, or show the chat");
+                info!("  shell <session>          - Open a shell on the host (~. to leave)");
+                info!("  forward <session> [-R] <port:host:port>");
+                info!("                           - Forward a local port to a host:port on the");
+                info!("                             peer (-R: a peer port to one here)");
+                info!("                             Example: forward <session> 8080:localhost:80");
+                info!("  forward <session>        - List a session's forwarded ports");
+                info!("  unforward <session> <ID> - Stop forwarding a port");
                 info!("  help                     - Show this help message");
                 info!("  quit / exit              - Exit the application");
                 info!("");
@@ -274,15 +425,20 @@ impl App {
 
                         // Attempt connection
                         match self.connection_manager.connect(remote_id, password.map(|s| s.to_string())).await {
-                            Ok(_) => {
-                                println!();
-                                println!("✓ Connection initiated successfully!");
-                                println!("  Status: Connected to {}", formatted_id);
-                                println!();
-                                println!("NOTE: This is a simulated local connection (Milestone 1.2)");
-                                println!("      Actual network connectivity requires QUIC implementation.");
-                                println!("      Use 'status' to see the connection state.");
-                                println!();
+                            Ok(connection) => {
+                                let session =
+                                    self.session_manager.create_connected_session(connection);
+                                match session.await {
+                                    Ok(session_id) => {
+                                        println!();
+                                        println!("✓ Connected to {}", formatted_id);
+                                        println!("  Session: {}", session_id);
+                                        println!("  Use 'status' to see the session state.");
+                                        println!();
+                                        self.watch_chat(&session_id).await;
+                                    }
+                                    Err(e) => println!("✗ Failed to start session: {}", e),
+                                }
                             }
                             Err(e) => {
                                 println!("✗ Connection failed: {}", e);
@@ -295,6 +451,41 @@ impl App {
                     }
                 }
             }
+            "allow" | "deny" => {
+                let connection_id = match parts.get(1).map(|id| id.parse::<u64>()) {
+                    Some(Ok(id)) => id,
+                    _ => {
+                        error!("Usage: {} <request>", parts[0]);
+                        return Ok(());
+                    }
+                };
+
+                if parts[0] == "deny" {
+                    let reason = RejectReason::UserDenied;
+                    match self.connection_manager.reject_connection(connection_id, reason).await {
+                        Ok(()) => info!("Rejected connection request {}", connection_id),
+                        Err(e) => error!("Failed to reject request {}: {}", connection_id, e),
+                    }
+                    return Ok(());
+                }
+
+                let accepted = self.connection_manager.accept_connection(connection_id).await;
+                let connection = match accepted {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        error!("Failed to accept request {}: {}", connection_id, e);
+                        return Ok(());
+                    }
+                };
+                let peer = connection.remote_name.clone();
+                match self.session_manager.create_connected_session(connection).await {
+                    Ok(session_id) => {
+                        info!("Session {} started with {}", session_id, peer);
+                        self.watch_chat(&session_id).await;
+                    }
+                    Err(e) => error!("Failed to start session with {}: {}", peer, e),
+                }
+            }
             "password" => {
                 if parts.len() < 2 {
                     error!("Usage: password <new_password>");
@@ -371,8 +562,265 @@ impl App {
                     }
                 }
 
+                let sessions = self.session_manager.list_sessions().await;
+                if !sessions.is_empty() {
+                    info!("  Sessions: {}", sessions.len());
+                    for session in sessions {
+                        info!("    - {} ({:?}) [{}]", session.id, session.session_type, session.state);
+                    }
+                }
+
                 info!("");
             }
+            "history" => {
+                let filter = match parse_history_filter(&parts[1..]) {
+                    Ok(filter) => filter,
+                    Err(e) => {
+                        error!("{}", e);
+                        error!("Usage: history [--peer <ID>] [--event <type>] [--since <30m|24h|7d>] [--limit <N>]");
+                        return Ok(());
+                    }
+                };
+
+                let audit_log = self.connection_manager.audit_log();
+                match audit_log.read(&filter) {
+                    Ok(records) if records.is_empty() => {
+                        println!("No matching connection history in {:?}", audit_log.path());
+                    }
+                    Ok(records) => {
+                        println!();
+                        for record in &records {
+                            println!("{}", format_history_record(record));
+                        }
+                        println!();
+                    }
+                    Err(e) => {
+                        error!("Failed to read connection history: {}", e);
+                    }
+                }
+            }
+            "macros" => {
+                println!();
+                for key_macro in &self.macros {
+                    println!("  {:<20} {}", key_macro.name, key_macro.sequence);
+                }
+                println!();
+            }
+            "keys" => {
+                if parts.len() < 3 {
+                    error!("Usage: keys <session> <macro name or key sequence>");
+                    error!("Example: keys <session> Ctrl+Alt+Del");
+                    return Ok(());
+                }
+
+                let session_id = parts[1];
+                let spec = parts[2..].join(" ");
+                let sequence = match KeyMacro::resolve(&spec, &self.macros) {
+                    Ok(sequence) => sequence,
+                    Err(e) => {
+                        error!("{}", e);
+                        error!("Type 'macros' to list the available macros");
+                        return Ok(());
+                    }
+                };
+
+                match self.session_manager.send_key_sequence(session_id, &sequence).await {
+                    Ok(()) => info!("Sent {} to session {}", sequence, session_id),
+                    Err(e) => error!("Failed to send keys: {}", e),
+                }
+            }
+            "send" => {
+                if parts.len() < 3 {
+                    error!("Usage: send <session> <path>");
+                    return Ok(());
+                }
+
+                let session_id = parts[1];
+                let path = std::path::PathBuf::from(parts[2..].join(" "));
+                let transfers = self.session_manager.file_transfers(session_id).await?;
+
+                match transfers.send_file(&path).await {
+                    Ok(transfer_id) => info!(
+                        "Offered {} as transfer {}; waiting for the peer to accept",
+                        path.display(),
+                        transfer_id
+                    ),
+                    Err(e) => error!("Failed to send {}: {}", path.display(), e),
+                }
+            }
+            "transfers" => {
+                let transfers = self.session_manager.list_transfers().await;
+                if transfers.is_empty() {
+                    println!("No file transfers");
+                    return Ok(());
+                }
+
+                println!();
+                for (session_id, transfer) in &transfers {
+                    println!("{}", format_transfer(session_id, transfer));
+                }
+                println!();
+            }
+            "accept" | "decline" => {
+                let transfer_id = match parts.get(2).map(|id| id.parse::<u64>()) {
+                    Some(Ok(id)) => id,
+                    _ => {
+                        error!("Usage: {} <session> <transfer ID>", parts[0]);
+                        error!("Type 'transfers' to list the transfers");
+                        return Ok(());
+                    }
+                };
+
+                let transfers = self.session_manager.file_transfers(parts[1]).await?;
+                let result = if parts[0] == "accept" {
+                    transfers.accept(transfer_id).await
+                } else {
+                    transfers.cancel(transfer_id).await
+                };
+
+                match result {
+                    Ok(()) if parts[0] == "accept" => info!(
+                        "Receiving transfer {} into {:?}",
+                        transfer_id,
+                        transfers.download_dir()
+                    ),
+                    Ok(()) => info!("Stopped transfer {}", transfer_id),
+                    Err(e) => error!("Failed to {} transfer {}: {}", parts[0], transfer_id, e),
+                }
+            }
+            "ls" => {
+                if parts.len() < 2 {
+                    error!("Usage: ls <session> [path]");
+                    return Ok(());
+                }
+
+                let path = if parts.len() > 2 {
+                    parts[2..].join(" ")
+                } else {
+                    "/".to_string()
+                };
+                let browser = self.session_manager.file_browser(parts[1]).await?;
+
+                match browser.list(&path).await {
+                    Ok(entries) if entries.is_empty() => println!("{} is empty", path),
+                    Ok(entries) => {
+                        println!();
+                        for entry in &entries {
+                            println!("{}", format_entry(entry));
+                        }
+                        println!();
+                    }
+                    Err(e) => error!("Failed to list {}: {}", path, e),
+                }
+            }
+            "get" => {
+                if parts.len() < 3 {
+                    error!("Usage: get <session> <path>");
+                    error!("Type 'ls <session>' to see what the host shares");
+                    return Ok(());
+                }
+
+                let path = parts[2..].join(" ");
+                let transfers = self.session_manager.file_transfers(parts[1]).await?;
+                info!("Downloading {} into {:?}", path, transfers.download_dir());
+
+                // Large files take a while; keep the prompt responsive
+                tokio::spawn(async move {
+                    let dir = transfers.download_dir().to_path_buf();
+                    match transfers.fetch_remote_file(&path, &dir).await {
+                        Ok(saved) => info!("Downloaded {} to {:?}", path, saved),
+                        Err(e) => error!("Failed to download {}: {}", path, e),
+                    }
+                });
+            }
+            "chat" => {
+                if parts.len() < 2 {
+                    error!("Usage: chat <session> [message]");
+                    return Ok(());
+                }
+
+                let chat = self.session_manager.chat(parts[1]).await?;
+                if parts.len() == 2 {
+                    let history = chat.history();
+                    if history.is_empty() {
+                        println!("No chat messages with {}", chat.peer_name());
+                        return Ok(());
+                    }
+
+                    println!();
+                    for entry in &history {
+                        println!("{}", format_chat_entry(entry));
+                    }
+                    println!();
+                    return Ok(());
+                }
+
+                if let Err(e) = chat.send(&parts[2..].join(" ")).await {
+                    error!("Failed to send chat message: {}", e);
+                }
+            }
+            "forward" => {
+                if parts.len() < 2 {
+                    error!("Usage: forward <session> [-R] <bind_port:host:port>");
+                    error!("Example: forward <session> 8080:localhost:80");
+                    return Ok(());
+                }
+
+                let forwarder = self.session_manager.port_forwarder(parts[1]).await?;
+                if parts.len() == 2 {
+                    let forwards = forwarder.list();
+                    if forwards.is_empty() {
+                        println!("No forwarded ports");
+                        return Ok(());
+                    }
+
+                    println!();
+                    for forward in &forwards {
+                        println!("{}", format_forward(forward));
+                    }
+                    println!();
+                    return Ok(());
+                }
+
+                let remote = parts[2] == "-R";
+                let spec = match parts.get(if remote { 3 } else { 2 }).map(|s| s.parse::<ForwardSpec>()) {
+                    Some(Ok(spec)) => spec,
+                    Some(Err(e)) => {
+                        error!("{}", e);
+                        return Ok(());
+                    }
+                    None => {
+                        error!("Usage: forward <session> [-R] <bind_port:host:port>");
+                        return Ok(());
+                    }
+                };
+
+                let result = if remote {
+                    forwarder.forward_remote(spec).await
+                } else {
+                    forwarder.forward_local(spec).await
+                };
+                match result {
+                    Ok(forward) => info!("{}", format_forward(&forward)),
+                    Err(e) => error!("Failed to forward port: {}", e),
+                }
+            }
+            "unforward" => {
+                let forward_id = match parts.get(2).map(|id| id.parse::<u64>()) {
+                    Some(Ok(id)) => id,
+                    _ => {
+                        error!("Usage: unforward <session> <forward ID>");
+                        error!("Type 'forward <session>' to list the forwarded ports");
+                        return Ok(());
+                    }
+                };
+
+                let forwarder = self.session_manager.port_forwarder(parts[1]).await?;
+                match forwarder.stop(forward_id) {
+                    Ok(()) => info!("Stopped forward {}", forward_id),
+                    Err(e) => error!("Failed to stop forward {}: {}", forward_id, e),
+                }
+            }
             "disconnect" => {
                 if parts.len() < 2 {
                     error!("Usage: disconnect <ID>");
@@ -420,6 +868,209 @@ impl App {
     }
 }
 
+/// Default number of entries shown by the `history` command
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+/// Size of remote shells when the local terminal's isn't known
+const DEFAULT_SHELL_COLS: u16 = 80;
+const DEFAULT_SHELL_ROWS: u16 = 24;
+
+/// Parses `history` command arguments into an audit filter
+fn parse_history_filter(args: &[&str]) -> std::result::Result<AuditFilter, String> {
+    let mut filter = AuditFilter {
+        limit: Some(DEFAULT_HISTORY_LIMIT),
+        ..Default::default()
+    };
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", flag))?;
+
+        match *flag {
+            "--peer" => {
+                let id = value
+                    .parse::<remote_desk::security::DeviceId>()
+                    .map_err(|e| format!("Invalid device ID: {}", e))?;
+                filter.peer_id = Some(id);
+            }
+            "--event" => filter.kind = Some(value.to_string()),
+            "--since" => {
+                let window_ms = parse_duration_ms(value)
+                    .ok_or_else(|| format!("Invalid duration: {} (e.g. 30m, 24h, 7d)", value))?;
+                filter.since_ms = Some(unix_time_ms().saturating_sub(window_ms));
+            }
+            "--limit" => {
+                let limit = value
+                    .parse()
+                    .map_err(|_| format!("Invalid limit: {}", value))?;
+                filter.limit = Some(limit);
+            }
+            other => return Err(format!("Unknown option: {}", other)),
+        }
+    }
+
+    Ok(filter)
+}
+
+/// Parses a duration like `30m`, `24h` or `7d` into milliseconds
+fn parse_duration_ms(value: &str) -> Option<u64> {
+    let (number, unit) = value.split_at(value.len().checked_sub(1)?);
+    let number: u64 = number.parse().ok()?;
+
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
+    };
+
+    number.checked_mul(unit_secs)?.checked_mul(1000)
+}
+
+/// Tells the user about a connection event, such as a request to answer
+fn show_connection_event(event: ConnectionEvent) {
+    match event {
+        ConnectionEvent::ConnectionRequest {
+            remote_id,
+            remote_name,
+            connection_id,
+            ..
+        } => {
+            info!(
+                "Connection request {} from {} ({})",
+                connection_id,
+                remote_name,
+                remote_id.format_with_spaces()
+            );
+            info!(
+                "Type 'allow {}' to accept it or 'deny {}' to reject it",
+                connection_id, connection_id
+            );
+        }
+        ConnectionEvent::Disconnected { remote_id, reason } => {
+            info!("{} disconnected: {}", remote_id.format_with_spaces(), reason);
+        }
+        _ => {}
+    }
+}
+
+/// Formats an audit record as a single line for display
+fn format_history_record(record: &AuditRecord) -> String {
+    let timestamp = time::OffsetDateTime::from_unix_timestamp((record.timestamp_ms / 1000) as i64)
+        .map(|t| {
+            format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+                t.year(),
+                t.month() as u8,
+                t.day(),
+                t.hour(),
+                t.minute(),
+                t.second()
+            )
+        })
+        .unwrap_or_else(|_| record.timestamp_ms.to_string());
+
+    let peer = record
+        .event
+        .peer_id()
+        .and_then(|id| remote_desk::security::DeviceId::from_u32(id).ok())
+        .map(|id| id.format_with_spaces())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let addr = record
+        .event
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_else(|| "-".to_string());
+
+    format!("{}  {}  {}  {}", timestamp, peer, addr, record.event)
+}
+
+/// Formats a chat message as a single line for display
+fn format_chat_entry(entry: &ChatEntry) -> String {
+    let time = time::OffsetDateTime::from_unix_timestamp((entry.timestamp_ms / 1000) as i64)
+        .map(|t| format!("{:02}:{:02}:{:02}", t.hour(), t.minute(), t.second()))
+        .unwrap_or_else(|_| "--:--:--".to_string());
+
+    format!("[{} UTC] {}: {}", time, entry.sender, entry.text)
+}
+
+/// Formats a file transfer as a single line for display
+fn format_transfer(session_id: &str, transfer: &TransferInfo) -> String {
+    let arrow = match transfer.direction {
+        TransferDirection::Outgoing => "->",
+        TransferDirection::Incoming => "<-",
+    };
+    let percent = (transfer.transferred * 100)
+        .checked_div(transfer.size)
+        .unwrap_or(100);
+
+    format!(
+        "{}  #{:<4} {} {}  {}/{} bytes ({}%)  {}",
+        session_id,
+        transfer.transfer_id,
+        arrow,
+        transfer.name,
+        transfer.transferred,
+        transfer.size,
+        percent,
+        transfer.status
+    )
+}
+
+/// Formats a forwarded port as a single line for display
+fn format_forward(forward: &ForwardInfo) -> String {
+    let (listener, target) = match forward.direction {
+        ForwardDirection::Local => ("here", "on the peer"),
+        ForwardDirection::Remote => ("on the peer", "here"),
+    };
+
+    format!(
+        "#{:<4} port {} {} -> {}:{} {}  ({} connections)",
+        forward.forward_id,
+        forward.spec.bind_port,
+        listener,
+        forward.spec.host,
+        forward.spec.port,
+        target,
+        forward.connections
+    )
+}
+
+fn format_entry(entry: &RemoteEntry) -> String {
+    if entry.is_dir {
+        format!("  {}/", entry.name)
+    } else {
+        format!("  {:<40} {:>12} bytes", entry.name, entry.size)
+    }
+}
+
+/// Returns the size to open remote shells with, from `$COLUMNS` and
+/// `$LINES` if the local shell exports them
+fn shell_size() -> (u16, u16) {
+    let from_env = |name: &str, default: u16| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(default)
+    };
+    (
+        from_env("COLUMNS", DEFAULT_SHELL_COLS),
+        from_env("LINES", DEFAULT_SHELL_ROWS),
+    )
+}
+
+fn unix_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
 #[tokio::main]
 async fn main() {
     // Initialize logging
@@ -0,0 +1,102 @@
+//! Bounded decoding of peer data
+//!
+//! `bincode::deserialize` trusts the lengths inside the data it reads: a
+//! crafted length on a `Vec` or `String` makes it allocate as much as the
+//! peer asks for. [`decode`] reads the same encoding as `bincode::serialize`
+//! but never reads past the end of its input, so what it allocates is
+//! bounded by the input's size. Everything received from a peer is decoded
+//! with it.
+//!
+//! Frames are also checked once decoded: their dimensions must be plausible
+//! and raw frames must hold exactly one pixel per 4 bytes of data. Input
+//! that fails either way is reported to the peer as
+//! [`ErrorCode::InvalidPayload`](crate::network::ErrorCode::InvalidPayload).
+
+use bincode::Options;
+use serde::de::DeserializeOwned;
+
+/// Largest frame width or height accepted from a peer
+pub const MAX_FRAME_DIMENSION: u32 = 16384;
+
+/// Bytes per pixel of raw frames
+const RAW_BYTES_PER_PIXEL: usize = 4;
+
+/// Decodes a value from bytes received from a peer
+///
+/// # Errors
+///
+/// Returns error if the bytes don't hold a valid `T`, including lengths
+/// that run past the end of the input
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> bincode::Result<T> {
+    // The options `bincode::serialize` uses, with a limit
+    bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(bytes.len() as u64)
+        .deserialize(bytes)
+}
+
+/// Checks a received frame's dimensions against its data
+///
+/// # Errors
+///
+/// Returns what is wrong with the frame
+pub fn validate_frame(width: u32, height: u32, raw: bool, data_len: usize) -> Result<(), String> {
+    if width == 0 || height == 0 || width > MAX_FRAME_DIMENSION || height > MAX_FRAME_DIMENSION {
+        return Err(format!("invalid frame size {}x{}", width, height));
+    }
+    let raw_len = raw_frame_size(width, height);
+    if raw && data_len != raw_len {
+        return Err(format!(
+            "raw {}x{} frame has {} bytes, expected {}",
+            width, height, data_len, raw_len
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the size of a frame's uncompressed pixels
+pub fn raw_frame_size(width: u32, height: u32) -> usize {
+    width as usize * height as usize * RAW_BYTES_PER_PIXEL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{Message, MessagePayload, MessageType, SessionControlData};
+
+    #[test]
+    fn test_decodes_what_bincode_encodes() {
+        let message = Message::new(
+            MessageType::SessionControl,
+            MessagePayload::SessionControl(SessionControlData::new(vec![1, 2, 3])),
+        );
+        let bytes = bincode::serialize(&message).unwrap();
+        let decoded: Message = decode(&bytes).unwrap();
+        assert_eq!(decoded.message_id, message.message_id);
+        assert!(matches!(
+            decoded.payload,
+            MessagePayload::SessionControl(ref control) if control.data == [1, 2, 3]
+        ));
+    }
+
+    #[test]
+    fn test_lengths_past_the_input_are_refused() {
+        // A vector claiming an exabyte of data, followed by a few bytes
+        let mut bytes = u64::MAX.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[0; 16]);
+        assert!(decode::<Vec<u8>>(&bytes).is_err());
+        assert!(decode::<String>(&bytes).is_err());
+        assert!(decode::<Vec<String>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_validate_frame() {
+        assert!(validate_frame(1920, 1080, false, 1000).is_ok());
+        assert!(validate_frame(2, 2, true, 16).is_ok());
+        assert!(validate_frame(2, 2, true, 15).is_err());
+        assert!(validate_frame(0, 1080, false, 1000).is_err());
+        assert!(validate_frame(MAX_FRAME_DIMENSION + 1, 1, false, 1000).is_err());
+        assert_eq!(raw_frame_size(3840, 2160), 33_177_600);
+    }
+}
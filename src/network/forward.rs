@@ -0,0 +1,840 @@
+//! TCP port forwarding over a session's QUIC connection
+//!
+//! Like SSH's `-L` and `-R`, a [`PortForwarder`] either listens locally
+//! and tunnels every connection to a target reachable from the peer
+//! ([`PortForwarder::forward_local`]), or asks the peer to listen and
+//! tunnel connections back to a target reachable from here
+//! ([`PortForwarder::forward_remote`]).
+//!
+//! Each forwarded TCP connection gets a bidirectional QUIC stream of its
+//! own. The stream starts with a [`TunnelMessage`] header and its answer,
+//! then carries the connection's raw bytes. Forwarded ports only listen on
+//! the loopback interface.
+//!
+//! The host decides what its clients may reach with a [`ForwardPolicy`].
+//! The other side only ever connects to targets of its own remote
+//! forwards.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use quinn::{RecvStream, SendStream};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+use crate::config::ForwardingConfig;
+use crate::error::{ForwardError, ForwardResult};
+use crate::network::quic::QuicConnection;
+use crate::network::stream::{StreamReceiver, StreamSender};
+
+/// How long to wait for a tunnel to be set up
+pub const TUNNEL_SETUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Header and answer at the start of a tunnel stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TunnelMessage {
+    /// Connect to `host:port` and relay the stream's bytes to it
+    Connect {
+        /// Target host name or address
+        host: String,
+        /// Target port
+        port: u16,
+    },
+    /// Listen on the loopback port `bind_port` (0 for any) and tunnel each
+    /// connection back with `Connect { host, port }`
+    ///
+    /// The listener stays open for as long as the stream.
+    Listen {
+        /// Port to listen on
+        bind_port: u16,
+        /// Target host on the requesting side
+        host: String,
+        /// Target port on the requesting side
+        port: u16,
+    },
+    /// Answer to `Connect`: bytes follow
+    Connected,
+    /// Answer to `Listen`, with the port listened on
+    Listening {
+        /// Port listened on
+        port: u16,
+    },
+    /// The request was refused or failed
+    Refused {
+        /// Why
+        reason: String,
+    },
+}
+
+/// A port to forward and where its connections go, as in `8080:localhost:80`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardSpec {
+    /// Port to listen on (0 for any free port)
+    pub bind_port: u16,
+    /// Target host name or address
+    pub host: String,
+    /// Target port
+    pub port: u16,
+}
+
+impl FromStr for ForwardSpec {
+    type Err = ForwardError;
+
+    /// Parses `bind_port:host:port`; IPv6 hosts go in brackets
+    fn from_str(spec: &str) -> ForwardResult<Self> {
+        let invalid = || ForwardError::InvalidSpec(spec.to_string());
+
+        let (bind_port, target) = spec.split_once(':').ok_or_else(invalid)?;
+        let (host, port) = target.rsplit_once(':').ok_or_else(invalid)?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            bind_port: bind_port.parse().map_err(|_| invalid())?,
+            host: host.to_string(),
+            port: port
+                .parse()
+                .ok()
+                .filter(|port| *port != 0)
+                .ok_or_else(invalid)?,
+        })
+    }
+}
+
+impl fmt::Display for ForwardSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "{}:[{}]:{}", self.bind_port, self.host, self.port)
+        } else {
+            write!(f, "{}:{}:{}", self.bind_port, self.host, self.port)
+        }
+    }
+}
+
+/// A target clients may connect to, as in `localhost:80` or `db.lan:*`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TargetPattern {
+    /// Host, or None for any
+    host: Option<String>,
+    /// Port, or None for any
+    port: Option<u16>,
+}
+
+impl TargetPattern {
+    fn parse(pattern: &str) -> ForwardResult<Self> {
+        let invalid = || ForwardError::InvalidSpec(pattern.to_string());
+
+        let (host, port) = pattern.rsplit_once(':').ok_or_else(invalid)?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            host: (host != "*").then(|| host.to_ascii_lowercase()),
+            port: match port {
+                "*" => None,
+                port => Some(port.parse().map_err(|_| invalid())?),
+            },
+        })
+    }
+
+    fn matches(&self, host: &str, port: u16) -> bool {
+        self.host
+            .as_deref()
+            .is_none_or(|pattern| pattern.eq_ignore_ascii_case(host))
+            && self.port.is_none_or(|pattern| pattern == port)
+    }
+}
+
+/// What the peer may do through port forwarding
+///
+/// The default refuses everything.
+#[derive(Debug, Clone, Default)]
+pub struct ForwardPolicy {
+    targets: Vec<TargetPattern>,
+    listen_ports: Vec<u16>,
+}
+
+impl ForwardPolicy {
+    /// Builds the policy from the forwarding configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a target pattern is invalid, even while
+    /// forwarding is disabled
+    pub fn from_config(config: &ForwardingConfig) -> ForwardResult<Self> {
+        let targets = config
+            .allowed_targets
+            .iter()
+            .map(|target| TargetPattern::parse(target))
+            .collect::<ForwardResult<_>>()?;
+        if !config.enabled {
+            return Ok(Self::default());
+        }
+
+        Ok(Self {
+            targets,
+            listen_ports: config.listen_ports.clone(),
+        })
+    }
+
+    /// Returns true if the peer may connect to `host:port` through us
+    ///
+    /// Hosts are compared by name, so `localhost` and `127.0.0.1` are
+    /// different targets.
+    pub fn allows_connect(&self, host: &str, port: u16) -> bool {
+        self.targets.iter().any(|target| target.matches(host, port))
+    }
+
+    /// Returns true if the peer may have us listen on `port`
+    pub fn allows_listen(&self, port: u16) -> bool {
+        self.listen_ports.contains(&port)
+    }
+}
+
+/// Which side listens for a forward
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardDirection {
+    /// We listen; the peer connects to the target
+    Local,
+    /// The peer listens; we connect to the target
+    Remote,
+}
+
+/// Snapshot of an active forward
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardInfo {
+    /// Forward ID
+    pub forward_id: u64,
+    /// Which side listens
+    pub direction: ForwardDirection,
+    /// Port and target, with the port actually listened on
+    pub spec: ForwardSpec,
+    /// Connections forwarded so far
+    pub connections: u64,
+}
+
+struct Forward {
+    direction: ForwardDirection,
+    spec: ForwardSpec,
+    connections: Arc<AtomicU64>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Forward {
+    fn info(&self, forward_id: u64) -> ForwardInfo {
+        ForwardInfo {
+            forward_id,
+            direction: self.direction,
+            spec: self.spec.clone(),
+            connections: self.connections.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct Inner {
+    connection: QuicConnection,
+    policy: ForwardPolicy,
+    forwards: Mutex<HashMap<u64, Forward>>,
+    next_id: AtomicU64,
+}
+
+/// Port forwarding service for a session's connection
+pub struct PortForwarder {
+    inner: Arc<Inner>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl PortForwarder {
+    /// Starts serving tunnels the peer opens on `connection`
+    ///
+    /// Must only be started once the session's own streams have been
+    /// accepted, since every later stream the peer opens is taken as a
+    /// tunnel.
+    pub fn start(connection: QuicConnection, policy: ForwardPolicy) -> Self {
+        let inner = Arc::new(Inner {
+            connection,
+            policy,
+            forwards: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        });
+
+        let task_inner = Arc::clone(&inner);
+        let task = tokio::spawn(async move {
+            while let Ok((send, recv)) = task_inner.connection.accept_bi().await {
+                let inner = Arc::clone(&task_inner);
+                tokio::spawn(async move {
+                    if let Err(e) = serve_tunnel(&inner, send, recv).await {
+                        debug!("Tunnel ended: {}", e);
+                    }
+                });
+            }
+            debug!("Stopped accepting tunnels");
+        });
+
+        Self { inner, task }
+    }
+
+    /// Returns what the peer may do through us
+    pub fn policy(&self) -> &ForwardPolicy {
+        &self.inner.policy
+    }
+
+    /// Listens on the local loopback port and tunnels each connection to
+    /// the target as seen from the peer
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the port can't be listened on
+    pub async fn forward_local(&self, spec: ForwardSpec) -> ForwardResult<ForwardInfo> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, spec.bind_port)).await?;
+        let spec = ForwardSpec {
+            bind_port: listener.local_addr()?.port(),
+            ..spec
+        };
+        info!(
+            "Forwarding local port {} to {}:{} on the peer",
+            spec.bind_port, spec.host, spec.port
+        );
+
+        let connections = Arc::new(AtomicU64::new(0));
+        let task = spawn_listener(
+            Arc::clone(&self.inner),
+            listener,
+            spec.host.clone(),
+            spec.port,
+            Arc::clone(&connections),
+        );
+        Ok(self.add(ForwardDirection::Local, spec, connections, task))
+    }
+
+    /// Has the peer listen on its loopback port and tunnel each connection
+    /// back to the target as seen from here
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the peer refuses or doesn't answer in time
+    pub async fn forward_remote(&self, spec: ForwardSpec) -> ForwardResult<ForwardInfo> {
+        let request = TunnelMessage::Listen {
+            bind_port: spec.bind_port,
+            host: spec.host.clone(),
+            port: spec.port,
+        };
+        let (send, mut recv) = open_tunnel(&self.inner.connection, request).await?;
+        let port = match recv.recv().await.map_err(stream_error)? {
+            TunnelMessage::Listening { port } => port,
+            TunnelMessage::Refused { reason } => return Err(ForwardError::Refused(reason)),
+            other => return Err(unexpected(other)),
+        };
+        let spec = ForwardSpec {
+            bind_port: port,
+            ..spec
+        };
+        info!(
+            "Peer forwarding its port {} to {}:{} here",
+            port, spec.host, spec.port
+        );
+
+        // The peer listens until this stream ends
+        let task = tokio::spawn(async move {
+            let _send = send;
+            let mut recv = recv.into_inner();
+            let mut buffer = [0u8; 64];
+            while let Ok(Some(_)) = recv.read(&mut buffer).await {}
+            debug!("Peer stopped listening on port {}", port);
+        });
+        Ok(self.add(
+            ForwardDirection::Remote,
+            spec,
+            Arc::new(AtomicU64::new(0)),
+            task,
+        ))
+    }
+
+    /// Stops a forward; tunnelled connections already open carry on
+    ///
+    /// # Errors
+    ///
+    /// Returns error if there is no such forward
+    pub fn stop(&self, forward_id: u64) -> ForwardResult<()> {
+        let forward = lock(&self.inner.forwards)
+            .remove(&forward_id)
+            .ok_or(ForwardError::UnknownForward(forward_id))?;
+        forward.task.abort();
+        info!("Stopped forwarding {}", forward.spec);
+        Ok(())
+    }
+
+    /// Returns the active forwards
+    pub fn list(&self) -> Vec<ForwardInfo> {
+        let mut forwards: Vec<ForwardInfo> = lock(&self.inner.forwards)
+            .iter()
+            .map(|(id, forward)| forward.info(*id))
+            .collect();
+        forwards.sort_by_key(|info| info.forward_id);
+        forwards
+    }
+
+    fn add(
+        &self,
+        direction: ForwardDirection,
+        spec: ForwardSpec,
+        connections: Arc<AtomicU64>,
+        task: tokio::task::JoinHandle<()>,
+    ) -> ForwardInfo {
+        let forward_id = self.inner.next_id.fetch_add(1, Ordering::SeqCst);
+        let forward = Forward {
+            direction,
+            spec,
+            connections,
+            task,
+        };
+        let info = forward.info(forward_id);
+        lock(&self.inner.forwards).insert(forward_id, forward);
+        info
+    }
+}
+
+impl Drop for PortForwarder {
+    fn drop(&mut self) {
+        self.task.abort();
+        for forward in lock(&self.inner.forwards).values() {
+            forward.task.abort();
+        }
+    }
+}
+
+/// Accepts connections on `listener` and tunnels each to `host:port` on
+/// the peer
+fn spawn_listener(
+    inner: Arc<Inner>,
+    listener: TcpListener,
+    host: String,
+    port: u16,
+    connections: Arc<AtomicU64>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let (tcp, from) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Stopped forwarding port: {}", e);
+                    break;
+                }
+            };
+            connections.fetch_add(1, Ordering::Relaxed);
+
+            let connection = inner.connection.clone();
+            let host = host.clone();
+            tokio::spawn(async move {
+                if let Err(e) = tunnel_out(&connection, tcp, host.clone(), port).await {
+                    warn!("Couldn't forward {} to {}:{}: {}", from, host, port, e);
+                }
+            });
+        }
+    })
+}
+
+/// Tunnels a local TCP connection to `host:port` on the peer
+async fn tunnel_out(
+    connection: &QuicConnection,
+    tcp: TcpStream,
+    host: String,
+    port: u16,
+) -> ForwardResult<()> {
+    let request = TunnelMessage::Connect { host, port };
+    let (send, mut recv) = open_tunnel(connection, request).await?;
+
+    let answer = tokio::time::timeout(TUNNEL_SETUP_TIMEOUT, recv.recv())
+        .await
+        .map_err(|_| ForwardError::Timeout)?
+        .map_err(stream_error)?;
+    match answer {
+        TunnelMessage::Connected => relay(tcp, send.into_inner(), recv.into_inner()).await,
+        TunnelMessage::Refused { reason } => Err(ForwardError::Refused(reason)),
+        other => Err(unexpected(other)),
+    }
+}
+
+/// Serves a tunnel the peer opened
+async fn serve_tunnel(inner: &Arc<Inner>, send: SendStream, recv: RecvStream) -> ForwardResult<()> {
+    let mut sender = StreamSender::<TunnelMessage>::new(send);
+    let mut receiver = StreamReceiver::<TunnelMessage>::new(recv);
+
+    let request = tokio::time::timeout(TUNNEL_SETUP_TIMEOUT, receiver.recv())
+        .await
+        .map_err(|_| ForwardError::Timeout)?
+        .map_err(stream_error)?;
+
+    match request {
+        TunnelMessage::Connect { host, port } => {
+            let tcp = match connect_target(inner, &host, port).await {
+                Ok(tcp) => tcp,
+                Err(e) => {
+                    warn!("Refused tunnel to {}:{}: {}", host, port, e);
+                    let reason = e.to_string();
+                    let _ = sender.send(TunnelMessage::Refused { reason }).await;
+                    return Err(e);
+                }
+            };
+            info!("Tunnel opened to {}:{}", host, port);
+            sender
+                .send(TunnelMessage::Connected)
+                .await
+                .map_err(stream_error)?;
+            relay(tcp, sender.into_inner(), receiver.into_inner()).await
+        }
+        TunnelMessage::Listen {
+            bind_port,
+            host,
+            port,
+        } => {
+            let listener = match listen_for_peer(inner, bind_port).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    warn!("Refused to listen on port {}: {}", bind_port, e);
+                    let reason = e.to_string();
+                    let _ = sender.send(TunnelMessage::Refused { reason }).await;
+                    return Err(e);
+                }
+            };
+            let bound = listener.local_addr()?.port();
+            info!("Listening on port {} for the peer", bound);
+            sender
+                .send(TunnelMessage::Listening { port: bound })
+                .await
+                .map_err(stream_error)?;
+
+            // Listen until the peer ends the request stream
+            let task = spawn_listener(
+                Arc::clone(inner),
+                listener,
+                host,
+                port,
+                Arc::new(AtomicU64::new(0)),
+            );
+            let mut recv = receiver.into_inner();
+            let mut buffer = [0u8; 64];
+            while let Ok(Some(_)) = recv.read(&mut buffer).await {}
+            task.abort();
+            info!("Stopped listening on port {} for the peer", bound);
+            Ok(())
+        }
+        other => Err(unexpected(other)),
+    }
+}
+
+/// Connects to a target the peer asked for, if it may
+async fn connect_target(inner: &Inner, host: &str, port: u16) -> ForwardResult<TcpStream> {
+    let allowed = inner.policy.allows_connect(host, port)
+        || lock(&inner.forwards).values().any(|forward| {
+            forward.direction == ForwardDirection::Remote
+                && forward.spec.host == host
+                && forward.spec.port == port
+        });
+    if !allowed {
+        return Err(ForwardError::Refused(format!(
+            "{}:{} is not allowed",
+            host, port
+        )));
+    }
+
+    // Count the connection against the remote forward it came through
+    if let Some(forward) = lock(&inner.forwards).values().find(|forward| {
+        forward.direction == ForwardDirection::Remote
+            && forward.spec.host == host
+            && forward.spec.port == port
+    }) {
+        forward.connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let tcp = tokio::time::timeout(TUNNEL_SETUP_TIMEOUT, TcpStream::connect((host, port)))
+        .await
+        .map_err(|_| ForwardError::Timeout)??;
+    Ok(tcp)
+}
+
+/// Listens on a loopback port for the peer, if it may
+async fn listen_for_peer(inner: &Inner, port: u16) -> ForwardResult<TcpListener> {
+    if !inner.policy.allows_listen(port) {
+        return Err(ForwardError::Refused(format!(
+            "listening on port {} is not allowed",
+            port
+        )));
+    }
+    Ok(TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port))).await?)
+}
+
+/// Opens a tunnel stream and sends its header
+async fn open_tunnel(
+    connection: &QuicConnection,
+    request: TunnelMessage,
+) -> ForwardResult<(StreamSender<TunnelMessage>, StreamReceiver<TunnelMessage>)> {
+    let (send, recv) = connection.open_bi().await.map_err(stream_error)?;
+    let mut sender = StreamSender::new(send);
+    sender.send(request).await.map_err(stream_error)?;
+    Ok((sender, StreamReceiver::new(recv)))
+}
+
+/// Copies bytes both ways until both sides are done
+async fn relay(mut tcp: TcpStream, send: SendStream, recv: RecvStream) -> ForwardResult<()> {
+    let mut tunnel = tokio::io::join(recv, send);
+    let (sent, received) = tokio::io::copy_bidirectional(&mut tcp, &mut tunnel).await?;
+    debug!(
+        "Tunnel closed after {} bytes out, {} bytes in",
+        sent, received
+    );
+    Ok(())
+}
+
+fn unexpected(message: TunnelMessage) -> ForwardError {
+    ForwardError::Stream(format!("unexpected message: {:?}", message))
+}
+
+fn stream_error(error: impl fmt::Display) -> ForwardError {
+    ForwardError::Stream(error.to_string())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::cert;
+    use crate::network::quic::{QuicConfig, QuicEndpoint};
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn endpoint(dir: &TempDir) -> QuicEndpoint {
+        let cert_pair = cert::load_or_create_cert(dir.path(), 123456789).unwrap();
+        let config = QuicConfig::default()
+            .with_bind_addr(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .with_cert_pair(cert_pair);
+        QuicEndpoint::new(config).unwrap()
+    }
+
+    /// Endpoints and certificate directory that must outlive a test
+    type Endpoints = (QuicEndpoint, QuicEndpoint, TempDir);
+
+    /// Connects two endpoints and returns (host, client) connections
+    async fn connection_pair() -> (QuicConnection, QuicConnection, Endpoints) {
+        let dir = TempDir::new().unwrap();
+        let host_endpoint = endpoint(&dir);
+        let client_endpoint = QuicEndpoint::client_only().unwrap();
+        let addr = host_endpoint.local_addr();
+
+        let accept = tokio::spawn(async move {
+            let connection = host_endpoint.accept().await.unwrap().unwrap();
+            (connection, host_endpoint)
+        });
+        let client = client_endpoint.connect(addr, "localhost").await.unwrap();
+        let (host, host_endpoint) = accept.await.unwrap();
+        (host, client, (host_endpoint, client_endpoint, dir))
+    }
+
+    /// Starts a TCP server that answers each line with it in upper case
+    async fn upper_case_server() -> u16 {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut tcp, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buffer = [0u8; 1024];
+                    while let Ok(read) = tcp.read(&mut buffer).await {
+                        if read == 0 {
+                            break;
+                        }
+                        let upper = buffer[..read].to_ascii_uppercase();
+                        if tcp.write_all(&upper).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    async fn round_trip(port: u16, text: &[u8]) -> Vec<u8> {
+        let mut tcp = TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+            .await
+            .unwrap();
+        tcp.write_all(text).await.unwrap();
+        let mut reply = vec![0u8; text.len()];
+        tokio::time::timeout(Duration::from_secs(10), tcp.read_exact(&mut reply))
+            .await
+            .unwrap()
+            .unwrap();
+        reply
+    }
+
+    fn policy(targets: &[&str], listen_ports: &[u16]) -> ForwardPolicy {
+        ForwardPolicy::from_config(&ForwardingConfig {
+            enabled: true,
+            allowed_targets: targets.iter().map(|t| t.to_string()).collect(),
+            listen_ports: listen_ports.to_vec(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let spec: ForwardSpec = "8080:localhost:80".parse().unwrap();
+        assert_eq!(spec.bind_port, 8080);
+        assert_eq!(spec.host, "localhost");
+        assert_eq!(spec.port, 80);
+        assert_eq!(spec.to_string(), "8080:localhost:80");
+
+        let spec: ForwardSpec = "0:[::1]:5432".parse().unwrap();
+        assert_eq!(spec.host, "::1");
+        assert_eq!(spec.to_string(), "0:[::1]:5432");
+
+        for invalid in [
+            "8080",
+            "localhost:80",
+            "x:localhost:80",
+            "8080::80",
+            "1:host:0",
+        ] {
+            assert!(invalid.parse::<ForwardSpec>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        assert!(!ForwardPolicy::default().allows_connect("localhost", 80));
+
+        let policy = policy(&["localhost:80", "DB.lan:*", "*:8443"], &[9000]);
+        assert!(policy.allows_connect("localhost", 80));
+        assert!(!policy.allows_connect("localhost", 22));
+        assert!(!policy.allows_connect("127.0.0.1", 80));
+        assert!(policy.allows_connect("db.LAN", 5432));
+        assert!(policy.allows_connect("example.com", 8443));
+        assert!(policy.allows_listen(9000));
+        assert!(!policy.allows_listen(9001));
+
+        // Nothing is allowed while forwarding is disabled
+        let disabled = ForwardPolicy::from_config(&ForwardingConfig {
+            enabled: false,
+            allowed_targets: vec!["*:*".to_string()],
+            listen_ports: vec![9000],
+        })
+        .unwrap();
+        assert!(!disabled.allows_connect("localhost", 80));
+        assert!(!disabled.allows_listen(9000));
+
+        assert!(ForwardPolicy::from_config(&ForwardingConfig {
+            enabled: true,
+            allowed_targets: vec!["localhost".to_string()],
+            listen_ports: Vec::new(),
+        })
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_local_forward() {
+        let (host, client, _endpoints) = connection_pair().await;
+        let target = upper_case_server().await;
+        let _host = PortForwarder::start(host, policy(&[&format!("127.0.0.1:{}", target)], &[]));
+        let client = PortForwarder::start(client, ForwardPolicy::default());
+
+        let spec = ForwardSpec {
+            bind_port: 0,
+            host: "127.0.0.1".to_string(),
+            port: target,
+        };
+        let info = client.forward_local(spec).await.unwrap();
+        assert_ne!(info.spec.bind_port, 0);
+        assert_eq!(info.direction, ForwardDirection::Local);
+
+        assert_eq!(round_trip(info.spec.bind_port, b"hello").await, b"HELLO");
+        assert_eq!(round_trip(info.spec.bind_port, b"again").await, b"AGAIN");
+        assert_eq!(client.list()[0].connections, 2);
+
+        client.stop(info.forward_id).unwrap();
+        assert!(client.list().is_empty());
+        assert!(matches!(
+            client.stop(info.forward_id),
+            Err(ForwardError::UnknownForward(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_local_forward_refused_by_policy() {
+        let (host, client, _endpoints) = connection_pair().await;
+        let target = upper_case_server().await;
+        let _host = PortForwarder::start(host, ForwardPolicy::default());
+        let client = PortForwarder::start(client, ForwardPolicy::default());
+
+        let spec = ForwardSpec {
+            bind_port: 0,
+            host: "127.0.0.1".to_string(),
+            port: target,
+        };
+        let info = client.forward_local(spec).await.unwrap();
+
+        // The tunnel is refused, so the local connection just closes
+        let mut tcp = TcpStream::connect((Ipv4Addr::LOCALHOST, info.spec.bind_port))
+            .await
+            .unwrap();
+        let mut buffer = [0u8; 16];
+        let read = tokio::time::timeout(Duration::from_secs(10), tcp.read(&mut buffer))
+            .await
+            .unwrap();
+        assert!(matches!(read, Ok(0) | Err(_)));
+    }
+
+    #[tokio::test]
+    async fn test_remote_forward() {
+        let (host, client, _endpoints) = connection_pair().await;
+        let target = upper_case_server().await;
+        let host = PortForwarder::start(host, policy(&[], &[0]));
+        let client = PortForwarder::start(client, ForwardPolicy::default());
+
+        // The client's target is reachable through the host's port
+        let spec = ForwardSpec {
+            bind_port: 0,
+            host: "127.0.0.1".to_string(),
+            port: target,
+        };
+        let info = client.forward_remote(spec).await.unwrap();
+        assert_eq!(info.direction, ForwardDirection::Remote);
+        assert_eq!(round_trip(info.spec.bind_port, b"remote").await, b"REMOTE");
+        assert_eq!(client.list()[0].connections, 1);
+
+        // The host can't use that to reach other targets on the client
+        let other = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let other_port = other.local_addr().unwrap().port();
+        let request = TunnelMessage::Connect {
+            host: "127.0.0.1".to_string(),
+            port: other_port,
+        };
+        let (_send, mut recv) = open_tunnel(&host.inner.connection, request).await.unwrap();
+        assert!(matches!(
+            recv.recv().await.unwrap(),
+            TunnelMessage::Refused { .. }
+        ));
+
+        // Nor may the client listen on ports the host doesn't allow
+        let spec = ForwardSpec {
+            bind_port: 1,
+            host: "127.0.0.1".to_string(),
+            port: target,
+        };
+        assert!(matches!(
+            client.forward_remote(spec).await,
+            Err(ForwardError::Refused(_))
+        ));
+    }
+}
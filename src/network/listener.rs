@@ -6,17 +6,26 @@
 //! - Setting up session transports for accepted connections
 
 use std::net::SocketAddr;
-use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
 use tracing::{debug, error, info, warn};
 
 use crate::network::protocol::{
-    ConnectionAccept, ConnectionReject, ConnectionRequest, DesktopInfo, Message, MessagePayload,
-    MessageType, RejectReason, CURRENT_PROTOCOL_VERSION,
+    negotiate_channels, ConnectionAccept, ConnectionReject, ConnectionRequest, DesktopInfo,
+    ErrorMessage, Message, MessagePayload, MessageType, RejectReason, CURRENT_PROTOCOL_VERSION,
 };
 use crate::network::quic::{QuicConnection, QuicEndpoint, QuicError, QuicResult};
-use crate::network::stream::{BiStream, StreamReceiver, StreamSender};
-use crate::security::DeviceId;
+use crate::network::stream::{BiStream, StreamError, StreamReceiver, StreamSender};
+use crate::config::RateLimitConfig;
+use crate::error::SecurityError;
+use crate::security::{AuditEvent, AuditLog, DeviceId, PeerPermissions, RateLimiter};
+
+/// Time allowed for a peer to send its connection request
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time a connection request waits for the user to accept or reject it
+pub(crate) const PROMPT_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// Event emitted when a connection request is received
 #[derive(Debug, Clone)]
@@ -64,6 +73,14 @@ pub struct ConnectionListener {
     local_device_name: String,
     /// Channel for incoming connection events
     incoming_tx: mpsc::UnboundedSender<(IncomingConnection, PendingConnection)>,
+    /// Rate limiter shared with the connection manager
+    rate_limiter: Arc<Mutex<RateLimiter>>,
+    /// Slots for handshakes still waiting for the peer's request
+    handshake_slots: Arc<Semaphore>,
+    /// Slots for requests waiting to be accepted or rejected
+    request_slots: Arc<Semaphore>,
+    /// Audit log for handshake rejections
+    audit_log: Option<Arc<AuditLog>>,
     /// Running flag
     running: Arc<std::sync::atomic::AtomicBool>,
 }
@@ -76,6 +93,12 @@ pub struct PendingConnection {
     control_stream: BiStream<Message>,
     /// Original request message
     request: ConnectionRequest,
+    /// Permissions granted to the peer by the access policy
+    permissions: PeerPermissions,
+    /// Custom channels agreed with the peer
+    channels: Vec<String>,
+    /// Request slot, freed once the request is answered or dropped
+    _request_slot: Option<OwnedSemaphorePermit>,
 }
 
 impl PendingConnection {
@@ -85,8 +108,12 @@ impl PendingConnection {
         host_name: String,
         desktop_info: DesktopInfo,
     ) -> QuicResult<AcceptedConnection> {
-        // Create accept message
-        let accept = ConnectionAccept::new(host_name.clone(), desktop_info.clone());
+        // Create accept message, advertising only what the peer is permitted to use
+        let mut accept = ConnectionAccept::new(host_name.clone(), desktop_info.clone());
+        accept
+            .host_capabilities
+            .retain(|capability| self.permissions.allows(*capability));
+        accept.channels = self.channels.clone();
         let session_id = accept.session_id;
 
         let response = Message::new(
@@ -112,12 +139,14 @@ impl PendingConnection {
                 .map_err(|e| QuicError::ConnectionFailed(e.to_string()))?,
             remote_name: self.request.client_name,
             session_id,
+            permissions: self.permissions,
+            channels: self.channels,
         })
     }
 
     /// Rejects the connection
     pub async fn reject(mut self, reason: RejectReason, message: Option<String>) -> QuicResult<()> {
-        let reject = ConnectionReject::new(reason, message);
+        let reject = ConnectionReject::new(reason.clone(), message);
 
         let response = Message::new(
             MessageType::ConnectionReject,
@@ -132,6 +161,9 @@ impl PendingConnection {
             self.request.client_id, reason
         );
 
+        // Let the response arrive before closing, which would discard it
+        let _ = tokio::time::timeout(HANDSHAKE_TIMEOUT, self.control_stream.sender.finish()).await;
+
         // Close the connection
         self.connection.close("connection rejected");
 
@@ -147,6 +179,28 @@ impl PendingConnection {
     pub fn remote_addr(&self) -> SocketAddr {
         self.connection.remote_address()
     }
+
+    /// Returns the permissions that will be granted on accept
+    pub fn permissions(&self) -> PeerPermissions {
+        self.permissions
+    }
+
+    /// Sets the permissions granted to the peer on accept
+    pub fn set_permissions(&mut self, permissions: PeerPermissions) {
+        self.permissions = permissions;
+    }
+
+    /// Returns the custom channels that will be agreed on accept
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Agrees on the requested custom channels that are in `registered`
+    ///
+    /// No custom channels are agreed unless this is called.
+    pub fn set_channels(&mut self, registered: &[String]) {
+        self.channels = negotiate_channels(&self.request.channels, registered);
+    }
 }
 
 /// An accepted connection ready for session use
@@ -161,6 +215,10 @@ pub struct AcceptedConnection {
     pub remote_name: String,
     /// Session ID
     pub session_id: [u8; 16],
+    /// Permissions granted to the peer
+    pub permissions: PeerPermissions,
+    /// Custom channels agreed with the peer
+    pub channels: Vec<String>,
 }
 
 impl ConnectionListener {
@@ -171,18 +229,44 @@ impl ConnectionListener {
         local_device_name: String,
     ) -> (Self, mpsc::UnboundedReceiver<(IncomingConnection, PendingConnection)>) {
         let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        let rate_limit = RateLimitConfig::default();
 
         let listener = Self {
             endpoint,
             local_device_id,
             local_device_name,
             incoming_tx,
+            handshake_slots: Arc::new(Semaphore::new(rate_limit.max_pending_handshakes)),
+            request_slots: Arc::new(Semaphore::new(rate_limit.max_pending_requests)),
+            rate_limiter: Arc::new(Mutex::new(RateLimiter::new(rate_limit))),
+            audit_log: None,
             running: Arc::new(std::sync::atomic::AtomicBool::new(false)),
         };
 
         (listener, incoming_rx)
     }
 
+    /// Uses a shared rate limiter, sizing the handshake and request caps from
+    /// its limits
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<Mutex<RateLimiter>>) -> Self {
+        let (max_handshakes, max_requests) = {
+            let limiter = lock_limiter(&rate_limiter);
+            let config = limiter.config();
+            (config.max_pending_handshakes, config.max_pending_requests)
+        };
+
+        self.handshake_slots = Arc::new(Semaphore::new(max_handshakes));
+        self.request_slots = Arc::new(Semaphore::new(max_requests));
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Records handshake rejections to the given audit log
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
     /// Starts the listener loop
     ///
     /// This runs in a loop accepting connections until stopped.
@@ -195,28 +279,70 @@ impl ConnectionListener {
         while self.running.load(std::sync::atomic::Ordering::SeqCst) {
             match self.endpoint.accept().await {
                 Some(Ok(connection)) => {
+                    let remote_addr = connection.remote_address();
+
+                    // Throttle by source address before spending any work on the peer
+                    if let Err(e) = lock_limiter(&self.rate_limiter).check_ip(remote_addr.ip()) {
+                        warn!("Dropping connection from {}: {}", remote_addr, e);
+                        connection.close("rate limited");
+                        continue;
+                    }
+
+                    let permit = match self.handshake_slots.clone().try_acquire_owned() {
+                        Ok(permit) => permit,
+                        Err(_) => {
+                            warn!(
+                                "Dropping connection from {}: too many pending handshakes",
+                                remote_addr
+                            );
+                            connection.close("too many pending handshakes");
+                            continue;
+                        }
+                    };
+
                     connection_counter += 1;
                     let connection_id = connection_counter;
 
                     // Handle connection in a separate task
                     let incoming_tx = self.incoming_tx.clone();
                     let local_device_id = self.local_device_id;
+                    let rate_limiter = self.rate_limiter.clone();
+                    let audit_log = self.audit_log.clone();
+                    let request_slots = self.request_slots.clone();
 
                     tokio::spawn(async move {
-                        match Self::handle_incoming_connection(
+                        let handshake = Self::handle_incoming_connection(
                             connection,
                             connection_id,
                             local_device_id,
+                            rate_limiter,
                         )
-                        .await
-                        {
-                            Ok((incoming, pending)) => {
+                        .await;
+                        // Requests waiting for an answer hold a request slot instead
+                        drop(permit);
+
+                        match handshake {
+                            Ok((incoming, mut pending)) => {
+                                match request_slots.try_acquire_owned() {
+                                    Ok(slot) => pending._request_slot = Some(slot),
+                                    Err(_) => {
+                                        Self::reject_busy(incoming, pending, audit_log).await;
+                                        return;
+                                    }
+                                }
                                 if incoming_tx.send((incoming, pending)).is_err() {
                                     warn!("Failed to send incoming connection event");
                                 }
                             }
                             Err(e) => {
                                 error!("Failed to handle incoming connection: {}", e);
+                                if let Some(audit_log) = audit_log {
+                                    audit_log.record(AuditEvent::ConnectionRejected {
+                                        peer_id: None,
+                                        peer_addr: remote_addr,
+                                        reason: e.to_string(),
+                                    });
+                                }
                             }
                         }
                     });
@@ -239,24 +365,74 @@ impl ConnectionListener {
         self.running.store(false, std::sync::atomic::Ordering::SeqCst);
     }
 
+    /// Rejects a request because too many are waiting for an answer
+    async fn reject_busy(
+        incoming: IncomingConnection,
+        pending: PendingConnection,
+        audit_log: Option<Arc<AuditLog>>,
+    ) {
+        let message = "Too many connection requests waiting for an answer".to_string();
+        warn!(
+            "Rejecting connection from {}: {}",
+            incoming.remote_device_id.format_with_spaces(),
+            message
+        );
+        if let Some(audit_log) = audit_log {
+            audit_log.record(AuditEvent::ConnectionRejected {
+                peer_id: Some(incoming.remote_device_id.as_u32()),
+                peer_addr: incoming.remote_addr,
+                reason: message.clone(),
+            });
+        }
+        let reason = RejectReason::RateLimited {
+            retry_after_secs: PROMPT_TIMEOUT.as_secs(),
+        };
+        let _ = pending.reject(reason, Some(message)).await;
+    }
+
     /// Handles an incoming connection, performing the initial handshake
     async fn handle_incoming_connection(
         connection: QuicConnection,
         connection_id: u64,
         local_device_id: DeviceId,
+        rate_limiter: Arc<Mutex<RateLimiter>>,
     ) -> QuicResult<(IncomingConnection, PendingConnection)> {
         let remote_addr = connection.remote_address();
         debug!("Handling incoming connection from {}", remote_addr);
 
-        // Accept the control stream (client opens it first)
-        let (send_stream, recv_stream) = connection.accept_bi().await?;
-        let mut control_stream: BiStream<Message> = BiStream::new(send_stream, recv_stream);
+        // Accept the control stream and receive the request (client opens it first)
+        let handshake = async {
+            let (send_stream, recv_stream) = connection.accept_bi().await?;
+            let mut control_stream: BiStream<Message> = BiStream::new(send_stream, recv_stream);
+
+            let request_msg = match control_stream.recv().await {
+                Ok(msg) => msg,
+                Err(StreamError::Deserialization(e)) => {
+                    // Tell the peer why before dropping it
+                    let response = Message::new(
+                        MessageType::Error,
+                        MessagePayload::Error(ErrorMessage::invalid_payload(&e)),
+                    );
+                    let _ = control_stream.send(response).await;
+                    return Err(QuicError::StreamError(format!("Malformed request: {}", e)));
+                }
+                Err(e) => return Err(QuicError::StreamError(e.to_string())),
+            };
 
-        // Receive connection request
-        let request_msg = control_stream
-            .recv()
-            .await
-            .map_err(|e| QuicError::StreamError(e.to_string()))?;
+            Ok::<_, QuicError>((control_stream, request_msg))
+        };
+
+        let (mut control_stream, request_msg) =
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake).await {
+                Ok(result) => result?,
+                Err(_) => {
+                    connection.close("handshake timeout");
+                    return Err(QuicError::ConnectionFailed(format!(
+                        "Handshake from {} timed out",
+                        remote_addr
+                    )));
+                }
+            };
 
         // Validate message type
         let request = match request_msg.payload {
@@ -284,6 +460,7 @@ impl ConnectionListener {
             );
             let _ = control_stream.send(response).await;
             connection.close("protocol version mismatch");
+            lock_limiter(&rate_limiter).record_rejection(remote_addr.ip(), None);
 
             return Err(QuicError::ConnectionFailed(
                 "Protocol version mismatch".to_string(),
@@ -302,6 +479,7 @@ impl ConnectionListener {
             );
             let _ = control_stream.send(response).await;
             connection.close("invalid host ID");
+            lock_limiter(&rate_limiter).record_rejection(remote_addr.ip(), None);
 
             return Err(QuicError::ConnectionFailed("Invalid host ID".to_string()));
         }
@@ -309,6 +487,38 @@ impl ConnectionListener {
         let remote_device_id = DeviceId::from_u32(request.client_id)
             .map_err(|e| QuicError::ConnectionFailed(e.to_string()))?;
 
+        // Throttle by claimed device ID
+        let device_check = lock_limiter(&rate_limiter).check_device(remote_device_id);
+        if let Err(e) = device_check {
+            let retry_after_secs = match e {
+                SecurityError::TemporarilyBanned { seconds } => seconds,
+                _ => {
+                    let per_minute = lock_limiter(&rate_limiter)
+                        .config()
+                        .device_attempts_per_minute
+                        .max(1) as u64;
+                    60u64.div_ceil(per_minute)
+                }
+            };
+
+            let reject = ConnectionReject::new(
+                RejectReason::RateLimited { retry_after_secs },
+                Some(e.to_string()),
+            );
+            let response = Message::new(
+                MessageType::ConnectionReject,
+                MessagePayload::ConnectionReject(reject),
+            );
+            let _ = control_stream.send(response).await;
+            connection.close("rate limited");
+
+            return Err(QuicError::ConnectionFailed(format!(
+                "Device {} rate limited: {}",
+                remote_device_id.format_with_spaces(),
+                e
+            )));
+        }
+
         info!(
             "Received connection request from {} ({})",
             remote_device_id.format_with_spaces(),
@@ -328,12 +538,20 @@ impl ConnectionListener {
             connection,
             control_stream,
             request,
+            permissions: PeerPermissions::all(),
+            channels: Vec::new(),
+            _request_slot: None,
         };
 
         Ok((incoming, pending))
     }
 }
 
+/// Locks the rate limiter, recovering from a poisoned lock
+pub(crate) fn lock_limiter(limiter: &Mutex<RateLimiter>) -> std::sync::MutexGuard<'_, RateLimiter> {
+    limiter.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -7,7 +7,7 @@
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn};
@@ -15,15 +15,22 @@ use tracing::{debug, error, info, warn};
 use crate::error::{NetworkError, NetworkResult};
 use crate::network::cert::{self, CertPair};
 use crate::network::discovery::{PeerDiscovery, PeerEvent, PeerInfo};
-use crate::network::listener::{AcceptedConnection, ConnectionListener, IncomingConnection, PendingConnection};
+use crate::config::{AuditConfig, RateLimitConfig};
+use crate::network::listener::{
+    lock_limiter, AcceptedConnection, ConnectionListener, IncomingConnection, PendingConnection,
+    PROMPT_TIMEOUT,
+};
 use crate::network::protocol::{
-    ConnectionAccept, ConnectionRequest, DesktopInfo, Message, MessagePayload, MessageType,
+    negotiate_channels, ConnectionAccept, ConnectionRequest, DesktopInfo, Message, MessagePayload, MessageType,
     RejectReason, CURRENT_PROTOCOL_VERSION,
 };
 use crate::network::quic::{QuicConfig, QuicConnection, QuicEndpoint, QuicError, DEFAULT_QUIC_PORT};
 use crate::network::stream::BiStream;
 use crate::network::{Connection, ConnectionInfo, ConnectionRole, ConnectionState};
-use crate::security::{DeviceId, PasswordManager};
+use crate::security::{
+    audit, AccessPolicy, AuditEvent, AuditLog, DeviceId, PasswordManager, PeerPermissions,
+    PolicyDecision, RateLimiter,
+};
 
 /// Connection manager configuration
 #[derive(Debug, Clone)]
@@ -38,8 +45,18 @@ pub struct ManagerConfig {
     pub config_dir: PathBuf,
     /// Path to password hash file
     pub password_hash_path: PathBuf,
+    /// Path to access policy file
+    pub policy_path: PathBuf,
+    /// Path to connection audit log
+    pub audit_log_path: PathBuf,
     /// Maximum concurrent connections
     pub max_connections: usize,
+    /// Connection rate limits
+    pub rate_limit: RateLimitConfig,
+    /// Audit log settings
+    pub audit: AuditConfig,
+    /// Names of the custom channels this application uses
+    pub channels: Vec<String>,
 }
 
 impl ManagerConfig {
@@ -50,8 +67,13 @@ impl ManagerConfig {
             device_name,
             service_port: DEFAULT_QUIC_PORT,
             password_hash_path: config_dir.join("password.hash"),
+            policy_path: config_dir.join("policy.toml"),
+            audit_log_path: config_dir.join("connections.log"),
             config_dir,
             max_connections: 5,
+            rate_limit: RateLimitConfig::default(),
+            audit: AuditConfig::default(),
+            channels: Vec::new(),
         }
     }
 
@@ -60,6 +82,14 @@ impl ManagerConfig {
         self.service_port = port;
         self
     }
+
+    /// Registers custom channels to agree on with peers
+    ///
+    /// A session can use the channels both sides registered.
+    pub fn with_channels(mut self, channels: Vec<String>) -> Self {
+        self.channels = channels;
+        self
+    }
 }
 
 /// Events emitted by the connection manager
@@ -96,6 +126,10 @@ pub struct EstablishedConnection {
     pub session_id: [u8; 16],
     /// Connection role
     pub role: ConnectionRole,
+    /// Permissions granted to the remote peer
+    pub permissions: PeerPermissions,
+    /// Custom channels agreed with the remote peer
+    pub channels: Vec<String>,
 }
 
 /// Main connection manager
@@ -120,6 +154,10 @@ pub struct ConnectionManager {
     event_rx: Arc<RwLock<mpsc::UnboundedReceiver<ConnectionEvent>>>,
     /// Listener task handle
     listener_handle: Option<tokio::task::JoinHandle<()>>,
+    /// Rate limiter for incoming connection attempts
+    rate_limiter: Arc<Mutex<RateLimiter>>,
+    /// Connection audit log
+    audit_log: Arc<AuditLog>,
 }
 
 impl ConnectionManager {
@@ -137,6 +175,12 @@ impl ConnectionManager {
             config.service_port,
         );
 
+        let rate_limiter = Arc::new(Mutex::new(RateLimiter::new(config.rate_limit.clone())));
+        let audit_log = Arc::new(AuditLog::new(
+            config.audit_log_path.clone(),
+            config.audit.clone(),
+        ));
+
         Ok(Self {
             config,
             cert_pair,
@@ -148,6 +192,8 @@ impl ConnectionManager {
             event_tx,
             event_rx: Arc::new(RwLock::new(event_rx)),
             listener_handle: None,
+            rate_limiter,
+            audit_log,
         })
     }
 
@@ -173,6 +219,9 @@ impl ConnectionManager {
             self.config.device_id,
             self.config.device_name.clone(),
         );
+        let listener = listener
+            .with_rate_limiter(self.rate_limiter.clone())
+            .with_audit_log(self.audit_log.clone());
 
         // Spawn listener task
         let listener_handle = tokio::spawn(async move {
@@ -184,9 +233,65 @@ impl ConnectionManager {
         let event_tx = self.event_tx.clone();
         let pending_connections = self.pending_connections.clone();
         let password_hash_path = self.config.password_hash_path.clone();
+        let policy_path = self.config.policy_path.clone();
+        let rate_limiter = self.rate_limiter.clone();
+        let audit_log = self.audit_log.clone();
 
         tokio::spawn(async move {
-            while let Some((incoming, pending)) = incoming_rx.recv().await {
+            while let Some((incoming, mut pending)) = incoming_rx.recv().await {
+                audit_log.record(AuditEvent::ConnectionRequest {
+                    peer_id: incoming.remote_device_id.as_u32(),
+                    peer_name: incoming.remote_name.clone(),
+                    peer_addr: incoming.remote_addr,
+                    has_password: incoming.has_password,
+                });
+
+                // Evaluate access policy before anything else; fail closed on a broken policy
+                let decision = match AccessPolicy::load_or_default(&policy_path) {
+                    Ok(policy) => {
+                        policy.evaluate(incoming.remote_device_id, incoming.remote_addr.ip())
+                    }
+                    Err(e) => {
+                        error!("Failed to load access policy: {}", e);
+                        PolicyDecision::Deny {
+                            rule: None,
+                            message: "Host access policy could not be loaded".to_string(),
+                        }
+                    }
+                };
+
+                match decision {
+                    PolicyDecision::Allow { rule, permissions } => {
+                        debug!(
+                            "Access policy allowed {} (rule: {:?})",
+                            incoming.remote_device_id.format_with_spaces(),
+                            rule
+                        );
+                        pending.set_permissions(permissions);
+                    }
+                    PolicyDecision::Deny { rule, message } => {
+                        warn!(
+                            "Rejecting connection from {} ({}): {}",
+                            incoming.remote_device_id.format_with_spaces(),
+                            incoming.remote_addr,
+                            message
+                        );
+                        lock_limiter(&rate_limiter).record_rejection(
+                            incoming.remote_addr.ip(),
+                            Some(incoming.remote_device_id),
+                        );
+                        audit_log.record(AuditEvent::ConnectionRejected {
+                            peer_id: Some(incoming.remote_device_id.as_u32()),
+                            peer_addr: incoming.remote_addr,
+                            reason: message.clone(),
+                        });
+                        let _ = pending
+                            .reject(RejectReason::PolicyDenied { rule }, Some(message))
+                            .await;
+                        continue;
+                    }
+                }
+
                 // Check if password is required
                 let password_required = PasswordManager::is_password_set(&password_hash_path);
 
@@ -196,6 +301,20 @@ impl ConnectionManager {
                         "Rejecting connection from {}: password required",
                         incoming.remote_device_id.format_with_spaces()
                     );
+                    lock_limiter(&rate_limiter).record_rejection(
+                        incoming.remote_addr.ip(),
+                        Some(incoming.remote_device_id),
+                    );
+                    audit_log.record(AuditEvent::AuthFailure {
+                        peer_id: incoming.remote_device_id.as_u32(),
+                        peer_addr: incoming.remote_addr,
+                        reason: "Password required but not provided".to_string(),
+                    });
+                    audit_log.record(AuditEvent::ConnectionRejected {
+                        peer_id: Some(incoming.remote_device_id.as_u32()),
+                        peer_addr: incoming.remote_addr,
+                        reason: format!("{:?}", RejectReason::InvalidPassword),
+                    });
                     let _ = pending.reject(RejectReason::InvalidPassword, None).await;
                     continue;
                 }
@@ -206,6 +325,36 @@ impl ConnectionManager {
                     .await
                     .insert(incoming.connection_id, pending);
 
+                // Drop the request if nobody answers it in time
+                let expired_connections = pending_connections.clone();
+                let expired_log = audit_log.clone();
+                let (connection_id, remote_id, remote_addr) = (
+                    incoming.connection_id,
+                    incoming.remote_device_id,
+                    incoming.remote_addr,
+                );
+                tokio::spawn(async move {
+                    tokio::time::sleep(PROMPT_TIMEOUT).await;
+                    let expired = expired_connections.write().await.remove(&connection_id);
+                    if let Some(pending) = expired {
+                        info!(
+                            "Connection request from {} was not answered in time",
+                            remote_id.format_with_spaces()
+                        );
+                        expired_log.record(AuditEvent::ConnectionRejected {
+                            peer_id: Some(remote_id.as_u32()),
+                            peer_addr: remote_addr,
+                            reason: "Request not answered in time".to_string(),
+                        });
+                        let _ = pending
+                            .reject(
+                                RejectReason::UserDenied,
+                                Some("Request not answered in time".to_string()),
+                            )
+                            .await;
+                    }
+                });
+
                 // Emit event for UI to handle
                 let _ = event_tx.send(ConnectionEvent::ConnectionRequest {
                     remote_id: incoming.remote_device_id,
@@ -354,7 +503,8 @@ impl ConnectionManager {
             self.config.device_name.clone(),
             remote_id,
             password_hash,
-        );
+        )
+        .with_channels(self.config.channels.clone());
 
         let request_msg = Message::new(
             MessageType::ConnectionRequest,
@@ -397,6 +547,7 @@ impl ConnectionManager {
 
                 // Emit connected event
                 let _ = self.event_tx.send(ConnectionEvent::Connected { remote_id });
+                self.audit_session(&quic_conn, remote_id, accept.session_id, ConnectionRole::Client);
 
                 Ok(EstablishedConnection {
                     connection: quic_conn,
@@ -405,6 +556,9 @@ impl ConnectionManager {
                     remote_name: accept.host_name,
                     session_id: accept.session_id,
                     role: ConnectionRole::Client,
+                    permissions: PeerPermissions::all(),
+                    // Only what we asked for, whatever the host answered
+                    channels: negotiate_channels(&accept.channels, &self.config.channels),
                 })
             }
             MessagePayload::ConnectionReject(reject) => {
@@ -419,6 +573,10 @@ impl ConnectionManager {
                     reject.message.unwrap_or_default()
                 )))
             }
+            MessagePayload::Error(error) => Err(NetworkError::ProtocolError(format!(
+                "Host refused the connection request ({:?}): {}",
+                error.error_code, error.message
+            ))),
             _ => Err(NetworkError::ProtocolError(
                 "Unexpected response to connection request".to_string(),
             )),
@@ -435,14 +593,20 @@ impl ConnectionManager {
             pending_conns.remove(&connection_id)
         };
 
-        let pending = pending.ok_or_else(|| {
+        let mut pending = pending.ok_or_else(|| {
             NetworkError::ConnectionFailed("Pending connection not found".to_string())
         })?;
+        pending.set_channels(&self.config.channels);
 
         let remote_id = DeviceId::from_u32(pending.request().client_id)
             .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
 
         info!("Accepting connection from {}", remote_id.format_with_spaces());
+        lock_limiter(&self.rate_limiter).record_success(pending.remote_addr().ip(), remote_id);
+        self.audit_log.record(AuditEvent::ConnectionAccepted {
+            peer_id: remote_id.as_u32(),
+            peer_addr: pending.remote_addr(),
+        });
 
         // Accept the connection
         let accepted = pending
@@ -471,6 +635,12 @@ impl ConnectionManager {
         let _ = self.event_tx.send(ConnectionEvent::Connected {
             remote_id: accepted.remote_device_id,
         });
+        self.audit_session(
+            &accepted.connection,
+            accepted.remote_device_id,
+            accepted.session_id,
+            ConnectionRole::Host,
+        );
 
         Ok(EstablishedConnection {
             connection: accepted.connection,
@@ -479,6 +649,8 @@ impl ConnectionManager {
             remote_name: accepted.remote_name,
             session_id: accepted.session_id,
             role: ConnectionRole::Host,
+            permissions: accepted.permissions,
+            channels: accepted.channels,
         })
     }
 
@@ -497,6 +669,14 @@ impl ConnectionManager {
             NetworkError::ConnectionFailed("Pending connection not found".to_string())
         })?;
 
+        let remote_id = DeviceId::from_u32(pending.request().client_id).ok();
+        lock_limiter(&self.rate_limiter).record_rejection(pending.remote_addr().ip(), remote_id);
+        self.audit_log.record(AuditEvent::ConnectionRejected {
+            peer_id: remote_id.map(|id| id.as_u32()),
+            peer_addr: pending.remote_addr(),
+            reason: format!("{:?}", reason),
+        });
+
         pending
             .reject(reason, None)
             .await
@@ -505,6 +685,48 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// Records the start of a session and its end once the connection closes
+    fn audit_session(
+        &self,
+        connection: &QuicConnection,
+        remote_id: DeviceId,
+        session_id: [u8; 16],
+        role: ConnectionRole,
+    ) {
+        let peer_addr = connection.remote_address();
+        let session_id = audit::session_id_hex(&session_id);
+
+        self.audit_log.record(AuditEvent::SessionStarted {
+            peer_id: remote_id.as_u32(),
+            peer_addr,
+            session_id: session_id.clone(),
+            role: role.to_string(),
+        });
+
+        let connection = connection.clone();
+        let audit_log = self.audit_log.clone();
+        let started = std::time::Instant::now();
+
+        tokio::spawn(async move {
+            let reason = connection.closed().await;
+
+            audit_log.record(AuditEvent::SessionEnded {
+                peer_id: remote_id.as_u32(),
+                peer_addr,
+                session_id,
+                duration_secs: started.elapsed().as_secs(),
+                bytes_sent: connection.bytes_sent(),
+                bytes_received: connection.bytes_received(),
+                reason,
+            });
+        });
+    }
+
+    /// Returns the connection audit log
+    pub fn audit_log(&self) -> &Arc<AuditLog> {
+        &self.audit_log
+    }
+
     /// Disconnects from a remote device
     pub async fn disconnect(&self, remote_id: DeviceId) -> NetworkResult<()> {
         info!("Disconnecting from {}", remote_id.format_with_spaces());
@@ -572,6 +794,11 @@ impl ConnectionManager {
         rx.try_recv().ok()
     }
 
+    /// Waits for the next connection event
+    pub async fn recv_event(&self) -> Option<ConnectionEvent> {
+        self.event_rx.write().await.recv().await
+    }
+
     /// Gets the event receiver for subscription
     pub fn event_sender(&self) -> mpsc::UnboundedSender<ConnectionEvent> {
         self.event_tx.clone()
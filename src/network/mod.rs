@@ -6,10 +6,13 @@
 //! - Protocol implementation
 //! - Connection lifecycle management
 //! - TLS certificate management
+//! - TCP port forwarding over sessions
 
 pub mod cert;
+pub mod codec;
 pub mod connection;
 pub mod discovery;
+pub mod forward;
 pub mod listener;
 pub mod manager;
 pub mod protocol;
@@ -19,14 +22,20 @@ pub mod stream;
 // Re-export commonly used types
 pub use connection::{Connection, ConnectionInfo, ConnectionRole, ConnectionState, ConnectionStats};
 pub use discovery::{PeerDiscovery, PeerEvent, PeerInfo, DEFAULT_SERVICE_PORT};
+pub use forward::{
+    ForwardDirection, ForwardInfo, ForwardPolicy, ForwardSpec, PortForwarder, TunnelMessage,
+};
 pub use manager::{ConnectionEvent, ConnectionManager, EstablishedConnection, ManagerConfig};
 pub use protocol::{
     Capability, ConnectionAccept, ConnectionReject, ConnectionRequest, DesktopInfo, Disconnect,
     DisconnectReason, ErrorCode, ErrorMessage, FrameFormat, Heartbeat, KeyboardEventData,
     KeyboardEventTypeData, Message, MessagePayload, MessageType, MouseEventData,
-    MouseEventTypeData, RejectReason, ScreenFrameData, CURRENT_PROTOCOL_VERSION,
+    MouseEventTypeData, RejectReason, ScreenFrameData, SessionControlData, CURRENT_PROTOCOL_VERSION,
 };
 pub use quic::{QuicConfig, QuicConnection, QuicEndpoint, QuicError, StreamType, DEFAULT_QUIC_PORT};
-pub use stream::{BiStream, StreamError, StreamReceiver, StreamSender};
+pub use stream::{
+    BiStream, StreamError, StreamReceiver, StreamSender, DEFAULT_MAX_MESSAGE_SIZE,
+    FRAGMENT_SIZE, MAX_FRAME_SIZE, MAX_VIDEO_MESSAGE_SIZE,
+};
 pub use cert::{CertError, CertPair};
 pub use listener::{AcceptedConnection, ConnectionListener, IncomingConnection, PendingConnection};
@@ -3,6 +3,7 @@
 //! This module defines all message types used in the P2P protocol.
 //! Based on the specification in docs/PROTOCOL.md
 
+use crate::network::codec::{decode, validate_frame};
 use crate::security::DeviceId;
 use serde::{Deserialize, Serialize};
 
@@ -16,6 +17,12 @@ pub const CURRENT_PROTOCOL_VERSION: u8 = PROTOCOL_VERSION;
 /// Maximum message size in bytes
 pub const MAX_MESSAGE_SIZE_BYTES: usize = MAX_MESSAGE_SIZE;
 
+/// Longest custom channel name, in bytes
+pub const MAX_CHANNEL_NAME_LEN: usize = 64;
+
+/// Most custom channels a session can agree on
+pub const MAX_CUSTOM_CHANNELS: usize = 32;
+
 /// Message type identifiers
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
@@ -45,6 +52,9 @@ pub enum MessageType {
     QualityUpdate = 0x50,
     Statistics = 0x51,
 
+    // Session Control (0x60 - 0x6F)
+    SessionControl = 0x60,
+
     // Error (0xF0 - 0xFF)
     Error = 0xF0,
 }
@@ -74,6 +84,7 @@ pub enum MessagePayload {
     ScreenFrame(ScreenFrameData),
     KeyboardEvent(KeyboardEventData),
     MouseEvent(MouseEventData),
+    SessionControl(SessionControlData),
 }
 
 /// Connection request message
@@ -96,6 +107,9 @@ pub struct ConnectionRequest {
 
     /// Requested capabilities
     pub requested_capabilities: Vec<Capability>,
+
+    /// Names of the custom channels the client registered
+    pub channels: Vec<String>,
 }
 
 /// Connection accept message
@@ -110,6 +124,9 @@ pub struct ConnectionAccept {
     /// Host capabilities
     pub host_capabilities: Vec<Capability>,
 
+    /// Names of the requested custom channels the host registered too
+    pub channels: Vec<String>,
+
     /// Desktop information
     pub desktop_info: DesktopInfo,
 }
@@ -138,6 +155,16 @@ pub struct Heartbeat {
     pub timestamp: u64,
 }
 
+/// Session control message
+///
+/// Carries a session-layer control message encoded by the session
+/// transport, so the session layer can evolve without protocol changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionControlData {
+    /// Encoded control message
+    pub data: Vec<u8>,
+}
+
 /// Error message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorMessage {
@@ -149,7 +176,10 @@ pub struct ErrorMessage {
 }
 
 /// Screen frame data message
+///
+/// Decoding checks the frame's dimensions against its data.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ScreenFrameFields")]
 pub struct ScreenFrameData {
     /// Frame sequence number
     pub sequence: u64,
@@ -170,6 +200,34 @@ pub struct ScreenFrameData {
     pub timestamp: u64,
 }
 
+/// A [`ScreenFrameData`] as decoded, before it is checked
+#[derive(Deserialize)]
+struct ScreenFrameFields {
+    sequence: u64,
+    width: u32,
+    height: u32,
+    format: FrameFormat,
+    data: Vec<u8>,
+    timestamp: u64,
+}
+
+impl TryFrom<ScreenFrameFields> for ScreenFrameData {
+    type Error = String;
+
+    fn try_from(fields: ScreenFrameFields) -> Result<Self, Self::Error> {
+        let raw = fields.format == FrameFormat::Raw;
+        validate_frame(fields.width, fields.height, raw, fields.data.len())?;
+        Ok(Self {
+            sequence: fields.sequence,
+            width: fields.width,
+            height: fields.height,
+            format: fields.format,
+            data: fields.data,
+            timestamp: fields.timestamp,
+        })
+    }
+}
+
 /// Frame encoding format
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
@@ -242,10 +300,17 @@ pub enum MouseEventTypeData {
         /// Vertical scroll delta
         delta_y: i32,
     },
+    /// Mouse moved by a relative amount
+    MoveRelative {
+        /// Horizontal movement
+        dx: i32,
+        /// Vertical movement
+        dy: i32,
+    },
 }
 
 /// Reason for connection rejection
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RejectReason {
     /// User manually rejected
     UserDenied,
@@ -264,6 +329,18 @@ pub enum RejectReason {
 
     /// Protocol version mismatch
     UnsupportedVersion,
+
+    /// Denied by the host's access policy
+    PolicyDenied {
+        /// Name of the matching rule (None if the default action applied)
+        rule: Option<String>,
+    },
+
+    /// Too many connection attempts
+    RateLimited {
+        /// Seconds before the peer may try again
+        retry_after_secs: u64,
+    },
 }
 
 /// Reason for disconnection
@@ -299,8 +376,17 @@ pub enum Capability {
     /// Clipboard synchronization
     ClipboardSync,
 
-    /// File transfer (future)
+    /// File transfer over the file stream
     FileTransfer,
+
+    /// Browsing and downloading the host's shared directories
+    FileBrowse,
+
+    /// Shells on the host over the terminal stream
+    Terminal,
+
+    /// TCP port forwarding over tunnel streams
+    PortForward,
 }
 
 /// Desktop information
@@ -334,9 +420,12 @@ impl Message {
         bincode::serialize(self)
     }
 
-    /// Deserializes a message from bytes
+    /// Deserializes a message received from a peer
+    ///
+    /// Lengths inside the message can't exceed the input, and frames are
+    /// checked, see [`codec`](crate::network::codec).
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, bincode::Error> {
-        bincode::deserialize(bytes)
+        decode(bytes)
     }
 
     /// Validates message size
@@ -359,9 +448,23 @@ impl ConnectionRequest {
             client_name,
             host_id: host_id.as_u32(),
             password_hash,
-            requested_capabilities: vec![Capability::RemoteControl, Capability::ClipboardSync],
+            requested_capabilities: vec![
+                Capability::RemoteControl,
+                Capability::ClipboardSync,
+                Capability::FileTransfer,
+                Capability::FileBrowse,
+                Capability::Terminal,
+                Capability::PortForward,
+            ],
+            channels: Vec::new(),
         }
     }
+
+    /// Requests custom channels by name
+    pub fn with_channels(mut self, channels: Vec<String>) -> Self {
+        self.channels = channels;
+        self
+    }
 }
 
 impl ConnectionAccept {
@@ -377,12 +480,48 @@ impl ConnectionAccept {
         Self {
             session_id,
             host_name,
-            host_capabilities: vec![Capability::RemoteControl, Capability::ClipboardSync],
+            host_capabilities: vec![
+                Capability::RemoteControl,
+                Capability::ClipboardSync,
+                Capability::FileTransfer,
+                Capability::FileBrowse,
+                Capability::Terminal,
+                Capability::PortForward,
+            ],
+            channels: Vec::new(),
             desktop_info,
         }
     }
 }
 
+/// Returns true if `name` can name a custom channel
+///
+/// Names are 1 to [`MAX_CHANNEL_NAME_LEN`] bytes of printable ASCII without
+/// spaces, such as `acme.tickets`.
+pub fn is_valid_channel_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_CHANNEL_NAME_LEN
+        && name.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// Returns the custom channels both sides registered, in the order they
+/// were requested
+///
+/// Invalid and repeated names are left out, as is anything past
+/// [`MAX_CUSTOM_CHANNELS`].
+pub fn negotiate_channels(requested: &[String], registered: &[String]) -> Vec<String> {
+    let mut agreed: Vec<String> = Vec::new();
+    for name in requested {
+        if agreed.len() == MAX_CUSTOM_CHANNELS {
+            break;
+        }
+        if is_valid_channel_name(name) && registered.contains(name) && !agreed.contains(name) {
+            agreed.push(name.clone());
+        }
+    }
+    agreed
+}
+
 impl ConnectionReject {
     /// Creates a new connection reject message
     pub fn new(reason: RejectReason, message: Option<String>) -> Self {
@@ -416,11 +555,26 @@ impl Default for Heartbeat {
     }
 }
 
+impl SessionControlData {
+    /// Creates a new session control message
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+}
+
 impl ErrorMessage {
     /// Creates a new error message
     pub fn new(error_code: ErrorCode, message: String) -> Self {
         Self { error_code, message }
     }
+
+    /// Creates the error answering a message that couldn't be decoded
+    pub fn invalid_payload(reason: impl std::fmt::Display) -> Self {
+        Self::new(
+            ErrorCode::InvalidPayload,
+            format!("Invalid payload: {}", reason),
+        )
+    }
 }
 
 impl DesktopInfo {
@@ -498,6 +652,11 @@ impl MouseEventData {
         Self::new(MouseEventTypeData::Move { x, y })
     }
 
+    /// Creates a relative mouse move event
+    pub fn move_relative(dx: i32, dy: i32) -> Self {
+        Self::new(MouseEventTypeData::MoveRelative { dx, dy })
+    }
+
     /// Creates a mouse button press event
     pub fn button_press(button: u8) -> Self {
         Self::new(MouseEventTypeData::ButtonPress { button })
@@ -569,5 +728,24 @@ mod tests {
         assert_eq!(request.protocol_version, CURRENT_PROTOCOL_VERSION);
         assert_eq!(request.client_id, 123456789);
         assert_eq!(request.host_id, 987654321);
+        assert!(request.channels.is_empty());
+    }
+
+    #[test]
+    fn test_negotiate_channels() {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert!(is_valid_channel_name("acme.tickets"));
+        assert!(!is_valid_channel_name(""));
+        assert!(!is_valid_channel_name("has space"));
+        assert!(!is_valid_channel_name(&"x".repeat(MAX_CHANNEL_NAME_LEN + 1)));
+
+        let requested = names(&["diag", "tickets", "bad name", "tickets", "other"]);
+        let registered = names(&["tickets", "bad name", "diag"]);
+        assert_eq!(negotiate_channels(&requested, &registered), names(&["diag", "tickets"]));
+        assert!(negotiate_channels(&requested, &[]).is_empty());
+
+        let many: Vec<String> = (0..MAX_CUSTOM_CHANNELS + 5).map(|i| format!("c{}", i)).collect();
+        assert_eq!(negotiate_channels(&many, &many).len(), MAX_CUSTOM_CHANNELS);
     }
 }
@@ -325,6 +325,21 @@ impl QuicConnection {
     pub fn close_reason(&self) -> Option<String> {
         self.connection.close_reason().map(|e| e.to_string())
     }
+
+    /// Waits for the connection to close and returns the reason
+    pub async fn closed(&self) -> String {
+        self.connection.closed().await.to_string()
+    }
+
+    /// Returns the total UDP bytes sent on this connection
+    pub fn bytes_sent(&self) -> u64 {
+        self.connection.stats().udp_tx.bytes
+    }
+
+    /// Returns the total UDP bytes received on this connection
+    pub fn bytes_received(&self) -> u64 {
+        self.connection.stats().udp_rx.bytes
+    }
 }
 
 impl Clone for QuicConnection {
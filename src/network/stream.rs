@@ -2,6 +2,12 @@
 //!
 //! This module provides adapters that bridge QUIC streams to typed message
 //! channels, enabling seamless integration with the session transport system.
+//!
+//! Messages up to [`MAX_FRAME_SIZE`] are written as a single length-prefixed
+//! frame. Larger ones, such as raw 4K screen frames, are split into
+//! fragments of [`FRAGMENT_SIZE`] and reassembled by the receiver, which
+//! refuses to buffer more than its message size limit for any one message.
+//! Only streams opted into [`MAX_VIDEO_MESSAGE_SIZE`] carry such messages.
 
 use bytes::{Buf, BufMut, BytesMut};
 use quinn::{RecvStream, SendStream};
@@ -10,10 +16,27 @@ use std::marker::PhantomData;
 use tokio::sync::mpsc;
 use tracing::{debug, error, trace, warn};
 
+use crate::network::codec::decode;
 use crate::network::quic::QuicError;
 
-/// Maximum message size (10 MB)
-const MAX_MESSAGE_SIZE: usize = 10 * 1024 * 1024;
+/// Largest frame on the stream (10 MB), and largest message sent unfragmented
+pub const MAX_FRAME_SIZE: usize = 10 * 1024 * 1024;
+
+/// Size of the fragments a larger message is split into (1 MiB)
+pub const FRAGMENT_SIZE: usize = 1024 * 1024;
+
+/// Largest message sent or reassembled by default (10 MB)
+///
+/// Every message fits in one frame, so nothing is fragmented.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = MAX_FRAME_SIZE;
+
+/// Largest message on the screen frame stream (64 MiB)
+///
+/// Leaves room for a raw 4K frame (3840×2160×4 bytes, about 33 MB).
+pub const MAX_VIDEO_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Set in a length prefix when more fragments of the message follow
+const MORE_FRAGMENTS: u32 = 1 << 31;
 
 /// Length prefix size (4 bytes for u32)
 const LENGTH_PREFIX_SIZE: usize = 4;
@@ -42,6 +65,16 @@ pub enum StreamError {
     #[error("Message too large: {size} bytes (max {max})")]
     MessageTooLarge { size: usize, max: usize },
 
+    /// A message over the receiver's size limit was skipped; the stream
+    /// can still be read
+    #[error("Dropped message over the size limit: {size} bytes (max {max})")]
+    MessageDropped {
+        /// Size of the whole message
+        size: usize,
+        /// Receiver's size limit
+        max: usize,
+    },
+
     #[error("Channel closed")]
     ChannelClosed,
 
@@ -55,12 +88,37 @@ impl From<StreamError> for QuicError {
     }
 }
 
+/// Splits a serialized message into frames as (length prefix, data)
+///
+/// A message that fits in one frame is sent whole, as before fragmentation
+/// existed. Otherwise every fragment but the last has [`MORE_FRAGMENTS`]
+/// set in its prefix.
+fn frames(data: &[u8]) -> Vec<(u32, &[u8])> {
+    if data.len() <= MAX_FRAME_SIZE {
+        return vec![(data.len() as u32, data)];
+    }
+    let count = data.len().div_ceil(FRAGMENT_SIZE);
+    data.chunks(FRAGMENT_SIZE)
+        .enumerate()
+        .map(|(i, fragment)| {
+            let more = if i + 1 < count { MORE_FRAGMENTS } else { 0 };
+            (fragment.len() as u32 | more, fragment)
+        })
+        .collect()
+}
+
+fn serialize<T: Serialize>(msg: &T) -> StreamResult<Vec<u8>> {
+    bincode::serialize(msg).map_err(|e| StreamError::Serialization(e.to_string()))
+}
+
 /// Typed adapter for sending messages over a QUIC stream
 ///
 /// Uses length-prefixed framing: each message is prefixed with a 4-byte
-/// big-endian length, followed by bincode-serialized data.
+/// big-endian length, followed by bincode-serialized data. Messages larger
+/// than [`MAX_FRAME_SIZE`] are sent as several fragments.
 pub struct StreamSender<T> {
     stream: SendStream,
+    max_message_size: usize,
     _phantom: PhantomData<T>,
 }
 
@@ -69,72 +127,62 @@ impl<T: Serialize> StreamSender<T> {
     pub fn new(stream: SendStream) -> Self {
         Self {
             stream,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
             _phantom: PhantomData,
         }
     }
 
+    /// Sets the largest message that may be sent
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
     /// Sends a message over the stream (takes ownership for Send safety)
     pub async fn send(&mut self, msg: T) -> StreamResult<usize> {
-        // Serialize the message
-        let data = bincode::serialize(&msg)
-            .map_err(|e| StreamError::Serialization(e.to_string()))?;
-
-        let size = data.len();
-
-        if size > MAX_MESSAGE_SIZE {
-            return Err(StreamError::MessageTooLarge {
-                size,
-                max: MAX_MESSAGE_SIZE,
-            });
-        }
-
-        // Write length prefix
-        let len_bytes = (size as u32).to_be_bytes();
-        self.stream
-            .write_all(&len_bytes)
-            .await
-            .map_err(|e| StreamError::WriteError(e.to_string()))?;
-
-        // Write message data
-        self.stream
-            .write_all(&data)
-            .await
-            .map_err(|e| StreamError::WriteError(e.to_string()))?;
-
-        trace!("Sent message: {} bytes", size);
-        Ok(size + LENGTH_PREFIX_SIZE)
+        let data = serialize(&msg)?;
+        self.write_message(&data).await
     }
 
     /// Sends a reference to a message (for cases where ownership isn't needed)
     pub async fn send_ref(&mut self, msg: &T) -> StreamResult<usize> {
-        // Serialize the message
-        let data = bincode::serialize(msg)
-            .map_err(|e| StreamError::Serialization(e.to_string()))?;
+        let data = serialize(msg)?;
+        self.write_message(&data).await
+    }
 
+    /// Writes a serialized message, fragmented if needed
+    async fn write_message(&mut self, data: &[u8]) -> StreamResult<usize> {
         let size = data.len();
 
-        if size > MAX_MESSAGE_SIZE {
+        if size > self.max_message_size {
             return Err(StreamError::MessageTooLarge {
                 size,
-                max: MAX_MESSAGE_SIZE,
+                max: self.max_message_size,
             });
         }
 
-        // Write length prefix
-        let len_bytes = (size as u32).to_be_bytes();
-        self.stream
-            .write_all(&len_bytes)
-            .await
-            .map_err(|e| StreamError::WriteError(e.to_string()))?;
+        let frames = frames(data);
+        for (prefix, fragment) in &frames {
+            // Write length prefix
+            self.stream
+                .write_all(&prefix.to_be_bytes())
+                .await
+                .map_err(|e| StreamError::WriteError(e.to_string()))?;
+
+            // Write message data
+            self.stream
+                .write_all(fragment)
+                .await
+                .map_err(|e| StreamError::WriteError(e.to_string()))?;
+        }
 
-        // Write message data
-        self.stream
-            .write_all(&data)
-            .await
-            .map_err(|e| StreamError::WriteError(e.to_string()))?;
+        trace!("Sent message: {} bytes in {} frames", size, frames.len());
+        Ok(size + frames.len() * LENGTH_PREFIX_SIZE)
+    }
 
-        trace!("Sent message: {} bytes", size);
-        Ok(size + LENGTH_PREFIX_SIZE)
+    /// Returns the underlying stream, e.g. to send raw bytes after a header
+    pub fn into_inner(self) -> SendStream {
+        self.stream
     }
 
     /// Finishes the stream, signaling no more data will be sent
@@ -151,6 +199,7 @@ impl<T: Serialize> StreamSender<T> {
 pub struct StreamReceiver<T> {
     stream: RecvStream,
     buffer: BytesMut,
+    max_message_size: usize,
     _phantom: PhantomData<T>,
 }
 
@@ -160,39 +209,95 @@ impl<T: DeserializeOwned> StreamReceiver<T> {
         Self {
             stream,
             buffer: BytesMut::with_capacity(64 * 1024),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
             _phantom: PhantomData,
         }
     }
 
+    /// Sets the largest message that is reassembled
+    ///
+    /// Fragments of a larger message are read and dropped, so at most this
+    /// much is buffered for any one message.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
     /// Receives a message from the stream
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StreamError::MessageDropped`] for a message over the size
+    /// limit and [`StreamError::Deserialization`] for a malformed one; the
+    /// stream can still be read after these. Other errors leave the stream
+    /// unusable.
     pub async fn recv(&mut self) -> StreamResult<T> {
-        // Read length prefix
-        let len = self.read_length().await?;
+        let data = self.read_message().await?;
 
-        if len > MAX_MESSAGE_SIZE {
-            return Err(StreamError::MessageTooLarge {
-                size: len,
-                max: MAX_MESSAGE_SIZE,
-            });
-        }
+        // Deserialize, reading no further than the message
+        let msg = decode(&data).map_err(|e| StreamError::Deserialization(e.to_string()))?;
 
-        // Read message data
-        let data = self.read_exact(len).await?;
+        trace!("Received message: {} bytes", data.len());
+        Ok(msg)
+    }
 
-        // Deserialize
-        let msg = bincode::deserialize(&data)
-            .map_err(|e| StreamError::Deserialization(e.to_string()))?;
+    /// Reads the frames of the next message and reassembles it
+    async fn read_message(&mut self) -> StreamResult<Vec<u8>> {
+        let mut message = Vec::new();
+        let mut size = 0usize;
 
-        trace!("Received message: {} bytes", len);
-        Ok(msg)
+        loop {
+            // Read length prefix
+            let prefix = self.read_length().await?;
+            let more = prefix & MORE_FRAGMENTS != 0;
+            let len = (prefix & !MORE_FRAGMENTS) as usize;
+
+            // The stream can't be followed past a frame this large
+            if len > MAX_FRAME_SIZE {
+                return Err(StreamError::MessageTooLarge {
+                    size: len,
+                    max: MAX_FRAME_SIZE,
+                });
+            }
+
+            // Read message data, keeping it only while within the limit
+            let fragment = self.read_exact(len).await?;
+            size = size.saturating_add(len);
+            if size > self.max_message_size {
+                message = Vec::new();
+            } else if message.is_empty() {
+                message = fragment;
+            } else {
+                message.extend_from_slice(&fragment);
+            }
+
+            if !more {
+                break;
+            }
+        }
+
+        if size > self.max_message_size {
+            return Err(StreamError::MessageDropped {
+                size,
+                max: self.max_message_size,
+            });
+        }
+        Ok(message)
+    }
+
+    /// Returns the underlying stream, e.g. to read raw bytes after a header
+    ///
+    /// Nothing past the last received message has been read from it.
+    pub fn into_inner(self) -> RecvStream {
+        self.stream
     }
 
     /// Reads the 4-byte length prefix
-    async fn read_length(&mut self) -> StreamResult<usize> {
+    async fn read_length(&mut self) -> StreamResult<u32> {
         let data = self.read_exact(LENGTH_PREFIX_SIZE).await?;
         let mut len_bytes = [0u8; 4];
         len_bytes.copy_from_slice(&data);
-        Ok(u32::from_be_bytes(len_bytes) as usize)
+        Ok(u32::from_be_bytes(len_bytes))
     }
 
     /// Reads exactly `len` bytes from the stream
@@ -273,6 +378,10 @@ where
                     debug!("Stream closed, stopping receive bridge");
                     break;
                 }
+                // Only that message is lost
+                Err(e @ (StreamError::MessageDropped { .. } | StreamError::Deserialization(_))) => {
+                    warn!("Skipping message: {}", e)
+                }
                 Err(e) => {
                     error!("Stream receive error: {}", e);
                     break;
@@ -354,5 +463,27 @@ mod tests {
         assert!(matches!(quic_err, QuicError::StreamError(_)));
     }
 
+    #[test]
+    fn test_frames() {
+        let small = vec![1u8; 100];
+        assert_eq!(frames(&small), vec![(100, &small[..])]);
+
+        // Messages up to the frame size keep the unfragmented format
+        let whole = vec![2u8; MAX_FRAME_SIZE];
+        assert_eq!(frames(&whole).len(), 1);
+
+        let large = vec![3u8; MAX_FRAME_SIZE + 1];
+        let fragments = frames(&large);
+        assert_eq!(fragments.len(), 11);
+        let (last, rest) = fragments.split_last().unwrap();
+        for (prefix, fragment) in rest {
+            assert_eq!(*prefix, FRAGMENT_SIZE as u32 | MORE_FRAGMENTS);
+            assert_eq!(fragment.len(), FRAGMENT_SIZE);
+        }
+        assert_eq!(last.0, (MAX_FRAME_SIZE + 1 - 10 * FRAGMENT_SIZE) as u32);
+        let total: usize = fragments.iter().map(|(_, fragment)| fragment.len()).sum();
+        assert_eq!(total, large.len());
+    }
+
     // Integration tests with actual QUIC streams are in tests/quic_connection.rs
 }
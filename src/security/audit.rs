@@ -0,0 +1,676 @@
+//! Connection audit log
+//!
+//! This module records who accessed the machine and when. Events are
+//! appended to `connections.log` in the config directory as JSON lines:
+//! - Incoming connection requests
+//! - Accept/reject decisions with the reason
+//! - Authentication failures
+//! - Session start and end, with bytes transferred
+//! - Chat messages sent and received during a session
+//!
+//! The log is rotated by size, keeping a fixed number of older files
+//! (`connections.log.1`, `connections.log.2`, ...).
+
+use crate::config::AuditConfig;
+use crate::security::DeviceId;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+// Audit log constants (avoiding magic numbers)
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// A single audited event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// A peer asked to connect
+    ConnectionRequest {
+        /// Claimed device ID of the peer
+        peer_id: u32,
+        /// Name the peer reported
+        peer_name: String,
+        /// Peer network address
+        peer_addr: SocketAddr,
+        /// Whether a password was supplied
+        has_password: bool,
+    },
+    /// A connection was accepted
+    ConnectionAccepted {
+        /// Device ID of the peer
+        peer_id: u32,
+        /// Peer network address
+        peer_addr: SocketAddr,
+    },
+    /// A connection was rejected
+    ConnectionRejected {
+        /// Device ID of the peer, if the request was read
+        peer_id: Option<u32>,
+        /// Peer network address
+        peer_addr: SocketAddr,
+        /// Why the connection was rejected
+        reason: String,
+    },
+    /// A peer failed to authenticate
+    AuthFailure {
+        /// Device ID of the peer
+        peer_id: u32,
+        /// Peer network address
+        peer_addr: SocketAddr,
+        /// Why authentication failed
+        reason: String,
+    },
+    /// A session started
+    SessionStarted {
+        /// Device ID of the peer
+        peer_id: u32,
+        /// Peer network address
+        peer_addr: SocketAddr,
+        /// Session ID (hex)
+        session_id: String,
+        /// Local role ("Host" or "Client")
+        role: String,
+    },
+    /// A session ended
+    SessionEnded {
+        /// Device ID of the peer
+        peer_id: u32,
+        /// Peer network address
+        peer_addr: SocketAddr,
+        /// Session ID (hex)
+        session_id: String,
+        /// Session length in seconds
+        duration_secs: u64,
+        /// Bytes sent to the peer
+        bytes_sent: u64,
+        /// Bytes received from the peer
+        bytes_received: u64,
+        /// Why the session ended
+        reason: String,
+    },
+    /// A chat message was sent or received during a session
+    ChatMessage {
+        /// Session ID
+        session_id: String,
+        /// Device ID of the peer, if the session runs over a connection
+        #[serde(default)]
+        peer_id: Option<u32>,
+        /// Name of the sender
+        sender: String,
+        /// Whether this machine sent the message
+        outgoing: bool,
+        /// Message text
+        text: String,
+    },
+}
+
+impl AuditEvent {
+    /// Returns the event type name as written to the log
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuditEvent::ConnectionRequest { .. } => "connection_request",
+            AuditEvent::ConnectionAccepted { .. } => "connection_accepted",
+            AuditEvent::ConnectionRejected { .. } => "connection_rejected",
+            AuditEvent::AuthFailure { .. } => "auth_failure",
+            AuditEvent::SessionStarted { .. } => "session_started",
+            AuditEvent::SessionEnded { .. } => "session_ended",
+            AuditEvent::ChatMessage { .. } => "chat_message",
+        }
+    }
+
+    /// Returns the peer device ID, if known
+    pub fn peer_id(&self) -> Option<u32> {
+        match self {
+            AuditEvent::ConnectionRequest { peer_id, .. }
+            | AuditEvent::ConnectionAccepted { peer_id, .. }
+            | AuditEvent::AuthFailure { peer_id, .. }
+            | AuditEvent::SessionStarted { peer_id, .. }
+            | AuditEvent::SessionEnded { peer_id, .. } => Some(*peer_id),
+            AuditEvent::ConnectionRejected { peer_id, .. }
+            | AuditEvent::ChatMessage { peer_id, .. } => *peer_id,
+        }
+    }
+
+    /// Returns the peer network address, if known
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        match self {
+            AuditEvent::ConnectionRequest { peer_addr, .. }
+            | AuditEvent::ConnectionAccepted { peer_addr, .. }
+            | AuditEvent::ConnectionRejected { peer_addr, .. }
+            | AuditEvent::AuthFailure { peer_addr, .. }
+            | AuditEvent::SessionStarted { peer_addr, .. }
+            | AuditEvent::SessionEnded { peer_addr, .. } => Some(*peer_addr),
+            AuditEvent::ChatMessage { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for AuditEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditEvent::ConnectionRequest {
+                peer_name,
+                has_password,
+                ..
+            } => write!(
+                f,
+                "connection request from '{}' ({})",
+                peer_name,
+                if *has_password { "password" } else { "no password" }
+            ),
+            AuditEvent::ConnectionAccepted { .. } => write!(f, "connection accepted"),
+            AuditEvent::ConnectionRejected { reason, .. } => {
+                write!(f, "connection rejected: {}", reason)
+            }
+            AuditEvent::AuthFailure { reason, .. } => {
+                write!(f, "authentication failed: {}", reason)
+            }
+            AuditEvent::SessionStarted { role, .. } => write!(f, "session started as {}", role),
+            AuditEvent::SessionEnded {
+                duration_secs,
+                bytes_sent,
+                bytes_received,
+                reason,
+                ..
+            } => write!(
+                f,
+                "session ended after {}s (sent {} bytes, received {} bytes): {}",
+                duration_secs, bytes_sent, bytes_received, reason
+            ),
+            AuditEvent::ChatMessage {
+                sender,
+                outgoing,
+                text,
+                ..
+            } => write!(
+                f,
+                "chat message {} '{}': {}",
+                if *outgoing { "sent as" } else { "from" },
+                sender,
+                text
+            ),
+        }
+    }
+}
+
+/// A timestamped audit log entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Unix timestamp in milliseconds
+    pub timestamp_ms: u64,
+    /// The recorded event
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+/// Filter for reading audit history
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    /// Only entries for this peer
+    pub peer_id: Option<DeviceId>,
+    /// Only entries of this event type (see [`AuditEvent::kind`])
+    pub kind: Option<String>,
+    /// Only entries at or after this Unix timestamp in milliseconds
+    pub since_ms: Option<u64>,
+    /// Only the most recent N matching entries
+    pub limit: Option<usize>,
+}
+
+impl AuditFilter {
+    /// Returns true if the record passes the filter
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        if let Some(peer_id) = self.peer_id {
+            if record.event.peer_id() != Some(peer_id.as_u32()) {
+                return false;
+            }
+        }
+
+        if let Some(kind) = &self.kind {
+            if record.event.kind() != kind {
+                return false;
+            }
+        }
+
+        if let Some(since_ms) = self.since_ms {
+            if record.timestamp_ms < since_ms {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Append-only JSON-lines audit log with size-based rotation
+///
+/// Events passed to [`record`](Self::record) are written by a dedicated
+/// thread, so recording never blocks the async task that reports them.
+pub struct AuditLog {
+    /// The log files, shared with the writer thread
+    file: Arc<LogFile>,
+    /// Queue of the writer thread
+    writer: mpsc::Sender<WriterJob>,
+}
+
+/// Work for the audit log writer thread
+enum WriterJob {
+    /// Append a record
+    Append(AuditRecord),
+    /// Reply once every record queued before has been written
+    Flush(mpsc::Sender<()>),
+}
+
+impl AuditLog {
+    /// Creates an audit log writing to the given path
+    pub fn new(path: PathBuf, config: AuditConfig) -> Self {
+        let file = Arc::new(LogFile {
+            path,
+            config,
+            write_lock: Mutex::new(()),
+        });
+        let (writer, jobs) = mpsc::channel();
+
+        let writer_file = Arc::clone(&file);
+        let spawned = thread::Builder::new()
+            .name("audit-log".to_string())
+            .spawn(move || {
+                for job in jobs {
+                    match job {
+                        WriterJob::Append(record) => {
+                            if let Err(e) = writer_file.append(&record) {
+                                warn!("Failed to write audit log entry: {}", e);
+                            }
+                        }
+                        WriterJob::Flush(done) => {
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start audit log writer, writing inline: {}", e);
+        }
+
+        Self { file, writer }
+    }
+
+    /// Returns the path of the current log file
+    pub fn path(&self) -> &Path {
+        &self.file.path
+    }
+
+    /// Records an event, logging a warning if the write fails
+    ///
+    /// The event is queued for the writer thread and this returns at once.
+    /// Audit failures never interrupt the connection flow.
+    pub fn record(&self, event: AuditEvent) {
+        if !self.file.config.enabled {
+            return;
+        }
+
+        let record = AuditRecord {
+            timestamp_ms: now_ms(),
+            event,
+        };
+
+        // Without a writer thread, write here rather than lose the event
+        if let Err(mpsc::SendError(WriterJob::Append(record))) =
+            self.writer.send(WriterJob::Append(record))
+        {
+            if let Err(e) = self.file.append(&record) {
+                warn!("Failed to write audit log entry: {}", e);
+            }
+        }
+    }
+
+    /// Appends a record to the log, rotating first if the file is full
+    ///
+    /// Unlike [`record`](Self::record), this writes on the calling thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the record cannot be serialized or written
+    pub fn append(&self, record: &AuditRecord) -> io::Result<()> {
+        self.file.append(record)
+    }
+
+    /// Reads matching records from the rotated files and the current log,
+    /// oldest first
+    ///
+    /// Waits for recorded events to be written first. Lines that cannot be
+    /// parsed are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if an existing log file cannot be read
+    pub fn read(&self, filter: &AuditFilter) -> io::Result<Vec<AuditRecord>> {
+        let (done, written) = mpsc::channel();
+        if self.writer.send(WriterJob::Flush(done)).is_ok() {
+            let _ = written.recv();
+        }
+
+        self.file.read(filter)
+    }
+}
+
+/// The current and rotated log files
+struct LogFile {
+    path: PathBuf,
+    config: AuditConfig,
+    /// Serializes writes and rotation between threads
+    write_lock: Mutex<()>,
+}
+
+impl LogFile {
+    /// Appends a record to the log, rotating first if the file is full
+    fn append(&self, record: &AuditRecord) -> io::Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let current_size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if current_size > 0 && current_size + line.len() as u64 > self.max_size_bytes() {
+            self.rotate()?;
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Reads matching records, oldest first
+    fn read(&self, filter: &AuditFilter) -> io::Result<Vec<AuditRecord>> {
+        let mut records = Vec::new();
+
+        for index in (0..=self.config.max_files).rev() {
+            let path = self.rotated_path(index);
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                if let Ok(record) = serde_json::from_str::<AuditRecord>(&line) {
+                    if filter.matches(&record) {
+                        records.push(record);
+                    }
+                }
+            }
+        }
+
+        if let Some(limit) = filter.limit {
+            let skip = records.len().saturating_sub(limit);
+            records.drain(..skip);
+        }
+
+        Ok(records)
+    }
+
+    /// Shifts `connections.log.N` to `.N+1`, dropping the oldest file
+    fn rotate(&self) -> io::Result<()> {
+        if self.config.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+
+        let oldest = self.rotated_path(self.config.max_files);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+
+        for index in (0..self.config.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the path of a rotated file (index 0 is the current log)
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        if index == 0 {
+            return self.path.clone();
+        }
+
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn max_size_bytes(&self) -> u64 {
+        (self.config.max_size_mb.max(1) as u64) * BYTES_PER_MB
+    }
+}
+
+/// Formats a session ID as lowercase hex
+pub fn session_id_hex(session_id: &[u8; 16]) -> String {
+    session_id.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn addr() -> SocketAddr {
+        "192.168.1.10:4433".parse().unwrap()
+    }
+
+    fn request(peer_id: u32) -> AuditEvent {
+        AuditEvent::ConnectionRequest {
+            peer_id,
+            peer_name: "laptop".to_string(),
+            peer_addr: addr(),
+            has_password: false,
+        }
+    }
+
+    fn record(timestamp_ms: u64, event: AuditEvent) -> AuditRecord {
+        AuditRecord {
+            timestamp_ms,
+            event,
+        }
+    }
+
+    #[test]
+    fn test_record_json_format() {
+        let record = record(
+            1_700_000_000_000,
+            AuditEvent::ConnectionRejected {
+                peer_id: Some(123456789),
+                peer_addr: addr(),
+                reason: "InvalidPassword".to_string(),
+            },
+        );
+
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains("\"event\":\"connection_rejected\""));
+        assert!(json.contains("\"timestamp_ms\":1700000000000"));
+        assert!(json.contains("\"peer_addr\":\"192.168.1.10:4433\""));
+
+        let parsed: AuditRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.event.kind(), "connection_rejected");
+    }
+
+    #[test]
+    fn test_chat_message_record() {
+        let record = record(
+            1_700_000_000_000,
+            AuditEvent::ChatMessage {
+                session_id: "abc".to_string(),
+                peer_id: Some(123456789),
+                sender: "laptop".to_string(),
+                outgoing: false,
+                text: "Can you see my screen?".to_string(),
+            },
+        );
+
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains("\"event\":\"chat_message\""));
+        let parsed: AuditRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, record);
+
+        assert_eq!(record.event.peer_id(), Some(123456789));
+        assert_eq!(record.event.peer_addr(), None);
+        assert_eq!(
+            record.event.to_string(),
+            "chat message from 'laptop': Can you see my screen?"
+        );
+    }
+
+    #[test]
+    fn test_append_and_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog::new(temp_dir.path().join("connections.log"), AuditConfig::default());
+
+        log.append(&record(1000, request(111111111))).unwrap();
+        log.append(&record(2000, request(222222222))).unwrap();
+        log.append(&record(
+            3000,
+            AuditEvent::ConnectionAccepted {
+                peer_id: 111111111,
+                peer_addr: addr(),
+            },
+        ))
+        .unwrap();
+
+        let all = log.read(&AuditFilter::default()).unwrap();
+        assert_eq!(all.len(), 3);
+
+        let peer = AuditFilter {
+            peer_id: Some(DeviceId::from_u32(111111111).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(log.read(&peer).unwrap().len(), 2);
+
+        let accepted = AuditFilter {
+            kind: Some("connection_accepted".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(log.read(&accepted).unwrap().len(), 1);
+
+        let recent = AuditFilter {
+            since_ms: Some(2000),
+            limit: Some(1),
+            ..Default::default()
+        };
+        let recent = log.read(&recent).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].timestamp_ms, 3000);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("connections.log");
+        let config = AuditConfig {
+            enabled: true,
+            max_size_mb: 1,
+            max_files: 2,
+        };
+        let log = AuditLog::new(path.clone(), config);
+
+        // Fill well past three files' worth of entries
+        let padding = "x".repeat(64 * 1024);
+        for i in 0..60 {
+            log.append(&record(
+                i,
+                AuditEvent::AuthFailure {
+                    peer_id: 123456789,
+                    peer_addr: addr(),
+                    reason: padding.clone(),
+                },
+            ))
+            .unwrap();
+        }
+
+        assert!(path.exists());
+        assert!(temp_dir.path().join("connections.log.1").exists());
+        assert!(temp_dir.path().join("connections.log.2").exists());
+        assert!(!temp_dir.path().join("connections.log.3").exists());
+        assert!(fs::metadata(&path).unwrap().len() <= BYTES_PER_MB);
+
+        // History reads across rotated files in order, newest entry last
+        let records = log.read(&AuditFilter::default()).unwrap();
+        assert!(records.len() < 60);
+        assert_eq!(records.last().unwrap().timestamp_ms, 59);
+        assert!(records.windows(2).all(|w| w[0].timestamp_ms < w[1].timestamp_ms));
+    }
+
+    #[test]
+    fn test_recorded_events_are_read_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog::new(temp_dir.path().join("connections.log"), AuditConfig::default());
+
+        for peer_id in [111111111, 222222222, 333333333] {
+            log.record(request(peer_id));
+        }
+
+        // Reading waits for the writer thread
+        let records = log.read(&AuditFilter::default()).unwrap();
+        let peers: Vec<_> = records.iter().map(|r| r.event.peer_id()).collect();
+        assert_eq!(peers, [Some(111111111), Some(222222222), Some(333333333)]);
+    }
+
+    #[test]
+    fn test_disabled_log_writes_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("connections.log");
+        let config = AuditConfig {
+            enabled: false,
+            ..Default::default()
+        };
+
+        AuditLog::new(path.clone(), config).record(request(123456789));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_malformed_lines_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("connections.log");
+        let log = AuditLog::new(path.clone(), AuditConfig::default());
+
+        log.append(&record(1, request(123456789))).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "not json").unwrap();
+        log.append(&record(2, request(123456789))).unwrap();
+
+        assert_eq!(log.read(&AuditFilter::default()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_session_id_hex() {
+        let mut id = [0u8; 16];
+        id[0] = 0xab;
+        id[15] = 0x01;
+        assert_eq!(session_id_hex(&id), "ab000000000000000000000000000001");
+    }
+}
@@ -3,12 +3,21 @@
 //! This module contains all security-related functionality including:
 //! - Device ID generation and management
 //! - Password hashing and verification
+//! - Access policy evaluation for incoming connections
+//! - Connection rate limiting and temporary bans
+//! - Connection audit logging
 //! - Authentication (to be implemented)
 //! - Encryption (to be implemented)
 
+pub mod audit;
 pub mod id;
 pub mod password;
+pub mod policy;
+pub mod rate_limit;
 
 // Re-export commonly used types
+pub use audit::{AuditEvent, AuditFilter, AuditLog, AuditRecord};
 pub use id::{DeviceId, DeviceIdManager};
 pub use password::PasswordManager;
+pub use policy::{AccessPolicy, PeerPermissions, PolicyAction, PolicyDecision, PolicyRule};
+pub use rate_limit::RateLimiter;
@@ -0,0 +1,727 @@
+//! Access policy engine for incoming connections
+//!
+//! This module evaluates a rule-based policy file before any connection
+//! prompt is shown. Rules can:
+//! - Allow or deny specific device IDs
+//! - Allow or deny source subnets
+//! - Restrict access to time-of-day windows
+//! - Assign per-peer permissions (view, control, clipboard, files, browse,
+//!   terminal, forward)
+//!
+//! Rules are evaluated in order and the first matching rule wins. If no
+//! rule matches, the policy's default action applies.
+
+use crate::clipboard::ClipboardMode;
+use crate::error::{ConfigError, ConfigResult};
+use crate::network::protocol::Capability;
+use crate::security::DeviceId;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Policy constants (avoiding magic numbers)
+const MINUTES_PER_HOUR: u16 = 60;
+const MINUTES_PER_DAY: u16 = 24 * MINUTES_PER_HOUR;
+const SECONDS_PER_MINUTE: u64 = 60;
+const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+
+/// Action taken when a rule matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    /// Let the connection continue to the normal accept flow
+    #[default]
+    Allow,
+    /// Reject the connection immediately
+    Deny,
+}
+
+/// Permissions granted to a peer for the duration of a session
+///
+/// Permissions left out when deserializing are denied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerPermissions {
+    /// View the remote screen
+    pub view: bool,
+    /// Send keyboard and mouse input
+    pub control: bool,
+    /// Synchronize the clipboard
+    pub clipboard: bool,
+    /// Clipboard directions and content allowed when `clipboard` is granted
+    pub clipboard_mode: ClipboardMode,
+    /// Transfer files
+    pub files: bool,
+    /// Browse and download the host's shared directories when `files` is
+    /// granted
+    pub browse: bool,
+    /// Open a shell on the host, if the host has terminals enabled
+    pub terminal: bool,
+    /// Forward ports through the host, if the host has forwarding enabled
+    pub forward: bool,
+}
+
+impl PeerPermissions {
+    /// Grants every permission
+    pub fn all() -> Self {
+        Self {
+            view: true,
+            control: true,
+            clipboard: true,
+            clipboard_mode: ClipboardMode::Both,
+            files: true,
+            browse: true,
+            terminal: true,
+            forward: true,
+        }
+    }
+
+    /// Grants screen viewing only
+    pub fn view_only() -> Self {
+        Self {
+            view: true,
+            control: false,
+            clipboard: false,
+            clipboard_mode: ClipboardMode::Disabled,
+            files: false,
+            browse: false,
+            terminal: false,
+            forward: false,
+        }
+    }
+
+    /// Returns the clipboard mode these permissions allow
+    pub fn effective_clipboard_mode(&self) -> ClipboardMode {
+        if self.clipboard {
+            self.clipboard_mode
+        } else {
+            ClipboardMode::Disabled
+        }
+    }
+
+    /// Returns true if these permissions allow the given capability
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::RemoteControl => self.control,
+            Capability::ClipboardSync => self.clipboard,
+            Capability::FileTransfer => self.files,
+            Capability::FileBrowse => self.files && self.browse,
+            Capability::Terminal => self.terminal,
+            Capability::PortForward => self.forward,
+        }
+    }
+}
+
+/// An IP subnet in CIDR notation (e.g. `192.168.1.0/24`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Subnet {
+    /// Network address
+    addr: IpAddr,
+    /// Prefix length in bits
+    prefix_len: u8,
+}
+
+impl Subnet {
+    /// Returns true if the address falls within this subnet
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        let addr = match addr {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(addr),
+            IpAddr::V4(_) => addr,
+        };
+
+        match (self.addr, addr) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = prefix_mask_u32(self.prefix_len);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = prefix_mask_u128(self.prefix_len);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_mask_u32(prefix_len: u8) -> u32 {
+    match prefix_len {
+        0 => 0,
+        len => u32::MAX << (32 - len as u32),
+    }
+}
+
+fn prefix_mask_u128(prefix_len: u8) -> u128 {
+    match prefix_len {
+        0 => 0,
+        len => u128::MAX << (128 - len as u32),
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr_str, prefix_str) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+
+        let addr: IpAddr = addr_str
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid subnet address: {}", s))?;
+
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_str {
+            Some(p) => p
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_prefix)
+                .ok_or_else(|| format!("Invalid subnet prefix length: {}", s))?,
+            None => max_prefix,
+        };
+
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl TryFrom<String> for Subnet {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Subnet> for String {
+    fn from(subnet: Subnet) -> Self {
+        subnet.to_string()
+    }
+}
+
+impl fmt::Display for Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// A time of day in `HH:MM` format, stored as minutes since midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(u16);
+
+impl TimeOfDay {
+    /// Creates a time of day from hours and minutes
+    pub fn new(hour: u16, minute: u16) -> Option<Self> {
+        if hour < 24 && minute < MINUTES_PER_HOUR {
+            Some(Self(hour * MINUTES_PER_HOUR + minute))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of minutes since midnight
+    pub fn minutes(&self) -> u16 {
+        self.0
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid time of day (expected HH:MM): {}", s);
+        let (hour, minute) = s.trim().split_once(':').ok_or_else(invalid)?;
+        let hour = hour.parse().map_err(|_| invalid())?;
+        let minute = minute.parse().map_err(|_| invalid())?;
+        Self::new(hour, minute).ok_or_else(invalid)
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}",
+            self.0 / MINUTES_PER_HOUR,
+            self.0 % MINUTES_PER_HOUR
+        )
+    }
+}
+
+/// A daily time window; windows ending before they start wrap past midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    /// Start of the window (inclusive)
+    pub start: TimeOfDay,
+    /// End of the window (exclusive)
+    pub end: TimeOfDay,
+}
+
+impl TimeWindow {
+    /// Returns true if the given time falls within this window
+    pub fn contains(&self, time: TimeOfDay) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// A single access policy rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    /// Rule name, reported when the rule rejects a connection
+    pub name: String,
+
+    /// Action taken when the rule matches
+    #[serde(default)]
+    pub action: PolicyAction,
+
+    /// Device IDs this rule applies to (empty matches any device)
+    #[serde(default)]
+    pub device_ids: Vec<u32>,
+
+    /// Source subnets this rule applies to (empty matches any address)
+    #[serde(default)]
+    pub subnets: Vec<Subnet>,
+
+    /// Time windows in which the rule is in force (empty means always)
+    ///
+    /// For allow rules, connections outside every window are denied.
+    /// For deny rules, the rule only applies inside a window.
+    #[serde(default)]
+    pub time_windows: Vec<TimeWindow>,
+
+    /// Permissions granted when an allow rule matches (all when omitted)
+    #[serde(default = "PeerPermissions::all")]
+    pub permissions: PeerPermissions,
+}
+
+impl PolicyRule {
+    /// Returns true if the rule's device and subnet criteria match the peer
+    fn matches_peer(&self, device_id: DeviceId, addr: IpAddr) -> bool {
+        let device_matches =
+            self.device_ids.is_empty() || self.device_ids.contains(&device_id.as_u32());
+        let subnet_matches =
+            self.subnets.is_empty() || self.subnets.iter().any(|s| s.contains(addr));
+
+        device_matches && subnet_matches
+    }
+
+    /// Returns true if the time falls within one of the rule's windows
+    fn in_time_window(&self, time: TimeOfDay) -> bool {
+        self.time_windows.is_empty() || self.time_windows.iter().any(|w| w.contains(time))
+    }
+}
+
+/// Outcome of evaluating the access policy for a peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyDecision {
+    /// Connection may proceed with the given permissions
+    Allow {
+        /// Name of the matching rule (None for the default action)
+        rule: Option<String>,
+        /// Permissions granted to the peer
+        permissions: PeerPermissions,
+    },
+    /// Connection must be rejected
+    Deny {
+        /// Name of the matching rule (None for the default action)
+        rule: Option<String>,
+        /// Human-readable explanation
+        message: String,
+    },
+}
+
+impl PolicyDecision {
+    /// Returns true if the connection is allowed
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, PolicyDecision::Allow { .. })
+    }
+
+    /// Returns the name of the rule that produced this decision
+    pub fn rule(&self) -> Option<&str> {
+        match self {
+            PolicyDecision::Allow { rule, .. } | PolicyDecision::Deny { rule, .. } => {
+                rule.as_deref()
+            }
+        }
+    }
+}
+
+/// Rule-based access policy for incoming connections
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessPolicy {
+    /// Action applied when no rule matches
+    #[serde(default)]
+    pub default_action: PolicyAction,
+
+    /// Permissions granted by the default allow action (all when omitted)
+    #[serde(default = "PeerPermissions::all")]
+    pub default_permissions: PeerPermissions,
+
+    /// Offset from UTC, in minutes, used to evaluate time windows
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+
+    /// Ordered list of rules; the first match wins
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Default for AccessPolicy {
+    fn default() -> Self {
+        Self {
+            default_action: PolicyAction::Allow,
+            default_permissions: PeerPermissions::all(),
+            utc_offset_minutes: 0,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl AccessPolicy {
+    /// Loads the policy file, or returns the allow-all default if it doesn't exist
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file exists but cannot be read, parsed or validated
+    pub fn load_or_default(path: &Path) -> ConfigResult<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path).map_err(|e| {
+            ConfigError::LoadFailed(format!("Failed to read policy file: {}", e))
+        })?;
+
+        let policy: Self = toml::from_str(&content).map_err(|e| {
+            ConfigError::LoadFailed(format!("Failed to parse policy file: {}", e))
+        })?;
+
+        policy.validate()?;
+
+        Ok(policy)
+    }
+
+    /// Validates the policy
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a rule references an invalid device ID or the UTC
+    /// offset is out of range
+    pub fn validate(&self) -> ConfigResult<()> {
+        if self.utc_offset_minutes.abs() > MAX_UTC_OFFSET_MINUTES {
+            return Err(ConfigError::InvalidValue(
+                "Policy UTC offset must be between -14:00 and +14:00".to_string(),
+            ));
+        }
+
+        for rule in &self.rules {
+            for id in &rule.device_ids {
+                DeviceId::from_u32(*id).map_err(|e| {
+                    ConfigError::InvalidValue(format!("Policy rule '{}': {}", rule.name, e))
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates the policy for a peer at the current time
+    pub fn evaluate(&self, device_id: DeviceId, addr: IpAddr) -> PolicyDecision {
+        self.evaluate_at(device_id, addr, self.current_time())
+    }
+
+    /// Evaluates the policy for a peer at the given time of day
+    pub fn evaluate_at(&self, device_id: DeviceId, addr: IpAddr, time: TimeOfDay) -> PolicyDecision {
+        for rule in &self.rules {
+            if !rule.matches_peer(device_id, addr) {
+                continue;
+            }
+
+            match rule.action {
+                PolicyAction::Allow if rule.in_time_window(time) => {
+                    return PolicyDecision::Allow {
+                        rule: Some(rule.name.clone()),
+                        permissions: rule.permissions,
+                    };
+                }
+                PolicyAction::Allow => {
+                    return PolicyDecision::Deny {
+                        rule: Some(rule.name.clone()),
+                        message: format!(
+                            "Access policy rule '{}' does not allow connections at {}",
+                            rule.name, time
+                        ),
+                    };
+                }
+                PolicyAction::Deny if rule.in_time_window(time) => {
+                    return PolicyDecision::Deny {
+                        rule: Some(rule.name.clone()),
+                        message: format!("Denied by access policy rule '{}'", rule.name),
+                    };
+                }
+                // Deny rule outside its window: fall through to later rules
+                PolicyAction::Deny => {}
+            }
+        }
+
+        match self.default_action {
+            PolicyAction::Allow => PolicyDecision::Allow {
+                rule: None,
+                permissions: self.default_permissions,
+            },
+            PolicyAction::Deny => PolicyDecision::Deny {
+                rule: None,
+                message: "Denied by default access policy".to_string(),
+            },
+        }
+    }
+
+    /// Returns the current time of day in the policy's time zone
+    fn current_time(&self) -> TimeOfDay {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let utc_minutes = ((secs / SECONDS_PER_MINUTE) % MINUTES_PER_DAY as u64) as i32;
+        let local_minutes =
+            (utc_minutes + self.utc_offset_minutes).rem_euclid(MINUTES_PER_DAY as i32);
+
+        TimeOfDay(local_minutes as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn device(id: u32) -> DeviceId {
+        DeviceId::from_u32(id).unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn time(s: &str) -> TimeOfDay {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_subnet_contains() {
+        let subnet: Subnet = "192.168.1.0/24".parse().unwrap();
+        assert!(subnet.contains(ip("192.168.1.42")));
+        assert!(!subnet.contains(ip("192.168.2.1")));
+        assert!(subnet.contains(ip("::ffff:192.168.1.7")));
+
+        let host: Subnet = "10.0.0.5".parse().unwrap();
+        assert!(host.contains(ip("10.0.0.5")));
+        assert!(!host.contains(ip("10.0.0.6")));
+
+        let any: Subnet = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains(ip("8.8.8.8")));
+
+        let v6: Subnet = "fd00::/8".parse().unwrap();
+        assert!(v6.contains(ip("fd12::1")));
+        assert!(!v6.contains(ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn test_subnet_parse_errors() {
+        assert!("not-an-ip/24".parse::<Subnet>().is_err());
+        assert!("10.0.0.0/33".parse::<Subnet>().is_err());
+        assert!("10.0.0.0/abc".parse::<Subnet>().is_err());
+    }
+
+    #[test]
+    fn test_time_window() {
+        let office = TimeWindow {
+            start: time("09:00"),
+            end: time("17:30"),
+        };
+        assert!(office.contains(time("09:00")));
+        assert!(office.contains(time("17:29")));
+        assert!(!office.contains(time("17:30")));
+        assert!(!office.contains(time("08:59")));
+
+        let overnight = TimeWindow {
+            start: time("22:00"),
+            end: time("06:00"),
+        };
+        assert!(overnight.contains(time("23:15")));
+        assert!(overnight.contains(time("05:59")));
+        assert!(!overnight.contains(time("12:00")));
+
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+        assert!("9am".parse::<TimeOfDay>().is_err());
+    }
+
+    #[test]
+    fn test_default_policy_allows_all() {
+        let policy = AccessPolicy::default();
+        let decision = policy.evaluate(device(123456789), ip("1.2.3.4"));
+
+        assert_eq!(
+            decision,
+            PolicyDecision::Allow {
+                rule: None,
+                permissions: PeerPermissions::all(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let policy: AccessPolicy = toml::from_str(
+            r#"
+            default_action = "deny"
+
+            [[rules]]
+            name = "blocked-device"
+            action = "deny"
+            device_ids = [111111111]
+
+            [[rules]]
+            name = "lan"
+            action = "allow"
+            subnets = ["192.168.0.0/16"]
+            permissions = { view = true, control = false, clipboard = true, clipboard_mode = "host-to-client", files = false }
+            "#,
+        )
+        .unwrap();
+
+        let decision = policy.evaluate_at(device(111111111), ip("192.168.1.2"), time("12:00"));
+        assert!(!decision.is_allowed());
+        assert_eq!(decision.rule(), Some("blocked-device"));
+
+        let decision = policy.evaluate_at(device(222222222), ip("192.168.1.2"), time("12:00"));
+        match decision {
+            PolicyDecision::Allow { rule, permissions } => {
+                assert_eq!(rule.as_deref(), Some("lan"));
+                assert!(permissions.view);
+                assert!(!permissions.control);
+                assert!(permissions.clipboard);
+                assert_eq!(
+                    permissions.effective_clipboard_mode(),
+                    ClipboardMode::HostToClient
+                );
+                assert!(!permissions.files);
+                // Permissions the rule doesn't list are denied
+                assert!(!permissions.browse);
+                assert!(!permissions.allows(Capability::FileBrowse));
+                assert!(!permissions.allows(Capability::Terminal));
+                assert!(!permissions.allows(Capability::PortForward));
+            }
+            other => panic!("expected allow, got {:?}", other),
+        }
+
+        let decision = policy.evaluate_at(device(222222222), ip("10.0.0.1"), time("12:00"));
+        assert_eq!(decision.rule(), None);
+        assert!(!decision.is_allowed());
+    }
+
+    #[test]
+    fn test_rule_without_permissions_grants_all() {
+        let policy: AccessPolicy = toml::from_str(
+            r#"
+            [[rules]]
+            name = "trusted"
+            action = "allow"
+            device_ids = [123456789]
+            "#,
+        )
+        .unwrap();
+
+        let decision = policy.evaluate_at(device(123456789), ip("1.2.3.4"), time("12:00"));
+        assert_eq!(
+            decision,
+            PolicyDecision::Allow {
+                rule: Some("trusted".to_string()),
+                permissions: PeerPermissions::all(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_time_restricted_rules() {
+        let policy: AccessPolicy = toml::from_str(
+            r#"
+            [[rules]]
+            name = "maintenance"
+            action = "deny"
+            time_windows = [{ start = "02:00", end = "03:00" }]
+
+            [[rules]]
+            name = "support-hours"
+            action = "allow"
+            device_ids = [123456789]
+            time_windows = [{ start = "09:00", end = "17:00" }]
+            "#,
+        )
+        .unwrap();
+
+        let support = device(123456789);
+        let addr = ip("10.0.0.1");
+
+        assert!(policy.evaluate_at(support, addr, time("10:00")).is_allowed());
+
+        let after_hours = policy.evaluate_at(support, addr, time("18:00"));
+        assert!(!after_hours.is_allowed());
+        assert_eq!(after_hours.rule(), Some("support-hours"));
+
+        let maintenance = policy.evaluate_at(device(987654321), addr, time("02:30"));
+        assert_eq!(maintenance.rule(), Some("maintenance"));
+
+        // Deny rules outside their window fall through to the default
+        assert!(policy
+            .evaluate_at(device(987654321), addr, time("12:00"))
+            .is_allowed());
+    }
+
+    #[test]
+    fn test_load_or_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("policy.toml");
+
+        assert_eq!(
+            AccessPolicy::load_or_default(&path).unwrap(),
+            AccessPolicy::default()
+        );
+
+        fs::write(
+            &path,
+            "[[rules]]\nname = \"bad\"\naction = \"deny\"\ndevice_ids = [42]\n",
+        )
+        .unwrap();
+        assert!(AccessPolicy::load_or_default(&path).is_err());
+
+        fs::write(&path, "default_action = \"deny\"\n").unwrap();
+        let policy = AccessPolicy::load_or_default(&path).unwrap();
+        assert_eq!(policy.default_action, PolicyAction::Deny);
+    }
+}
@@ -0,0 +1,350 @@
+//! Connection rate limiting and brute-force protection
+//!
+//! This module throttles incoming connection attempts using token buckets
+//! keyed by source IP address and claimed device ID. Peers that are
+//! repeatedly rejected are temporarily banned, with the ban duration
+//! doubling on each repeat offence up to a configured maximum.
+
+use crate::config::RateLimitConfig;
+use crate::error::{SecurityError, SecurityResult};
+use crate::security::DeviceId;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+// Rate limiting constants (avoiding magic numbers)
+const SECONDS_PER_MINUTE: f64 = 60.0;
+const PRUNE_THRESHOLD: usize = 4096;
+const MAX_BAN_DOUBLINGS: u32 = 16;
+
+/// Key identifying a rate-limited peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PeerKey {
+    /// Source IP address
+    Ip(IpAddr),
+    /// Claimed device ID
+    Device(u32),
+}
+
+/// Token bucket refilled at a constant rate
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    /// Available tokens
+    tokens: f64,
+    /// Last time tokens were added
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, now: Instant) -> Self {
+        Self {
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    /// Refills the bucket and takes one token if available
+    fn try_take(&mut self, capacity: f64, per_second: f64, now: Instant) -> bool {
+        self.refill(capacity, per_second, now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn refill(&mut self, capacity: f64, per_second: f64, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_second).min(capacity);
+        self.last_refill = now;
+    }
+}
+
+/// Rejection history and ban state for a peer
+#[derive(Debug, Clone, Default)]
+struct Offences {
+    /// Rejections since the last ban or success
+    strikes: u32,
+    /// Number of bans issued so far
+    ban_count: u32,
+    /// When the current ban ends
+    banned_until: Option<Instant>,
+    /// Time of the last rejection
+    last_offence: Option<Instant>,
+}
+
+/// Rate limiter for incoming connection attempts
+///
+/// The limiter is synchronous and cheap to call; share it between tasks
+/// behind a `std::sync::Mutex`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: HashMap<PeerKey, TokenBucket>,
+    offences: HashMap<PeerKey, Offences>,
+}
+
+impl RateLimiter {
+    /// Creates a new rate limiter with the given limits
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: HashMap::new(),
+            offences: HashMap::new(),
+        }
+    }
+
+    /// Returns the configured limits
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// Records a connection attempt from an IP address
+    ///
+    /// # Errors
+    ///
+    /// Returns `TemporarilyBanned` if the address is banned, or
+    /// `RateLimitExceeded` if it has exhausted its attempts
+    pub fn check_ip(&mut self, addr: IpAddr) -> SecurityResult<()> {
+        self.check_at(PeerKey::Ip(addr), Instant::now())
+    }
+
+    /// Records a connection attempt claiming a device ID
+    ///
+    /// # Errors
+    ///
+    /// Returns `TemporarilyBanned` if the device is banned, or
+    /// `RateLimitExceeded` if it has exhausted its attempts
+    pub fn check_device(&mut self, device_id: DeviceId) -> SecurityResult<()> {
+        self.check_at(PeerKey::Device(device_id.as_u32()), Instant::now())
+    }
+
+    /// Records a rejected connection, banning the peer after repeated rejections
+    pub fn record_rejection(&mut self, addr: IpAddr, device_id: Option<DeviceId>) {
+        let now = Instant::now();
+        self.reject_at(PeerKey::Ip(addr), now);
+        if let Some(id) = device_id {
+            self.reject_at(PeerKey::Device(id.as_u32()), now);
+        }
+    }
+
+    /// Records an accepted connection, clearing the peer's rejection history
+    pub fn record_success(&mut self, addr: IpAddr, device_id: DeviceId) {
+        self.offences.remove(&PeerKey::Ip(addr));
+        self.offences.remove(&PeerKey::Device(device_id.as_u32()));
+    }
+
+    fn check_at(&mut self, key: PeerKey, now: Instant) -> SecurityResult<()> {
+        if self.buckets.len() + self.offences.len() > PRUNE_THRESHOLD {
+            self.prune(now);
+        }
+
+        if let Some(until) = self.offences.get(&key).and_then(|o| o.banned_until) {
+            if until > now {
+                let remaining = until.duration_since(now).as_secs().max(1);
+                return Err(SecurityError::TemporarilyBanned { seconds: remaining });
+            }
+        }
+
+        let (capacity, per_second) = bucket_params(&self.config, key);
+        let bucket = self
+            .buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(capacity, now));
+
+        if bucket.try_take(capacity, per_second, now) {
+            Ok(())
+        } else {
+            Err(SecurityError::RateLimitExceeded)
+        }
+    }
+
+    fn reject_at(&mut self, key: PeerKey, now: Instant) {
+        let max_ban = self.max_ban();
+        let offences = self.offences.entry(key).or_default();
+
+        // Forget old strikes once the peer has been quiet for a full ban period
+        if offences
+            .last_offence
+            .is_some_and(|last| now.saturating_duration_since(last) > max_ban)
+        {
+            offences.strikes = 0;
+        }
+
+        offences.strikes += 1;
+        offences.last_offence = Some(now);
+
+        if offences.strikes >= self.config.rejections_before_ban.max(1) {
+            let doublings = offences.ban_count.min(MAX_BAN_DOUBLINGS);
+            let ban = Duration::from_secs(self.config.base_ban_seconds.saturating_mul(1 << doublings))
+                .min(max_ban);
+
+            offences.banned_until = Some(now + ban);
+            offences.ban_count += 1;
+            offences.strikes = 0;
+        }
+    }
+
+    fn max_ban(&self) -> Duration {
+        Duration::from_secs(self.config.max_ban_minutes as u64 * 60)
+    }
+
+    /// Drops full buckets and expired offence records
+    fn prune(&mut self, now: Instant) {
+        let config = &self.config;
+        let max_ban = self.max_ban();
+
+        self.buckets.retain(|key, bucket| {
+            let (capacity, per_second) = bucket_params(config, *key);
+            bucket.refill(capacity, per_second, now);
+            bucket.tokens < capacity
+        });
+
+        self.offences.retain(|_, offences| {
+            let banned = offences.banned_until.is_some_and(|until| until > now);
+            let recent = offences
+                .last_offence
+                .is_some_and(|last| now.saturating_duration_since(last) <= max_ban);
+            banned || recent
+        });
+    }
+}
+
+/// Returns the bucket capacity and refill rate (tokens per second) for a key
+fn bucket_params(config: &RateLimitConfig, key: PeerKey) -> (f64, f64) {
+    let per_minute = match key {
+        PeerKey::Ip(_) => config.connections_per_minute,
+        PeerKey::Device(_) => config.device_attempts_per_minute,
+    };
+
+    (
+        config.connection_burst.max(1) as f64,
+        per_minute.max(1) as f64 / SECONDS_PER_MINUTE,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> RateLimitConfig {
+        RateLimitConfig {
+            connections_per_minute: 60,
+            connection_burst: 3,
+            device_attempts_per_minute: 60,
+            rejections_before_ban: 2,
+            base_ban_seconds: 10,
+            max_ban_minutes: 1,
+            max_pending_handshakes: 4,
+            max_pending_requests: 4,
+        }
+    }
+
+    fn ip(s: &str) -> PeerKey {
+        PeerKey::Ip(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_token_bucket_burst_and_refill() {
+        let mut limiter = RateLimiter::new(test_config());
+        let now = Instant::now();
+        let key = ip("10.0.0.1");
+
+        for _ in 0..3 {
+            assert!(limiter.check_at(key, now).is_ok());
+        }
+        assert!(matches!(
+            limiter.check_at(key, now),
+            Err(SecurityError::RateLimitExceeded)
+        ));
+
+        // Other addresses are unaffected
+        assert!(limiter.check_at(ip("10.0.0.2"), now).is_ok());
+
+        // One token per second at 60/minute
+        assert!(limiter.check_at(key, now + Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn test_device_and_ip_limited_independently() {
+        let mut limiter = RateLimiter::new(test_config());
+        let now = Instant::now();
+        let device = PeerKey::Device(123456789);
+
+        for _ in 0..3 {
+            assert!(limiter.check_at(device, now).is_ok());
+        }
+        assert!(limiter.check_at(device, now).is_err());
+        assert!(limiter.check_at(ip("10.0.0.1"), now).is_ok());
+    }
+
+    #[test]
+    fn test_escalating_bans() {
+        let mut limiter = RateLimiter::new(test_config());
+        let start = Instant::now();
+        let key = ip("10.0.0.1");
+
+        limiter.reject_at(key, start);
+        assert!(limiter.check_at(key, start).is_ok());
+
+        // Second rejection triggers a 10 second ban
+        limiter.reject_at(key, start);
+        assert!(matches!(
+            limiter.check_at(key, start),
+            Err(SecurityError::TemporarilyBanned { seconds: 10 })
+        ));
+        let later = start + Duration::from_secs(11);
+        assert!(limiter.check_at(key, later).is_ok());
+
+        // Next ban doubles to 20 seconds
+        limiter.reject_at(key, later);
+        limiter.reject_at(key, later);
+        assert!(matches!(
+            limiter.check_at(key, later),
+            Err(SecurityError::TemporarilyBanned { seconds: 20 })
+        ));
+
+        // Bans are capped at the configured maximum
+        let mut now = later;
+        for _ in 0..5 {
+            now += Duration::from_secs(61);
+            limiter.reject_at(key, now);
+            limiter.reject_at(key, now);
+        }
+        assert!(matches!(
+            limiter.check_at(key, now),
+            Err(SecurityError::TemporarilyBanned { seconds: 60 })
+        ));
+    }
+
+    #[test]
+    fn test_success_clears_history() {
+        let mut limiter = RateLimiter::new(test_config());
+        let addr: IpAddr = "10.0.0.1".parse().unwrap();
+        let device = DeviceId::from_u32(123456789).unwrap();
+
+        limiter.record_rejection(addr, Some(device));
+        limiter.record_success(addr, device);
+        limiter.record_rejection(addr, Some(device));
+
+        assert!(limiter.check_ip(addr).is_ok());
+        assert!(limiter.check_device(device).is_ok());
+    }
+
+    #[test]
+    fn test_prune_drops_idle_entries() {
+        let mut limiter = RateLimiter::new(test_config());
+        let now = Instant::now();
+
+        limiter.check_at(ip("10.0.0.1"), now).unwrap();
+        limiter.reject_at(ip("10.0.0.2"), now);
+        assert_eq!(limiter.buckets.len(), 1);
+        assert_eq!(limiter.offences.len(), 1);
+
+        limiter.prune(now + Duration::from_secs(120));
+        assert!(limiter.buckets.is_empty());
+        assert!(limiter.offences.is_empty());
+    }
+}
@@ -0,0 +1,400 @@
+//! Application-defined data channels
+//!
+//! Applications embedding RemoteDesk can exchange their own messages, such
+//! as ticket IDs or diagnostics, alongside a session, much like RDP virtual
+//! channels. Each channel has a name and carries opaque bytes or serde
+//! types; all of a session's channels share the custom channel stream.
+//!
+//! Channel names are agreed in the connection handshake: the client lists
+//! the names it registered (`ConnectionRequest.channels`) and the host
+//! answers with those it registered too (`ConnectionAccept.channels`).
+//! Only agreed channels can be opened. Data the peer sends on any other
+//! name is dropped and answered with [`ChannelMessage::Rejected`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::error::{CustomChannelError, CustomChannelResult};
+use crate::network::codec::decode;
+use crate::session::transport::{ChannelMessage, ChannelPair};
+
+/// Largest message a custom channel carries, in bytes
+pub const MAX_CHANNEL_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Received messages queued per channel until it is read
+///
+/// A channel whose queue is full holds up the session's other channels, so
+/// open every channel the peer sends on and keep reading it.
+const CHANNEL_QUEUE_SIZE: usize = 64;
+
+/// State of an agreed channel
+struct Slot {
+    /// Queue for received data, closed once the peer rejects the channel
+    /// or the session ends
+    incoming: Option<mpsc::Sender<Vec<u8>>>,
+    /// Receiving end of the queue, while nobody has the channel open
+    receiver: Option<mpsc::Receiver<Vec<u8>>>,
+    /// Why the peer rejected the channel
+    rejected: Option<String>,
+}
+
+struct Inner {
+    tx: mpsc::Sender<ChannelMessage>,
+    /// Agreed channel names, in handshake order
+    names: Vec<String>,
+    slots: Mutex<HashMap<String, Slot>>,
+}
+
+impl Inner {
+    fn slots(&self) -> std::sync::MutexGuard<'_, HashMap<String, Slot>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Closes every queue, so readers see the session end
+    fn close_all(&self) {
+        for slot in self.slots().values_mut() {
+            slot.incoming = None;
+        }
+    }
+
+    async fn receive(&self, channel: String, data: Vec<u8>) {
+        let incoming = self.slots().get(&channel).map(|slot| slot.incoming.clone());
+        match incoming {
+            Some(Some(incoming)) if data.len() <= MAX_CHANNEL_MESSAGE_SIZE => {
+                // The queue's receiver lives in the slot or an open channel
+                let _ = incoming.send(data).await;
+            }
+            Some(Some(_)) => warn!(
+                "Dropping {} byte message on channel {:?}: too large",
+                data.len(),
+                channel
+            ),
+            // We told the peer already
+            Some(None) => debug!("Dropping message on rejected channel {:?}", channel),
+            None => {
+                warn!("Rejecting message on unknown channel {:?}", channel);
+                let rejected = ChannelMessage::Rejected {
+                    channel,
+                    reason: "Unknown channel".to_string(),
+                };
+                let _ = self.tx.send(rejected).await;
+            }
+        }
+    }
+
+    fn rejected(&self, channel: String, reason: String) {
+        warn!("Peer rejected channel {:?}: {}", channel, reason);
+        if let Some(slot) = self.slots().get_mut(&channel) {
+            slot.incoming = None;
+            slot.rejected = Some(reason);
+        }
+    }
+}
+
+/// Custom channel service for a session
+pub struct CustomChannels {
+    inner: Arc<Inner>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl CustomChannels {
+    /// Starts serving the session's custom channel stream
+    ///
+    /// `names` are the channels agreed in the handshake.
+    pub fn start(channel: ChannelPair<ChannelMessage>, names: Vec<String>) -> Self {
+        let ChannelPair { tx, mut rx } = channel;
+
+        let slots = names
+            .iter()
+            .map(|name| {
+                let (incoming, receiver) = mpsc::channel(CHANNEL_QUEUE_SIZE);
+                let slot = Slot {
+                    incoming: Some(incoming),
+                    receiver: Some(receiver),
+                    rejected: None,
+                };
+                (name.clone(), slot)
+            })
+            .collect();
+        let inner = Arc::new(Inner {
+            tx,
+            names,
+            slots: Mutex::new(slots),
+        });
+
+        let task_inner = Arc::clone(&inner);
+        let task = tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                match message {
+                    ChannelMessage::Data { channel, data } => {
+                        task_inner.receive(channel, data).await
+                    }
+                    ChannelMessage::Rejected { channel, reason } => {
+                        task_inner.rejected(channel, reason)
+                    }
+                }
+            }
+            task_inner.close_all();
+            debug!("Custom channel stream closed");
+        });
+
+        Self { inner, task }
+    }
+
+    /// Returns the names of the agreed channels
+    pub fn names(&self) -> &[String] {
+        &self.inner.names
+    }
+
+    /// Opens an agreed channel
+    ///
+    /// Messages the peer sent before the channel was opened are kept for
+    /// it. A channel can be opened again once the last handle to it is
+    /// dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the channel wasn't agreed or is already open
+    pub fn open(&self, name: &str) -> CustomChannelResult<CustomChannel> {
+        let mut slots = self.inner.slots();
+        let slot = slots
+            .get_mut(name)
+            .ok_or_else(|| CustomChannelError::UnknownChannel(name.to_string()))?;
+        let rx = slot
+            .receiver
+            .take()
+            .ok_or_else(|| CustomChannelError::AlreadyOpen(name.to_string()))?;
+
+        Ok(CustomChannel {
+            name: name.to_string(),
+            inner: Arc::clone(&self.inner),
+            rx: Some(rx),
+        })
+    }
+}
+
+impl Drop for CustomChannels {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.inner.close_all();
+    }
+}
+
+/// An open custom channel
+pub struct CustomChannel {
+    name: String,
+    inner: Arc<Inner>,
+    /// Taken back by the service when the channel is dropped
+    rx: Option<mpsc::Receiver<Vec<u8>>>,
+}
+
+impl CustomChannel {
+    /// Returns the channel's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sends bytes to the peer
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the message is too large, the peer rejected the
+    /// channel, or the session has ended
+    pub async fn send_bytes(&self, data: Vec<u8>) -> CustomChannelResult<()> {
+        if data.len() > MAX_CHANNEL_MESSAGE_SIZE {
+            return Err(CustomChannelError::TooLarge {
+                size: data.len(),
+                max: MAX_CHANNEL_MESSAGE_SIZE,
+            });
+        }
+        self.check_rejected()?;
+
+        let message = ChannelMessage::Data {
+            channel: self.name.clone(),
+            data,
+        };
+        self.inner
+            .tx
+            .send(message)
+            .await
+            .map_err(|_| CustomChannelError::ChannelClosed)
+    }
+
+    /// Receives the next bytes the peer sent
+    ///
+    /// # Errors
+    ///
+    /// Returns error once the peer has rejected the channel or the session
+    /// has ended, after everything received before that has been read
+    pub async fn recv_bytes(&mut self) -> CustomChannelResult<Vec<u8>> {
+        let received = match self.rx.as_mut() {
+            Some(rx) => rx.recv().await,
+            None => None,
+        };
+        match received {
+            Some(data) => Ok(data),
+            None => {
+                self.check_rejected()?;
+                Err(CustomChannelError::ChannelClosed)
+            }
+        }
+    }
+
+    /// Sends a message to the peer, serialized with bincode
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the message can't be serialized or sent
+    pub async fn send<T: Serialize>(&self, message: &T) -> CustomChannelResult<()> {
+        let data = bincode::serialize(message)
+            .map_err(|e| CustomChannelError::Serialization(e.to_string()))?;
+        self.send_bytes(data).await
+    }
+
+    /// Receives the next message the peer sent with [`CustomChannel::send`]
+    ///
+    /// # Errors
+    ///
+    /// Returns error if nothing more can be received, or the message isn't
+    /// a `T`
+    pub async fn recv<T: DeserializeOwned>(&mut self) -> CustomChannelResult<T> {
+        let data = self.recv_bytes().await?;
+        decode(&data).map_err(|e| CustomChannelError::Serialization(e.to_string()))
+    }
+
+    fn check_rejected(&self) -> CustomChannelResult<()> {
+        match self
+            .inner
+            .slots()
+            .get(&self.name)
+            .and_then(|slot| slot.rejected.clone())
+        {
+            Some(reason) => Err(CustomChannelError::Rejected {
+                channel: self.name.clone(),
+                reason,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for CustomChannel {
+    fn drop(&mut self) {
+        // Hand the queue back so the channel can be opened again
+        if let Some(slot) = self.inner.slots().get_mut(&self.name) {
+            slot.receiver = self.rx.take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::transport::create_loopback_transport;
+    use serde::Deserialize;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn channels_pair(host: &[&str], client: &[&str]) -> (CustomChannels, CustomChannels) {
+        let (host_transport, client_transport) = create_loopback_transport();
+        (
+            CustomChannels::start(host_transport.channels, names(host)),
+            CustomChannels::start(client_transport.channels, names(client)),
+        )
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Ticket {
+        id: u32,
+        title: String,
+    }
+
+    #[tokio::test]
+    async fn test_bytes_and_messages() {
+        let (host, client) = channels_pair(&["tickets", "diag"], &["tickets", "diag"]);
+        assert_eq!(host.names(), ["tickets", "diag"]);
+
+        // Messages sent before the channel is opened are kept for it
+        let host_tickets = host.open("tickets").unwrap();
+        let ticket = Ticket {
+            id: 42,
+            title: "Printer offline".to_string(),
+        };
+        host_tickets.send(&ticket).await.unwrap();
+
+        let mut client_tickets = client.open("tickets").unwrap();
+        assert_eq!(client_tickets.recv::<Ticket>().await.unwrap(), ticket);
+
+        let mut host_diag = host.open("diag").unwrap();
+        client
+            .open("diag")
+            .unwrap()
+            .send_bytes(b"load 0.5".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(host_diag.recv_bytes().await.unwrap(), b"load 0.5");
+    }
+
+    #[tokio::test]
+    async fn test_open_rules() {
+        let (host, _client) = channels_pair(&["tickets"], &["tickets"]);
+
+        assert!(matches!(
+            host.open("other"),
+            Err(CustomChannelError::UnknownChannel(_))
+        ));
+
+        let channel = host.open("tickets").unwrap();
+        assert!(matches!(
+            host.open("tickets"),
+            Err(CustomChannelError::AlreadyOpen(_))
+        ));
+        drop(channel);
+        let channel = host.open("tickets").unwrap();
+
+        let too_large = vec![0; MAX_CHANNEL_MESSAGE_SIZE + 1];
+        assert!(matches!(
+            channel.send_bytes(too_large).await,
+            Err(CustomChannelError::TooLarge { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_channel_is_rejected() {
+        // The client doesn't know the channel, as if it skipped negotiation
+        let (host, client) = channels_pair(&["tickets"], &[]);
+        let mut tickets = host.open("tickets").unwrap();
+        tickets.send_bytes(b"hello".to_vec()).await.unwrap();
+
+        let error = tickets.recv_bytes().await.unwrap_err();
+        assert!(
+            matches!(error, CustomChannelError::Rejected { .. }),
+            "{}",
+            error
+        );
+        assert!(matches!(
+            tickets.send_bytes(b"again".to_vec()).await,
+            Err(CustomChannelError::Rejected { .. })
+        ));
+        assert!(client.names().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_session_end_closes_channels() {
+        let (host, client) = channels_pair(&["tickets"], &["tickets"]);
+        let mut tickets = client.open("tickets").unwrap();
+        drop(host);
+        drop(client);
+        assert!(matches!(
+            tickets.recv_bytes().await,
+            Err(CustomChannelError::ChannelClosed)
+        ));
+    }
+}
@@ -0,0 +1,267 @@
+//! In-session text chat
+//!
+//! Both sides of a session run a [`ChatService`] on the chat stream. Every
+//! message, sent or received, is kept in a short history and published to
+//! subscribers; that is how the viewer's chat panel, the host's chat window
+//! and the audit log see it. Received messages are labelled with the name
+//! the peer gave in the connection handshake (`ConnectionRequest.client_name`
+//! or `ConnectionAccept.host_name`).
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, warn};
+
+use crate::error::{SessionError, SessionResult};
+use crate::session::transport::{ChannelPair, ChatMessage};
+
+/// Longest chat message accepted, in characters
+pub const MAX_CHAT_MESSAGE_CHARS: usize = 4096;
+
+/// Capacity of the chat event channel
+pub const CHAT_EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Messages kept in a session's chat history
+const MAX_CHAT_HISTORY: usize = 200;
+
+/// A chat message as shown to the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatEntry {
+    /// Name of the sender
+    pub sender: String,
+    /// Message text
+    pub text: String,
+    /// When the message was sent, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Whether this side sent the message
+    pub outgoing: bool,
+}
+
+struct Inner {
+    tx: mpsc::Sender<ChatMessage>,
+    /// Name our messages are shown with
+    local_name: String,
+    /// Name the peer's messages are shown with
+    peer_name: String,
+    history: Mutex<VecDeque<ChatEntry>>,
+    events: broadcast::Sender<ChatEntry>,
+}
+
+impl Inner {
+    /// Adds an entry to the history and publishes it
+    fn push(&self, entry: ChatEntry) {
+        {
+            let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+            if history.len() == MAX_CHAT_HISTORY {
+                history.pop_front();
+            }
+            history.push_back(entry.clone());
+        }
+        // Nobody may be listening
+        let _ = self.events.send(entry);
+    }
+}
+
+/// Chat service for a session
+pub struct ChatService {
+    inner: Arc<Inner>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl ChatService {
+    /// Starts serving the session's chat stream
+    ///
+    /// Our messages are shown as `local_name` and the peer's as
+    /// `peer_name`.
+    pub fn start(channel: ChannelPair<ChatMessage>, local_name: String, peer_name: String) -> Self {
+        let ChannelPair { tx, mut rx } = channel;
+        let (events, _) = broadcast::channel(CHAT_EVENT_CHANNEL_CAPACITY);
+
+        let inner = Arc::new(Inner {
+            tx,
+            local_name,
+            peer_name,
+            history: Mutex::new(VecDeque::new()),
+            events,
+        });
+
+        let task_inner = Arc::clone(&inner);
+        let task = tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                match validate_text(&message.text) {
+                    Ok(text) => task_inner.push(ChatEntry {
+                        sender: task_inner.peer_name.clone(),
+                        text,
+                        timestamp_ms: message.timestamp_ms,
+                        outgoing: false,
+                    }),
+                    Err(e) => warn!("Ignoring chat message from {}: {}", task_inner.peer_name, e),
+                }
+            }
+            debug!("Chat channel closed");
+        });
+
+        Self { inner, task }
+    }
+
+    /// Returns the name our messages are shown with
+    pub fn local_name(&self) -> &str {
+        &self.inner.local_name
+    }
+
+    /// Returns the name the peer's messages are shown with
+    pub fn peer_name(&self) -> &str {
+        &self.inner.peer_name
+    }
+
+    /// Subscribes to sent and received messages
+    pub fn subscribe(&self) -> broadcast::Receiver<ChatEntry> {
+        self.inner.events.subscribe()
+    }
+
+    /// Returns the most recent messages, oldest first
+    pub fn history(&self) -> Vec<ChatEntry> {
+        let history = self.inner.history.lock().unwrap_or_else(|e| e.into_inner());
+        history.iter().cloned().collect()
+    }
+
+    /// Sends a message to the peer and returns it as shown locally
+    ///
+    /// Surrounding whitespace is trimmed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the message is empty or too long, or the session
+    /// has ended
+    pub async fn send(&self, text: &str) -> SessionResult<ChatEntry> {
+        let text = validate_text(text)?;
+        let timestamp_ms = now_ms();
+
+        let message = ChatMessage {
+            text: text.clone(),
+            timestamp_ms,
+        };
+        self.inner
+            .tx
+            .send(message)
+            .await
+            .map_err(|_| SessionError::ChannelClosed)?;
+
+        let entry = ChatEntry {
+            sender: self.inner.local_name.clone(),
+            text,
+            timestamp_ms,
+            outgoing: true,
+        };
+        self.inner.push(entry.clone());
+        Ok(entry)
+    }
+}
+
+impl Drop for ChatService {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Returns the message text to show, if it is acceptable
+///
+/// Control characters other than newlines and tabs are removed, so a peer
+/// can't mess with a terminal the history is printed to.
+fn validate_text(text: &str) -> SessionResult<String> {
+    let text: String = text
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect();
+
+    if text.is_empty() {
+        return Err(SessionError::InvalidChatMessage(
+            "message is empty".to_string(),
+        ));
+    }
+    let chars = text.chars().count();
+    if chars > MAX_CHAT_MESSAGE_CHARS {
+        return Err(SessionError::InvalidChatMessage(format!(
+            "message is {} characters, the limit is {}",
+            chars, MAX_CHAT_MESSAGE_CHARS
+        )));
+    }
+
+    Ok(text)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::transport::create_loopback_transport;
+
+    fn chat_pair() -> (ChatService, ChatService) {
+        let (host, client) = create_loopback_transport();
+        (
+            ChatService::start(host.chat, "office-pc".to_string(), "laptop".to_string()),
+            ChatService::start(client.chat, "laptop".to_string(), "office-pc".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_validate_text() {
+        assert_eq!(validate_text("  hello \n").unwrap(), "hello");
+        assert_eq!(validate_text("a\u{1b}[2Jb\nc").unwrap(), "a[2Jb\nc");
+        assert!(validate_text("   ").is_err());
+        assert!(validate_text(&"x".repeat(MAX_CHAT_MESSAGE_CHARS + 1)).is_err());
+        assert!(validate_text(&"é".repeat(MAX_CHAT_MESSAGE_CHARS)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_chat_between_peers() {
+        let (host, client) = chat_pair();
+        let mut host_events = host.subscribe();
+
+        let sent = client.send(" Can you see my screen? ").await.unwrap();
+        assert_eq!(sent.sender, "laptop");
+        assert_eq!(sent.text, "Can you see my screen?");
+        assert!(sent.outgoing);
+
+        let received = host_events.recv().await.unwrap();
+        assert_eq!(received.sender, "laptop");
+        assert_eq!(received.text, sent.text);
+        assert_eq!(received.timestamp_ms, sent.timestamp_ms);
+        assert!(!received.outgoing);
+
+        host.send("Yes").await.unwrap();
+        let history = host.history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].sender, "office-pc");
+        assert!(history[1].outgoing);
+
+        assert!(matches!(
+            client.send("").await,
+            Err(SessionError::InvalidChatMessage(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_history_is_bounded() {
+        let (host, client) = chat_pair();
+        let mut host_events = host.subscribe();
+
+        for i in 0..MAX_CHAT_HISTORY + 5 {
+            client.send(&format!("message {}", i)).await.unwrap();
+            host_events.recv().await.unwrap();
+        }
+
+        let history = host.history();
+        assert_eq!(history.len(), MAX_CHAT_HISTORY);
+        assert_eq!(history[0].text, "message 5");
+    }
+}
@@ -4,15 +4,23 @@
 
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
 use crate::desktop::FrameDecoder;
-use crate::error::{SessionError, SessionResult};
+use crate::error::{RpcError, RpcResult, SessionError, SessionResult};
+use crate::input::KeySequence;
+use crate::network::DisconnectReason;
+use crate::security::DeviceId;
+use crate::session::events::{
+    SessionEvent, SessionEventEmitter, SessionStatsSnapshot, DEFAULT_STATS_INTERVAL,
+};
+use crate::session::rpc::{control_answer, unsupported_request, RpcCalls};
 use crate::session::state::{SessionState, SessionStateMachine};
+use crate::session::timeout::TimeoutKind;
 use crate::session::transport::{
     ControlMessage, SessionTransport, TransportFrame, TransportInput,
 };
@@ -22,18 +30,27 @@ use crate::session::transport::{
 pub struct ClientSessionConfig {
     /// Session identifier
     pub session_id: String,
+    /// Host name from the connection accept, shown in chat
+    pub peer_name: String,
+    /// Host device ID, if the session runs over a connection
+    pub peer_id: Option<DeviceId>,
     /// Whether to send input events
     pub send_input: bool,
     /// Buffer size for frames
     pub frame_buffer_size: usize,
+    /// Custom channels agreed with the host in the handshake
+    pub channels: Vec<String>,
 }
 
 impl Default for ClientSessionConfig {
     fn default() -> Self {
         Self {
             session_id: uuid::Uuid::new_v4().to_string(),
+            peer_name: "Host".to_string(),
+            peer_id: None,
             send_input: true,
             frame_buffer_size: 4,
+            channels: Vec::new(),
         }
     }
 }
@@ -55,6 +72,24 @@ impl ClientSessionConfig {
         self.send_input = send;
         self
     }
+
+    /// Sets the host name shown in chat
+    pub fn with_peer_name(mut self, name: String) -> Self {
+        self.peer_name = name;
+        self
+    }
+
+    /// Sets the host device ID
+    pub fn with_peer_id(mut self, id: DeviceId) -> Self {
+        self.peer_id = Some(id);
+        self
+    }
+
+    /// Sets the custom channels agreed with the host
+    pub fn with_channels(mut self, channels: Vec<String>) -> Self {
+        self.channels = channels;
+        self
+    }
 }
 
 /// Statistics for the client session
@@ -98,6 +133,36 @@ impl ClientSessionStats {
     }
 }
 
+/// Timeout warning received from the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutNotice {
+    /// Limit that will end the session
+    pub kind: TimeoutKind,
+    /// Seconds left when the warning was sent
+    pub seconds_remaining: u64,
+    /// When the warning was received
+    pub received_at: Instant,
+}
+
+impl TimeoutNotice {
+    /// Returns the estimated seconds left before the session ends
+    pub fn seconds_left(&self) -> u64 {
+        self.seconds_remaining
+            .saturating_sub(self.received_at.elapsed().as_secs())
+    }
+}
+
+/// The display a host shares, as reported by the host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteDisplay {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Display name
+    pub name: String,
+}
+
 /// Client session for receiving remote desktop
 pub struct ClientSession {
     /// Session configuration
@@ -114,6 +179,14 @@ pub struct ClientSession {
     decoder: Arc<FrameDecoder>,
     /// Input sequence counter
     input_sequence: Arc<AtomicU64>,
+    /// Pending timeout warning from the host
+    timeout_notice: Arc<RwLock<Option<TimeoutNotice>>>,
+    /// Reason the host ended the session
+    disconnect_reason: Arc<RwLock<Option<DisconnectReason>>>,
+    /// Requests to the host waiting for an answer
+    rpc: Arc<RpcCalls<ControlMessage>>,
+    /// Publishes session events
+    events: SessionEventEmitter,
 }
 
 impl ClientSession {
@@ -127,6 +200,10 @@ impl ClientSession {
             stats: Arc::new(RwLock::new(ClientSessionStats::default())),
             decoder: Arc::new(FrameDecoder::new()),
             input_sequence: Arc::new(AtomicU64::new(0)),
+            timeout_notice: Arc::new(RwLock::new(None)),
+            disconnect_reason: Arc::new(RwLock::new(None)),
+            rpc: Arc::new(RpcCalls::new()),
+            events: SessionEventEmitter::default(),
         }
     }
 
@@ -145,11 +222,35 @@ impl ClientSession {
         self.stats.read().await.clone()
     }
 
+    /// Returns how long the session has been in its current state
+    pub async fn time_in_state(&self) -> Duration {
+        self.state.read().await.time_in_state()
+    }
+
+    /// Publishes this session's events on the given channel
+    pub async fn set_event_sender(&mut self, tx: broadcast::Sender<SessionEvent>) {
+        self.events = SessionEventEmitter::new(self.config.session_id.clone(), tx);
+        self.state
+            .write()
+            .await
+            .set_event_emitter(self.events.clone());
+    }
+
     /// Returns the frame decoder
     pub fn decoder(&self) -> &FrameDecoder {
         &self.decoder
     }
 
+    /// Returns the pending timeout warning, if the host sent one
+    pub async fn timeout_notice(&self) -> Option<TimeoutNotice> {
+        *self.timeout_notice.read().await
+    }
+
+    /// Returns why the host ended the session, if it did
+    pub async fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        *self.disconnect_reason.read().await
+    }
+
     /// Starts the client session
     pub async fn start(&mut self) -> SessionResult<()> {
         // Transition to Connecting state
@@ -178,6 +279,9 @@ impl ClientSession {
         // Start background tasks
         self.spawn_frame_receiver_task();
         self.spawn_control_handler_task();
+        if self.events.is_enabled() {
+            self.spawn_stats_publisher_task();
+        }
 
         Ok(())
     }
@@ -209,6 +313,10 @@ impl ClientSession {
     pub async fn pause(&mut self) -> SessionResult<()> {
         let mut state = self.state.write().await;
         state.transition(SessionState::Paused)?;
+        // No input is sent while paused, so don't leave keys held on the host
+        if self.config.send_input {
+            let _ = self.release_all_input();
+        }
         info!("Client session {} paused", self.config.session_id);
         Ok(())
     }
@@ -250,23 +358,142 @@ impl ClientSession {
     }
 
     /// Spawns the control message handler task
-    fn spawn_control_handler_task(&self) {
+    fn spawn_control_handler_task(&mut self) {
         let is_running = Arc::clone(&self.is_running);
         let state = Arc::clone(&self.state);
-        let stats = Arc::clone(&self.stats);
+        let timeout_notice = Arc::clone(&self.timeout_notice);
+        let disconnect_reason = Arc::clone(&self.disconnect_reason);
+        let rpc = Arc::clone(&self.rpc);
+        let control_tx = self.transport.control.tx.clone();
+        let mut control_rx = self.transport.control.take_rx();
 
         tokio::spawn(async move {
             info!("Starting control handler task");
 
-            while is_running.load(Ordering::SeqCst) {
-                // Handle control messages
-                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            while let Some(message) = control_rx.recv().await {
+                if !is_running.load(Ordering::SeqCst) {
+                    break;
+                }
+                // Answers to this side's requests go to the waiting calls
+                let Some(message) = rpc.resolve_control(message) else {
+                    continue;
+                };
+
+                match message {
+                    ControlMessage::Ping { timestamp_ms } => {
+                        let pong = ControlMessage::Pong {
+                            original_timestamp_ms: timestamp_ms,
+                        };
+                        if control_tx.send(pong).await.is_err() {
+                            break;
+                        }
+                    }
+                    ControlMessage::Request {
+                        request_id,
+                        request,
+                    } => {
+                        let answer = match *request {
+                            ControlMessage::Ping { timestamp_ms } => Ok(ControlMessage::Pong {
+                                original_timestamp_ms: timestamp_ms,
+                            }),
+                            ref other => Err(unsupported_request(other)),
+                        };
+                        if control_tx.send(control_answer(request_id, answer)).await.is_err() {
+                            break;
+                        }
+                    }
+                    ControlMessage::TimeoutWarning {
+                        kind,
+                        seconds_remaining,
+                    } => {
+                        warn!(
+                            "Host will end the session in {}s ({} timeout)",
+                            seconds_remaining, kind
+                        );
+                        *timeout_notice.write().await = Some(TimeoutNotice {
+                            kind,
+                            seconds_remaining,
+                            received_at: Instant::now(),
+                        });
+                    }
+                    ControlMessage::ExtendResult {
+                        granted,
+                        seconds_remaining,
+                    } => {
+                        if granted {
+                            info!("Session extended ({:?}s remaining)", seconds_remaining);
+                            *timeout_notice.write().await = None;
+                        } else {
+                            warn!("Host denied the session extension");
+                        }
+                    }
+                    ControlMessage::Disconnect { reason } => {
+                        info!("Host ended the session: {:?}", reason);
+                        *disconnect_reason.write().await = Some(reason);
+
+                        is_running.store(false, Ordering::SeqCst);
+                        let mut state = state.write().await;
+                        if state.can_transition(SessionState::Disconnecting) {
+                            let _ = state.transition(SessionState::Disconnecting);
+                        }
+                        state.force_transition(SessionState::Disconnected);
+                        break;
+                    }
+                    other => debug!("Ignoring control message: {:?}", other),
+                }
             }
 
+            rpc.close();
             info!("Control handler task stopped");
         });
     }
 
+    /// Spawns the task that publishes periodic statistics snapshots
+    fn spawn_stats_publisher_task(&self) {
+        let is_running = Arc::clone(&self.is_running);
+        let stats = Arc::clone(&self.stats);
+        let events = self.events.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(DEFAULT_STATS_INTERVAL);
+
+            while is_running.load(Ordering::SeqCst) {
+                interval.tick().await;
+                let snapshot = stats.read().await.clone();
+                events.stats(SessionStatsSnapshot::Client(snapshot));
+            }
+        });
+    }
+
+    /// Asks the host to extend the session by the given number of minutes
+    ///
+    /// The host replies with whether the extension was granted; a granted
+    /// extension clears the pending timeout notice.
+    pub fn request_extension(&self, minutes: u32) -> SessionResult<()> {
+        self.transport
+            .control
+            .tx
+            .try_send(ControlMessage::ExtendSession { minutes })
+            .map_err(|_| SessionError::ChannelClosed)
+    }
+
+    /// Asks the host to release every key and button this client holds down
+    pub fn release_all_input(&self) -> SessionResult<()> {
+        self.transport
+            .control
+            .tx
+            .try_send(ControlMessage::ReleaseAllInput)
+            .map_err(|_| SessionError::ChannelClosed)
+    }
+
+    /// Sends a key sequence to the host as a single batch
+    ///
+    /// The host injects the presses and releases in order without
+    /// interleaving other input.
+    pub fn send_key_sequence(&self, sequence: &KeySequence) -> SessionResult<()> {
+        self.send_input(sequence.to_batch())
+    }
+
     /// Sends an input event to the host
     pub fn send_input(&self, event: crate::input::InputEvent) -> SessionResult<()> {
         if !self.config.send_input {
@@ -306,14 +533,67 @@ impl ClientSession {
         self.transport.input.tx.clone()
     }
 
-    /// Measures latency by sending a ping
-    pub async fn measure_latency(&self) -> SessionResult<()> {
-        let timestamp_ms = Instant::now().elapsed().as_millis() as u64;
-        let ping = ControlMessage::Ping { timestamp_ms };
+    /// Returns a sender for control messages (for use with viewer)
+    pub fn control_sender(&self) -> mpsc::Sender<ControlMessage> {
+        self.transport.control.tx.clone()
+    }
+
+    /// Sends a request to the host and waits for its answer
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the host doesn't answer in time or can't answer
+    /// the request
+    pub async fn call(&self, request: ControlMessage) -> RpcResult<ControlMessage> {
+        self.rpc
+            .call_control(&self.transport.control.tx, request)
+            .await
+    }
+
+    /// Measures the round trip to the host with a ping
+    ///
+    /// The session statistics record half the round trip as the latency.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the host doesn't answer the ping
+    pub async fn measure_latency(&self) -> RpcResult<Duration> {
+        let sent = Instant::now();
+        let timestamp_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or_default();
+
+        match self.call(ControlMessage::Ping { timestamp_ms }).await? {
+            ControlMessage::Pong {
+                original_timestamp_ms,
+            } if original_timestamp_ms == timestamp_ms => {
+                let round_trip = sent.elapsed();
+                self.stats.write().await.latency_ms = Some(round_trip.as_millis() as u64 / 2);
+                Ok(round_trip)
+            }
+            other => Err(RpcError::UnexpectedResponse(format!("{:?}", other))),
+        }
+    }
 
-        match self.transport.control.tx.try_send(ping) {
-            Ok(()) => Ok(()),
-            Err(_) => Err(SessionError::ChannelClosed),
+    /// Asks the host about the display it shares
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the host doesn't answer, or hasn't started
+    /// capturing its screen
+    pub async fn display_info(&self) -> RpcResult<RemoteDisplay> {
+        match self.call(ControlMessage::RequestDisplayInfo).await? {
+            ControlMessage::DisplayInfo {
+                width,
+                height,
+                name,
+            } => Ok(RemoteDisplay {
+                width,
+                height,
+                name,
+            }),
+            other => Err(RpcError::UnexpectedResponse(format!("{:?}", other))),
         }
     }
 }
@@ -342,6 +622,62 @@ mod tests {
         assert_eq!(config.session_id, "test-client");
     }
 
+    #[tokio::test]
+    async fn test_client_session_handles_timeout_messages() {
+        let config = ClientSessionConfig::default();
+        let (host_transport, client_transport) = create_loopback_transport();
+
+        let mut session = ClientSession::new(config, client_transport);
+        session.start().await.unwrap();
+
+        host_transport
+            .control
+            .tx
+            .send(ControlMessage::TimeoutWarning {
+                kind: TimeoutKind::Idle,
+                seconds_remaining: 30,
+            })
+            .await
+            .unwrap();
+        host_transport
+            .control
+            .tx
+            .send(ControlMessage::Disconnect {
+                reason: DisconnectReason::SessionTimeout,
+            })
+            .await
+            .unwrap();
+
+        for _ in 0..50 {
+            if session.state().await == SessionState::Disconnected {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(session.state().await, SessionState::Disconnected);
+        assert_eq!(
+            session.disconnect_reason().await,
+            Some(DisconnectReason::SessionTimeout)
+        );
+        let notice = session.timeout_notice().await.unwrap();
+        assert_eq!(notice.kind, TimeoutKind::Idle);
+        assert_eq!(notice.seconds_remaining, 30);
+    }
+
+    #[tokio::test]
+    async fn test_client_session_pause_releases_input() {
+        let config = ClientSessionConfig::default();
+        let (mut host_transport, client_transport) = create_loopback_transport();
+
+        let mut session = ClientSession::new(config, client_transport);
+        session.start().await.unwrap();
+        session.pause().await.unwrap();
+
+        let message = host_transport.control.rx.recv().await.unwrap();
+        assert!(matches!(message, ControlMessage::ReleaseAllInput));
+    }
+
     #[tokio::test]
     async fn test_client_session_stats() {
         let config = ClientSessionConfig::default();
@@ -354,4 +690,66 @@ mod tests {
         assert_eq!(stats.frames_decoded, 0);
         assert_eq!(stats.input_events_sent, 0);
     }
+
+    #[tokio::test]
+    async fn test_client_session_requests() {
+        let config = ClientSessionConfig::default();
+        let (mut host_transport, client_transport) = create_loopback_transport();
+
+        let mut session = ClientSession::new(config, client_transport);
+        session.start().await.unwrap();
+
+        // The host answers the ping, and refuses a display info request
+        let host = tokio::spawn(async move {
+            let control = &mut host_transport.control;
+            for _ in 0..2 {
+                let Some(ControlMessage::Request {
+                    request_id,
+                    request,
+                }) = control.rx.recv().await
+                else {
+                    panic!("expected a request");
+                };
+                let answer = match *request {
+                    ControlMessage::Ping { timestamp_ms } => Ok(ControlMessage::Pong {
+                        original_timestamp_ms: timestamp_ms,
+                    }),
+                    ref other => Err(unsupported_request(other)),
+                };
+                control.tx.send(control_answer(request_id, answer)).await.unwrap();
+            }
+
+            // The client answers the host's requests too
+            let ping = ControlMessage::Ping { timestamp_ms: 5 };
+            control
+                .tx
+                .send(ControlMessage::Request {
+                    request_id: 77,
+                    request: Box::new(ping),
+                })
+                .await
+                .unwrap();
+            control.rx.recv().await.unwrap()
+        });
+
+        session.measure_latency().await.unwrap();
+        assert!(session.stats().await.latency_ms.is_some());
+        assert!(matches!(
+            session.display_info().await,
+            Err(RpcError::Unsupported(_))
+        ));
+
+        match host.await.unwrap() {
+            ControlMessage::Response {
+                request_id: 77,
+                response,
+            } => assert!(matches!(
+                *response,
+                ControlMessage::Pong {
+                    original_timestamp_ms: 5
+                }
+            )),
+            other => panic!("unexpected answer: {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,124 @@
+//! Session event stream
+//!
+//! Sessions publish state transitions, periodic statistics snapshots and
+//! errors on a `tokio::sync::broadcast` channel so front-ends can react to
+//! changes instead of polling the session manager.
+
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+
+use crate::session::client::ClientSessionStats;
+use crate::session::host::HostSessionStats;
+use crate::session::state::SessionState;
+
+/// Capacity of the session event channel
+///
+/// Slow subscribers that fall further behind than this miss events and
+/// receive `RecvError::Lagged`.
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Default interval between statistics snapshots
+pub const DEFAULT_STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Statistics snapshot for either side of a session
+#[derive(Debug, Clone)]
+pub enum SessionStatsSnapshot {
+    /// Host session statistics
+    Host(HostSessionStats),
+    /// Client session statistics
+    Client(ClientSessionStats),
+}
+
+/// Event published by a managed session
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    /// Session moved to a new state
+    StateChanged {
+        /// Session that changed
+        session_id: String,
+        /// Previous state
+        from: SessionState,
+        /// New state
+        to: SessionState,
+    },
+    /// Periodic statistics snapshot
+    Stats {
+        /// Session the statistics belong to
+        session_id: String,
+        /// Statistics at the time of the snapshot
+        stats: SessionStatsSnapshot,
+    },
+    /// Error reported by a session task
+    Error {
+        /// Session that failed
+        session_id: String,
+        /// Error description
+        message: String,
+    },
+}
+
+impl SessionEvent {
+    /// Returns the ID of the session the event belongs to
+    pub fn session_id(&self) -> &str {
+        match self {
+            SessionEvent::StateChanged { session_id, .. }
+            | SessionEvent::Stats { session_id, .. }
+            | SessionEvent::Error { session_id, .. } => session_id,
+        }
+    }
+}
+
+/// Publishes events for a single session
+///
+/// Emitting is a no-op until a sender is attached, and events are dropped
+/// silently when nobody is subscribed.
+#[derive(Debug, Clone, Default)]
+pub struct SessionEventEmitter {
+    session_id: String,
+    tx: Option<broadcast::Sender<SessionEvent>>,
+}
+
+impl SessionEventEmitter {
+    /// Creates an emitter publishing on the given channel
+    pub fn new(session_id: String, tx: broadcast::Sender<SessionEvent>) -> Self {
+        Self {
+            session_id,
+            tx: Some(tx),
+        }
+    }
+
+    /// Returns true if a channel is attached
+    pub fn is_enabled(&self) -> bool {
+        self.tx.is_some()
+    }
+
+    /// Publishes a state transition
+    pub fn state_changed(&self, from: SessionState, to: SessionState) {
+        self.emit(|session_id| SessionEvent::StateChanged {
+            session_id,
+            from,
+            to,
+        });
+    }
+
+    /// Publishes a statistics snapshot
+    pub fn stats(&self, stats: SessionStatsSnapshot) {
+        self.emit(|session_id| SessionEvent::Stats { session_id, stats });
+    }
+
+    /// Publishes an error
+    pub fn error(&self, message: impl Into<String>) {
+        self.emit(|session_id| SessionEvent::Error {
+            session_id,
+            message: message.into(),
+        });
+    }
+
+    fn emit(&self, build: impl FnOnce(String) -> SessionEvent) {
+        if let Some(tx) = &self.tx {
+            // Sending only fails when there are no subscribers
+            let _ = tx.send(build(self.session_id.clone()));
+        }
+    }
+}
@@ -4,30 +4,72 @@
 //! remote input events.
 
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
 
-use tokio::sync::mpsc;
-use tokio::sync::RwLock;
+use tokio::sync::broadcast;
+use tokio::sync::{Mutex, RwLock};
 use tracing::{debug, error, info, warn};
 
+use crate::clipboard::ClipboardMode;
+use crate::config::SecurityConfig;
 use crate::desktop::{CaptureConfig, FrameEncoder, FrameFormat, ScreenCapturer};
 use crate::error::{RemoteDeskError, SessionError, SessionResult};
-use crate::input::{InputEvent, InputSimulator};
+use crate::input::{InputEvent, InputSimulator, PressedInputs};
+use crate::network::protocol::Capability;
+use crate::network::{DisconnectReason, ErrorCode, ErrorMessage};
+use crate::security::{DeviceId, PeerPermissions};
+use crate::session::events::{
+    SessionEvent, SessionEventEmitter, SessionStatsSnapshot, DEFAULT_STATS_INTERVAL,
+};
+use crate::session::rpc::{control_answer, unsupported_request};
 use crate::session::state::{SessionState, SessionStateMachine};
+use crate::session::timeout::{SessionTimer, TimeoutPolicy, TimerStatus};
 use crate::session::transport::{
     ControlMessage, SessionTransport, TransportFrame, TransportInput,
 };
 
+/// How often the timeout watchdog checks the session deadlines
+const TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Minutes to seconds conversion (avoiding magic numbers)
+const SECONDS_PER_MINUTE: u64 = 60;
+
 /// Configuration for host session
 #[derive(Debug, Clone)]
 pub struct HostSessionConfig {
     /// Capture configuration
     pub capture: CaptureConfig,
+    /// Whether to stream the screen to the client, following the peer's
+    /// `view` permission
+    pub allow_view: bool,
     /// Whether to allow input simulation
     pub allow_input: bool,
+    /// Whether the client may offer and fetch files, following the peer's
+    /// `files` permission
+    pub allow_files: bool,
+    /// Whether the client may browse the shared directories, following
+    /// the peer's `browse` permission
+    pub allow_browse: bool,
+    /// Whether the client may open terminals, following the peer's
+    /// `terminal` permission
+    pub allow_terminal: bool,
+    /// Whether the client may forward ports through this machine,
+    /// following the peer's `forward` permission
+    pub allow_forward: bool,
+    /// Clipboard sync allowed with the client, following the peer's
+    /// `clipboard` permissions
+    pub clipboard_mode: ClipboardMode,
+    /// Custom channels agreed with the client in the handshake
+    pub channels: Vec<String>,
     /// Session identifier
     pub session_id: String,
+    /// Client name from the connection request, shown in chat
+    pub peer_name: String,
+    /// Client device ID, if the session runs over a connection
+    pub peer_id: Option<DeviceId>,
+    /// Session and idle timeout limits
+    pub timeouts: TimeoutPolicy,
 }
 
 impl Default for HostSessionConfig {
@@ -35,7 +77,17 @@ impl Default for HostSessionConfig {
         Self {
             capture: CaptureConfig::default(),
             session_id: uuid::Uuid::new_v4().to_string(),
+            allow_view: true,
             allow_input: true,
+            allow_files: true,
+            allow_browse: true,
+            allow_terminal: true,
+            allow_forward: true,
+            clipboard_mode: ClipboardMode::Both,
+            channels: Vec::new(),
+            peer_name: "Client".to_string(),
+            peer_id: None,
+            timeouts: TimeoutPolicy::default(),
         }
     }
 }
@@ -49,17 +101,93 @@ impl HostSessionConfig {
         }
     }
 
+    /// Sets whether to stream the screen to the client
+    pub fn with_view(mut self, allow: bool) -> Self {
+        self.allow_view = allow;
+        self
+    }
+
     /// Sets whether to allow input simulation
     pub fn with_input(mut self, allow: bool) -> Self {
         self.allow_input = allow;
         self
     }
 
+    /// Sets whether the client may offer and fetch files
+    pub fn with_files(mut self, allow: bool) -> Self {
+        self.allow_files = allow;
+        self
+    }
+
+    /// Sets whether the client may browse the shared directories
+    pub fn with_browse(mut self, allow: bool) -> Self {
+        self.allow_browse = allow;
+        self
+    }
+
+    /// Sets whether the client may open terminals
+    pub fn with_terminal(mut self, allow: bool) -> Self {
+        self.allow_terminal = allow;
+        self
+    }
+
+    /// Sets whether the client may forward ports through this machine
+    pub fn with_forward(mut self, allow: bool) -> Self {
+        self.allow_forward = allow;
+        self
+    }
+
+    /// Sets the clipboard sync allowed with the client
+    pub fn with_clipboard_mode(mut self, mode: ClipboardMode) -> Self {
+        self.clipboard_mode = mode;
+        self
+    }
+
+    /// Grants the client what the peer's permissions allow
+    pub fn with_permissions(self, permissions: &PeerPermissions) -> Self {
+        self.with_view(permissions.view)
+            .with_input(permissions.control)
+            .with_files(permissions.files)
+            .with_browse(permissions.allows(Capability::FileBrowse))
+            .with_terminal(permissions.terminal)
+            .with_forward(permissions.forward)
+            .with_clipboard_mode(permissions.effective_clipboard_mode())
+    }
+
+    /// Sets the custom channels agreed with the client
+    pub fn with_channels(mut self, channels: Vec<String>) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    /// Sets the client name shown in chat
+    pub fn with_peer_name(mut self, name: String) -> Self {
+        self.peer_name = name;
+        self
+    }
+
+    /// Sets the client device ID
+    pub fn with_peer_id(mut self, id: DeviceId) -> Self {
+        self.peer_id = Some(id);
+        self
+    }
+
     /// Sets the session ID
     pub fn with_session_id(mut self, id: String) -> Self {
         self.session_id = id;
         self
     }
+
+    /// Sets the session and idle timeout limits
+    pub fn with_timeouts(mut self, timeouts: TimeoutPolicy) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Applies the timeout settings from the security configuration
+    pub fn with_security_config(self, security: &SecurityConfig) -> Self {
+        self.with_timeouts(TimeoutPolicy::from_security_config(security))
+    }
 }
 
 /// Statistics for the host session
@@ -124,11 +252,22 @@ pub struct HostSession {
     frame_sequence: Arc<AtomicU64>,
     /// Session start time
     session_start: Arc<RwLock<Option<Instant>>>,
+    /// Session and idle deadlines
+    timer: Arc<Mutex<SessionTimer>>,
+    /// Keys and buttons the client currently holds down
+    pressed: Arc<Mutex<PressedInputs>>,
+    /// Simulates the client's input for the whole session
+    simulator: Arc<InputSimulator>,
+    /// Width and height of the captured display, once capture has started
+    display_size: Arc<StdMutex<Option<(u32, u32)>>>,
+    /// Publishes session events
+    events: SessionEventEmitter,
 }
 
 impl HostSession {
     /// Creates a new host session
     pub fn new(config: HostSessionConfig, transport: SessionTransport) -> Self {
+        let timer = SessionTimer::new(config.timeouts.clone(), Instant::now());
         Self {
             config,
             state: Arc::new(RwLock::new(SessionStateMachine::new())),
@@ -137,6 +276,11 @@ impl HostSession {
             stats: Arc::new(RwLock::new(HostSessionStats::default())),
             frame_sequence: Arc::new(AtomicU64::new(0)),
             session_start: Arc::new(RwLock::new(None)),
+            timer: Arc::new(Mutex::new(timer)),
+            pressed: Arc::new(Mutex::new(PressedInputs::new())),
+            simulator: Arc::new(InputSimulator::new()),
+            display_size: Arc::new(StdMutex::new(None)),
+            events: SessionEventEmitter::default(),
         }
     }
 
@@ -145,6 +289,11 @@ impl HostSession {
         &self.config.session_id
     }
 
+    /// Returns the session's configuration
+    pub fn config(&self) -> &HostSessionConfig {
+        &self.config
+    }
+
     /// Returns the current session state
     pub async fn state(&self) -> SessionState {
         self.state.read().await.current()
@@ -155,6 +304,20 @@ impl HostSession {
         self.stats.read().await.clone()
     }
 
+    /// Returns how long the session has been in its current state
+    pub async fn time_in_state(&self) -> Duration {
+        self.state.read().await.time_in_state()
+    }
+
+    /// Publishes this session's events on the given channel
+    pub async fn set_event_sender(&mut self, tx: broadcast::Sender<SessionEvent>) {
+        self.events = SessionEventEmitter::new(self.config.session_id.clone(), tx);
+        self.state
+            .write()
+            .await
+            .set_event_emitter(self.events.clone());
+    }
+
     /// Starts the host session
     ///
     /// This spawns background tasks for:
@@ -183,14 +346,26 @@ impl HostSession {
             *start = Some(Instant::now());
             let mut stats = self.stats.write().await;
             stats.started_at = Some(Instant::now());
+            let mut timer = self.timer.lock().await;
+            *timer = SessionTimer::new(self.config.timeouts.clone(), Instant::now());
         }
 
         info!("Host session {} started", self.config.session_id);
 
         // Start background tasks
-        self.spawn_frame_capture_task();
+        if self.config.allow_view {
+            self.spawn_frame_capture_task();
+        } else {
+            info!("Screen not shared: the client may not view it");
+        }
         self.spawn_input_receiver_task();
         self.spawn_control_handler_task();
+        if !self.config.timeouts.is_disabled() {
+            self.spawn_timeout_watchdog_task();
+        }
+        if self.events.is_enabled() {
+            self.spawn_stats_publisher_task();
+        }
 
         Ok(())
     }
@@ -203,6 +378,7 @@ impl HostSession {
         }
 
         self.is_running.store(false, Ordering::SeqCst);
+        release_pressed_inputs(&self.pressed, &self.simulator, &self.config.session_id).await;
 
         // Transition through disconnecting to disconnected
         {
@@ -222,6 +398,8 @@ impl HostSession {
     pub async fn pause(&mut self) -> SessionResult<()> {
         let mut state = self.state.write().await;
         state.transition(SessionState::Paused)?;
+        // Input is ignored while paused, so nothing could release these later
+        release_pressed_inputs(&self.pressed, &self.simulator, &self.config.session_id).await;
         info!("Host session {} paused", self.config.session_id);
         Ok(())
     }
@@ -245,6 +423,8 @@ impl HostSession {
         let frame_tx = self.transport.frames.tx.clone();
         let frame_sequence = Arc::clone(&self.frame_sequence);
         let session_start = Arc::clone(&self.session_start);
+        let display_size = Arc::clone(&self.display_size);
+        let events = self.events.clone();
 
         // Use std::thread for blocking screen capture (scrap::Capturer is not Send)
         std::thread::spawn(move || {
@@ -258,6 +438,7 @@ impl HostSession {
                 Ok(rt) => rt,
                 Err(e) => {
                     error!("Failed to create runtime: {}", e);
+                    events.error(format!("Failed to create runtime: {}", e));
                     return;
                 }
             };
@@ -267,17 +448,21 @@ impl HostSession {
                 Ok(d) => d,
                 Err(e) => {
                     error!("Failed to get primary display: {}", e);
+                    events.error(format!("Failed to get primary display: {}", e));
                     return;
                 }
             };
 
             let width = display.width();
             let height = display.height();
+            *display_size.lock().unwrap_or_else(|e| e.into_inner()) =
+                Some((width as u32, height as u32));
 
             let mut capturer = match scrap::Capturer::new(display) {
                 Ok(c) => c,
                 Err(e) => {
                     error!("Failed to create capturer: {}", e);
+                    events.error(format!("Failed to create capturer: {}", e));
                     return;
                 }
             };
@@ -381,6 +566,7 @@ impl HostSession {
                                     s.frames_dropped += 1;
                                 });
                                 error!("Failed to encode frame: {}", e);
+                                events.error(format!("Failed to encode frame: {}", e));
                             }
                         }
                     }
@@ -393,6 +579,7 @@ impl HostSession {
 
                         if consecutive_failures >= MAX_FAILURES {
                             error!("Too many consecutive capture failures, stopping");
+                            events.error(format!("Screen capture stopped: {}", e));
                             break;
                         }
                     }
@@ -455,66 +642,188 @@ impl HostSession {
     }
 
     /// Spawns the input receiver task
-    fn spawn_input_receiver_task(&self) {
-        let config = self.config.clone();
+    ///
+    /// Every input event counts as activity for the idle timeout; events are
+    /// only simulated while the session is active. Anything still held down
+    /// when the input channel closes is released.
+    fn spawn_input_receiver_task(&mut self) {
+        let session_id = self.config.session_id.clone();
+        let allow_input = self.config.allow_input;
         let is_running = Arc::clone(&self.is_running);
         let state = Arc::clone(&self.state);
         let stats = Arc::clone(&self.stats);
+        let timer = Arc::clone(&self.timer);
+        let pressed = Arc::clone(&self.pressed);
+        let simulator = Arc::clone(&self.simulator);
+        let events = self.events.clone();
+        let mut input_rx = self.transport.input.take_rx();
 
-        // Take ownership of the input receiver
-        // Note: This requires the transport to be passed by value or use interior mutability
-        // For now, we'll create a simple placeholder that handles input from the channel
+        tokio::spawn(async move {
+            info!("Starting input receiver task");
 
-        let is_running_clone = Arc::clone(&is_running);
-        let state_clone = Arc::clone(&state);
-        let stats_clone = Arc::clone(&stats);
+            while let Some(input) = input_rx.recv().await {
+                if !is_running.load(Ordering::SeqCst) {
+                    break;
+                }
 
-        // The actual receiver would need to be moved out of self.transport
-        // This is a design limitation - in practice, you'd use Arc<Mutex<Option<Receiver>>>
-        // or restructure the code
+                timer.lock().await.record_activity(Instant::now());
+                stats.write().await.input_events_received += 1;
 
-        tokio::spawn(async move {
-            info!("Starting input receiver task");
+                if !allow_input || state.read().await.current() != SessionState::Active {
+                    continue;
+                }
 
-            let simulator = InputSimulator::new();
-
-            // For now, we'll just log that the task is running
-            // In a full implementation, we'd receive from the input channel here
-            while is_running_clone.load(Ordering::SeqCst) {
-                // Check state
-                {
-                    let current_state = state_clone.read().await.current();
-                    if current_state != SessionState::Active {
-                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                        continue;
+                match simulator.simulate(&input.event) {
+                    Ok(()) => {
+                        pressed.lock().await.record(&input.event);
+                        stats.write().await.input_events_processed += 1;
+                    }
+                    Err(e) => {
+                        warn!("Failed to simulate input {}: {}", input.sequence, e);
+                        events.error(format!("Failed to simulate input: {}", e));
                     }
                 }
-
-                // Wait for shutdown
-                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
             }
 
+            release_pressed_inputs(&pressed, &simulator, &session_id).await;
             info!("Input receiver task stopped");
         });
     }
 
     /// Spawns the control message handler task
-    fn spawn_control_handler_task(&self) {
+    fn spawn_control_handler_task(&mut self) {
+        let handler = ControlHandler {
+            session_id: self.config.session_id.clone(),
+            timer: Arc::clone(&self.timer),
+            pressed: Arc::clone(&self.pressed),
+            simulator: Arc::clone(&self.simulator),
+            display_size: Arc::clone(&self.display_size),
+        };
         let is_running = Arc::clone(&self.is_running);
-        let state = Arc::clone(&self.state);
+        let control_tx = self.transport.control.tx.clone();
+        let mut control_rx = self.transport.control.take_rx();
 
         tokio::spawn(async move {
             info!("Starting control handler task");
 
-            while is_running.load(Ordering::SeqCst) {
-                // Handle control messages
-                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            while let Some(message) = control_rx.recv().await {
+                if !is_running.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let reply = match message {
+                    ControlMessage::Request {
+                        request_id,
+                        request,
+                    } => {
+                        let answer = handler
+                            .handle(&request)
+                            .await
+                            .unwrap_or_else(|| Err(unsupported_request(&request)));
+                        Some(control_answer(request_id, answer))
+                    }
+                    message => match handler.handle(&message).await {
+                        Some(Ok(reply)) => Some(reply),
+                        Some(Err(error)) => {
+                            debug!("Not answering {:?}: {}", message, error.message);
+                            None
+                        }
+                        None => None,
+                    },
+                };
+
+                if let Some(reply) = reply {
+                    if control_tx.send(reply).await.is_err() {
+                        break;
+                    }
+                }
             }
 
             info!("Control handler task stopped");
         });
     }
 
+    /// Spawns the task that enforces the session and idle timeouts
+    ///
+    /// The client is warned once as each deadline approaches. When a deadline
+    /// passes, the client is sent a `SessionTimeout` disconnect and the
+    /// session is stopped.
+    fn spawn_timeout_watchdog_task(&self) {
+        let session_id = self.config.session_id.clone();
+        let is_running = Arc::clone(&self.is_running);
+        let state = Arc::clone(&self.state);
+        let timer = Arc::clone(&self.timer);
+        let pressed = Arc::clone(&self.pressed);
+        let simulator = Arc::clone(&self.simulator);
+        let control_tx = self.transport.control.tx.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TIMEOUT_CHECK_INTERVAL);
+
+            while is_running.load(Ordering::SeqCst) {
+                interval.tick().await;
+
+                let status = timer.lock().await.poll(Instant::now());
+                match status {
+                    TimerStatus::Ok => {}
+                    TimerStatus::Warn { kind, remaining } => {
+                        info!(
+                            "Host session {} will end in {}s ({} timeout)",
+                            session_id,
+                            remaining.as_secs(),
+                            kind
+                        );
+                        let warning = ControlMessage::TimeoutWarning {
+                            kind,
+                            seconds_remaining: remaining.as_secs(),
+                        };
+                        if control_tx.send(warning).await.is_err() {
+                            warn!("Control channel closed, cannot send timeout warning");
+                        }
+                    }
+                    TimerStatus::Expired(kind) => {
+                        info!("Host session {} ended by {} timeout", session_id, kind);
+
+                        is_running.store(false, Ordering::SeqCst);
+                        release_pressed_inputs(&pressed, &simulator, &session_id).await;
+                        {
+                            let mut state = state.write().await;
+                            if state.can_transition(SessionState::Disconnecting) {
+                                let _ = state.transition(SessionState::Disconnecting);
+                            }
+                            state.force_transition(SessionState::Disconnected);
+                        }
+
+                        let disconnect = ControlMessage::Disconnect {
+                            reason: DisconnectReason::SessionTimeout,
+                        };
+                        if control_tx.send(disconnect).await.is_err() {
+                            warn!("Control channel closed, cannot send disconnect");
+                        }
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Spawns the task that publishes periodic statistics snapshots
+    fn spawn_stats_publisher_task(&self) {
+        let is_running = Arc::clone(&self.is_running);
+        let stats = Arc::clone(&self.stats);
+        let events = self.events.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(DEFAULT_STATS_INTERVAL);
+
+            while is_running.load(Ordering::SeqCst) {
+                interval.tick().await;
+                let snapshot = stats.read().await.clone();
+                events.stats(SessionStatsSnapshot::Host(snapshot));
+            }
+        });
+    }
+
     /// Processes an input event directly (for testing/loopback)
     pub fn process_input(&self, input: &TransportInput) -> SessionResult<()> {
         if !self.config.allow_input {
@@ -523,8 +832,7 @@ impl HostSession {
             ));
         }
 
-        let simulator = InputSimulator::new();
-        simulator
+        self.simulator
             .simulate(&input.event)
             .map_err(|e| SessionError::InputError(e.to_string()))
     }
@@ -542,6 +850,97 @@ impl HostSession {
     }
 }
 
+/// Releases every key and button a client left held down
+async fn release_pressed_inputs(
+    pressed: &Mutex<PressedInputs>,
+    simulator: &InputSimulator,
+    session_id: &str,
+) {
+    let releases = pressed.lock().await.release_all();
+    if releases.is_empty() {
+        return;
+    }
+
+    info!(
+        "Releasing {} held keys and buttons for session {}",
+        releases.len(),
+        session_id
+    );
+
+    for event in &releases {
+        if let Err(e) = simulator.simulate(event) {
+            warn!("Failed to release held input: {}", e);
+        }
+    }
+}
+
+/// Acts on control messages from the client
+struct ControlHandler {
+    session_id: String,
+    timer: Arc<Mutex<SessionTimer>>,
+    pressed: Arc<Mutex<PressedInputs>>,
+    simulator: Arc<InputSimulator>,
+    display_size: Arc<StdMutex<Option<(u32, u32)>>>,
+}
+
+impl ControlHandler {
+    /// Handles a message, returning the answer for the client if it has one
+    async fn handle(
+        &self,
+        message: &ControlMessage,
+    ) -> Option<Result<ControlMessage, ErrorMessage>> {
+        match *message {
+            ControlMessage::Ping { timestamp_ms } => Some(Ok(ControlMessage::Pong {
+                original_timestamp_ms: timestamp_ms,
+            })),
+            ControlMessage::RequestDisplayInfo => {
+                let size = *self.display_size.lock().unwrap_or_else(|e| e.into_inner());
+                Some(match size {
+                    Some((width, height)) => Ok(ControlMessage::DisplayInfo {
+                        width,
+                        height,
+                        name: "Primary display".to_string(),
+                    }),
+                    None => Err(ErrorMessage::new(
+                        ErrorCode::UnknownError,
+                        "Screen capture hasn't started".to_string(),
+                    )),
+                })
+            }
+            ControlMessage::ExtendSession { minutes } => {
+                let now = Instant::now();
+                let mut timer = self.timer.lock().await;
+                let requested = Duration::from_secs(minutes as u64 * SECONDS_PER_MINUTE);
+                let granted = timer.extend(requested, now);
+
+                info!(
+                    "Session extension of {} minutes {}",
+                    minutes,
+                    if granted { "granted" } else { "denied" }
+                );
+
+                Some(Ok(ControlMessage::ExtendResult {
+                    granted,
+                    seconds_remaining: timer.session_remaining(now).map(|d| d.as_secs()),
+                }))
+            }
+            ControlMessage::ReleaseAllInput => {
+                release_pressed_inputs(&self.pressed, &self.simulator, &self.session_id).await;
+                None
+            }
+            ControlMessage::Disconnect { reason } => {
+                info!("Client is disconnecting: {:?}", reason);
+                release_pressed_inputs(&self.pressed, &self.simulator, &self.session_id).await;
+                None
+            }
+            ref other => {
+                debug!("Ignoring control message: {:?}", other);
+                None
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -560,14 +959,50 @@ mod tests {
     async fn test_host_session_config() {
         let config = HostSessionConfig::new(30, 80)
             .with_input(false)
+            .with_browse(false)
+            .with_terminal(false)
+            .with_forward(false)
+            .with_channels(vec!["tickets".to_string()])
+            .with_peer_name("laptop".to_string())
             .with_session_id("test-session".to_string());
 
         assert_eq!(config.capture.fps, 30);
         assert_eq!(config.capture.quality, 80);
         assert!(!config.allow_input);
+        assert!(!config.allow_browse);
+        assert!(!config.allow_terminal);
+        assert!(!config.allow_forward);
+        assert_eq!(config.channels, ["tickets"]);
+        assert_eq!(config.peer_name, "laptop");
         assert_eq!(config.session_id, "test-session");
     }
 
+    #[tokio::test]
+    async fn test_host_session_config_from_permissions() {
+        let config = HostSessionConfig::default().with_permissions(&PeerPermissions::view_only());
+        assert!(config.allow_view);
+        assert!(!config.allow_input);
+        assert!(!config.allow_files);
+        assert!(!config.allow_browse);
+        assert!(!config.allow_terminal);
+        assert!(!config.allow_forward);
+        assert_eq!(config.clipboard_mode, ClipboardMode::Disabled);
+
+        // Browsing needs file transfer too
+        let permissions = PeerPermissions {
+            view: false,
+            files: false,
+            ..PeerPermissions::all()
+        };
+        let config = HostSessionConfig::default().with_permissions(&permissions);
+        assert!(!config.allow_view);
+        assert!(config.allow_input);
+        assert!(!config.allow_browse);
+        assert!(config.allow_terminal);
+        assert!(config.allow_forward);
+        assert_eq!(config.clipboard_mode, ClipboardMode::Both);
+    }
+
     #[tokio::test]
     async fn test_host_session_state_transitions() {
         let config = HostSessionConfig::default();
@@ -594,6 +1029,132 @@ mod tests {
         assert_eq!(stats.bytes_sent, 0);
     }
 
+    #[tokio::test]
+    async fn test_host_session_extend_request() {
+        let timeouts = TimeoutPolicy {
+            session: Some(Duration::from_secs(600)),
+            allow_extend: true,
+            ..Default::default()
+        };
+        let config = HostSessionConfig::default().with_timeouts(timeouts);
+        let (host_transport, mut client_transport) = create_loopback_transport();
+
+        let mut session = HostSession::new(config, host_transport);
+        session.is_running.store(true, Ordering::SeqCst);
+        session.spawn_control_handler_task();
+
+        client_transport
+            .control
+            .tx
+            .send(ControlMessage::ExtendSession { minutes: 5 })
+            .await
+            .unwrap();
+
+        match client_transport.control.rx.recv().await.unwrap() {
+            ControlMessage::ExtendResult {
+                granted,
+                seconds_remaining,
+            } => {
+                assert!(granted);
+                assert!(seconds_remaining.unwrap() > 600);
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_host_session_answers_requests() {
+        use crate::error::RpcError;
+        use crate::session::rpc::RpcCalls;
+
+        let (host_transport, client_transport) = create_loopback_transport();
+        let mut session = HostSession::new(HostSessionConfig::default(), host_transport);
+        session.is_running.store(true, Ordering::SeqCst);
+        session.spawn_control_handler_task();
+
+        let tx = client_transport.control.tx;
+        let mut rx = client_transport.control.rx;
+        let calls = Arc::new(RpcCalls::new());
+        let router = Arc::clone(&calls);
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                router.resolve_control(message);
+            }
+        });
+
+        let pong = calls
+            .call_control(&tx, ControlMessage::Ping { timestamp_ms: 7 })
+            .await
+            .unwrap();
+        assert!(matches!(
+            pong,
+            ControlMessage::Pong {
+                original_timestamp_ms: 7
+            }
+        ));
+
+        // Display info is known once capture has started
+        let error = calls
+            .call_control(&tx, ControlMessage::RequestDisplayInfo)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, RpcError::Remote(_)));
+        *session.display_size.lock().unwrap() = Some((1280, 720));
+        let info = calls
+            .call_control(&tx, ControlMessage::RequestDisplayInfo)
+            .await
+            .unwrap();
+        assert!(matches!(
+            info,
+            ControlMessage::DisplayInfo {
+                width: 1280,
+                height: 720,
+                ..
+            }
+        ));
+
+        let error = calls.call_control(&tx, ControlMessage::Pause).await.unwrap_err();
+        assert!(matches!(error, RpcError::Unsupported(_)));
+    }
+
+    #[tokio::test]
+    async fn test_host_session_timeout_disconnects_client() {
+        let timeouts = TimeoutPolicy {
+            session: Some(Duration::from_millis(500)),
+            ..Default::default()
+        };
+        let config = HostSessionConfig::default().with_timeouts(timeouts);
+        let (host_transport, mut client_transport) = create_loopback_transport();
+
+        let session = HostSession::new(config, host_transport);
+        session.is_running.store(true, Ordering::SeqCst);
+        {
+            let mut state = session.state.write().await;
+            state.transition(SessionState::Connecting).unwrap();
+            state.transition(SessionState::Authenticating).unwrap();
+            state.transition(SessionState::Active).unwrap();
+        }
+        session.spawn_timeout_watchdog_task();
+
+        let rx = &mut client_transport.control.rx;
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            ControlMessage::TimeoutWarning {
+                kind: crate::session::timeout::TimeoutKind::Session,
+                ..
+            }
+        ));
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            ControlMessage::Disconnect {
+                reason: DisconnectReason::SessionTimeout
+            }
+        ));
+
+        assert_eq!(session.state().await, SessionState::Disconnected);
+        assert!(!session.is_running.load(Ordering::SeqCst));
+    }
+
     #[test]
     fn test_host_session_stats_calculations() {
         let mut stats = HostSessionStats::default();
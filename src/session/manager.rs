@@ -1,23 +1,47 @@
 //! Session manager for coordinating remote desktop sessions
 //!
 //! This module provides a central manager for creating, tracking, and
-//! managing host and client sessions.
+//! managing host and client sessions, and the clipboard, file, chat,
+//! terminal, port forwarding and custom channel services that run alongside
+//! them.
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, Mutex, RwLock};
 use tracing::{debug, error, info, warn};
 
+use crate::clipboard::{ClipboardMode, ClipboardSync};
+use crate::config::{ClipboardConfig, FilesConfig, SecurityConfig, TerminalConfig};
 use crate::error::{SessionError, SessionResult};
+use crate::files::{BrowseRoots, FileBrowser, FileTransfers, SharedFiles, TransferInfo};
+use crate::input::KeySequence;
+use crate::network::{
+    ConnectionRole, EstablishedConnection, ForwardPolicy, PortForwarder, QuicConnection,
+};
+use crate::security::audit;
+use crate::security::{AuditEvent, AuditLog, DeviceId};
+use crate::session::channels::CustomChannels;
+use crate::session::chat::ChatService;
 use crate::session::client::{ClientSession, ClientSessionConfig};
+use crate::session::events::{SessionEvent, SessionStatsSnapshot, EVENT_CHANNEL_CAPACITY};
 use crate::session::host::{HostSession, HostSessionConfig};
 use crate::session::state::SessionState;
-use crate::session::transport::{create_loopback_transport, SessionTransport};
+use crate::session::timeout::TimeoutPolicy;
+use crate::session::transport::{
+    create_loopback_transport, create_quic_transport, ChannelPair, QuicTransportHandle,
+    SessionTransport,
+};
+use crate::terminal::{resolve_shell, Terminals};
 
 /// Unique identifier for a session
 pub type SessionId = String;
 
+/// Name our chat messages are shown with when none is set
+const DEFAULT_DEVICE_NAME: &str = "RemoteDesk";
+
 /// Type of session
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SessionType {
@@ -36,6 +60,10 @@ pub struct SessionInfo {
     pub session_type: SessionType,
     /// Current state
     pub state: SessionState,
+    /// Time spent in the current state
+    pub time_in_state: Duration,
+    /// Latest session statistics
+    pub stats: SessionStatsSnapshot,
     /// Remote peer ID (if connected)
     pub remote_id: Option<String>,
 }
@@ -64,6 +92,39 @@ impl ManagedSession {
             ManagedSession::Client(_) => SessionType::Client,
         }
     }
+
+    /// Returns the current session state
+    pub async fn state(&self) -> SessionState {
+        match self {
+            ManagedSession::Host(s) => s.state().await,
+            ManagedSession::Client(s) => s.state().await,
+        }
+    }
+
+    /// Returns a snapshot of the session statistics
+    pub async fn stats(&self) -> SessionStatsSnapshot {
+        match self {
+            ManagedSession::Host(s) => SessionStatsSnapshot::Host(s.stats().await),
+            ManagedSession::Client(s) => SessionStatsSnapshot::Client(s.stats().await),
+        }
+    }
+
+    /// Returns information about the session
+    pub async fn info(&self) -> SessionInfo {
+        let time_in_state = match self {
+            ManagedSession::Host(s) => s.time_in_state().await,
+            ManagedSession::Client(s) => s.time_in_state().await,
+        };
+
+        SessionInfo {
+            id: self.id().to_string(),
+            session_type: self.session_type(),
+            state: self.state().await,
+            time_in_state,
+            stats: self.stats().await,
+            remote_id: None,
+        }
+    }
 }
 
 /// Session manager for coordinating sessions
@@ -72,6 +133,42 @@ pub struct SessionManager {
     sessions: Arc<RwLock<HashMap<SessionId, ManagedSession>>>,
     /// Local device ID
     local_id: Option<String>,
+    /// Events from all managed sessions
+    events: broadcast::Sender<SessionEvent>,
+    /// File transfer service of each session
+    transfers: Arc<RwLock<HashMap<SessionId, Arc<FileTransfers>>>>,
+    /// File browsing service of each session
+    browsers: Arc<RwLock<HashMap<SessionId, Arc<FileBrowser>>>>,
+    /// Directory files offered by peers are saved in
+    download_dir: PathBuf,
+    /// Directory host sessions accept client files into without asking
+    drop_dir: Option<PathBuf>,
+    /// Directories clients of host sessions may browse
+    browse_roots: BrowseRoots,
+    /// Chat service of each session
+    chats: Arc<RwLock<HashMap<SessionId, Arc<ChatService>>>>,
+    /// Name our chat messages are shown with
+    device_name: String,
+    /// Audit log chat messages are recorded to
+    audit_log: Option<Arc<AuditLog>>,
+    /// Terminal service of each session
+    terminals: Arc<RwLock<HashMap<SessionId, Arc<Terminals>>>>,
+    /// Shell clients of host sessions may open, if terminals are enabled
+    shell: Option<PathBuf>,
+    /// Port forwarding service of each session
+    forwarders: Arc<RwLock<HashMap<SessionId, Arc<PortForwarder>>>>,
+    /// What clients of host sessions may reach through port forwarding
+    forward_policy: ForwardPolicy,
+    /// Custom channel service of each session
+    custom_channels: Arc<RwLock<HashMap<SessionId, Arc<CustomChannels>>>>,
+    /// Stream bridges of sessions running over a QUIC connection
+    quic_transports: Arc<RwLock<HashMap<SessionId, QuicTransportHandle>>>,
+    /// Session and idle limits of host sessions that set none themselves
+    timeouts: TimeoutPolicy,
+    /// Clipboard sync settings; sessions don't sync the clipboard without
+    clipboard: Option<ClipboardConfig>,
+    /// Clipboard sync of each session
+    clipboards: Arc<RwLock<HashMap<SessionId, Arc<Mutex<ClipboardSync>>>>>,
 }
 
 impl Default for SessionManager {
@@ -83,27 +180,128 @@ impl Default for SessionManager {
 impl SessionManager {
     /// Creates a new session manager
     pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             local_id: None,
+            events,
+            transfers: Arc::new(RwLock::new(HashMap::new())),
+            browsers: Arc::new(RwLock::new(HashMap::new())),
+            download_dir: FilesConfig::default().download_dir(),
+            drop_dir: None,
+            browse_roots: BrowseRoots::default(),
+            chats: Arc::new(RwLock::new(HashMap::new())),
+            device_name: DEFAULT_DEVICE_NAME.to_string(),
+            audit_log: None,
+            terminals: Arc::new(RwLock::new(HashMap::new())),
+            shell: None,
+            forwarders: Arc::new(RwLock::new(HashMap::new())),
+            forward_policy: ForwardPolicy::default(),
+            custom_channels: Arc::new(RwLock::new(HashMap::new())),
+            quic_transports: Arc::new(RwLock::new(HashMap::new())),
+            timeouts: TimeoutPolicy::default(),
+            clipboard: None,
+            clipboards: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
+    /// Sets the directory files offered by peers are saved in
+    pub fn with_download_dir(mut self, download_dir: PathBuf) -> Self {
+        self.download_dir = download_dir;
+        self
+    }
+
+    /// Sets the directory host sessions save client files in
+    ///
+    /// Files a client offers, such as those dropped onto its viewer, are
+    /// accepted without asking. Client sessions keep using the download
+    /// directory and still ask.
+    pub fn with_drop_dir(mut self, drop_dir: PathBuf) -> Self {
+        self.drop_dir = Some(drop_dir);
+        self
+    }
+
+    /// Sets the directories clients of host sessions may browse
+    ///
+    /// Only host sessions whose config allows browsing share them.
+    pub fn with_browse_roots(mut self, roots: BrowseRoots) -> Self {
+        self.browse_roots = roots;
+        self
+    }
+
+    /// Sets the name our chat messages are shown with
+    ///
+    /// This should match the name sent in the connection handshake.
+    pub fn with_device_name(mut self, name: String) -> Self {
+        self.device_name = name;
+        self
+    }
+
+    /// Records chat messages of every session to the audit log
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Lets clients of host sessions open terminals, if enabled
+    ///
+    /// Only host sessions whose config allows terminals run a shell.
+    pub fn with_terminal(mut self, config: &TerminalConfig) -> Self {
+        self.shell = config.enabled.then(|| resolve_shell(config.shell.as_deref()));
+        self
+    }
+
+    /// Sets what clients of host sessions may reach through port forwarding
+    ///
+    /// Only host sessions whose config allows forwarding use it; the rest
+    /// refuse every tunnel the peer asks for.
+    pub fn with_forward_policy(mut self, policy: ForwardPolicy) -> Self {
+        self.forward_policy = policy;
+        self
+    }
+
+    /// Applies the configured session and idle timeouts to host sessions
+    ///
+    /// Host sessions whose config already sets a limit keep their own.
+    pub fn with_security_config(mut self, security: &SecurityConfig) -> Self {
+        self.timeouts = TimeoutPolicy::from_security_config(security);
+        self
+    }
+
+    /// Syncs the clipboard of every session with its peer, as configured
+    ///
+    /// Host sessions sync no more than their config's clipboard mode allows.
+    pub fn with_clipboard(mut self, config: &ClipboardConfig) -> Self {
+        self.clipboard = Some(config.clone());
+        self
+    }
+
     /// Creates a session manager with local device ID
     pub fn with_local_id(local_id: String) -> Self {
         Self {
-            sessions: Arc::new(RwLock::new(HashMap::new())),
             local_id: Some(local_id),
+            ..Self::new()
         }
     }
 
+    /// Subscribes to events from all managed sessions
+    ///
+    /// The stream carries state transitions, periodic statistics snapshots
+    /// for running sessions, and errors reported by session tasks.
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionEvent> {
+        self.events.subscribe()
+    }
+
     /// Creates a new host session
     pub async fn create_host_session(
         &self,
-        config: HostSessionConfig,
-        transport: SessionTransport,
+        mut config: HostSessionConfig,
+        mut transport: SessionTransport,
     ) -> SessionResult<SessionId> {
         let session_id = config.session_id.clone();
+        if config.timeouts.is_disabled() {
+            config.timeouts = self.timeouts.clone();
+        }
 
         // Check for existing session
         {
@@ -113,7 +311,39 @@ impl SessionManager {
             }
         }
 
-        let session = HostSession::new(config, transport);
+        let roots = if config.allow_browse {
+            self.browse_roots.clone()
+        } else {
+            BrowseRoots::default()
+        };
+        let clipboard_files = self
+            .start_clipboard(
+                &session_id,
+                ConnectionRole::Host,
+                config.clipboard_mode,
+                &mut transport,
+            )
+            .await;
+        self.start_file_services(
+            &session_id,
+            ConnectionRole::Host,
+            clipboard_files.with_roots(roots),
+            &mut transport,
+        )
+        .await;
+        if !config.allow_files {
+            if let Some(transfers) = self.transfers.read().await.get(&session_id) {
+                transfers.set_peer_allowed(false);
+            }
+        }
+        self.start_chat(&session_id, config.peer_name.clone(), config.peer_id, &mut transport)
+            .await;
+        let shell = self.shell.clone().filter(|_| config.allow_terminal);
+        self.start_terminals(&session_id, shell, &mut transport).await;
+        self.start_custom_channels(&session_id, config.channels.clone(), &mut transport)
+            .await;
+        let mut session = HostSession::new(config, transport);
+        session.set_event_sender(self.events.clone()).await;
 
         {
             let mut sessions = self.sessions.write().await;
@@ -128,7 +358,7 @@ impl SessionManager {
     pub async fn create_client_session(
         &self,
         config: ClientSessionConfig,
-        transport: SessionTransport,
+        mut transport: SessionTransport,
     ) -> SessionResult<SessionId> {
         let session_id = config.session_id.clone();
 
@@ -140,7 +370,28 @@ impl SessionManager {
             }
         }
 
-        let session = ClientSession::new(config, transport);
+        let clipboard_files = self
+            .start_clipboard(
+                &session_id,
+                ConnectionRole::Client,
+                ClipboardMode::Both,
+                &mut transport,
+            )
+            .await;
+        self.start_file_services(
+            &session_id,
+            ConnectionRole::Client,
+            clipboard_files,
+            &mut transport,
+        )
+        .await;
+        self.start_chat(&session_id, config.peer_name.clone(), config.peer_id, &mut transport)
+            .await;
+        self.start_terminals(&session_id, None, &mut transport).await;
+        self.start_custom_channels(&session_id, config.channels.clone(), &mut transport)
+            .await;
+        let mut session = ClientSession::new(config, transport);
+        session.set_event_sender(self.events.clone()).await;
 
         {
             let mut sessions = self.sessions.write().await;
@@ -151,6 +402,69 @@ impl SessionManager {
         Ok(session_id)
     }
 
+    /// Creates and starts a session over an established connection
+    ///
+    /// The session takes the connection's role, ID, custom channels and
+    /// peer name, and forwards ports over the connection. A host session
+    /// grants the client only what the peer's permissions allow.
+    pub async fn create_connected_session(
+        &self,
+        connection: EstablishedConnection,
+    ) -> SessionResult<SessionId> {
+        let EstablishedConnection {
+            connection,
+            control_stream,
+            remote_device_id,
+            remote_name,
+            session_id,
+            role,
+            permissions,
+            channels,
+            ..
+        } = connection;
+        let session_id = audit::session_id_hex(&session_id);
+        let tunnels = connection.clone();
+
+        let (transport, handle) = create_quic_transport(connection, role, control_stream)
+            .await
+            .map_err(|e| SessionError::TransportError(e.to_string()))?;
+
+        let created = match role {
+            ConnectionRole::Host => {
+                let config = HostSessionConfig::default()
+                    .with_session_id(session_id)
+                    .with_peer_name(remote_name)
+                    .with_peer_id(remote_device_id)
+                    .with_channels(channels)
+                    .with_permissions(&permissions);
+                self.create_host_session(config, transport).await
+            }
+            ConnectionRole::Client => {
+                let config = ClientSessionConfig::default()
+                    .with_session_id(session_id)
+                    .with_peer_name(remote_name)
+                    .with_peer_id(remote_device_id)
+                    .with_channels(channels);
+                self.create_client_session(config, transport).await
+            }
+        };
+        let session_id = match created {
+            Ok(session_id) => session_id,
+            Err(e) => {
+                handle.abort();
+                return Err(e);
+            }
+        };
+        self.quic_transports
+            .write()
+            .await
+            .insert(session_id.clone(), handle);
+        self.start_port_forwarding(&session_id, tunnels).await?;
+
+        self.start_session(&session_id).await?;
+        Ok(session_id)
+    }
+
     /// Creates a loopback session (host + client connected together)
     ///
     /// This is primarily for testing and demonstration.
@@ -183,21 +497,20 @@ impl SessionManager {
     pub async fn start_session(&self, session_id: &str) -> SessionResult<()> {
         let mut sessions = self.sessions.write().await;
 
-        match sessions.get_mut(session_id) {
-            Some(ManagedSession::Host(session)) => {
-                session.start().await?;
-                info!("Started host session: {}", session_id);
-            }
-            Some(ManagedSession::Client(session)) => {
-                session.start().await?;
-                info!("Started client session: {}", session_id);
-            }
+        let result = match sessions.get_mut(session_id) {
+            Some(ManagedSession::Host(session)) => session.start().await,
+            Some(ManagedSession::Client(session)) => session.start().await,
             None => {
                 return Err(SessionError::SessionNotFound(session_id.to_string()));
             }
+        };
+
+        match &result {
+            Ok(()) => info!("Started session: {}", session_id),
+            Err(e) => self.report_error(session_id, format!("Failed to start session: {}", e)),
         }
 
-        Ok(())
+        result
     }
 
     /// Stops a session
@@ -224,6 +537,18 @@ impl SessionManager {
     /// Removes a session
     pub async fn remove_session(&self, session_id: &str) -> SessionResult<()> {
         let mut sessions = self.sessions.write().await;
+        if let Some(clipboard) = self.clipboards.write().await.remove(session_id) {
+            clipboard.lock().await.stop();
+        }
+        self.transfers.write().await.remove(session_id);
+        self.browsers.write().await.remove(session_id);
+        self.chats.write().await.remove(session_id);
+        self.terminals.write().await.remove(session_id);
+        self.forwarders.write().await.remove(session_id);
+        self.custom_channels.write().await.remove(session_id);
+        if let Some(handle) = self.quic_transports.write().await.remove(session_id) {
+            handle.abort();
+        }
 
         if sessions.remove(session_id).is_some() {
             info!("Removed session: {}", session_id);
@@ -236,16 +561,10 @@ impl SessionManager {
     /// Returns information about all sessions
     pub async fn list_sessions(&self) -> Vec<SessionInfo> {
         let sessions = self.sessions.read().await;
-        let mut infos = Vec::new();
-
-        for (id, session) in sessions.iter() {
-            let info = SessionInfo {
-                id: id.clone(),
-                session_type: session.session_type(),
-                state: SessionState::Idle, // Simplified - would need async state check
-                remote_id: None,
-            };
-            infos.push(info);
+        let mut infos = Vec::with_capacity(sessions.len());
+
+        for session in sessions.values() {
+            infos.push(session.info().await);
         }
 
         infos
@@ -255,12 +574,10 @@ impl SessionManager {
     pub async fn get_session_info(&self, session_id: &str) -> Option<SessionInfo> {
         let sessions = self.sessions.read().await;
 
-        sessions.get(session_id).map(|session| SessionInfo {
-            id: session_id.to_string(),
-            session_type: session.session_type(),
-            state: SessionState::Idle, // Simplified
-            remote_id: None,
-        })
+        match sessions.get(session_id) {
+            Some(session) => Some(session.info().await),
+            None => None,
+        }
     }
 
     /// Returns the number of active sessions
@@ -268,6 +585,307 @@ impl SessionManager {
         self.sessions.read().await.len()
     }
 
+    /// Sends a key sequence through a client session
+    pub async fn send_key_sequence(
+        &self,
+        session_id: &str,
+        sequence: &KeySequence,
+    ) -> SessionResult<()> {
+        self.with_client_session(session_id, |session| session.send_key_sequence(sequence))
+            .await?
+    }
+
+    /// Returns the clipboard sync of a session
+    pub async fn clipboard(&self, session_id: &str) -> SessionResult<Arc<Mutex<ClipboardSync>>> {
+        self.clipboards
+            .read()
+            .await
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| SessionError::SessionNotFound(session_id.to_string()))
+    }
+
+    /// Returns the file transfer service of a session
+    pub async fn file_transfers(&self, session_id: &str) -> SessionResult<Arc<FileTransfers>> {
+        self.transfers
+            .read()
+            .await
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| SessionError::SessionNotFound(session_id.to_string()))
+    }
+
+    /// Returns the file browsing service of a session
+    pub async fn file_browser(&self, session_id: &str) -> SessionResult<Arc<FileBrowser>> {
+        self.browsers
+            .read()
+            .await
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| SessionError::SessionNotFound(session_id.to_string()))
+    }
+
+    /// Returns the chat service of a session
+    pub async fn chat(&self, session_id: &str) -> SessionResult<Arc<ChatService>> {
+        self.chats
+            .read()
+            .await
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| SessionError::SessionNotFound(session_id.to_string()))
+    }
+
+    /// Returns the terminal service of a session
+    pub async fn terminals(&self, session_id: &str) -> SessionResult<Arc<Terminals>> {
+        self.terminals
+            .read()
+            .await
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| SessionError::SessionNotFound(session_id.to_string()))
+    }
+
+    /// Returns the custom channel service of a session
+    pub async fn custom_channels(&self, session_id: &str) -> SessionResult<Arc<CustomChannels>> {
+        self.custom_channels
+            .read()
+            .await
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| SessionError::SessionNotFound(session_id.to_string()))
+    }
+
+    /// Starts port forwarding over the session's QUIC connection
+    ///
+    /// Must be called once the session's transport has been set up on the
+    /// connection, since streams the peer opens later are taken as tunnels.
+    pub async fn start_port_forwarding(
+        &self,
+        session_id: &str,
+        connection: QuicConnection,
+    ) -> SessionResult<Arc<PortForwarder>> {
+        let policy = match self.sessions.read().await.get(session_id) {
+            Some(ManagedSession::Host(session)) if session.config().allow_forward => {
+                self.forward_policy.clone()
+            }
+            Some(_) => ForwardPolicy::default(),
+            None => return Err(SessionError::SessionNotFound(session_id.to_string())),
+        };
+
+        let forwarder = Arc::new(PortForwarder::start(connection, policy));
+        self.forwarders
+            .write()
+            .await
+            .insert(session_id.to_string(), Arc::clone(&forwarder));
+        info!("Started port forwarding for session {}", session_id);
+        Ok(forwarder)
+    }
+
+    /// Returns the port forwarding service of a session
+    pub async fn port_forwarder(&self, session_id: &str) -> SessionResult<Arc<PortForwarder>> {
+        self.forwarders
+            .read()
+            .await
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| SessionError::SessionNotFound(session_id.to_string()))
+    }
+
+    /// Returns the file transfers of all sessions
+    pub async fn list_transfers(&self) -> Vec<(SessionId, TransferInfo)> {
+        let services: Vec<(SessionId, Arc<FileTransfers>)> = self
+            .transfers
+            .read()
+            .await
+            .iter()
+            .map(|(id, service)| (id.clone(), Arc::clone(service)))
+            .collect();
+
+        let mut transfers = Vec::new();
+        for (session_id, service) in services {
+            for info in service.list().await {
+                transfers.push((session_id.clone(), info));
+            }
+        }
+        transfers
+    }
+
+    /// Starts clipboard sync on the session's clipboard stream, if the
+    /// clipboard is configured
+    ///
+    /// Sync is narrowed to `mode`. Returns the copied files the peer may
+    /// fetch, for the session's file transfer service.
+    async fn start_clipboard(
+        &self,
+        session_id: &str,
+        role: ConnectionRole,
+        mode: ClipboardMode,
+        transport: &mut SessionTransport,
+    ) -> SharedFiles {
+        let Some(config) = &self.clipboard else {
+            return SharedFiles::default();
+        };
+        let mut sync = match ClipboardSync::with_config(config, role) {
+            Ok(sync) => sync,
+            Err(e) => {
+                error!("Session {}: clipboard not synced: {}", session_id, e);
+                return SharedFiles::default();
+            }
+        };
+        sync.restrict(mode);
+        let shared = sync.shared_files();
+
+        let mut incoming = transport.clipboard.take_rx();
+        sync.start(transport.clipboard.tx.clone());
+        let sync = Arc::new(Mutex::new(sync));
+
+        let task_sync = Arc::clone(&sync);
+        let id = session_id.to_string();
+        tokio::spawn(async move {
+            while let Some(message) = incoming.recv().await {
+                if let Err(e) = task_sync.lock().await.handle_remote(message).await {
+                    warn!("Session {}: remote clipboard not applied: {}", id, e);
+                }
+            }
+            task_sync.lock().await.stop();
+        });
+
+        self.clipboards
+            .write()
+            .await
+            .insert(session_id.to_string(), sync);
+        shared
+    }
+
+    /// Starts the file transfer and browsing services on the session's
+    /// file and browse streams
+    ///
+    /// The sessions themselves never use these streams. The peer may browse
+    /// and download from the roots of `shared`, and fetch the files copied
+    /// to its clipboard.
+    async fn start_file_services(
+        &self,
+        session_id: &str,
+        role: ConnectionRole,
+        shared: SharedFiles,
+        transport: &mut SessionTransport,
+    ) {
+        let roots = shared.roots().clone();
+        let channel = ChannelPair {
+            tx: transport.files.tx.clone(),
+            rx: transport.files.take_rx(),
+        };
+        let drop_dir = self
+            .drop_dir
+            .as_ref()
+            .filter(|_| role == ConnectionRole::Host);
+        let download_dir = drop_dir.unwrap_or(&self.download_dir).clone();
+
+        let service = FileTransfers::start(channel, role, download_dir, shared);
+        service.set_auto_accept(drop_dir.is_some());
+
+        let browse_channel = ChannelPair {
+            tx: transport.browse.tx.clone(),
+            rx: transport.browse.take_rx(),
+        };
+        let browser = FileBrowser::start(browse_channel, roots);
+
+        self.transfers
+            .write()
+            .await
+            .insert(session_id.to_string(), Arc::new(service));
+        self.browsers
+            .write()
+            .await
+            .insert(session_id.to_string(), Arc::new(browser));
+    }
+
+    /// Starts the chat service on the session's chat stream
+    ///
+    /// Messages are recorded to the audit log, if there is one, until the
+    /// service is dropped.
+    async fn start_chat(
+        &self,
+        session_id: &str,
+        peer_name: String,
+        peer_id: Option<DeviceId>,
+        transport: &mut SessionTransport,
+    ) {
+        let channel = ChannelPair {
+            tx: transport.chat.tx.clone(),
+            rx: transport.chat.take_rx(),
+        };
+        let chat = ChatService::start(channel, self.device_name.clone(), peer_name);
+
+        if let Some(audit_log) = self.audit_log.clone() {
+            let mut entries = chat.subscribe();
+            let session_id = session_id.to_string();
+            tokio::spawn(async move {
+                loop {
+                    match entries.recv().await {
+                        Ok(entry) => audit_log.record(AuditEvent::ChatMessage {
+                            session_id: session_id.clone(),
+                            peer_id: peer_id.map(|id| id.as_u32()),
+                            sender: entry.sender,
+                            outgoing: entry.outgoing,
+                            text: entry.text,
+                        }),
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            warn!("Session {}: {} chat messages not audited", session_id, missed);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+
+        self.chats
+            .write()
+            .await
+            .insert(session_id.to_string(), Arc::new(chat));
+    }
+
+    /// Starts the terminal service on the session's terminal stream
+    ///
+    /// The peer may open terminals running `shell`, if one is given.
+    async fn start_terminals(
+        &self,
+        session_id: &str,
+        shell: Option<PathBuf>,
+        transport: &mut SessionTransport,
+    ) {
+        let channel = ChannelPair {
+            tx: transport.terminal.tx.clone(),
+            rx: transport.terminal.take_rx(),
+        };
+        let terminals = Terminals::start(channel, shell);
+
+        self.terminals
+            .write()
+            .await
+            .insert(session_id.to_string(), Arc::new(terminals));
+    }
+
+    /// Starts the custom channel service on the session's custom channel
+    /// stream, with the channels agreed in the handshake
+    async fn start_custom_channels(
+        &self,
+        session_id: &str,
+        names: Vec<String>,
+        transport: &mut SessionTransport,
+    ) {
+        let channel = ChannelPair {
+            tx: transport.channels.tx.clone(),
+            rx: transport.channels.take_rx(),
+        };
+        let channels = CustomChannels::start(channel, names);
+
+        self.custom_channels
+            .write()
+            .await
+            .insert(session_id.to_string(), Arc::new(channels));
+    }
+
     /// Stops all sessions
     pub async fn stop_all_sessions(&self) -> SessionResult<()> {
         let session_ids: Vec<String> = {
@@ -284,6 +902,15 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Publishes an error for a session
+    fn report_error(&self, session_id: &str, message: String) {
+        error!("Session {}: {}", session_id, message);
+        let _ = self.events.send(SessionEvent::Error {
+            session_id: session_id.to_string(),
+            message,
+        });
+    }
+
     /// Gets access to a host session (for advanced operations)
     pub async fn with_host_session<F, R>(&self, session_id: &str, f: F) -> SessionResult<R>
     where
@@ -322,6 +949,7 @@ impl SessionManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::input::InputEvent;
 
     #[tokio::test]
     async fn test_session_manager_creation() {
@@ -364,6 +992,470 @@ mod tests {
         assert!(matches!(result, Err(SessionError::SessionNotFound(_))));
     }
 
+    #[tokio::test]
+    async fn test_send_key_sequence() {
+        let manager = SessionManager::new();
+        let (mut host_transport, client_transport) = create_loopback_transport();
+
+        let client_id = manager
+            .create_client_session(ClientSessionConfig::default(), client_transport)
+            .await
+            .unwrap();
+
+        let sequence: KeySequence = "Ctrl+Alt+Delete".parse().unwrap();
+        manager.send_key_sequence(&client_id, &sequence).await.unwrap();
+
+        let input = host_transport.input.rx.recv().await.unwrap();
+        match input.event {
+            InputEvent::Batch(events) => assert_eq!(events.len(), 6),
+            other => panic!("expected a batch, got {:?}", other),
+        }
+
+        let result = manager.send_key_sequence("nonexistent", &sequence).await;
+        assert!(matches!(result, Err(SessionError::SessionNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_send_file_between_sessions() {
+        use crate::files::{FileTransferEvent, TransferStatus};
+
+        let source = tempfile::TempDir::new().unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+        let manager = SessionManager::new().with_download_dir(downloads.path().to_path_buf());
+
+        let (host_id, client_id) = manager
+            .create_loopback_session(HostSessionConfig::default(), ClientSessionConfig::default())
+            .await
+            .unwrap();
+        let host_files = manager.file_transfers(&host_id).await.unwrap();
+        let client_files = manager.file_transfers(&client_id).await.unwrap();
+        let mut events = client_files.subscribe();
+
+        let path = source.path().join("support.log");
+        std::fs::write(&path, b"error: disk full").unwrap();
+        let transfer_id = host_files.send_file(&path).await.unwrap();
+
+        loop {
+            match events.recv().await.unwrap() {
+                FileTransferEvent::Offered { .. } => client_files.accept(transfer_id).await.unwrap(),
+                FileTransferEvent::Completed { .. } => break,
+                FileTransferEvent::Failed { message, .. } => panic!("{}", message),
+                FileTransferEvent::Progress { .. } => {}
+            }
+        }
+
+        assert_eq!(
+            std::fs::read(downloads.path().join("support.log")).unwrap(),
+            b"error: disk full"
+        );
+        let transfers = manager.list_transfers().await;
+        assert!(transfers
+            .iter()
+            .any(|(id, info)| *id == client_id && info.status == TransferStatus::Completed));
+
+        manager.remove_session(&client_id).await.unwrap();
+        assert!(manager.file_transfers(&client_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_drop_dir_accepts_client_files() {
+        use crate::files::FileTransferEvent;
+
+        let source = tempfile::TempDir::new().unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+        let drops = tempfile::TempDir::new().unwrap();
+        let manager = SessionManager::new()
+            .with_download_dir(downloads.path().to_path_buf())
+            .with_drop_dir(drops.path().to_path_buf());
+
+        let (host_id, client_id) = manager
+            .create_loopback_session(HostSessionConfig::default(), ClientSessionConfig::default())
+            .await
+            .unwrap();
+        let host_files = manager.file_transfers(&host_id).await.unwrap();
+        let client_files = manager.file_transfers(&client_id).await.unwrap();
+        assert!(host_files.auto_accept());
+        assert!(!client_files.auto_accept());
+        assert_eq!(client_files.download_dir(), downloads.path());
+
+        let mut events = host_files.subscribe();
+        let path = source.path().join("slides.pdf");
+        std::fs::write(&path, b"%PDF").unwrap();
+        client_files.send_file(&path).await.unwrap();
+
+        let saved = loop {
+            match events.recv().await.unwrap() {
+                FileTransferEvent::Completed { path, .. } => break path,
+                FileTransferEvent::Failed { message, .. } => panic!("{}", message),
+                _ => {}
+            }
+        };
+        assert_eq!(saved, drops.path().join("slides.pdf"));
+    }
+
+    #[tokio::test]
+    async fn test_host_sessions_use_configured_timeouts() {
+        let security = SecurityConfig {
+            session_timeout_minutes: 30,
+            idle_timeout_minutes: 0,
+            allow_session_extend: true,
+            ..SecurityConfig::default()
+        };
+        let manager = SessionManager::new().with_security_config(&security);
+
+        let (host_id, _) = manager
+            .create_loopback_session(HostSessionConfig::default(), ClientSessionConfig::default())
+            .await
+            .unwrap();
+        let timeouts = manager
+            .with_host_session(&host_id, |session| session.config().timeouts.clone())
+            .await
+            .unwrap();
+        assert_eq!(timeouts.session, Some(Duration::from_secs(30 * 60)));
+        assert_eq!(timeouts.idle, None);
+        assert!(timeouts.allow_extend);
+
+        // A session with limits of its own keeps them
+        let own = TimeoutPolicy {
+            idle: Some(Duration::from_secs(60)),
+            ..TimeoutPolicy::default()
+        };
+        let (host_id, _) = manager
+            .create_loopback_session(
+                HostSessionConfig::default().with_timeouts(own),
+                ClientSessionConfig::default(),
+            )
+            .await
+            .unwrap();
+        let timeouts = manager
+            .with_host_session(&host_id, |session| session.config().timeouts.clone())
+            .await
+            .unwrap();
+        assert_eq!(timeouts.session, None);
+        assert_eq!(timeouts.idle, Some(Duration::from_secs(60)));
+    }
+
+    #[tokio::test]
+    async fn test_host_session_follows_peer_permissions() {
+        use crate::files::FileTransferEvent;
+        use crate::security::PeerPermissions;
+
+        let source = tempfile::TempDir::new().unwrap();
+        let drops = tempfile::TempDir::new().unwrap();
+        let manager = SessionManager::new().with_drop_dir(drops.path().to_path_buf());
+
+        let config = HostSessionConfig::default().with_permissions(&PeerPermissions::view_only());
+        let (_, client_id) = manager
+            .create_loopback_session(config, ClientSessionConfig::default())
+            .await
+            .unwrap();
+        let client_files = manager.file_transfers(&client_id).await.unwrap();
+
+        let mut events = client_files.subscribe();
+        let path = source.path().join("slides.pdf");
+        std::fs::write(&path, b"%PDF").unwrap();
+        client_files.send_file(&path).await.unwrap();
+
+        let message = loop {
+            match events.recv().await.unwrap() {
+                FileTransferEvent::Failed { message, .. } => break message,
+                FileTransferEvent::Completed { .. } => panic!("file sent without permission"),
+                _ => {}
+            }
+        };
+        assert!(message.contains("not permitted"));
+        assert_eq!(std::fs::read_dir(drops.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_host_clipboard_follows_peer_permissions() {
+        use crate::clipboard::TransferDirection::{Incoming, Outgoing};
+        use crate::security::PeerPermissions;
+        use crate::session::transport::ClipboardContentType::Text;
+
+        let manager = SessionManager::new().with_clipboard(&ClipboardConfig::default());
+        let permissions = PeerPermissions {
+            clipboard_mode: ClipboardMode::HostToClient,
+            ..PeerPermissions::all()
+        };
+        let config = HostSessionConfig::default().with_permissions(&permissions);
+        let (host_id, client_id) = manager
+            .create_loopback_session(config, ClientSessionConfig::default())
+            .await
+            .unwrap();
+
+        let host = manager.clipboard(&host_id).await.unwrap().lock().await.policy();
+        assert!(host.allows(Outgoing, Text));
+        assert!(!host.allows(Incoming, Text));
+        let client = manager.clipboard(&client_id).await.unwrap().lock().await.policy();
+        assert!(client.allows(Incoming, Text));
+
+        // Without a clipboard config, sessions don't sync the clipboard
+        let manager = SessionManager::new();
+        let (host_id, _) = manager
+            .create_loopback_session(HostSessionConfig::default(), ClientSessionConfig::default())
+            .await
+            .unwrap();
+        assert!(manager.clipboard(&host_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_browse_host_session() {
+        let shared = tempfile::TempDir::new().unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+        std::fs::write(shared.path().join("readme.md"), b"# Shared").unwrap();
+        let root = shared.path().file_name().unwrap().to_string_lossy().into_owned();
+        let manager = SessionManager::new()
+            .with_download_dir(downloads.path().to_path_buf())
+            .with_browse_roots(BrowseRoots::new([shared.path().to_path_buf()]));
+
+        let (_, client_id) = manager
+            .create_loopback_session(HostSessionConfig::default(), ClientSessionConfig::default())
+            .await
+            .unwrap();
+        let browser = manager.file_browser(&client_id).await.unwrap();
+        let entries = browser.list(&format!("/{}", root)).await.unwrap();
+        assert_eq!(entries[0].name, "readme.md");
+
+        let transfers = manager.file_transfers(&client_id).await.unwrap();
+        let path = transfers
+            .fetch_remote_file(&format!("/{}/readme.md", root), downloads.path())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), b"# Shared");
+
+        // A host session that doesn't allow browsing shares nothing
+        let (_, client_id) = manager
+            .create_loopback_session(
+                HostSessionConfig::default().with_browse(false),
+                ClientSessionConfig::default(),
+            )
+            .await
+            .unwrap();
+        let browser = manager.file_browser(&client_id).await.unwrap();
+        assert!(browser.list("/").await.is_err());
+
+        // Nor does one with a peer whose permissions leave out browsing
+        let permissions = crate::security::PeerPermissions {
+            browse: false,
+            ..crate::security::PeerPermissions::all()
+        };
+        let (_, client_id) = manager
+            .create_loopback_session(
+                HostSessionConfig::default().with_permissions(&permissions),
+                ClientSessionConfig::default(),
+            )
+            .await
+            .unwrap();
+        let browser = manager.file_browser(&client_id).await.unwrap();
+        assert!(browser.list(&format!("/{}", root)).await.is_err());
+        let transfers = manager.file_transfers(&client_id).await.unwrap();
+        assert!(transfers
+            .fetch_remote_file(&format!("/{}/readme.md", root), downloads.path())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chat_is_audited() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let audit_log = Arc::new(AuditLog::new(
+            dir.path().join("connections.log"),
+            crate::config::AuditConfig::default(),
+        ));
+        let manager = SessionManager::new()
+            .with_device_name("office-pc".to_string())
+            .with_audit_log(Arc::clone(&audit_log));
+
+        let (host_id, client_id) = manager
+            .create_loopback_session(
+                HostSessionConfig::default()
+                    .with_peer_name("laptop".to_string())
+                    .with_peer_id(DeviceId::from_u32(123456789).unwrap()),
+                ClientSessionConfig::default(),
+            )
+            .await
+            .unwrap();
+        let host_chat = manager.chat(&host_id).await.unwrap();
+        let mut received = host_chat.subscribe();
+
+        manager.chat(&client_id).await.unwrap().send("hello").await.unwrap();
+        let entry = received.recv().await.unwrap();
+        assert_eq!(entry.sender, "laptop");
+        assert_eq!(entry.text, "hello");
+
+        // Both sides record the message once their audit task has run
+        let filter = crate::security::AuditFilter {
+            kind: Some("chat_message".to_string()),
+            ..Default::default()
+        };
+        let mut records = Vec::new();
+        for _ in 0..50 {
+            records = audit_log.read(&filter).unwrap();
+            if records.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(records.len(), 2);
+
+        // The host side knows the peer, so history for it includes the chat
+        let peer = crate::security::AuditFilter {
+            peer_id: Some(DeviceId::from_u32(123456789).unwrap()),
+            ..filter
+        };
+        let records_for_peer = audit_log.read(&peer).unwrap();
+        assert_eq!(records_for_peer.len(), 1);
+        assert!(matches!(
+            &records_for_peer[0].event,
+            AuditEvent::ChatMessage { session_id, .. } if *session_id == host_id
+        ));
+        assert!(records.iter().any(|r| matches!(
+            &r.event,
+            AuditEvent::ChatMessage { session_id, sender, outgoing: false, .. }
+                if *session_id == host_id && sender == "laptop"
+        )));
+        assert!(records.iter().any(|r| matches!(
+            &r.event,
+            AuditEvent::ChatMessage { session_id, sender, outgoing: true, .. }
+                if *session_id == client_id && sender == "office-pc"
+        )));
+
+        manager.remove_session(&host_id).await.unwrap();
+        assert!(manager.chat(&host_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_terminals_are_opt_in() {
+        // Terminals are off unless enabled in the configuration
+        let manager = SessionManager::new();
+        let (host_id, client_id) = manager
+            .create_loopback_session(HostSessionConfig::default(), ClientSessionConfig::default())
+            .await
+            .unwrap();
+        assert!(!manager.terminals(&host_id).await.unwrap().serves_shell());
+        let terminals = manager.terminals(&client_id).await.unwrap();
+        assert!(terminals.open(80, 24).await.is_err());
+
+        let config = TerminalConfig {
+            enabled: true,
+            shell: Some(PathBuf::from("/bin/sh")),
+        };
+        let manager = SessionManager::new().with_terminal(&config);
+        let (host_id, _) = manager
+            .create_loopback_session(HostSessionConfig::default(), ClientSessionConfig::default())
+            .await
+            .unwrap();
+        assert!(manager.terminals(&host_id).await.unwrap().serves_shell());
+
+        // Nor are they offered to a peer without the permission
+        let permissions = crate::security::PeerPermissions {
+            terminal: false,
+            ..crate::security::PeerPermissions::all()
+        };
+        let (host_id, client_id) = manager
+            .create_loopback_session(
+                HostSessionConfig::default().with_permissions(&permissions),
+                ClientSessionConfig::default(),
+            )
+            .await
+            .unwrap();
+        assert!(!manager.terminals(&host_id).await.unwrap().serves_shell());
+        let terminals = manager.terminals(&client_id).await.unwrap();
+        assert!(terminals.open(80, 24).await.is_err());
+
+        manager.remove_session(&host_id).await.unwrap();
+        assert!(manager.terminals(&host_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_custom_channels() {
+        let manager = SessionManager::new();
+        let channels = vec!["acme.tickets".to_string()];
+        let (host_id, client_id) = manager
+            .create_loopback_session(
+                HostSessionConfig::default().with_channels(channels.clone()),
+                ClientSessionConfig::default().with_channels(channels),
+            )
+            .await
+            .unwrap();
+
+        let host_channels = manager.custom_channels(&host_id).await.unwrap();
+        let mut host_tickets = host_channels.open("acme.tickets").unwrap();
+        let client_channels = manager.custom_channels(&client_id).await.unwrap();
+        let client_tickets = client_channels.open("acme.tickets").unwrap();
+        client_tickets.send(&42u32).await.unwrap();
+        assert_eq!(host_tickets.recv::<u32>().await.unwrap(), 42);
+        assert!(client_channels.open("other").is_err());
+
+        manager.remove_session(&host_id).await.unwrap();
+        assert!(manager.custom_channels(&host_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_port_forwarding_follows_host_config() {
+        use crate::network::{cert, QuicConfig, QuicEndpoint};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let cert_pair = cert::load_or_create_cert(dir.path(), 123456789).unwrap();
+        let host_endpoint = QuicEndpoint::new(
+            QuicConfig::default()
+                .with_bind_addr("127.0.0.1:0".parse().unwrap())
+                .with_cert_pair(cert_pair),
+        )
+        .unwrap();
+        let client_endpoint = QuicEndpoint::client_only().unwrap();
+        let connection = client_endpoint
+            .connect(host_endpoint.local_addr(), "localhost")
+            .await
+            .unwrap();
+
+        let config = crate::config::ForwardingConfig {
+            enabled: true,
+            allowed_targets: vec!["localhost:80".to_string()],
+            listen_ports: Vec::new(),
+        };
+        let manager = SessionManager::new()
+            .with_forward_policy(ForwardPolicy::from_config(&config).unwrap());
+        assert!(manager.start_port_forwarding("missing", connection.clone()).await.is_err());
+
+        let (host_id, client_id) = manager
+            .create_loopback_session(HostSessionConfig::default(), ClientSessionConfig::default())
+            .await
+            .unwrap();
+        let forwarder = manager
+            .start_port_forwarding(&host_id, connection.clone())
+            .await
+            .unwrap();
+        assert!(forwarder.policy().allows_connect("localhost", 80));
+
+        // Clients never let the host reach anything through them
+        let forwarder = manager
+            .start_port_forwarding(&client_id, connection.clone())
+            .await
+            .unwrap();
+        assert!(!forwarder.policy().allows_connect("localhost", 80));
+
+        // Nor do hosts whose peer lacks the permission
+        let permissions = crate::security::PeerPermissions {
+            forward: false,
+            ..crate::security::PeerPermissions::all()
+        };
+        let (denied_id, _) = manager
+            .create_loopback_session(
+                HostSessionConfig::default().with_permissions(&permissions),
+                ClientSessionConfig::default(),
+            )
+            .await
+            .unwrap();
+        let forwarder = manager.start_port_forwarding(&denied_id, connection).await.unwrap();
+        assert!(!forwarder.policy().allows_connect("localhost", 80));
+
+        manager.remove_session(&host_id).await.unwrap();
+        assert!(manager.port_forwarder(&host_id).await.is_err());
+        assert!(manager.port_forwarder(&client_id).await.is_ok());
+    }
+
     #[tokio::test]
     async fn test_duplicate_session() {
         let manager = SessionManager::new();
@@ -402,6 +1494,83 @@ mod tests {
         assert_eq!(manager.session_count().await, 0);
     }
 
+    #[tokio::test]
+    async fn test_session_info_reflects_state() {
+        let manager = SessionManager::new();
+        let (_, client_id) = manager
+            .create_loopback_session(HostSessionConfig::default(), ClientSessionConfig::default())
+            .await
+            .unwrap();
+
+        let info = manager.get_session_info(&client_id).await.unwrap();
+        assert_eq!(info.state, SessionState::Idle);
+        assert!(matches!(info.stats, SessionStatsSnapshot::Client(_)));
+
+        manager.start_session(&client_id).await.unwrap();
+        let info = manager.get_session_info(&client_id).await.unwrap();
+        assert_eq!(info.state, SessionState::Active);
+
+        manager.stop_session(&client_id).await.unwrap();
+        let info = manager.get_session_info(&client_id).await.unwrap();
+        assert_eq!(info.state, SessionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_session_events() {
+        let manager = SessionManager::new();
+        let mut events = manager.subscribe();
+
+        let (_, client_id) = manager
+            .create_loopback_session(HostSessionConfig::default(), ClientSessionConfig::default())
+            .await
+            .unwrap();
+
+        manager.start_session(&client_id).await.unwrap();
+
+        let mut transitions = Vec::new();
+        while transitions.len() < 3 {
+            match events.recv().await.unwrap() {
+                SessionEvent::StateChanged { session_id, to, .. } => {
+                    assert_eq!(session_id, client_id);
+                    transitions.push(to);
+                }
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(
+            transitions,
+            vec![
+                SessionState::Connecting,
+                SessionState::Authenticating,
+                SessionState::Active
+            ]
+        );
+
+        // Running sessions publish statistics snapshots
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            event,
+            SessionEvent::Stats {
+                stats: SessionStatsSnapshot::Client(_),
+                ..
+            }
+        ));
+
+        // Failures are reported as error events
+        assert!(manager.start_session(&client_id).await.is_err());
+        loop {
+            if let SessionEvent::Error { session_id, .. } = events.recv().await.unwrap() {
+                assert_eq!(session_id, client_id);
+                break;
+            }
+        }
+
+        manager.stop_session(&client_id).await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_stop_all_sessions() {
         let manager = SessionManager::new();
@@ -1,22 +1,39 @@
 //! Remote desktop session module
 //!
 //! This module handles the integration of screen capture, input simulation,
-//! and network communication for remote desktop sessions.
+//! and network communication for remote desktop sessions, along with the
+//! in-session chat and application-defined channels.
 
+pub mod channels;
+pub mod chat;
 pub mod client;
+pub mod events;
 pub mod host;
 pub mod manager;
+pub mod rpc;
 pub mod state;
+pub mod timeout;
 pub mod transport;
 pub mod types;
 
-pub use client::{ClientSession, ClientSessionConfig, ClientSessionStats};
+pub use channels::{CustomChannel, CustomChannels, MAX_CHANNEL_MESSAGE_SIZE};
+pub use chat::{ChatEntry, ChatService};
+pub use client::{
+    ClientSession, ClientSessionConfig, ClientSessionStats, RemoteDisplay, TimeoutNotice,
+};
+pub use events::{
+    SessionEvent, SessionEventEmitter, SessionStatsSnapshot, DEFAULT_STATS_INTERVAL,
+    EVENT_CHANNEL_CAPACITY,
+};
 pub use host::{HostSession, HostSessionConfig, HostSessionStats};
 pub use manager::{ManagedSession, SessionId, SessionInfo, SessionManager, SessionType};
+pub use rpc::{control_answer, unsupported_request, RpcCalls, DEFAULT_RPC_TIMEOUT};
 pub use state::{SessionState, SessionStateMachine, StateTransition};
+pub use timeout::{SessionTimer, TimeoutKind, TimeoutPolicy, TimerStatus};
 pub use transport::{
-    create_loopback_transport, create_quic_transport, ChannelPair, ClipboardContentType,
-    ControlMessage, QuicTransportHandle, SessionTransport, TransportClipboard, TransportError,
-    TransportFrame, TransportInput, TransportResult, TransportStats,
+    create_loopback_transport, create_quic_transport, BrowseMessage, ChannelMessage, ChannelPair,
+    ChatMessage, ClipboardCancelReason, ClipboardContentType, ClipboardMessage, ControlMessage, FileMessage,
+    QuicTransportHandle, RemoteEntry, SessionTransport, TerminalMessage, TransportClipboard,
+    TransportError, TransportFrame, TransportInput, TransportResult, TransportStats,
 };
 pub use types::{Session, SessionConfig, SessionMode, SessionStats};
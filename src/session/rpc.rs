@@ -0,0 +1,332 @@
+//! Request/response calls over session streams
+//!
+//! Session streams carry messages both ways, and nothing on the wire ties
+//! an answer to the message it answers. [`RpcCalls`] keeps that
+//! bookkeeping for the side making requests: each call gets a request ID
+//! that the peer's answer carries back, waits a bounded time for it, and
+//! sees a refusal from the peer as a typed [`RpcError`].
+//!
+//! On the control stream a request is wrapped in
+//! [`ControlMessage::Request`] and answered with
+//! [`ControlMessage::Response`] or [`ControlMessage::Failed`]; over QUIC
+//! the request ID is the protocol message ID and a failure is an `Error`
+//! message. Streams with their own request IDs, such as file browsing, use
+//! [`RpcCalls`] with their own messages.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+
+use crate::error::{RpcError, RpcResult};
+use crate::network::{ErrorCode, ErrorMessage};
+use crate::session::transport::ControlMessage;
+
+/// How long a call waits for the peer's answer by default
+pub const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Calls waiting for the peer's answer
+pub struct RpcCalls<R> {
+    pending: Mutex<HashMap<u32, oneshot::Sender<RpcResult<R>>>>,
+    next_id: AtomicU32,
+    timeout: Duration,
+}
+
+impl<R> Default for RpcCalls<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> RpcCalls<R> {
+    /// Creates an empty call table with the default timeout
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU32::new(1),
+            timeout: DEFAULT_RPC_TIMEOUT,
+        }
+    }
+
+    /// Sets how long a call waits for its answer
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns how long a call waits for its answer
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the number of calls waiting for an answer
+    pub fn pending(&self) -> usize {
+        self.calls().len()
+    }
+
+    fn calls(&self) -> std::sync::MutexGuard<'_, HashMap<u32, oneshot::Sender<RpcResult<R>>>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sends a request and waits for its answer
+    ///
+    /// `request` builds the message from the call's request ID, which the
+    /// answer must carry for [`RpcCalls::resolve`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the request can't be sent, the peer doesn't answer
+    /// in time, or the peer's answer is a failure
+    pub async fn call<M>(
+        &self,
+        tx: &mpsc::Sender<M>,
+        request: impl FnOnce(u32) -> M,
+    ) -> RpcResult<R> {
+        let (answer_tx, answer_rx) = oneshot::channel();
+        let request_id = self.register(answer_tx);
+        // Forgets the call however it ends, including being cancelled
+        let _call = PendingCall {
+            calls: self,
+            request_id,
+        };
+
+        tx.send(request(request_id))
+            .await
+            .map_err(|_| RpcError::ChannelClosed)?;
+
+        match tokio::time::timeout(self.timeout, answer_rx).await {
+            Ok(Ok(answer)) => answer,
+            Ok(Err(_)) => Err(RpcError::ChannelClosed),
+            Err(_) => Err(RpcError::Timeout),
+        }
+    }
+
+    /// Hands the peer's answer to the call waiting for it
+    ///
+    /// Returns false if no call is waiting for the request ID, such as
+    /// when the call already timed out.
+    pub fn resolve(&self, request_id: u32, answer: RpcResult<R>) -> bool {
+        match self.calls().remove(&request_id) {
+            Some(waiting) => {
+                let _ = waiting.send(answer);
+                true
+            }
+            None => {
+                debug!("Answer to unknown request {}", request_id);
+                false
+            }
+        }
+    }
+
+    /// Fails every waiting call with [`RpcError::ChannelClosed`]
+    ///
+    /// Called when the stream closes, so calls don't wait out their timeout.
+    pub fn close(&self) {
+        self.calls().clear();
+    }
+
+    /// Picks an unused request ID for a call
+    fn register(&self, answer_tx: oneshot::Sender<RpcResult<R>>) -> u32 {
+        let mut calls = self.calls();
+        loop {
+            let request_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+            // IDs wrap around, skipping calls still waiting
+            if let std::collections::hash_map::Entry::Vacant(entry) = calls.entry(request_id) {
+                entry.insert(answer_tx);
+                return request_id;
+            }
+        }
+    }
+}
+
+/// Removes a call from the table once it is no longer waiting
+struct PendingCall<'a, R> {
+    calls: &'a RpcCalls<R>,
+    request_id: u32,
+}
+
+impl<R> Drop for PendingCall<'_, R> {
+    fn drop(&mut self) {
+        self.calls.calls().remove(&self.request_id);
+    }
+}
+
+impl From<ErrorMessage> for RpcError {
+    fn from(error: ErrorMessage) -> Self {
+        let message = error.message;
+        match error.error_code {
+            ErrorCode::UnsupportedMessage => RpcError::Unsupported(message),
+            ErrorCode::PermissionDenied => RpcError::PermissionDenied(message),
+            ErrorCode::InvalidPayload | ErrorCode::ProtocolViolation => {
+                RpcError::InvalidRequest(message)
+            }
+            ErrorCode::ResourceExhausted => RpcError::Busy(message),
+            ErrorCode::UnknownError => RpcError::Remote(message),
+        }
+    }
+}
+
+impl RpcCalls<ControlMessage> {
+    /// Makes a request on the control stream
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the request fails, see [`RpcCalls::call`]
+    pub async fn call_control(
+        &self,
+        tx: &mpsc::Sender<ControlMessage>,
+        request: ControlMessage,
+    ) -> RpcResult<ControlMessage> {
+        self.call(tx, |request_id| ControlMessage::Request {
+            request_id,
+            request: Box::new(request),
+        })
+        .await
+    }
+
+    /// Takes an answer from the control stream
+    ///
+    /// Returns the message back if it isn't an answer to a request.
+    pub fn resolve_control(&self, message: ControlMessage) -> Option<ControlMessage> {
+        match message {
+            ControlMessage::Response {
+                request_id,
+                response,
+            } => {
+                self.resolve(request_id, Ok(*response));
+                None
+            }
+            ControlMessage::Failed { request_id, error } => {
+                self.resolve(request_id, Err(error.into()));
+                None
+            }
+            other => Some(other),
+        }
+    }
+}
+
+/// Builds the answer to a control request
+pub fn control_answer(
+    request_id: u32,
+    answer: Result<ControlMessage, ErrorMessage>,
+) -> ControlMessage {
+    match answer {
+        Ok(response) => ControlMessage::Response {
+            request_id,
+            response: Box::new(response),
+        },
+        Err(error) => ControlMessage::Failed { request_id, error },
+    }
+}
+
+/// Builds the failure for a request this side has no answer to
+pub fn unsupported_request(request: &ControlMessage) -> ErrorMessage {
+    ErrorMessage::new(
+        ErrorCode::UnsupportedMessage,
+        format!("No answer to {:?}", request),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_answers_are_matched_by_id() {
+        let calls = Arc::new(RpcCalls::<String>::new());
+        let (tx, mut rx) = mpsc::channel(8);
+
+        let first = tokio::spawn({
+            let calls = Arc::clone(&calls);
+            let tx = tx.clone();
+            async move { calls.call(&tx, |id| (id, "first")).await }
+        });
+        let second = tokio::spawn({
+            let calls = Arc::clone(&calls);
+            async move { calls.call(&tx, |id| (id, "second")).await }
+        });
+
+        let mut requests = vec![rx.recv().await.unwrap(), rx.recv().await.unwrap()];
+        assert_ne!(requests[0].0, requests[1].0);
+        // Answer out of order
+        requests.reverse();
+        for (request_id, name) in requests {
+            assert!(calls.resolve(request_id, Ok(name.to_uppercase())));
+        }
+
+        assert_eq!(first.await.unwrap().unwrap(), "FIRST");
+        assert_eq!(second.await.unwrap().unwrap(), "SECOND");
+        assert_eq!(calls.pending(), 0);
+        assert!(!calls.resolve(1, Ok("late".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_close() {
+        let calls = RpcCalls::<()>::new().with_timeout(Duration::from_millis(20));
+        let (tx, mut rx) = mpsc::channel(8);
+
+        assert_eq!(calls.call(&tx, |id| id).await, Err(RpcError::Timeout));
+        assert_eq!(calls.pending(), 0);
+
+        let call = calls.call(&tx, |id| id);
+        let close = async {
+            rx.recv().await.unwrap();
+            calls.close();
+        };
+        let (result, ()) = tokio::join!(call, close);
+        assert_eq!(result, Err(RpcError::ChannelClosed));
+
+        drop(rx);
+        assert_eq!(calls.call(&tx, |id| id).await, Err(RpcError::ChannelClosed));
+        assert_eq!(calls.pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_control_failure_is_typed() {
+        let calls = RpcCalls::new();
+        let (tx, mut rx) = mpsc::channel(8);
+
+        let call = calls.call_control(&tx, ControlMessage::RequestDisplayInfo);
+        let answer = async {
+            let Some(ControlMessage::Request {
+                request_id,
+                request,
+            }) = rx.recv().await
+            else {
+                panic!("expected a request");
+            };
+            assert!(matches!(*request, ControlMessage::RequestDisplayInfo));
+            let failed = control_answer(request_id, Err(unsupported_request(&request)));
+            assert!(calls.resolve_control(failed).is_none());
+        };
+        let (result, ()) = tokio::join!(call, answer);
+        assert_eq!(
+            result.unwrap_err(),
+            RpcError::Unsupported("No answer to RequestDisplayInfo".to_string())
+        );
+
+        // Anything else is passed through
+        assert!(calls.resolve_control(ControlMessage::Pause).is_some());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let error = |code| RpcError::from(ErrorMessage::new(code, "why".to_string()));
+        assert_eq!(
+            error(ErrorCode::PermissionDenied),
+            RpcError::PermissionDenied("why".to_string())
+        );
+        assert_eq!(
+            error(ErrorCode::ResourceExhausted),
+            RpcError::Busy("why".to_string())
+        );
+        assert_eq!(
+            error(ErrorCode::UnknownError),
+            RpcError::Remote("why".to_string())
+        );
+    }
+}
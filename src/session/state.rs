@@ -6,6 +6,7 @@ use std::fmt;
 use std::time::Instant;
 
 use crate::error::SessionError;
+use crate::session::events::SessionEventEmitter;
 
 /// Possible states for a remote desktop session
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -111,6 +112,8 @@ pub struct SessionStateMachine {
     history: Vec<StateTransition>,
     /// Maximum history size to keep
     max_history: usize,
+    /// Publishes transitions to event subscribers
+    events: SessionEventEmitter,
 }
 
 impl Default for SessionStateMachine {
@@ -130,6 +133,7 @@ impl SessionStateMachine {
             state_entered_at: Instant::now(),
             history: Vec::new(),
             max_history: Self::DEFAULT_MAX_HISTORY,
+            events: SessionEventEmitter::default(),
         }
     }
 
@@ -141,6 +145,11 @@ impl SessionStateMachine {
         }
     }
 
+    /// Sets the emitter that publishes state transitions
+    pub fn set_event_emitter(&mut self, events: SessionEventEmitter) {
+        self.events = events;
+    }
+
     /// Returns the current state
     pub fn current(&self) -> SessionState {
         self.current
@@ -170,6 +179,7 @@ impl SessionStateMachine {
 
         self.current = to;
         self.state_entered_at = transition.timestamp;
+        self.events.state_changed(transition.from, to);
 
         // Add to history, trimming if necessary
         self.history.push(transition);
@@ -192,6 +202,7 @@ impl SessionStateMachine {
 
         self.current = to;
         self.state_entered_at = transition.timestamp;
+        self.events.state_changed(transition.from, to);
         self.history.push(transition);
 
         if self.history.len() > self.max_history {
@@ -358,4 +369,35 @@ mod tests {
         assert_eq!(sm.current(), SessionState::Idle);
         assert!(sm.history().is_empty());
     }
+
+    #[test]
+    fn test_transitions_are_published() {
+        use crate::session::events::SessionEvent;
+
+        let (tx, mut rx) = tokio::sync::broadcast::channel(8);
+        let mut sm = SessionStateMachine::new();
+        sm.set_event_emitter(SessionEventEmitter::new("s1".to_string(), tx));
+
+        sm.transition(SessionState::Connecting).unwrap();
+        assert!(sm.transition(SessionState::Paused).is_err());
+        sm.force_transition(SessionState::Disconnected);
+
+        for (from, to) in [
+            (SessionState::Idle, SessionState::Connecting),
+            (SessionState::Connecting, SessionState::Disconnected),
+        ] {
+            match rx.try_recv().unwrap() {
+                SessionEvent::StateChanged {
+                    session_id,
+                    from: f,
+                    to: t,
+                } => {
+                    assert_eq!(session_id, "s1");
+                    assert_eq!((f, t), (from, to));
+                }
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert!(rx.try_recv().is_err());
+    }
 }
@@ -0,0 +1,323 @@
+//! Session and idle timeout tracking
+//!
+//! The host uses a [`SessionTimer`] to end sessions that run past the
+//! configured session limit or go without input for longer than the idle
+//! limit. The timer is driven by explicit timestamps so the watchdog task
+//! and tests share the same logic.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::config::SecurityConfig;
+
+/// Default warning period before a timeout disconnects the client
+pub const DEFAULT_TIMEOUT_WARNING: Duration = Duration::from_secs(60);
+
+// Timeout constants (avoiding magic numbers)
+const SECONDS_PER_MINUTE: u64 = 60;
+
+/// Which limit is about to end a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeoutKind {
+    /// Total session duration limit
+    Session,
+    /// No input received for too long
+    Idle,
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutKind::Session => write!(f, "session"),
+            TimeoutKind::Idle => write!(f, "idle"),
+        }
+    }
+}
+
+/// Timeout limits for a host session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeoutPolicy {
+    /// Maximum session duration (None disables the limit)
+    pub session: Option<Duration>,
+    /// Maximum time without input (None disables the limit)
+    pub idle: Option<Duration>,
+    /// How long before a timeout the client is warned
+    pub warning: Duration,
+    /// Whether clients may extend the session limit
+    pub allow_extend: bool,
+}
+
+impl Default for TimeoutPolicy {
+    fn default() -> Self {
+        Self {
+            session: None,
+            idle: None,
+            warning: DEFAULT_TIMEOUT_WARNING,
+            allow_extend: false,
+        }
+    }
+}
+
+impl TimeoutPolicy {
+    /// Builds a policy from the security configuration
+    ///
+    /// A timeout of zero minutes disables that limit.
+    pub fn from_security_config(config: &SecurityConfig) -> Self {
+        Self {
+            session: minutes(config.session_timeout_minutes),
+            idle: minutes(config.idle_timeout_minutes),
+            warning: DEFAULT_TIMEOUT_WARNING,
+            allow_extend: config.allow_session_extend,
+        }
+    }
+
+    /// Returns true if neither limit is enabled
+    pub fn is_disabled(&self) -> bool {
+        self.session.is_none() && self.idle.is_none()
+    }
+}
+
+/// Converts a minute count from the config, treating zero as disabled
+fn minutes(value: u32) -> Option<Duration> {
+    (value > 0).then(|| Duration::from_secs(value as u64 * SECONDS_PER_MINUTE))
+}
+
+/// Result of checking a timer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerStatus {
+    /// No limit is close
+    Ok,
+    /// A limit is within the warning period; emitted once per approach
+    Warn {
+        /// Limit that will be reached
+        kind: TimeoutKind,
+        /// Time left before the session ends
+        remaining: Duration,
+    },
+    /// A limit has been reached
+    Expired(TimeoutKind),
+}
+
+/// Tracks session and idle deadlines for one session
+#[derive(Debug, Clone)]
+pub struct SessionTimer {
+    policy: TimeoutPolicy,
+    /// When the session limit is reached
+    session_deadline: Option<Instant>,
+    /// Last time input was received
+    last_activity: Instant,
+    /// Whether the current session deadline has been warned about
+    session_warned: bool,
+    /// Whether the current idle period has been warned about
+    idle_warned: bool,
+}
+
+impl SessionTimer {
+    /// Creates a timer for a session starting at `now`
+    pub fn new(policy: TimeoutPolicy, now: Instant) -> Self {
+        Self {
+            session_deadline: policy.session.map(|limit| now + limit),
+            policy,
+            last_activity: now,
+            session_warned: false,
+            idle_warned: false,
+        }
+    }
+
+    /// Returns the timeout policy
+    pub fn policy(&self) -> &TimeoutPolicy {
+        &self.policy
+    }
+
+    /// Records input activity, restarting the idle period
+    pub fn record_activity(&mut self, now: Instant) {
+        self.last_activity = now;
+        self.idle_warned = false;
+    }
+
+    /// Returns the time left before the session limit, if one is set
+    pub fn session_remaining(&self, now: Instant) -> Option<Duration> {
+        self.session_deadline
+            .map(|deadline| deadline.saturating_duration_since(now))
+    }
+
+    /// Handles a client request to extend the session
+    ///
+    /// The extension is capped at one full session period and also counts as
+    /// activity. Returns false if the policy does not allow extensions.
+    pub fn extend(&mut self, requested: Duration, now: Instant) -> bool {
+        if !self.policy.allow_extend {
+            return false;
+        }
+
+        self.record_activity(now);
+
+        if let (Some(deadline), Some(limit)) = (self.session_deadline, self.policy.session) {
+            self.session_deadline = Some(deadline.max(now) + requested.min(limit));
+            self.session_warned = false;
+        }
+
+        true
+    }
+
+    /// Checks the deadlines at `now`
+    pub fn poll(&mut self, now: Instant) -> TimerStatus {
+        let idle_deadline = self.policy.idle.map(|limit| self.last_activity + limit);
+
+        // Report whichever limit is reached first
+        let mut next: Option<(TimeoutKind, Instant)> = None;
+        for candidate in [
+            self.session_deadline.map(|d| (TimeoutKind::Session, d)),
+            idle_deadline.map(|d| (TimeoutKind::Idle, d)),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if next.is_none_or(|(_, d)| candidate.1 < d) {
+                next = Some(candidate);
+            }
+        }
+
+        let Some((kind, deadline)) = next else {
+            return TimerStatus::Ok;
+        };
+
+        if now >= deadline {
+            return TimerStatus::Expired(kind);
+        }
+
+        let remaining = deadline.duration_since(now);
+        if remaining > self.policy.warning {
+            return TimerStatus::Ok;
+        }
+
+        let warned = match kind {
+            TimeoutKind::Session => &mut self.session_warned,
+            TimeoutKind::Idle => &mut self.idle_warned,
+        };
+        if *warned {
+            return TimerStatus::Ok;
+        }
+        *warned = true;
+
+        TimerStatus::Warn { kind, remaining }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(session_secs: Option<u64>, idle_secs: Option<u64>) -> TimeoutPolicy {
+        TimeoutPolicy {
+            session: session_secs.map(Duration::from_secs),
+            idle: idle_secs.map(Duration::from_secs),
+            warning: Duration::from_secs(10),
+            allow_extend: true,
+        }
+    }
+
+    #[test]
+    fn test_session_timeout_warns_then_expires() {
+        let start = Instant::now();
+        let mut timer = SessionTimer::new(policy(Some(60), None), start);
+
+        assert_eq!(timer.poll(start + Duration::from_secs(30)), TimerStatus::Ok);
+        assert_eq!(
+            timer.poll(start + Duration::from_secs(55)),
+            TimerStatus::Warn {
+                kind: TimeoutKind::Session,
+                remaining: Duration::from_secs(5),
+            }
+        );
+
+        // The warning is only sent once
+        assert_eq!(timer.poll(start + Duration::from_secs(56)), TimerStatus::Ok);
+        assert_eq!(
+            timer.poll(start + Duration::from_secs(60)),
+            TimerStatus::Expired(TimeoutKind::Session)
+        );
+    }
+
+    #[test]
+    fn test_activity_resets_idle_timeout() {
+        let start = Instant::now();
+        let mut timer = SessionTimer::new(policy(None, Some(30)), start);
+
+        assert!(matches!(
+            timer.poll(start + Duration::from_secs(25)),
+            TimerStatus::Warn { kind: TimeoutKind::Idle, .. }
+        ));
+
+        timer.record_activity(start + Duration::from_secs(26));
+        assert_eq!(timer.poll(start + Duration::from_secs(40)), TimerStatus::Ok);
+
+        // A new idle period warns again
+        assert!(matches!(
+            timer.poll(start + Duration::from_secs(50)),
+            TimerStatus::Warn { kind: TimeoutKind::Idle, .. }
+        ));
+        assert_eq!(
+            timer.poll(start + Duration::from_secs(56)),
+            TimerStatus::Expired(TimeoutKind::Idle)
+        );
+    }
+
+    #[test]
+    fn test_earliest_deadline_wins() {
+        let start = Instant::now();
+        let mut timer = SessionTimer::new(policy(Some(100), Some(30)), start);
+
+        assert_eq!(
+            timer.poll(start + Duration::from_secs(30)),
+            TimerStatus::Expired(TimeoutKind::Idle)
+        );
+
+        timer.record_activity(start + Duration::from_secs(90));
+        assert_eq!(
+            timer.poll(start + Duration::from_secs(100)),
+            TimerStatus::Expired(TimeoutKind::Session)
+        );
+    }
+
+    #[test]
+    fn test_extend_session() {
+        let start = Instant::now();
+        let mut timer = SessionTimer::new(policy(Some(60), None), start);
+        let now = start + Duration::from_secs(55);
+
+        assert!(timer.extend(Duration::from_secs(30), now));
+        assert_eq!(timer.session_remaining(now), Some(Duration::from_secs(35)));
+
+        // Extensions are capped at one full session period
+        assert!(timer.extend(Duration::from_secs(600), now));
+        assert_eq!(timer.session_remaining(now), Some(Duration::from_secs(95)));
+    }
+
+    #[test]
+    fn test_extend_not_allowed() {
+        let start = Instant::now();
+        let mut config = policy(Some(60), None);
+        config.allow_extend = false;
+        let mut timer = SessionTimer::new(config, start);
+
+        assert!(!timer.extend(Duration::from_secs(30), start));
+        assert_eq!(timer.session_remaining(start), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_policy_from_security_config() {
+        let config = SecurityConfig {
+            session_timeout_minutes: 0,
+            idle_timeout_minutes: 5,
+            ..Default::default()
+        };
+
+        let policy = TimeoutPolicy::from_security_config(&config);
+        assert_eq!(policy.session, None);
+        assert_eq!(policy.idle, Some(Duration::from_secs(300)));
+        assert!(!TimeoutPolicy::default().allow_extend);
+        assert!(TimeoutPolicy::default().is_disabled());
+    }
+}
@@ -4,16 +4,24 @@
 //! - Loopback testing (host and client in same process)
 //! - QUIC-based networking over real network connections
 
-use serde::{Deserialize, Serialize};
+use quinn::{RecvStream, SendStream};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cell::Cell;
+use std::fmt;
 use std::time::Instant;
 use tokio::sync::mpsc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
+use crate::clipboard::MAX_CLIPBOARD_MESSAGE_SIZE;
 use crate::desktop::FrameFormat;
 use crate::input::InputEvent;
+use crate::network::codec::{decode, raw_frame_size, validate_frame};
 use crate::network::{
-    BiStream, ConnectionRole, Message, QuicConnection, StreamReceiver, StreamSender,
+    BiStream, ConnectionRole, DisconnectReason, ErrorMessage, Message, QuicConnection,
+    StreamError, StreamReceiver, StreamSender, MAX_VIDEO_MESSAGE_SIZE,
 };
+use crate::session::timeout::TimeoutKind;
 
 /// Default channel buffer size
 pub const DEFAULT_CHANNEL_BUFFER: usize = 32;
@@ -23,8 +31,10 @@ pub const FRAME_CHANNEL_BUFFER: usize = 4;
 
 /// Frame data ready for transport
 ///
-/// This is a serializable version of Frame/EncodedFrame for transmission
+/// This is a serializable version of Frame/EncodedFrame for transmission.
+/// Decoding checks the frame with [`TransportFrame::validate`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "TransportFrameFields")]
 pub struct TransportFrame {
     /// Frame sequence number for ordering
     pub sequence: u64,
@@ -42,6 +52,36 @@ pub struct TransportFrame {
     pub timestamp_ms: u64,
 }
 
+/// A [`TransportFrame`] as decoded, before it is checked
+#[derive(Deserialize)]
+struct TransportFrameFields {
+    sequence: u64,
+    width: u32,
+    height: u32,
+    format: FrameFormat,
+    data: Vec<u8>,
+    original_size: usize,
+    timestamp_ms: u64,
+}
+
+impl TryFrom<TransportFrameFields> for TransportFrame {
+    type Error = String;
+
+    fn try_from(fields: TransportFrameFields) -> Result<Self, Self::Error> {
+        let frame = Self::new(
+            fields.sequence,
+            fields.width,
+            fields.height,
+            fields.format,
+            fields.data,
+            fields.original_size,
+            fields.timestamp_ms,
+        );
+        frame.validate()?;
+        Ok(frame)
+    }
+}
+
 impl TransportFrame {
     /// Creates a new transport frame
     pub fn new(
@@ -64,6 +104,23 @@ impl TransportFrame {
         }
     }
 
+    /// Checks the frame's dimensions against its data
+    ///
+    /// # Errors
+    ///
+    /// Returns what is wrong with the frame
+    pub fn validate(&self) -> Result<(), String> {
+        let raw = self.format == FrameFormat::Raw;
+        validate_frame(self.width, self.height, raw, self.data.len())?;
+        if self.original_size > raw_frame_size(self.width, self.height) {
+            return Err(format!(
+                "{}x{} frame claims {} bytes uncompressed",
+                self.width, self.height, self.original_size
+            ));
+        }
+        Ok(())
+    }
+
     /// Returns the size of the encoded data
     pub fn encoded_size(&self) -> usize {
         self.data.len()
@@ -110,7 +167,7 @@ impl TransportInput {
 }
 
 /// Clipboard content for transport
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TransportClipboard {
     /// Clipboard content type
     pub content_type: ClipboardContentType,
@@ -122,6 +179,88 @@ pub struct TransportClipboard {
     pub sequence: u64,
 }
 
+/// Message on the clipboard stream
+///
+/// Items that fit in one message are sent whole. Larger items are announced
+/// with `ChunkStart` and followed by their chunks in order. The sending side
+/// abandons a transfer with `Cancel`, the receiving side with `Reject`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClipboardMessage {
+    /// Complete clipboard item
+    Item(TransportClipboard),
+    /// Announces an item sent in chunks
+    ChunkStart {
+        /// Sequence number of the item
+        sequence: u64,
+        /// Clipboard content type
+        content_type: ClipboardContentType,
+        /// Hash of the complete content
+        content_hash: u64,
+        /// Size of the complete content in bytes
+        total_size: u64,
+        /// Number of chunks that follow
+        chunk_count: u32,
+    },
+    /// Part of a chunked item
+    Chunk {
+        /// Sequence number of the item
+        sequence: u64,
+        /// Position of the chunk, starting at zero
+        index: u32,
+        /// Chunk data
+        data: Vec<u8>,
+    },
+    /// Sender stopped sending an item
+    Cancel {
+        /// Sequence number of the item
+        sequence: u64,
+        /// Why the transfer stopped
+        reason: ClipboardCancelReason,
+    },
+    /// Receiver refused or stopped receiving an item
+    Reject {
+        /// Sequence number of the item
+        sequence: u64,
+        /// Why the transfer stopped
+        reason: ClipboardCancelReason,
+    },
+}
+
+/// Why a clipboard transfer did not complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClipboardCancelReason {
+    /// Item is larger than the configured clipboard limit
+    TooLarge {
+        /// Item size in bytes
+        size: u64,
+        /// Limit in bytes
+        limit: u64,
+    },
+    /// A user cancelled the transfer
+    Cancelled,
+    /// Chunks didn't match the announced item
+    Invalid,
+    /// Clipboard policy or a content filter doesn't allow the item
+    Blocked,
+}
+
+impl fmt::Display for ClipboardCancelReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+        match self {
+            ClipboardCancelReason::TooLarge { size, limit } => write!(
+                f,
+                "clipboard item is {:.1} MB, over the {:.0} MB limit",
+                *size as f64 / BYTES_PER_MB,
+                *limit as f64 / BYTES_PER_MB
+            ),
+            ClipboardCancelReason::Cancelled => write!(f, "transfer cancelled"),
+            ClipboardCancelReason::Invalid => write!(f, "transfer data was invalid"),
+            ClipboardCancelReason::Blocked => write!(f, "blocked by clipboard policy"),
+        }
+    }
+}
+
 /// Type of clipboard content
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ClipboardContentType {
@@ -131,6 +270,230 @@ pub enum ClipboardContentType {
     Html,
     /// Image content (PNG)
     Image,
+    /// File names and sizes; contents are fetched on paste
+    FileList,
+}
+
+/// Message on the file transfer stream
+///
+/// Several transfers can be in progress at once; every message carries the
+/// ID chosen by the side that started the transfer. The host picks odd IDs
+/// and the client even ones, so the two never collide.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FileMessage {
+    /// Asks the peer for a file from the file list on its clipboard
+    Request {
+        /// Transfer ID chosen by the requester
+        transfer_id: u64,
+        /// Sequence number of the clipboard item listing the file
+        sequence: u64,
+        /// Position of the file in the list
+        index: u32,
+    },
+    /// Asks the host for a file in one of its browsable directories
+    Fetch {
+        /// Transfer ID chosen by the requester
+        transfer_id: u64,
+        /// Remote path, as used on the browse stream
+        path: String,
+    },
+    /// Offers to send a file; the peer answers with `Accept` or `Cancel`
+    Offer {
+        /// Transfer ID chosen by the sender
+        transfer_id: u64,
+        /// File name without any directory
+        name: String,
+        /// File size in bytes
+        size: u64,
+        /// SHA-256 digest of the whole file
+        sha256: [u8; 32],
+    },
+    /// Accepts an offer
+    Accept {
+        /// Transfer ID
+        transfer_id: u64,
+        /// Bytes already received by an earlier, interrupted transfer
+        offset: u64,
+    },
+    /// Describes the requested file about to be sent
+    Start {
+        /// Transfer ID
+        transfer_id: u64,
+        /// File name without any directory
+        name: String,
+        /// File size in bytes
+        size: u64,
+        /// SHA-256 digest of the whole file
+        sha256: [u8; 32],
+    },
+    /// Next part of the file, starting at the accepted offset
+    Chunk {
+        /// Transfer ID
+        transfer_id: u64,
+        /// File data
+        data: Vec<u8>,
+    },
+    /// Every chunk has been sent
+    End {
+        /// Transfer ID
+        transfer_id: u64,
+    },
+    /// Either side stopped the transfer
+    Cancel {
+        /// Transfer ID
+        transfer_id: u64,
+        /// Why the transfer stopped
+        reason: String,
+    },
+}
+
+/// A file or directory on the browse stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteEntry {
+    /// File or directory name
+    pub name: String,
+    /// Whether this is a directory
+    pub is_dir: bool,
+    /// File size in bytes (0 for directories)
+    pub size: u64,
+    /// Last modification time in seconds since the Unix epoch, if known
+    pub modified: Option<u64>,
+}
+
+/// Messages on the file browsing stream
+///
+/// Each request is answered by exactly one response carrying the same
+/// request ID. Paths use `/` separators and start with the name of one of
+/// the host's browsable directories; `/` lists those directories. Files are
+/// downloaded over the file stream with [`FileMessage::Fetch`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BrowseMessage {
+    /// Lists a directory
+    List {
+        /// Request ID chosen by the requester
+        request_id: u64,
+        /// Remote path of the directory
+        path: String,
+    },
+    /// Describes a file or directory
+    Stat {
+        /// Request ID chosen by the requester
+        request_id: u64,
+        /// Remote path
+        path: String,
+    },
+    /// Contents of a listed directory, directories first
+    Entries {
+        /// Request ID
+        request_id: u64,
+        /// Directory entries
+        entries: Vec<RemoteEntry>,
+    },
+    /// A described file or directory
+    Entry {
+        /// Request ID
+        request_id: u64,
+        /// The entry
+        entry: RemoteEntry,
+    },
+    /// The request failed
+    Error {
+        /// Request ID
+        request_id: u64,
+        /// What went wrong
+        message: String,
+    },
+}
+
+/// A chat message on the chat stream
+///
+/// The receiver labels the message with the name the peer gave during the
+/// connection handshake, so the sender's name isn't sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// Message text
+    pub text: String,
+    /// When the message was sent, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+}
+
+/// Messages on the terminal stream
+///
+/// The client opens terminals and the host runs a shell in a
+/// pseudo-terminal for each. Terminal IDs are chosen by the client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TerminalMessage {
+    /// Client request to start a shell
+    Open {
+        /// Terminal ID
+        terminal_id: u32,
+        /// Width in characters
+        cols: u16,
+        /// Height in lines
+        rows: u16,
+    },
+    /// The shell is running
+    Opened {
+        /// Terminal ID
+        terminal_id: u32,
+    },
+    /// Keystrokes for the shell
+    Input {
+        /// Terminal ID
+        terminal_id: u32,
+        /// Bytes typed
+        data: Vec<u8>,
+    },
+    /// Output from the shell
+    Output {
+        /// Terminal ID
+        terminal_id: u32,
+        /// Bytes written to the terminal
+        data: Vec<u8>,
+    },
+    /// The client's terminal changed size
+    Resize {
+        /// Terminal ID
+        terminal_id: u32,
+        /// Width in characters
+        cols: u16,
+        /// Height in lines
+        rows: u16,
+    },
+    /// Client request to end the shell
+    Close {
+        /// Terminal ID
+        terminal_id: u32,
+    },
+    /// The terminal couldn't be opened or has ended
+    Closed {
+        /// Terminal ID
+        terminal_id: u32,
+        /// Why, such as the shell's exit status
+        reason: String,
+    },
+}
+
+/// Messages on the custom channel stream
+///
+/// Carries data for the application-defined channels agreed in the
+/// connection handshake.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChannelMessage {
+    /// Data on a channel
+    Data {
+        /// Channel name
+        channel: String,
+        /// Opaque bytes, or a serialized application message
+        data: Vec<u8>,
+    },
+    /// The peer has no such channel and dropped the data sent on it
+    Rejected {
+        /// Channel name
+        channel: String,
+        /// Why
+        reason: String,
+    },
 }
 
 /// Control messages for session management
@@ -156,6 +519,87 @@ pub enum ControlMessage {
     RequestDisplayInfo,
     /// Display info response
     DisplayInfo { width: u32, height: u32, name: String },
+    /// Host warning that a timeout will end the session soon
+    TimeoutWarning {
+        /// Limit that will end the session
+        kind: TimeoutKind,
+        /// Seconds left before the session ends
+        seconds_remaining: u64,
+    },
+    /// Client request to extend the session timeout
+    ExtendSession {
+        /// Requested extension in minutes
+        minutes: u32,
+    },
+    /// Host response to an extension request
+    ExtendResult {
+        /// Whether the host allowed the extension
+        granted: bool,
+        /// Seconds left before the session limit (None if unlimited)
+        seconds_remaining: Option<u64>,
+    },
+    /// Client request to release every key and button it holds down,
+    /// sent when the viewer loses focus or stops sending input
+    ReleaseAllInput,
+    /// Session is ending
+    Disconnect {
+        /// Why the session ended
+        reason: DisconnectReason,
+    },
+    /// A request the peer answers with `Response` or `Failed`
+    ///
+    /// Requests and responses can't be nested in each other.
+    Request {
+        /// Request ID chosen by the requester
+        request_id: u32,
+        /// The request
+        #[serde(deserialize_with = "deserialize_inner")]
+        request: Box<ControlMessage>,
+    },
+    /// Answer to a request
+    Response {
+        /// ID of the request answered
+        request_id: u32,
+        /// The answer
+        #[serde(deserialize_with = "deserialize_inner")]
+        response: Box<ControlMessage>,
+    },
+    /// The peer couldn't answer a request
+    Failed {
+        /// ID of the request that failed
+        request_id: u32,
+        /// Why it failed
+        error: ErrorMessage,
+    },
+}
+
+thread_local! {
+    /// Set while the message inside a request or response is decoded
+    static DECODING_INNER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Clears [`DECODING_INNER`] when the inner message is decoded
+struct InnerGuard;
+
+impl Drop for InnerGuard {
+    fn drop(&mut self) {
+        DECODING_INNER.with(|decoding| decoding.set(false));
+    }
+}
+
+/// Decodes the message inside a request or response
+///
+/// Fails on another request or response before decoding it, so a peer
+/// can't make decoding recurse until the stack overflows.
+fn deserialize_inner<'de, D>(deserializer: D) -> Result<Box<ControlMessage>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    if DECODING_INNER.with(|decoding| decoding.replace(true)) {
+        return Err(D::Error::custom("request or response nested in another"));
+    }
+    let _guard = InnerGuard;
+    Box::<ControlMessage>::deserialize(deserializer)
 }
 
 /// Statistics for a transport channel
@@ -206,6 +650,16 @@ pub struct ChannelPair<T> {
     pub rx: mpsc::Receiver<T>,
 }
 
+impl<T> ChannelPair<T> {
+    /// Takes the receiving half so a task can own it
+    ///
+    /// A closed receiver is left in its place.
+    pub fn take_rx(&mut self) -> mpsc::Receiver<T> {
+        let (_, closed) = mpsc::channel(1);
+        std::mem::replace(&mut self.rx, closed)
+    }
+}
+
 /// Complete transport channels for a session
 pub struct SessionTransport {
     /// Channel for sending/receiving frames
@@ -213,7 +667,17 @@ pub struct SessionTransport {
     /// Channel for sending/receiving input events
     pub input: ChannelPair<TransportInput>,
     /// Channel for clipboard synchronization
-    pub clipboard: ChannelPair<TransportClipboard>,
+    pub clipboard: ChannelPair<ClipboardMessage>,
+    /// Channel for file transfers
+    pub files: ChannelPair<FileMessage>,
+    /// Channel for browsing the host's files
+    pub browse: ChannelPair<BrowseMessage>,
+    /// Channel for chat messages
+    pub chat: ChannelPair<ChatMessage>,
+    /// Channel for remote terminals
+    pub terminal: ChannelPair<TerminalMessage>,
+    /// Channel for application-defined channels
+    pub channels: ChannelPair<ChannelMessage>,
     /// Channel for control messages
     pub control: ChannelPair<ControlMessage>,
 }
@@ -237,6 +701,26 @@ pub fn create_loopback_transport() -> (SessionTransport, SessionTransport) {
     let (host_clipboard_tx, client_clipboard_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
     let (client_clipboard_tx, host_clipboard_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
 
+    // Create file transfer channels (bidirectional)
+    let (host_files_tx, client_files_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+    let (client_files_tx, host_files_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+
+    // Create file browsing channels (bidirectional)
+    let (host_browse_tx, client_browse_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+    let (client_browse_tx, host_browse_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+
+    // Create chat channels (bidirectional)
+    let (host_chat_tx, client_chat_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+    let (client_chat_tx, host_chat_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+
+    // Create terminal channels (bidirectional)
+    let (host_terminal_tx, client_terminal_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+    let (client_terminal_tx, host_terminal_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+
+    // Create custom channel channels (bidirectional)
+    let (host_channels_tx, client_channels_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+    let (client_channels_tx, host_channels_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+
     // Create control channels (bidirectional)
     let (host_control_tx, client_control_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
     let (client_control_tx, host_control_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
@@ -254,6 +738,26 @@ pub fn create_loopback_transport() -> (SessionTransport, SessionTransport) {
             tx: host_clipboard_tx,
             rx: host_clipboard_rx,
         },
+        files: ChannelPair {
+            tx: host_files_tx,
+            rx: host_files_rx,
+        },
+        browse: ChannelPair {
+            tx: host_browse_tx,
+            rx: host_browse_rx,
+        },
+        chat: ChannelPair {
+            tx: host_chat_tx,
+            rx: host_chat_rx,
+        },
+        terminal: ChannelPair {
+            tx: host_terminal_tx,
+            rx: host_terminal_rx,
+        },
+        channels: ChannelPair {
+            tx: host_channels_tx,
+            rx: host_channels_rx,
+        },
         control: ChannelPair {
             tx: host_control_tx,
             rx: host_control_rx,
@@ -273,6 +777,26 @@ pub fn create_loopback_transport() -> (SessionTransport, SessionTransport) {
             tx: client_clipboard_tx,
             rx: client_clipboard_rx,
         },
+        files: ChannelPair {
+            tx: client_files_tx,
+            rx: client_files_rx,
+        },
+        browse: ChannelPair {
+            tx: client_browse_tx,
+            rx: client_browse_rx,
+        },
+        chat: ChannelPair {
+            tx: client_chat_tx,
+            rx: client_chat_rx,
+        },
+        terminal: ChannelPair {
+            tx: client_terminal_tx,
+            rx: client_terminal_rx,
+        },
+        channels: ChannelPair {
+            tx: client_channels_tx,
+            rx: client_channels_rx,
+        },
         control: ChannelPair {
             tx: client_control_tx,
             rx: client_control_rx,
@@ -329,8 +853,16 @@ impl QuicTransportHandle {
 /// - Stream 1: Video frames (host → client, unidirectional)
 /// - Stream 2: Input events (client → host, unidirectional)
 /// - Stream 3: Clipboard (bidirectional)
+/// - Stream 4: File transfers (bidirectional)
+/// - Stream 5: File browsing (bidirectional)
+/// - Stream 6: Chat (bidirectional)
+/// - Stream 7: Remote terminals (bidirectional)
+/// - Stream 8: Custom channels (bidirectional)
 /// - Control messages use the existing control stream from connection handshake
 ///
+/// The opener writes [`STREAM_OPEN_MARKER`] on each stream, since QUIC only
+/// tells the peer about a stream once data is sent on it.
+///
 /// # Arguments
 ///
 /// * `connection` - The established QUIC connection
@@ -359,77 +891,191 @@ pub async fn create_quic_transport(
     let (clipboard_out_tx, clipboard_out_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
     let (clipboard_in_tx, clipboard_in_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
 
+    let (files_out_tx, files_out_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+    let (files_in_tx, files_in_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+
+    let (browse_out_tx, browse_out_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+    let (browse_in_tx, browse_in_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+
+    let (chat_out_tx, chat_out_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+    let (chat_in_tx, chat_in_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+
+    let (terminal_out_tx, terminal_out_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+    let (terminal_in_tx, terminal_in_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+
+    let (channels_out_tx, channels_out_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+    let (channels_in_tx, channels_in_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
+
     let (control_out_tx, control_out_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
     let (control_in_tx, control_in_rx) = mpsc::channel(DEFAULT_CHANNEL_BUFFER);
 
+    // Stream bridges answer malformed messages on the control stream
+    let replies = control_out_tx.downgrade();
+
     match role {
         ConnectionRole::Host => {
             // Host opens video stream (unidirectional send)
-            let video_send = connection
-                .open_uni()
-                .await
-                .map_err(|e| TransportError::StreamError(e.to_string()))?;
+            let video_send = open_uni_stream(&connection).await?;
 
             // Host accepts input stream (unidirectional receive)
-            let input_recv = connection
-                .accept_uni()
-                .await
-                .map_err(|e| TransportError::StreamError(e.to_string()))?;
+            let input_recv = accept_uni_stream(&connection).await?;
 
             // Host opens clipboard stream (bidirectional)
-            let (clipboard_send, clipboard_recv) = connection
-                .open_bi()
-                .await
-                .map_err(|e| TransportError::StreamError(e.to_string()))?;
+            let (clipboard_send, clipboard_recv) = open_bi_stream(&connection).await?;
+
+            // Host opens file transfer stream (bidirectional)
+            let (files_send, files_recv) = open_bi_stream(&connection).await?;
+
+            // Host opens file browsing stream (bidirectional)
+            let (browse_send, browse_recv) = open_bi_stream(&connection).await?;
+
+            // Host opens chat stream (bidirectional)
+            let (chat_send, chat_recv) = open_bi_stream(&connection).await?;
+
+            // Host opens terminal stream (bidirectional)
+            let (terminal_send, terminal_recv) = open_bi_stream(&connection).await?;
+
+            // Host opens custom channel stream (bidirectional)
+            let (channels_send, channels_recv) = open_bi_stream(&connection).await?;
 
             // Bridge video frames: channel → QUIC stream
-            let sender: StreamSender<TransportFrame> = StreamSender::new(video_send);
+            let sender: StreamSender<TransportFrame> =
+                StreamSender::new(video_send).with_max_message_size(MAX_VIDEO_MESSAGE_SIZE);
             handles.push(spawn_channel_to_stream(frame_out_rx, sender));
 
             // Bridge input: QUIC stream → channel
             let receiver: StreamReceiver<TransportInput> = StreamReceiver::new(input_recv);
-            handles.push(spawn_stream_to_channel(receiver, input_in_tx));
+            handles.push(spawn_stream_to_channel(receiver, input_in_tx, replies.clone()));
 
             // Bridge clipboard both directions
-            let clip_sender: StreamSender<TransportClipboard> = StreamSender::new(clipboard_send);
-            let clip_receiver: StreamReceiver<TransportClipboard> =
-                StreamReceiver::new(clipboard_recv);
+            let clip_sender: StreamSender<ClipboardMessage> = StreamSender::new(clipboard_send);
+            let clip_receiver: StreamReceiver<ClipboardMessage> =
+                StreamReceiver::new(clipboard_recv)
+                    .with_max_message_size(MAX_CLIPBOARD_MESSAGE_SIZE);
             handles.push(spawn_channel_to_stream(clipboard_out_rx, clip_sender));
-            handles.push(spawn_stream_to_channel(clip_receiver, clipboard_in_tx));
+            handles.push(spawn_stream_to_channel(clip_receiver, clipboard_in_tx, replies.clone()));
+
+            // Bridge file transfers both directions
+            let file_sender: StreamSender<FileMessage> = StreamSender::new(files_send);
+            let file_receiver: StreamReceiver<FileMessage> = StreamReceiver::new(files_recv);
+            handles.push(spawn_channel_to_stream(files_out_rx, file_sender));
+            handles.push(spawn_stream_to_channel(file_receiver, files_in_tx, replies.clone()));
+
+            // Bridge file browsing both directions
+            let browse_sender: StreamSender<BrowseMessage> = StreamSender::new(browse_send);
+            let browse_receiver: StreamReceiver<BrowseMessage> = StreamReceiver::new(browse_recv);
+            handles.push(spawn_channel_to_stream(browse_out_rx, browse_sender));
+            handles.push(spawn_stream_to_channel(browse_receiver, browse_in_tx, replies.clone()));
+
+            // Bridge chat both directions
+            let chat_sender: StreamSender<ChatMessage> = StreamSender::new(chat_send);
+            let chat_receiver: StreamReceiver<ChatMessage> = StreamReceiver::new(chat_recv);
+            handles.push(spawn_channel_to_stream(chat_out_rx, chat_sender));
+            handles.push(spawn_stream_to_channel(chat_receiver, chat_in_tx, replies.clone()));
+
+            // Bridge terminals both directions
+            let terminal_sender: StreamSender<TerminalMessage> = StreamSender::new(terminal_send);
+            let terminal_receiver: StreamReceiver<TerminalMessage> =
+                StreamReceiver::new(terminal_recv);
+            handles.push(spawn_channel_to_stream(terminal_out_rx, terminal_sender));
+            handles.push(spawn_stream_to_channel(
+                terminal_receiver,
+                terminal_in_tx,
+                replies.clone(),
+            ));
+
+            // Bridge custom channels both directions
+            let channels_sender: StreamSender<ChannelMessage> = StreamSender::new(channels_send);
+            let channels_receiver: StreamReceiver<ChannelMessage> =
+                StreamReceiver::new(channels_recv);
+            handles.push(spawn_channel_to_stream(channels_out_rx, channels_sender));
+            handles.push(spawn_stream_to_channel(
+                channels_receiver,
+                channels_in_tx,
+                replies.clone(),
+            ));
         }
         ConnectionRole::Client => {
             // Client accepts video stream (unidirectional receive)
-            let video_recv = connection
-                .accept_uni()
-                .await
-                .map_err(|e| TransportError::StreamError(e.to_string()))?;
+            let video_recv = accept_uni_stream(&connection).await?;
 
             // Client opens input stream (unidirectional send)
-            let input_send = connection
-                .open_uni()
-                .await
-                .map_err(|e| TransportError::StreamError(e.to_string()))?;
+            let input_send = open_uni_stream(&connection).await?;
 
             // Client accepts clipboard stream (bidirectional)
-            let (clipboard_send, clipboard_recv) = connection
-                .accept_bi()
-                .await
-                .map_err(|e| TransportError::StreamError(e.to_string()))?;
+            let (clipboard_send, clipboard_recv) = accept_bi_stream(&connection).await?;
+
+            // Client accepts file transfer stream (bidirectional)
+            let (files_send, files_recv) = accept_bi_stream(&connection).await?;
+
+            // Client accepts file browsing stream (bidirectional)
+            let (browse_send, browse_recv) = accept_bi_stream(&connection).await?;
+
+            // Client accepts chat stream (bidirectional)
+            let (chat_send, chat_recv) = accept_bi_stream(&connection).await?;
+
+            // Client accepts terminal stream (bidirectional)
+            let (terminal_send, terminal_recv) = accept_bi_stream(&connection).await?;
+
+            // Client accepts custom channel stream (bidirectional)
+            let (channels_send, channels_recv) = accept_bi_stream(&connection).await?;
 
             // Bridge video frames: QUIC stream → channel
-            let receiver: StreamReceiver<TransportFrame> = StreamReceiver::new(video_recv);
-            handles.push(spawn_stream_to_channel(receiver, frame_in_tx));
+            let receiver: StreamReceiver<TransportFrame> =
+                StreamReceiver::new(video_recv).with_max_message_size(MAX_VIDEO_MESSAGE_SIZE);
+            handles.push(spawn_stream_to_channel(receiver, frame_in_tx, replies.clone()));
 
             // Bridge input: channel → QUIC stream
             let sender: StreamSender<TransportInput> = StreamSender::new(input_send);
             handles.push(spawn_channel_to_stream(input_out_rx, sender));
 
             // Bridge clipboard both directions
-            let clip_sender: StreamSender<TransportClipboard> = StreamSender::new(clipboard_send);
-            let clip_receiver: StreamReceiver<TransportClipboard> =
-                StreamReceiver::new(clipboard_recv);
+            let clip_sender: StreamSender<ClipboardMessage> = StreamSender::new(clipboard_send);
+            let clip_receiver: StreamReceiver<ClipboardMessage> =
+                StreamReceiver::new(clipboard_recv)
+                    .with_max_message_size(MAX_CLIPBOARD_MESSAGE_SIZE);
             handles.push(spawn_channel_to_stream(clipboard_out_rx, clip_sender));
-            handles.push(spawn_stream_to_channel(clip_receiver, clipboard_in_tx));
+            handles.push(spawn_stream_to_channel(clip_receiver, clipboard_in_tx, replies.clone()));
+
+            // Bridge file transfers both directions
+            let file_sender: StreamSender<FileMessage> = StreamSender::new(files_send);
+            let file_receiver: StreamReceiver<FileMessage> = StreamReceiver::new(files_recv);
+            handles.push(spawn_channel_to_stream(files_out_rx, file_sender));
+            handles.push(spawn_stream_to_channel(file_receiver, files_in_tx, replies.clone()));
+
+            // Bridge file browsing both directions
+            let browse_sender: StreamSender<BrowseMessage> = StreamSender::new(browse_send);
+            let browse_receiver: StreamReceiver<BrowseMessage> = StreamReceiver::new(browse_recv);
+            handles.push(spawn_channel_to_stream(browse_out_rx, browse_sender));
+            handles.push(spawn_stream_to_channel(browse_receiver, browse_in_tx, replies.clone()));
+
+            // Bridge chat both directions
+            let chat_sender: StreamSender<ChatMessage> = StreamSender::new(chat_send);
+            let chat_receiver: StreamReceiver<ChatMessage> = StreamReceiver::new(chat_recv);
+            handles.push(spawn_channel_to_stream(chat_out_rx, chat_sender));
+            handles.push(spawn_stream_to_channel(chat_receiver, chat_in_tx, replies.clone()));
+
+            // Bridge terminals both directions
+            let terminal_sender: StreamSender<TerminalMessage> = StreamSender::new(terminal_send);
+            let terminal_receiver: StreamReceiver<TerminalMessage> =
+                StreamReceiver::new(terminal_recv);
+            handles.push(spawn_channel_to_stream(terminal_out_rx, terminal_sender));
+            handles.push(spawn_stream_to_channel(
+                terminal_receiver,
+                terminal_in_tx,
+                replies.clone(),
+            ));
+
+            // Bridge custom channels both directions
+            let channels_sender: StreamSender<ChannelMessage> = StreamSender::new(channels_send);
+            let channels_receiver: StreamReceiver<ChannelMessage> =
+                StreamReceiver::new(channels_recv);
+            handles.push(spawn_channel_to_stream(channels_out_rx, channels_sender));
+            handles.push(spawn_stream_to_channel(
+                channels_receiver,
+                channels_in_tx,
+                replies.clone(),
+            ));
         }
     }
 
@@ -438,7 +1084,11 @@ pub async fn create_quic_transport(
     // in ControlMessage for the session layer
     let BiStream { sender, receiver } = control_stream;
     handles.push(spawn_control_sender(control_out_rx, sender));
-    handles.push(spawn_control_receiver(receiver, control_in_tx));
+    handles.push(spawn_control_receiver(
+        receiver,
+        control_in_tx,
+        control_out_tx.downgrade(),
+    ));
 
     let transport = SessionTransport {
         frames: ChannelPair {
@@ -453,6 +1103,26 @@ pub async fn create_quic_transport(
             tx: clipboard_out_tx,
             rx: clipboard_in_rx,
         },
+        files: ChannelPair {
+            tx: files_out_tx,
+            rx: files_in_rx,
+        },
+        browse: ChannelPair {
+            tx: browse_out_tx,
+            rx: browse_in_rx,
+        },
+        chat: ChannelPair {
+            tx: chat_out_tx,
+            rx: chat_in_rx,
+        },
+        terminal: ChannelPair {
+            tx: terminal_out_tx,
+            rx: terminal_in_rx,
+        },
+        channels: ChannelPair {
+            tx: channels_out_tx,
+            rx: channels_in_rx,
+        },
         control: ChannelPair {
             tx: control_out_tx,
             rx: control_in_rx,
@@ -465,6 +1135,73 @@ pub async fn create_quic_transport(
     Ok((transport, handle))
 }
 
+/// First byte written on each session stream by the side that opens it
+pub const STREAM_OPEN_MARKER: u8 = 0x01;
+
+/// Opens a unidirectional session stream and announces it to the peer
+async fn open_uni_stream(connection: &QuicConnection) -> TransportResult<SendStream> {
+    let mut send = connection
+        .open_uni()
+        .await
+        .map_err(|e| TransportError::StreamError(e.to_string()))?;
+    write_open_marker(&mut send).await?;
+    Ok(send)
+}
+
+/// Opens a bidirectional session stream and announces it to the peer
+async fn open_bi_stream(
+    connection: &QuicConnection,
+) -> TransportResult<(SendStream, RecvStream)> {
+    let (mut send, recv) = connection
+        .open_bi()
+        .await
+        .map_err(|e| TransportError::StreamError(e.to_string()))?;
+    write_open_marker(&mut send).await?;
+    Ok((send, recv))
+}
+
+/// Accepts a unidirectional session stream opened by the peer
+async fn accept_uni_stream(connection: &QuicConnection) -> TransportResult<RecvStream> {
+    let mut recv = connection
+        .accept_uni()
+        .await
+        .map_err(|e| TransportError::StreamError(e.to_string()))?;
+    read_open_marker(&mut recv).await?;
+    Ok(recv)
+}
+
+/// Accepts a bidirectional session stream opened by the peer
+async fn accept_bi_stream(
+    connection: &QuicConnection,
+) -> TransportResult<(SendStream, RecvStream)> {
+    let (send, mut recv) = connection
+        .accept_bi()
+        .await
+        .map_err(|e| TransportError::StreamError(e.to_string()))?;
+    read_open_marker(&mut recv).await?;
+    Ok((send, recv))
+}
+
+async fn write_open_marker(send: &mut SendStream) -> TransportResult<()> {
+    send.write_all(&[STREAM_OPEN_MARKER])
+        .await
+        .map_err(|e| TransportError::StreamError(e.to_string()))
+}
+
+async fn read_open_marker(recv: &mut RecvStream) -> TransportResult<()> {
+    let mut marker = [0u8; 1];
+    recv.read_exact(&mut marker)
+        .await
+        .map_err(|e| TransportError::StreamError(e.to_string()))?;
+    if marker[0] != STREAM_OPEN_MARKER {
+        return Err(TransportError::StreamError(format!(
+            "unexpected stream marker {:#04x}",
+            marker[0]
+        )));
+    }
+    Ok(())
+}
+
 /// Spawns a task that reads from an mpsc channel and writes to a QUIC stream
 fn spawn_channel_to_stream<T>(
     mut rx: mpsc::Receiver<T>,
@@ -485,9 +1222,14 @@ where
 }
 
 /// Spawns a task that reads from a QUIC stream and writes to an mpsc channel
+///
+/// A message over the stream's size limit or one that fails to decode is
+/// skipped, and the stream carries on with the next one. One that fails to
+/// decode is also answered with an `InvalidPayload` error through `replies`.
 fn spawn_stream_to_channel<T>(
     mut receiver: StreamReceiver<T>,
     tx: mpsc::Sender<T>,
+    replies: mpsc::WeakSender<ControlMessage>,
 ) -> tokio::task::JoinHandle<()>
 where
     T: serde::de::DeserializeOwned + Send + 'static,
@@ -501,6 +1243,20 @@ where
                         break;
                     }
                 }
+                // Only that message is lost
+                Err(e @ StreamError::MessageDropped { .. }) => {
+                    warn!("Skipping message: {}", e)
+                }
+                Err(StreamError::Deserialization(e)) => {
+                    warn!("Skipping malformed message: {}", e);
+                    if let Some(replies) = replies.upgrade() {
+                        let failed = ControlMessage::Failed {
+                            request_id: 0,
+                            error: ErrorMessage::invalid_payload(e),
+                        };
+                        let _ = replies.send(failed).await;
+                    }
+                }
                 Err(e) => {
                     debug!("Stream closed or error: {}", e);
                     break;
@@ -511,31 +1267,82 @@ where
     })
 }
 
+/// Converts a session control message into a protocol message
+///
+/// Requests and their answers use the request ID as the message ID, and a
+/// failed request becomes an `Error` message.
+fn control_to_message(ctrl: &ControlMessage) -> TransportResult<Message> {
+    use crate::network::{Disconnect, MessagePayload, MessageType, SessionControlData};
+
+    let mut msg = match ctrl {
+        ControlMessage::Disconnect { reason } => Message::new(
+            MessageType::Disconnect,
+            MessagePayload::Disconnect(Disconnect::new(*reason)),
+        ),
+        ControlMessage::Failed { error, .. } => {
+            Message::new(MessageType::Error, MessagePayload::Error(error.clone()))
+        }
+        _ => {
+            let data = bincode::serialize(ctrl)
+                .map_err(|e| TransportError::StreamError(e.to_string()))?;
+            Message::new(
+                MessageType::SessionControl,
+                MessagePayload::SessionControl(SessionControlData::new(data)),
+            )
+        }
+    };
+
+    if let ControlMessage::Request { request_id, .. }
+    | ControlMessage::Response { request_id, .. }
+    | ControlMessage::Failed { request_id, .. } = ctrl
+    {
+        msg.message_id = *request_id;
+    }
+
+    Ok(msg)
+}
+
+/// Converts a protocol message into a session control message
+///
+/// Returns None for messages that have no session-layer meaning.
+///
+/// # Errors
+///
+/// Returns the error for the peer if the session control data is malformed
+fn message_to_control(msg: Message) -> Result<Option<ControlMessage>, ErrorMessage> {
+    use crate::network::MessagePayload;
+
+    let ctrl = match msg.payload {
+        MessagePayload::Error(error) => ControlMessage::Failed {
+            request_id: msg.message_id,
+            error,
+        },
+        MessagePayload::SessionControl(control) => {
+            decode(&control.data).map_err(ErrorMessage::invalid_payload)?
+        }
+        MessagePayload::Disconnect(disconnect) => ControlMessage::Disconnect {
+            reason: disconnect.reason,
+        },
+        // Bare heartbeats from the peer are treated as pings
+        MessagePayload::Heartbeat(hb) => ControlMessage::Ping {
+            timestamp_ms: hb.timestamp,
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(ctrl))
+}
+
 /// Spawns a task that bridges control messages from channel to QUIC stream
 fn spawn_control_sender(
     mut rx: mpsc::Receiver<ControlMessage>,
     mut sender: StreamSender<Message>,
 ) -> tokio::task::JoinHandle<()> {
-    use crate::network::{MessagePayload, MessageType};
-
     tokio::spawn(async move {
         while let Some(ctrl) = rx.recv().await {
-            // Wrap ControlMessage in protocol Message
-            // For now, we'll use Heartbeat for ping/pong and custom handling
-            let msg = match ctrl {
-                ControlMessage::Ping { timestamp_ms } => Message::new(
-                    MessageType::Heartbeat,
-                    MessagePayload::Heartbeat(crate::network::Heartbeat { timestamp: timestamp_ms }),
-                ),
-                ControlMessage::Pong { original_timestamp_ms } => Message::new(
-                    MessageType::Heartbeat,
-                    MessagePayload::Heartbeat(crate::network::Heartbeat {
-                        timestamp: original_timestamp_ms,
-                    }),
-                ),
-                _ => {
-                    // For other control messages, we'd need to extend the protocol
-                    // For now, skip them
+            let msg = match control_to_message(&ctrl) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    error!("Failed to encode control message: {}", e);
                     continue;
                 }
             };
@@ -550,25 +1357,30 @@ fn spawn_control_sender(
 }
 
 /// Spawns a task that bridges control messages from QUIC stream to channel
+///
+/// Malformed messages are answered with an `InvalidPayload` error through
+/// `replies`, so a peer waiting on a request learns why.
 fn spawn_control_receiver(
     mut receiver: StreamReceiver<Message>,
     tx: mpsc::Sender<ControlMessage>,
+    replies: mpsc::WeakSender<ControlMessage>,
 ) -> tokio::task::JoinHandle<()> {
-    use crate::network::MessagePayload;
-
     tokio::spawn(async move {
         loop {
             match receiver.recv().await {
                 Ok(msg) => {
-                    // Convert protocol Message to ControlMessage
-                    let ctrl = match msg.payload {
-                        MessagePayload::Heartbeat(hb) => {
-                            // Could be ping or pong - use as ping for now
-                            ControlMessage::Ping {
-                                timestamp_ms: hb.timestamp,
+                    let request_id = msg.message_id;
+                    let ctrl = match message_to_control(msg) {
+                        Ok(Some(ctrl)) => ctrl,
+                        Ok(None) => continue,
+                        Err(error) => {
+                            debug!("Malformed control message: {}", error.message);
+                            if let Some(replies) = replies.upgrade() {
+                                let failed = ControlMessage::Failed { request_id, error };
+                                let _ = replies.send(failed).await;
                             }
+                            continue;
                         }
-                        _ => continue, // Skip other messages
                     };
 
                     if tx.send(ctrl).await.is_err() {
@@ -576,6 +1388,10 @@ fn spawn_control_receiver(
                         break;
                     }
                 }
+                // Only that message is lost
+                Err(StreamError::Deserialization(e)) => {
+                    debug!("Malformed control message: {}", e);
+                }
                 Err(e) => {
                     debug!("Control stream closed or error: {}", e);
                     break;
@@ -647,6 +1463,99 @@ mod tests {
         assert!(matches!(received, ControlMessage::Start));
     }
 
+    #[test]
+    fn test_control_message_protocol_roundtrip() {
+        let warning = ControlMessage::TimeoutWarning {
+            kind: TimeoutKind::Idle,
+            seconds_remaining: 42,
+        };
+        let msg = control_to_message(&warning).unwrap();
+        let decoded = Message::from_bytes(&msg.to_bytes().unwrap()).unwrap();
+        assert!(matches!(
+            message_to_control(decoded).unwrap(),
+            Some(ControlMessage::TimeoutWarning {
+                kind: TimeoutKind::Idle,
+                seconds_remaining: 42,
+            })
+        ));
+
+        // Pongs must not come back as pings
+        let pong = control_to_message(&ControlMessage::Pong {
+            original_timestamp_ms: 7,
+        })
+        .unwrap();
+        assert!(matches!(
+            message_to_control(pong).unwrap(),
+            Some(ControlMessage::Pong {
+                original_timestamp_ms: 7
+            })
+        ));
+
+        // Disconnects use the protocol disconnect message
+        let disconnect = control_to_message(&ControlMessage::Disconnect {
+            reason: DisconnectReason::SessionTimeout,
+        })
+        .unwrap();
+        assert_eq!(disconnect.message_type, crate::network::MessageType::Disconnect);
+        assert!(matches!(
+            message_to_control(disconnect).unwrap(),
+            Some(ControlMessage::Disconnect {
+                reason: DisconnectReason::SessionTimeout
+            })
+        ));
+    }
+
+    #[test]
+    fn test_request_ids_use_message_ids() {
+        use crate::network::{ErrorCode, MessagePayload};
+
+        let request = control_to_message(&ControlMessage::Request {
+            request_id: 9,
+            request: Box::new(ControlMessage::RequestDisplayInfo),
+        })
+        .unwrap();
+        assert_eq!(request.message_id, 9);
+        assert!(matches!(
+            message_to_control(request).unwrap(),
+            Some(ControlMessage::Request { request_id: 9, .. })
+        ));
+
+        // Failures travel as protocol errors answering the request's message
+        let failed = control_to_message(&ControlMessage::Failed {
+            request_id: 9,
+            error: ErrorMessage::new(ErrorCode::PermissionDenied, "no".to_string()),
+        })
+        .unwrap();
+        assert_eq!(failed.message_id, 9);
+        assert!(matches!(failed.payload, MessagePayload::Error(_)));
+        assert!(matches!(
+            message_to_control(failed).unwrap(),
+            Some(ControlMessage::Failed { request_id: 9, .. })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_input_is_refused() {
+        use crate::network::{ErrorCode, MessagePayload, MessageType, SessionControlData};
+
+        let garbage = Message::new(
+            MessageType::SessionControl,
+            MessagePayload::SessionControl(SessionControlData::new(vec![0xff; 12])),
+        );
+        let error = message_to_control(garbage).unwrap_err();
+        assert_eq!(error.error_code, ErrorCode::InvalidPayload);
+
+        // A raw frame whose data doesn't cover its pixels
+        let frame = TransportFrame::new(1, 64, 64, FrameFormat::Raw, vec![0; 100], 100, 0);
+        assert!(frame.validate().is_err());
+        let bytes = bincode::serialize(&frame).unwrap();
+        assert!(decode::<TransportFrame>(&bytes).is_err());
+
+        let frame = TransportFrame::new(1, 2, 2, FrameFormat::Raw, vec![0; 16], 16, 0);
+        let bytes = bincode::serialize(&frame).unwrap();
+        assert_eq!(decode::<TransportFrame>(&bytes).unwrap().data.len(), 16);
+    }
+
     #[test]
     fn test_transport_frame_compression_ratio() {
         let frame = TransportFrame::new(
@@ -672,4 +1581,126 @@ mod tests {
 
         assert_eq!(stats.latency_ms, Some(10));
     }
+
+    /// Builds host and client transports over a QUIC loopback connection
+    async fn quic_transport_pair() -> (
+        (SessionTransport, QuicTransportHandle),
+        (SessionTransport, QuicTransportHandle),
+    ) {
+        use crate::network::{cert, Heartbeat, MessagePayload, MessageType};
+        use crate::network::{QuicConfig, QuicEndpoint};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let cert_pair = cert::load_or_create_cert(dir.path(), 123456789).unwrap();
+        let host_endpoint = QuicEndpoint::new(
+            QuicConfig::default()
+                .with_bind_addr("127.0.0.1:0".parse().unwrap())
+                .with_cert_pair(cert_pair),
+        )
+        .unwrap();
+        let client_endpoint = QuicEndpoint::client_only().unwrap();
+
+        let client_conn = client_endpoint
+            .connect(host_endpoint.local_addr(), "localhost")
+            .await
+            .unwrap();
+        let host_conn = host_endpoint.accept().await.unwrap().unwrap();
+
+        // The client opens the control stream during the handshake
+        let (send, recv) = client_conn.open_bi().await.unwrap();
+        let mut client_control: BiStream<Message> = BiStream::new(send, recv);
+        let heartbeat = Message::new(
+            MessageType::Heartbeat,
+            MessagePayload::Heartbeat(Heartbeat { timestamp: 0 }),
+        );
+        client_control.send(heartbeat).await.unwrap();
+        let (send, recv) = host_conn.accept_bi().await.unwrap();
+        let mut host_control: BiStream<Message> = BiStream::new(send, recv);
+        host_control.recv().await.unwrap();
+
+        let (host, client) = tokio::join!(
+            create_quic_transport(host_conn, ConnectionRole::Host, host_control),
+            create_quic_transport(client_conn, ConnectionRole::Client, client_control),
+        );
+        (host.unwrap(), client.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_quic_transport_opens_streams() {
+        let result = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            let ((mut host, _host_handle), (mut client, _client_handle)) =
+                quic_transport_pair().await;
+
+            let chat = |text: &str| ChatMessage {
+                text: text.to_string(),
+                timestamp_ms: 0,
+            };
+            host.chat.tx.send(chat("from host")).await.unwrap();
+            client.chat.tx.send(chat("from client")).await.unwrap();
+
+            (client.chat.rx.recv().await, host.chat.rx.recv().await)
+        })
+        .await
+        .expect("transport setup stalled");
+
+        assert_eq!(result.0.unwrap().text, "from host");
+        assert_eq!(result.1.unwrap().text, "from client");
+    }
+
+    #[tokio::test]
+    async fn test_quic_stream_survives_oversized_message() {
+        let result = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            let ((host, _host_handle), (mut client, _client_handle)) =
+                quic_transport_pair().await;
+
+            // Over the clipboard stream's limit but within the sender's
+            let oversized = ClipboardMessage::Chunk {
+                sequence: 1,
+                index: 0,
+                data: vec![0u8; MAX_CLIPBOARD_MESSAGE_SIZE + 1],
+            };
+            let next = ClipboardMessage::Chunk {
+                sequence: 2,
+                index: 0,
+                data: vec![1u8; 16],
+            };
+            host.clipboard.tx.send(oversized).await.unwrap();
+            host.clipboard.tx.send(next).await.unwrap();
+
+            client.clipboard.rx.recv().await
+        })
+        .await
+        .expect("clipboard stream stalled");
+
+        match result {
+            Some(ClipboardMessage::Chunk { sequence, .. }) => assert_eq!(sequence, 2),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quic_stream_reports_and_survives_malformed_message() {
+        let result = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            let ((mut host, _host_handle), (mut client, _client_handle)) =
+                quic_transport_pair().await;
+
+            // Encodes fine but fails validation when decoded
+            let corrupt = TransportFrame::new(1, 0, 0, FrameFormat::Jpeg, vec![0u8; 16], 0, 0);
+            let next = TransportFrame::new(2, 64, 64, FrameFormat::Jpeg, vec![0u8; 16], 0, 0);
+            host.frames.tx.send(corrupt).await.unwrap();
+            host.frames.tx.send(next).await.unwrap();
+
+            (client.frames.rx.recv().await, host.control.rx.recv().await)
+        })
+        .await
+        .expect("frame stream stalled");
+
+        assert_eq!(result.0.unwrap().sequence, 2);
+        match result.1 {
+            Some(ControlMessage::Failed { error, .. }) => {
+                assert_eq!(error.error_code, crate::network::ErrorCode::InvalidPayload)
+            }
+            other => panic!("unexpected control message: {:?}", other),
+        }
+    }
 }
@@ -411,8 +411,6 @@ impl Session {
             KeyboardEventTypeData::KeyRelease => KeyboardEventType::KeyRelease,
         };
 
-        // Convert key code to Key enum
-        // This is a simplified mapping - in production you'd want a proper conversion
         let key = Self::key_from_code(event.key)?;
 
         let kb_event = KeyboardEvent {
@@ -440,6 +438,9 @@ impl Session {
                 delta_x: *delta_x,
                 delta_y: *delta_y,
             },
+            MouseEventTypeData::MoveRelative { dx, dy } => {
+                MouseEventType::MoveRelative { dx: *dx, dy: *dy }
+            }
         };
 
         let mouse_event = MouseEvent {
@@ -450,58 +451,12 @@ impl Session {
         self.process_input(&InputEvent::Mouse(mouse_event)).await
     }
 
-    /// Converts key code to Key enum (simplified mapping)
+    /// Converts key code to Key enum
     fn key_from_code(code: u16) -> Result<Key> {
-        let key = match code {
-            0x41 => Key::A,
-            0x42 => Key::B,
-            0x43 => Key::C,
-            0x44 => Key::D,
-            0x45 => Key::E,
-            0x46 => Key::F,
-            0x47 => Key::G,
-            0x48 => Key::H,
-            0x49 => Key::I,
-            0x4A => Key::J,
-            0x4B => Key::K,
-            0x4C => Key::L,
-            0x4D => Key::M,
-            0x4E => Key::N,
-            0x4F => Key::O,
-            0x50 => Key::P,
-            0x51 => Key::Q,
-            0x52 => Key::R,
-            0x53 => Key::S,
-            0x54 => Key::T,
-            0x55 => Key::U,
-            0x56 => Key::V,
-            0x57 => Key::W,
-            0x58 => Key::X,
-            0x59 => Key::Y,
-            0x5A => Key::Z,
-            0x30 => Key::Num0,
-            0x31 => Key::Num1,
-            0x32 => Key::Num2,
-            0x33 => Key::Num3,
-            0x34 => Key::Num4,
-            0x35 => Key::Num5,
-            0x36 => Key::Num6,
-            0x37 => Key::Num7,
-            0x38 => Key::Num8,
-            0x39 => Key::Num9,
-            0x0D => Key::Return,
-            0x20 => Key::Space,
-            0x1B => Key::Escape,
-            0x08 => Key::Backspace,
-            0x09 => Key::Tab,
-            0x10 => Key::Shift,
-            0x11 => Key::Control,
-            0x12 => Key::Alt,
-            _ => {
-                warn!("Unknown key code: 0x{:04X}", code);
-                Key::Unknown
-            }
-        };
+        let key = Key::from_code(code);
+        if key == Key::Unknown {
+            warn!("Unknown key code: 0x{:04X}", code);
+        }
 
         Ok(key)
     }
@@ -0,0 +1,17 @@
+//! Remote terminal module for RemoteDesk
+//!
+//! This module lets a client run a shell on the host: the host starts it in
+//! a pseudo-terminal and both sides stream keystrokes, output and size
+//! changes over the session's terminal stream. Terminals are off unless the
+//! host enables them in its configuration and the peer's permissions allow
+//! them.
+
+pub mod pty;
+pub mod screen;
+pub mod service;
+
+pub use pty::{default_shell, Pty};
+pub use screen::TerminalScreen;
+pub use service::{
+    resolve_shell, RemoteTerminal, TerminalEvent, TerminalInput, Terminals, MAX_TERMINALS,
+};
@@ -0,0 +1,257 @@
+//! Shells running in pseudo-terminals
+//!
+//! A [`Pty`] starts a shell as the session leader of a new pseudo-terminal,
+//! so line editing, job control and full-screen programs work as they do in
+//! a local terminal window. Only Unix hosts can run terminals.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+
+#[cfg(not(unix))]
+use crate::error::TerminalError;
+use crate::error::TerminalResult;
+
+/// Shell used when `$SHELL` isn't set
+#[cfg(unix)]
+const FALLBACK_SHELL: &str = "/bin/sh";
+
+/// Terminal type announced to programs in the shell
+const TERM: &str = "xterm-256color";
+
+/// Returns the shell to run when none is configured
+pub fn default_shell() -> PathBuf {
+    #[cfg(unix)]
+    {
+        std::env::var_os("SHELL")
+            .filter(|shell| !shell.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(FALLBACK_SHELL))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from("cmd.exe")
+    }
+}
+
+/// A shell running in a pseudo-terminal
+pub struct Pty {
+    /// Controlling side of the pseudo-terminal
+    #[cfg(unix)]
+    master: File,
+    child: Child,
+}
+
+impl Pty {
+    /// Starts `shell` in a new pseudo-terminal of the given size
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the pseudo-terminal can't be created or the shell
+    /// can't be started
+    #[cfg(unix)]
+    pub fn spawn(shell: &Path, cols: u16, rows: u16) -> TerminalResult<Self> {
+        use std::io;
+        use std::os::unix::io::{AsRawFd, FromRawFd};
+        use std::os::unix::process::CommandExt;
+
+        let size = winsize(cols, rows);
+        let mut master_fd: libc::c_int = -1;
+        let mut slave_fd: libc::c_int = -1;
+        // SAFETY: the out pointers are valid, and a null name and termios
+        // are allowed
+        let result = unsafe {
+            libc::openpty(
+                &mut master_fd,
+                &mut slave_fd,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                &size,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        // SAFETY: openpty returned two open descriptors nothing else owns
+        let master = unsafe { File::from_raw_fd(master_fd) };
+        let slave = unsafe { File::from_raw_fd(slave_fd) };
+        // The shell must only hold the terminal side
+        set_cloexec(master.as_raw_fd())?;
+        set_cloexec(slave.as_raw_fd())?;
+
+        let mut command = Command::new(shell);
+        command
+            .stdin(slave.try_clone()?)
+            .stdout(slave.try_clone()?)
+            .stderr(slave)
+            .env("TERM", TERM);
+        // SAFETY: only async-signal-safe calls are made between fork and exec
+        unsafe {
+            command.pre_exec(|| {
+                // Become the leader of a new session with the pseudo-terminal
+                // (now stdin) as its controlling terminal
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                if libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        // Dropping the command afterwards closes our copies of the slave
+        let child = command.spawn()?;
+
+        Ok(Self { master, child })
+    }
+
+    /// Starts `shell` in a new pseudo-terminal of the given size
+    ///
+    /// # Errors
+    ///
+    /// Always fails: terminals need a Unix host
+    #[cfg(not(unix))]
+    pub fn spawn(_shell: &Path, _cols: u16, _rows: u16) -> TerminalResult<Self> {
+        Err(TerminalError::Unsupported)
+    }
+
+    /// Returns a handle the shell's output can be read from
+    ///
+    /// Reads fail once the shell and everything it started have exited.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the handle can't be duplicated
+    pub fn reader(&self) -> TerminalResult<File> {
+        self.clone_master()
+    }
+
+    /// Returns a handle keystrokes can be written to
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the handle can't be duplicated
+    pub fn writer(&self) -> TerminalResult<File> {
+        self.clone_master()
+    }
+
+    #[cfg(unix)]
+    fn clone_master(&self) -> TerminalResult<File> {
+        Ok(self.master.try_clone()?)
+    }
+
+    #[cfg(not(unix))]
+    fn clone_master(&self) -> TerminalResult<File> {
+        Err(TerminalError::Unsupported)
+    }
+
+    /// Changes the terminal size, which the shell is told about
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the size can't be set
+    #[cfg(unix)]
+    pub fn resize(&self, cols: u16, rows: u16) -> TerminalResult<()> {
+        use std::os::unix::io::AsRawFd;
+
+        let size = winsize(cols, rows);
+        // SAFETY: the descriptor is open and `size` outlives the call
+        let result = unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &size) };
+        if result == -1 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Changes the terminal size, which the shell is told about
+    ///
+    /// # Errors
+    ///
+    /// Always fails: terminals need a Unix host
+    #[cfg(not(unix))]
+    pub fn resize(&self, _cols: u16, _rows: u16) -> TerminalResult<()> {
+        Err(TerminalError::Unsupported)
+    }
+
+    /// Returns the shell's process ID
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Ends the shell
+    ///
+    /// Programs it started get a hangup once the terminal closes.
+    pub fn kill(&mut self) {
+        // The shell may already have exited
+        let _ = self.child.kill();
+    }
+
+    /// Waits for the shell to exit
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the shell's status can't be read
+    pub fn wait(&mut self) -> TerminalResult<ExitStatus> {
+        Ok(self.child.wait()?)
+    }
+}
+
+#[cfg(unix)]
+fn winsize(cols: u16, rows: u16) -> libc::winsize {
+    libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+#[cfg(unix)]
+fn set_cloexec(fd: libc::c_int) -> std::io::Result<()> {
+    // SAFETY: fcntl on an open descriptor has no memory safety requirements
+    let result = unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    if result == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    /// Reads shell output until `needle` shows up or the shell exits
+    fn read_until(reader: &mut File, needle: &str) -> String {
+        let mut output = String::new();
+        let mut buffer = [0u8; 1024];
+        while !output.contains(needle) {
+            match reader.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => output.push_str(&String::from_utf8_lossy(&buffer[..read])),
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn test_shell_in_pty() {
+        let mut pty = Pty::spawn(Path::new("/bin/sh"), 100, 30).unwrap();
+        let mut reader = pty.reader().unwrap();
+        let mut writer = pty.writer().unwrap();
+
+        // The shell sees a terminal of the requested size
+        writer.write_all(b"stty size; echo do''ne\n").unwrap();
+        let output = read_until(&mut reader, "done");
+        assert!(output.contains("30 100"), "{:?}", output);
+
+        pty.resize(120, 40).unwrap();
+        writer.write_all(b"stty size; echo ag''ain\n").unwrap();
+        let output = read_until(&mut reader, "again");
+        assert!(output.contains("40 120"), "{:?}", output);
+
+        writer.write_all(b"exit 3\n").unwrap();
+        assert_eq!(pty.wait().unwrap().code(), Some(3));
+    }
+}
@@ -0,0 +1,254 @@
+//! Text view of a terminal's output
+//!
+//! [`TerminalScreen`] turns the bytes a shell writes into lines of text for
+//! the viewer's terminal panel. It follows carriage returns, backspaces and
+//! line erasing, which is enough for prompts and line editing, and drops
+//! other escape sequences such as colours. Full-screen programs are best
+//! used from the CLI's `shell` command.
+
+use std::collections::VecDeque;
+
+/// Lines kept, including scrollback
+pub const MAX_SCREEN_LINES: usize = 2000;
+
+/// Where the parser is in an escape sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Text,
+    /// After ESC
+    Escape,
+    /// In a control sequence (ESC `[`)
+    Csi,
+    /// In an operating system command (ESC `]`), such as a window title
+    Osc,
+    /// After ESC inside an operating system command
+    OscEscape,
+}
+
+/// Lines of text written to a terminal
+#[derive(Debug)]
+pub struct TerminalScreen {
+    lines: VecDeque<Vec<char>>,
+    /// Cursor position in the last line
+    column: usize,
+    state: State,
+    /// Parameters of the control sequence being read
+    params: String,
+    /// Start of a UTF-8 character split across writes
+    partial: Vec<u8>,
+}
+
+impl Default for TerminalScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TerminalScreen {
+    /// Creates an empty screen
+    pub fn new() -> Self {
+        Self {
+            lines: VecDeque::from([Vec::new()]),
+            column: 0,
+            state: State::Text,
+            params: String::new(),
+            partial: Vec::new(),
+        }
+    }
+
+    /// Returns the lines, oldest first
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.lines.iter().map(|line| line.iter().collect())
+    }
+
+    /// Returns all lines joined with newlines
+    pub fn text(&self) -> String {
+        self.lines().collect::<Vec<_>>().join("\n")
+    }
+
+    /// Removes every line
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.lines.push_back(Vec::new());
+        self.column = 0;
+    }
+
+    /// Adds output from the shell
+    pub fn feed(&mut self, data: &[u8]) {
+        let mut bytes = std::mem::take(&mut self.partial);
+        bytes.extend_from_slice(data);
+
+        let mut rest = bytes.as_slice();
+        while !rest.is_empty() {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    self.feed_str(text);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, invalid) = rest.split_at(e.valid_up_to());
+                    // Checked just above, so this never falls back
+                    self.feed_str(std::str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        Some(len) => {
+                            self.feed_char(char::REPLACEMENT_CHARACTER);
+                            rest = &invalid[len..];
+                        }
+                        // The character continues in the next write
+                        None => {
+                            self.partial = invalid.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn feed_str(&mut self, text: &str) {
+        for c in text.chars() {
+            self.feed_char(c);
+        }
+    }
+
+    fn feed_char(&mut self, c: char) {
+        match self.state {
+            State::Text => match c {
+                '\u{1b}' => self.state = State::Escape,
+                '\n' => self.new_line(),
+                '\r' => self.column = 0,
+                '\u{8}' => self.column = self.column.saturating_sub(1),
+                '\t' => {
+                    let next = (self.column / 8 + 1) * 8;
+                    while self.column < next {
+                        self.put(' ');
+                    }
+                }
+                c if c.is_control() => {}
+                c => self.put(c),
+            },
+            State::Escape => {
+                self.state = match c {
+                    '[' => {
+                        self.params.clear();
+                        State::Csi
+                    }
+                    ']' => State::Osc,
+                    _ => State::Text,
+                };
+            }
+            State::Csi => {
+                if ('\u{40}'..='\u{7e}').contains(&c) {
+                    self.state = State::Text;
+                    self.control_sequence(c);
+                } else {
+                    self.params.push(c);
+                }
+            }
+            State::Osc => match c {
+                '\u{7}' => self.state = State::Text,
+                '\u{1b}' => self.state = State::OscEscape,
+                _ => {}
+            },
+            State::OscEscape => {
+                self.state = if c == '\\' { State::Text } else { State::Osc };
+            }
+        }
+    }
+
+    /// Carries out the control sequences that change the text
+    fn control_sequence(&mut self, command: char) {
+        let param = self.params.trim_start_matches('?').parse::<usize>().ok();
+        let line = self.lines.back_mut().expect("screen has a line");
+        match command {
+            // Erase in line
+            'K' => match param.unwrap_or(0) {
+                0 => line.truncate(self.column),
+                1 => line.iter_mut().take(self.column + 1).for_each(|c| *c = ' '),
+                _ => line.clear(),
+            },
+            // Erase in display: only clearing everything is followed
+            'J' if param.unwrap_or(0) >= 2 => self.clear(),
+            // Cursor forward and back
+            'C' => self.column += param.unwrap_or(1).max(1),
+            'D' => self.column = self.column.saturating_sub(param.unwrap_or(1).max(1)),
+            // Cursor to column
+            'G' => self.column = param.unwrap_or(1).max(1) - 1,
+            // Cursor home
+            'H' if self.params.is_empty() => self.column = 0,
+            _ => {}
+        }
+    }
+
+    fn put(&mut self, c: char) {
+        let line = self.lines.back_mut().expect("screen has a line");
+        if line.len() < self.column {
+            line.resize(self.column, ' ');
+        }
+        if self.column < line.len() {
+            line[self.column] = c;
+        } else {
+            line.push(c);
+        }
+        self.column += 1;
+    }
+
+    fn new_line(&mut self) {
+        if self.lines.len() == MAX_SCREEN_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(Vec::new());
+        self.column = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(data: &[u8]) -> TerminalScreen {
+        let mut screen = TerminalScreen::new();
+        screen.feed(data);
+        screen
+    }
+
+    #[test]
+    fn test_lines_and_line_editing() {
+        assert_eq!(screen(b"one\r\ntwo").text(), "one\ntwo");
+        assert_eq!(screen(b"abc\rx").text(), "xbc");
+        assert_eq!(screen(b"abc\x08\x08\x1b[K").text(), "a");
+        assert_eq!(screen(b"a\tb").text(), "a       b");
+        assert_eq!(screen(b"old\x1b[2Jnew").text(), "new");
+    }
+
+    #[test]
+    fn test_escape_sequences_are_dropped() {
+        let colored = b"\x1b]0;user@host: ~\x07\x1b[1;32m$\x1b[0m ls";
+        assert_eq!(screen(colored).text(), "$ ls");
+        assert_eq!(screen(b"\x1b]2;title\x1b\\ok").text(), "ok");
+    }
+
+    #[test]
+    fn test_split_utf8() {
+        let mut screen = TerminalScreen::new();
+        let text = "café ✓".as_bytes();
+        screen.feed(&text[..4]);
+        screen.feed(&text[4..text.len() - 1]);
+        screen.feed(&text[text.len() - 1..]);
+        assert_eq!(screen.text(), "café ✓");
+
+        screen.clear();
+        screen.feed(b"a\xffb");
+        assert_eq!(screen.text(), "a\u{fffd}b");
+    }
+
+    #[test]
+    fn test_scrollback_is_bounded() {
+        let mut screen = TerminalScreen::new();
+        for i in 0..MAX_SCREEN_LINES + 10 {
+            screen.feed(format!("line {}\n", i).as_bytes());
+        }
+        assert_eq!(screen.lines().count(), MAX_SCREEN_LINES);
+        assert_eq!(screen.lines().next().unwrap(), "line 11");
+    }
+}
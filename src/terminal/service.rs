@@ -0,0 +1,580 @@
+//! Remote terminals over a session's terminal stream
+//!
+//! Both sides of a session run [`Terminals`]. The client opens terminals
+//! with [`Terminals::open`]; the host starts a shell in a pseudo-terminal
+//! for each, provided it was given a shell to run. Without one, every
+//! request is refused, which is how terminals stay off unless the host's
+//! configuration and the peer's permissions allow them.
+//!
+//! Each shell on the host has two threads: one copies its output to the
+//! stream and reports when it exits, the other writes keystrokes to it.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+use crate::error::{TerminalError, TerminalResult};
+use crate::session::transport::{ChannelPair, TerminalMessage};
+use crate::terminal::pty::Pty;
+
+/// Most terminals a peer may have open at once
+pub const MAX_TERMINALS: usize = 4;
+
+/// How long to wait for the host to start a shell
+pub const TERMINAL_OPEN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Capacity of a terminal's event channel
+pub const TERMINAL_EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Most shell output sent in one message
+const OUTPUT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Something that happened to a remote terminal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalEvent {
+    /// The shell wrote to the terminal
+    Output(Vec<u8>),
+    /// The terminal has ended, and why
+    Closed(String),
+}
+
+/// A shell this side is running for the peer
+struct Shell {
+    pty: Arc<Mutex<Pty>>,
+    /// Keystrokes for the writer thread
+    input: std::sync::mpsc::Sender<Vec<u8>>,
+}
+
+struct Inner {
+    tx: mpsc::Sender<TerminalMessage>,
+    /// Shell to run for the peer, if it may open terminals
+    shell: Option<PathBuf>,
+    /// Shells running for the peer
+    shells: Mutex<HashMap<u32, Shell>>,
+    /// Terminals we asked for that the peer hasn't answered yet
+    pending: Mutex<HashMap<u32, oneshot::Sender<Result<(), String>>>>,
+    /// Event channels of our open terminals
+    open: Mutex<HashMap<u32, mpsc::Sender<TerminalEvent>>>,
+}
+
+/// Remote terminal service for a session
+pub struct Terminals {
+    inner: Arc<Inner>,
+    next_id: AtomicU32,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Terminals {
+    /// Starts serving the session's terminal stream
+    ///
+    /// The peer may open terminals running `shell`; pass None to refuse
+    /// every request.
+    pub fn start(channel: ChannelPair<TerminalMessage>, shell: Option<PathBuf>) -> Self {
+        let ChannelPair { tx, mut rx } = channel;
+        let inner = Arc::new(Inner {
+            tx,
+            shell,
+            shells: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            open: Mutex::new(HashMap::new()),
+        });
+
+        let task_inner = Arc::clone(&inner);
+        let task = tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                handle_message(&task_inner, message).await;
+            }
+            debug!("Terminal channel closed");
+            // Dropping the senders fails waiting opens and ends our terminals
+            lock(&task_inner.pending).clear();
+            lock(&task_inner.open).clear();
+            kill_shells(&task_inner);
+        });
+
+        Self {
+            inner,
+            next_id: AtomicU32::new(1),
+            task,
+        }
+    }
+
+    /// Returns true if the peer may open terminals on this side
+    pub fn serves_shell(&self) -> bool {
+        self.inner.shell.is_some()
+    }
+
+    /// Returns the number of shells running for the peer
+    pub fn running_shells(&self) -> usize {
+        lock(&self.inner.shells).len()
+    }
+
+    /// Opens a terminal on the peer with the given size
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the peer refuses or doesn't answer in time
+    pub async fn open(&self, cols: u16, rows: u16) -> TerminalResult<RemoteTerminal> {
+        let terminal_id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (opened_tx, opened_rx) = oneshot::channel();
+        lock(&self.inner.pending).insert(terminal_id, opened_tx);
+        // Output may follow the shell's start before we see the answer
+        let (events_tx, events_rx) = mpsc::channel(TERMINAL_EVENT_CHANNEL_CAPACITY);
+        lock(&self.inner.open).insert(terminal_id, events_tx);
+
+        let forget = || {
+            lock(&self.inner.pending).remove(&terminal_id);
+            lock(&self.inner.open).remove(&terminal_id);
+        };
+
+        let request = TerminalMessage::Open {
+            terminal_id,
+            cols,
+            rows,
+        };
+        if let Err(e) = send(&self.inner, request).await {
+            forget();
+            return Err(e);
+        }
+
+        let result = match tokio::time::timeout(TERMINAL_OPEN_TIMEOUT, opened_rx).await {
+            Ok(Ok(Ok(()))) => Ok(()),
+            Ok(Ok(Err(reason))) => Err(TerminalError::Remote(reason)),
+            Ok(Err(_)) => Err(TerminalError::ChannelClosed),
+            Err(_) => Err(TerminalError::Timeout),
+        };
+        if let Err(e) = result {
+            forget();
+            return Err(e);
+        }
+
+        Ok(RemoteTerminal {
+            terminal_id,
+            input: TerminalInput {
+                terminal_id,
+                tx: self.inner.tx.clone(),
+            },
+            inner: Arc::clone(&self.inner),
+            events: events_rx,
+        })
+    }
+}
+
+impl Drop for Terminals {
+    fn drop(&mut self) {
+        self.task.abort();
+        kill_shells(&self.inner);
+    }
+}
+
+/// Sends keystrokes and size changes to a remote terminal
+///
+/// Cheap to clone, so input can be sent from another task than the one
+/// reading the terminal's output.
+#[derive(Clone)]
+pub struct TerminalInput {
+    terminal_id: u32,
+    tx: mpsc::Sender<TerminalMessage>,
+}
+
+impl TerminalInput {
+    /// Sends keystrokes to the shell
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the session has ended
+    pub async fn write(&self, data: &[u8]) -> TerminalResult<()> {
+        self.send(TerminalMessage::Input {
+            terminal_id: self.terminal_id,
+            data: data.to_vec(),
+        })
+        .await
+    }
+
+    /// Tells the shell the terminal has a new size
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the session has ended
+    pub async fn resize(&self, cols: u16, rows: u16) -> TerminalResult<()> {
+        self.send(TerminalMessage::Resize {
+            terminal_id: self.terminal_id,
+            cols,
+            rows,
+        })
+        .await
+    }
+
+    async fn send(&self, message: TerminalMessage) -> TerminalResult<()> {
+        self.tx
+            .send(message)
+            .await
+            .map_err(|_| TerminalError::ChannelClosed)
+    }
+}
+
+/// A terminal open on the peer
+///
+/// The shell is ended when this is dropped.
+pub struct RemoteTerminal {
+    terminal_id: u32,
+    input: TerminalInput,
+    inner: Arc<Inner>,
+    events: mpsc::Receiver<TerminalEvent>,
+}
+
+impl RemoteTerminal {
+    /// Returns the terminal's ID
+    pub fn id(&self) -> u32 {
+        self.terminal_id
+    }
+
+    /// Returns a handle for sending input to the terminal
+    pub fn input(&self) -> TerminalInput {
+        self.input.clone()
+    }
+
+    /// Sends keystrokes to the shell
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the session has ended
+    pub async fn write(&self, data: &[u8]) -> TerminalResult<()> {
+        self.input.write(data).await
+    }
+
+    /// Tells the shell the terminal has a new size
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the session has ended
+    pub async fn resize(&self, cols: u16, rows: u16) -> TerminalResult<()> {
+        self.input.resize(cols, rows).await
+    }
+
+    /// Waits for the next output or for the terminal to end
+    ///
+    /// Returns None after the terminal has ended.
+    pub async fn recv(&mut self) -> Option<TerminalEvent> {
+        self.events.recv().await
+    }
+
+    /// Returns the next output or end of the terminal, if there is one
+    pub fn try_recv(&mut self) -> Option<TerminalEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+impl Drop for RemoteTerminal {
+    fn drop(&mut self) {
+        // Nothing to close if the peer has already ended it
+        if lock(&self.inner.open).remove(&self.terminal_id).is_some() {
+            let close = TerminalMessage::Close {
+                terminal_id: self.terminal_id,
+            };
+            if self.inner.tx.try_send(close).is_err() {
+                debug!("Couldn't close terminal {}", self.terminal_id);
+            }
+        }
+    }
+}
+
+/// Handles one message from the peer
+async fn handle_message(inner: &Arc<Inner>, message: TerminalMessage) {
+    match message {
+        TerminalMessage::Open {
+            terminal_id,
+            cols,
+            rows,
+        } => {
+            let response = match open_shell(inner, terminal_id, cols, rows) {
+                Ok(()) => TerminalMessage::Opened { terminal_id },
+                Err(e) => TerminalMessage::Closed {
+                    terminal_id,
+                    reason: e.to_string(),
+                },
+            };
+            let _ = send(inner, response).await;
+        }
+        TerminalMessage::Input { terminal_id, data } => {
+            match lock(&inner.shells).get(&terminal_id) {
+                Some(shell) => {
+                    let _ = shell.input.send(data);
+                }
+                None => debug!("Input for unknown terminal {}", terminal_id),
+            }
+        }
+        TerminalMessage::Resize {
+            terminal_id,
+            cols,
+            rows,
+        } => {
+            let pty = lock(&inner.shells)
+                .get(&terminal_id)
+                .map(|shell| Arc::clone(&shell.pty));
+            if let Some(pty) = pty {
+                if let Err(e) = lock(&pty).resize(cols, rows) {
+                    warn!("Failed to resize terminal {}: {}", terminal_id, e);
+                }
+            }
+        }
+        TerminalMessage::Close { terminal_id } => {
+            let pty = lock(&inner.shells)
+                .get(&terminal_id)
+                .map(|shell| Arc::clone(&shell.pty));
+            // The output thread reports the shell's end
+            if let Some(pty) = pty {
+                lock(&pty).kill();
+            }
+        }
+        TerminalMessage::Opened { terminal_id } => {
+            if let Some(waiting) = lock(&inner.pending).remove(&terminal_id) {
+                let _ = waiting.send(Ok(()));
+            }
+        }
+        TerminalMessage::Output { terminal_id, data } => {
+            let events = lock(&inner.open).get(&terminal_id).cloned();
+            match events {
+                // Waiting here holds up the peer rather than dropping output
+                Some(events) => {
+                    let _ = events.send(TerminalEvent::Output(data)).await;
+                }
+                None => debug!("Output for unknown terminal {}", terminal_id),
+            }
+        }
+        TerminalMessage::Closed {
+            terminal_id,
+            reason,
+        } => {
+            if let Some(waiting) = lock(&inner.pending).remove(&terminal_id) {
+                lock(&inner.open).remove(&terminal_id);
+                let _ = waiting.send(Err(reason));
+                return;
+            }
+            let events = lock(&inner.open).remove(&terminal_id);
+            if let Some(events) = events {
+                let _ = events.send(TerminalEvent::Closed(reason)).await;
+            }
+        }
+    }
+}
+
+/// Starts a shell for the peer
+fn open_shell(inner: &Arc<Inner>, terminal_id: u32, cols: u16, rows: u16) -> TerminalResult<()> {
+    let shell_path = inner.shell.as_deref().ok_or(TerminalError::Disabled)?;
+
+    let mut shells = lock(&inner.shells);
+    if shells.len() >= MAX_TERMINALS {
+        return Err(TerminalError::TooManyTerminals(MAX_TERMINALS));
+    }
+    if shells.contains_key(&terminal_id) {
+        return Err(TerminalError::Remote(format!(
+            "terminal {} is already open",
+            terminal_id
+        )));
+    }
+
+    let pty = Pty::spawn(shell_path, cols, rows)?;
+    let reader = pty.reader()?;
+    let writer = pty.writer()?;
+    info!(
+        "Started {} for terminal {} (pid {})",
+        shell_path.display(),
+        terminal_id,
+        pty.pid()
+    );
+
+    let pty = Arc::new(Mutex::new(pty));
+    let (input_tx, input_rx) = std::sync::mpsc::channel();
+    shells.insert(
+        terminal_id,
+        Shell {
+            pty: Arc::clone(&pty),
+            input: input_tx,
+        },
+    );
+    drop(shells);
+
+    std::thread::spawn(move || write_input(writer, input_rx));
+    let inner = Arc::clone(inner);
+    std::thread::spawn(move || copy_output(inner, terminal_id, reader, pty));
+    Ok(())
+}
+
+/// Writes keystrokes to a shell until it is removed
+fn write_input(mut writer: File, input: std::sync::mpsc::Receiver<Vec<u8>>) {
+    for data in input {
+        if writer.write_all(&data).is_err() {
+            break;
+        }
+    }
+}
+
+/// Sends a shell's output to the peer, then reports how it ended
+fn copy_output(inner: Arc<Inner>, terminal_id: u32, mut reader: File, pty: Arc<Mutex<Pty>>) {
+    let mut buffer = vec![0u8; OUTPUT_CHUNK_SIZE];
+    loop {
+        // Fails once nothing has the terminal open any more
+        let read = match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        let output = TerminalMessage::Output {
+            terminal_id,
+            data: buffer[..read].to_vec(),
+        };
+        if inner.tx.blocking_send(output).is_err() {
+            break;
+        }
+    }
+
+    // Stop the shell in case the session ended first
+    let status = {
+        let mut pty = lock(&pty);
+        pty.kill();
+        pty.wait()
+    };
+    let reason = match status {
+        Ok(status) => match status.code() {
+            Some(code) => format!("Shell exited with status {}", code),
+            None => "Shell was terminated".to_string(),
+        },
+        Err(e) => format!("Shell ended: {}", e),
+    };
+    info!("Terminal {} closed: {}", terminal_id, reason);
+
+    // Dropping the input sender ends the writer thread
+    lock(&inner.shells).remove(&terminal_id);
+    let _ = inner.tx.blocking_send(TerminalMessage::Closed {
+        terminal_id,
+        reason,
+    });
+}
+
+/// Ends every shell running for the peer
+fn kill_shells(inner: &Inner) {
+    let shells: Vec<_> = lock(&inner.shells)
+        .values()
+        .map(|shell| Arc::clone(&shell.pty))
+        .collect();
+    for pty in shells {
+        lock(&pty).kill();
+    }
+}
+
+/// Returns the shell that would be run for `configured`
+pub fn resolve_shell(configured: Option<&Path>) -> PathBuf {
+    configured
+        .map(Path::to_path_buf)
+        .unwrap_or_else(crate::terminal::pty::default_shell)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+async fn send(inner: &Inner, message: TerminalMessage) -> TerminalResult<()> {
+    inner
+        .tx
+        .send(message)
+        .await
+        .map_err(|_| TerminalError::ChannelClosed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::transport::create_loopback_transport;
+
+    /// Collects output until `needle` shows up or the terminal ends
+    async fn read_until(terminal: &mut RemoteTerminal, needle: &str) -> (String, Option<String>) {
+        let mut output = String::new();
+        while !output.contains(needle) {
+            let event = tokio::time::timeout(Duration::from_secs(10), terminal.recv())
+                .await
+                .expect("terminal output");
+            match event {
+                Some(TerminalEvent::Output(data)) => {
+                    output.push_str(&String::from_utf8_lossy(&data))
+                }
+                Some(TerminalEvent::Closed(reason)) => return (output, Some(reason)),
+                None => break,
+            }
+        }
+        (output, None)
+    }
+
+    #[tokio::test]
+    async fn test_terminals_disabled() {
+        let (host, client) = create_loopback_transport();
+        let host = Terminals::start(host.terminal, None);
+        let client = Terminals::start(client.terminal, None);
+        assert!(!host.serves_shell());
+
+        match client.open(80, 24).await {
+            Err(TerminalError::Remote(reason)) => {
+                assert_eq!(reason, TerminalError::Disabled.to_string())
+            }
+            other => panic!("expected refusal, got {:?}", other.map(|t| t.id())),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remote_shell() {
+        let (host, client) = create_loopback_transport();
+        let host = Terminals::start(host.terminal, Some(PathBuf::from("/bin/sh")));
+        let client = Terminals::start(client.terminal, None);
+
+        let mut terminal = client.open(80, 24).await.unwrap();
+        assert_eq!(host.running_shells(), 1);
+
+        terminal.write(b"stty size; echo do''ne\n").await.unwrap();
+        let (output, closed) = read_until(&mut terminal, "done").await;
+        assert!(closed.is_none());
+        assert!(output.contains("24 80"), "{:?}", output);
+
+        terminal.resize(132, 43).await.unwrap();
+        terminal.write(b"stty size; echo ag''ain\n").await.unwrap();
+        let (output, _) = read_until(&mut terminal, "again").await;
+        assert!(output.contains("43 132"), "{:?}", output);
+
+        terminal.write(b"exit 7\n").await.unwrap();
+        let (_, closed) = read_until(&mut terminal, "\u{0}never").await;
+        assert_eq!(closed.as_deref(), Some("Shell exited with status 7"));
+        assert_eq!(host.running_shells(), 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_terminal_limit_and_close() {
+        let (host, client) = create_loopback_transport();
+        let host = Terminals::start(host.terminal, Some(PathBuf::from("/bin/sh")));
+        let client = Terminals::start(client.terminal, None);
+
+        let mut terminals = Vec::new();
+        for _ in 0..MAX_TERMINALS {
+            terminals.push(client.open(80, 24).await.unwrap());
+        }
+        assert!(matches!(
+            client.open(80, 24).await,
+            Err(TerminalError::Remote(_))
+        ));
+
+        // Dropping a terminal ends its shell
+        terminals.clear();
+        for _ in 0..100 {
+            if host.running_shells() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(host.running_shells(), 0);
+    }
+}
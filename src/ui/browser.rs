@@ -0,0 +1,254 @@
+//! File browser panel for the viewer window
+//!
+//! Lists the directories the host shares and downloads files from them into
+//! the local download directory. Requests run on the session's Tokio
+//! runtime and their results come back over a channel, so the window never
+//! waits on the network.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use eframe::egui;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::error::FileTransferResult;
+use crate::files::browse::{join_remote_path, parent_remote_path};
+use crate::files::{FileBrowser, FileTransfers};
+use crate::session::transport::RemoteEntry;
+
+/// Outcome of a request made by the panel
+enum BrowseResult {
+    /// A directory was listed
+    Listed {
+        path: String,
+        result: FileTransferResult<Vec<RemoteEntry>>,
+    },
+    /// A file was downloaded
+    Downloaded {
+        path: String,
+        result: FileTransferResult<PathBuf>,
+    },
+}
+
+/// What the user clicked while the panel was drawn
+enum PanelAction {
+    Open(String),
+    Download(String),
+    Refresh,
+}
+
+/// Side panel for browsing and downloading the host's files
+pub struct BrowsePanel {
+    browser: Arc<FileBrowser>,
+    transfers: Arc<FileTransfers>,
+    /// Runtime the session's services run on
+    runtime: tokio::runtime::Handle,
+    /// Whether the panel is shown
+    open: bool,
+    /// Remote directory being shown
+    path: String,
+    entries: Vec<RemoteEntry>,
+    /// Whether a listing is on its way
+    loading: bool,
+    /// Last error or finished download, shown under the listing
+    status: Option<String>,
+    results_tx: mpsc::UnboundedSender<BrowseResult>,
+    results_rx: mpsc::UnboundedReceiver<BrowseResult>,
+}
+
+impl BrowsePanel {
+    /// Creates a closed panel showing the host's shared directories
+    ///
+    /// Must be called from within the Tokio runtime the session runs on.
+    pub fn new(browser: Arc<FileBrowser>, transfers: Arc<FileTransfers>) -> Self {
+        let (results_tx, results_rx) = mpsc::unbounded_channel();
+        Self {
+            browser,
+            transfers,
+            runtime: tokio::runtime::Handle::current(),
+            open: false,
+            path: "/".to_string(),
+            entries: Vec::new(),
+            loading: false,
+            status: None,
+            results_tx,
+            results_rx,
+        }
+    }
+
+    /// Returns whether the panel is shown
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Shows or hides the panel, listing the current directory on opening
+    pub fn set_open(&mut self, open: bool) {
+        if open && !self.open {
+            self.refresh();
+        }
+        self.open = open;
+    }
+
+    /// Returns the remote directory being shown
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Shows a remote directory
+    pub fn navigate(&mut self, path: String) {
+        self.path = path;
+        self.entries.clear();
+        self.refresh();
+    }
+
+    /// Lists the current directory again
+    pub fn refresh(&mut self) {
+        self.loading = true;
+        self.status = None;
+
+        let browser = Arc::clone(&self.browser);
+        let results_tx = self.results_tx.clone();
+        let path = self.path.clone();
+        self.runtime.spawn(async move {
+            let result = browser.list(&path).await;
+            let _ = results_tx.send(BrowseResult::Listed { path, result });
+        });
+    }
+
+    /// Downloads a remote file into the download directory
+    pub fn download(&mut self, path: String) {
+        self.status = Some(format!("Downloading {}...", path));
+
+        let transfers = Arc::clone(&self.transfers);
+        let results_tx = self.results_tx.clone();
+        self.runtime.spawn(async move {
+            let dir = transfers.download_dir().to_path_buf();
+            let result = transfers.fetch_remote_file(&path, &dir).await;
+            let _ = results_tx.send(BrowseResult::Downloaded { path, result });
+        });
+    }
+
+    /// Applies the results of finished requests
+    fn process_results(&mut self) {
+        while let Ok(result) = self.results_rx.try_recv() {
+            match result {
+                BrowseResult::Listed { path, result } if path == self.path => {
+                    self.loading = false;
+                    match result {
+                        Ok(entries) => self.entries = entries,
+                        Err(e) => self.status = Some(format!("Can't list {}: {}", path, e)),
+                    }
+                }
+                // The user has moved on to another directory
+                BrowseResult::Listed { .. } => {}
+                BrowseResult::Downloaded { path, result } => match result {
+                    Ok(saved) => {
+                        info!("Downloaded {} to {}", path, saved.display());
+                        self.status = Some(format!("Saved {}", saved.display()));
+                    }
+                    Err(e) => {
+                        warn!("Failed to download {}: {}", path, e);
+                        self.status = Some(format!("Can't download {}: {}", path, e));
+                    }
+                },
+            }
+        }
+    }
+
+    /// Shows the panel if it is open
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.process_results();
+        if !self.open {
+            return;
+        }
+
+        let mut action: Option<PanelAction> = None;
+
+        egui::SidePanel::right("file_browser")
+            .resizable(true)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let up = ui.add_enabled(self.path != "/", egui::Button::new("Up"));
+                    if up.clicked() {
+                        action = Some(PanelAction::Open(parent_remote_path(&self.path)));
+                    }
+                    if ui.button("Refresh").clicked() {
+                        action = Some(PanelAction::Refresh);
+                    }
+                    ui.label(&self.path);
+                });
+                ui.separator();
+
+                if self.loading {
+                    ui.spinner();
+                }
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, true])
+                    .max_height(ui.available_height() - 24.0)
+                    .show(ui, |ui| {
+                        for entry in &self.entries {
+                            let path = join_remote_path(&self.path, &entry.name);
+                            if entry.is_dir {
+                                if ui.link(format!("{}/", entry.name)).clicked() {
+                                    action = Some(PanelAction::Open(path));
+                                }
+                                continue;
+                            }
+
+                            ui.horizontal(|ui| {
+                                if ui.small_button("Download").clicked() {
+                                    action = Some(PanelAction::Download(path));
+                                }
+                                ui.label(&entry.name);
+                                ui.weak(format_size(entry.size));
+                            });
+                        }
+                    });
+
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.label(status);
+                }
+            });
+
+        match action {
+            Some(PanelAction::Open(path)) => self.navigate(path),
+            Some(PanelAction::Download(path)) => self.download(path),
+            Some(PanelAction::Refresh) => self.refresh(),
+            None => {}
+        }
+    }
+}
+
+/// Formats a file size in human-readable form
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
@@ -0,0 +1,227 @@
+//! Chat panel and host chat window
+//!
+//! [`ChatPanel`] shows a session's chat history with a box to type into.
+//! The viewer embeds it in a panel; on the host it runs in a small window of
+//! its own with [`run_chat_window`].
+
+use std::sync::Arc;
+
+use eframe::egui;
+use tokio::sync::broadcast::error::TryRecvError;
+use tokio::sync::{broadcast, mpsc};
+use tracing::warn;
+
+use crate::session::chat::{ChatEntry, ChatService};
+
+/// Chat history and message box for a session
+pub struct ChatPanel {
+    chat: Arc<ChatService>,
+    /// Runtime the session's chat service runs on
+    runtime: tokio::runtime::Handle,
+    entries: Vec<ChatEntry>,
+    events: broadcast::Receiver<ChatEntry>,
+    /// Message being typed
+    draft: String,
+    /// Messages received since the panel was last read
+    unread: usize,
+    /// Why the last message couldn't be sent
+    error: Option<String>,
+    errors_tx: mpsc::UnboundedSender<String>,
+    errors_rx: mpsc::UnboundedReceiver<String>,
+}
+
+impl ChatPanel {
+    /// Creates a panel showing the session's chat so far
+    ///
+    /// Must be called from within the Tokio runtime the session runs on.
+    pub fn new(chat: Arc<ChatService>) -> Self {
+        let events = chat.subscribe();
+        let (errors_tx, errors_rx) = mpsc::unbounded_channel();
+        Self {
+            entries: chat.history(),
+            chat,
+            runtime: tokio::runtime::Handle::current(),
+            events,
+            draft: String::new(),
+            unread: 0,
+            error: None,
+            errors_tx,
+            errors_rx,
+        }
+    }
+
+    /// Returns the name of the peer being chatted with
+    pub fn peer_name(&self) -> &str {
+        self.chat.peer_name()
+    }
+
+    /// Returns the number of messages received since the panel was read
+    pub fn unread(&self) -> usize {
+        self.unread
+    }
+
+    /// Marks every message as read
+    pub fn mark_read(&mut self) {
+        self.unread = 0;
+    }
+
+    /// Picks up sent and received messages
+    pub fn poll(&mut self) {
+        loop {
+            match self.events.try_recv() {
+                Ok(entry) => {
+                    if !entry.outgoing {
+                        self.unread += 1;
+                    }
+                    self.entries.push(entry);
+                }
+                Err(TryRecvError::Lagged(_)) => self.entries = self.chat.history(),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+            }
+        }
+
+        while let Ok(error) = self.errors_rx.try_recv() {
+            self.error = Some(error);
+        }
+    }
+
+    /// Sends the typed message
+    fn send_draft(&mut self) {
+        let text = std::mem::take(&mut self.draft);
+        if text.trim().is_empty() {
+            return;
+        }
+        self.error = None;
+
+        let chat = Arc::clone(&self.chat);
+        let errors_tx = self.errors_tx.clone();
+        self.runtime.spawn(async move {
+            if let Err(e) = chat.send(&text).await {
+                warn!("Failed to send chat message: {}", e);
+                let _ = errors_tx.send(e.to_string());
+            }
+        });
+    }
+
+    /// Draws the history and message box
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let input_height = ui.spacing().interact_size.y * 2.0;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .max_height((ui.available_height() - input_height).max(0.0))
+            .show(ui, |ui| {
+                for entry in &self.entries {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.weak(format_time(entry.timestamp_ms));
+                        let sender = egui::RichText::new(format!("{}:", entry.sender)).strong();
+                        ui.label(if entry.outgoing {
+                            sender
+                        } else {
+                            sender.color(ui.visuals().hyperlink_color)
+                        });
+                        ui.label(&entry.text);
+                    });
+                }
+            });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        ui.horizontal(|ui| {
+            let send_width = 60.0;
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.draft)
+                    .hint_text(format!("Message {}", self.chat.peer_name()))
+                    .desired_width(ui.available_width() - send_width),
+            );
+            let entered =
+                response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            if ui.button("Send").clicked() || entered {
+                self.send_draft();
+                response.request_focus();
+            }
+        });
+    }
+}
+
+/// Formats a message time as `HH:MM` UTC
+fn format_time(timestamp_ms: u64) -> String {
+    let minutes = timestamp_ms / 1000 / 60;
+    format!("{:02}:{:02}", (minutes / 60) % 24, minutes % 60)
+}
+
+/// Runs a standalone chat window for a host session (blocking)
+///
+/// Must be called from within the Tokio runtime the session runs on.
+pub fn run_chat_window(chat: Arc<ChatService>) -> Result<(), eframe::Error> {
+    let title = format!("Chat with {}", chat.peer_name());
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([360.0, 420.0])
+            .with_title(&title),
+        ..Default::default()
+    };
+    let panel = ChatPanel::new(chat);
+
+    eframe::run_native(
+        &title,
+        options,
+        Box::new(move |_cc| Box::new(ChatWindowApp { panel })),
+    )
+}
+
+/// Standalone app for the host chat window
+struct ChatWindowApp {
+    panel: ChatPanel,
+}
+
+impl eframe::App for ChatWindowApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.panel.poll();
+        self.panel.mark_read();
+
+        egui::CentralPanel::default().show(ctx, |ui| self.panel.ui(ui));
+
+        // Messages arrive without any input to wake the window
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "00:00");
+        // 2023-11-14 22:13:20 UTC
+        assert_eq!(format_time(1_700_000_000_000), "22:13");
+    }
+
+    #[tokio::test]
+    async fn test_panel_counts_unread_messages() {
+        let (host, client) = crate::session::transport::create_loopback_transport();
+        let host_chat = Arc::new(ChatService::start(
+            host.chat,
+            "office-pc".to_string(),
+            "laptop".to_string(),
+        ));
+        let client_chat =
+            ChatService::start(client.chat, "laptop".to_string(), "office-pc".to_string());
+        let mut received = host_chat.subscribe();
+        let mut panel = ChatPanel::new(Arc::clone(&host_chat));
+
+        client_chat.send("hi").await.unwrap();
+        received.recv().await.unwrap();
+        host_chat.send("hello").await.unwrap();
+
+        panel.poll();
+        assert_eq!(panel.entries.len(), 2);
+        assert_eq!(panel.unread(), 1);
+        panel.mark_read();
+        assert_eq!(panel.unread(), 0);
+    }
+}
@@ -3,12 +3,18 @@
 //! This module handles the system tray icon, dialogs, and user interface.
 
 pub mod app;
+pub mod browser;
+pub mod chat;
 pub mod dialogs;
 pub mod overlay;
+pub mod terminal;
 pub mod tray;
 pub mod viewer;
 
 pub use app::{App, AppCommand};
+pub use browser::BrowsePanel;
+pub use chat::{run_chat_window, ChatPanel};
 pub use overlay::{OverlayConfig, OverlayPosition, StatusOverlay};
+pub use terminal::TerminalPanel;
 pub use tray::TrayIcon;
 pub use viewer::{ViewerConfig, ViewerStats, ViewerWindow};
@@ -1,11 +1,19 @@
 //! Status overlay for the viewer window
 //!
-//! Displays FPS, latency, bandwidth, and other statistics.
+//! Displays FPS, latency, bandwidth, and other statistics, followed by the
+//! progress of each file being sent.
 
 use eframe::egui;
 
+use crate::files::{TransferInfo, TransferStatus};
 use crate::ui::viewer::ViewerStats;
 
+/// Longest file name shown before it is shortened
+const MAX_NAME_CHARS: usize = 24;
+
+/// Height of a transfer progress bar
+const PROGRESS_BAR_HEIGHT: f32 = 4.0;
+
 /// Position for the overlay
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum OverlayPosition {
@@ -49,12 +57,17 @@ impl Default for OverlayConfig {
 pub struct StatusOverlay {
     /// Overlay configuration
     config: OverlayConfig,
+    /// File transfers in progress, oldest first
+    transfers: Vec<TransferInfo>,
 }
 
 impl StatusOverlay {
     /// Creates a new status overlay
     pub fn new(config: OverlayConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            transfers: Vec::new(),
+        }
     }
 
     /// Sets visibility
@@ -72,6 +85,28 @@ impl StatusOverlay {
         self.config.position = position;
     }
 
+    /// Adds a file transfer or updates its progress
+    pub fn set_transfer(&mut self, info: TransferInfo) {
+        match self
+            .transfers
+            .iter_mut()
+            .find(|t| t.transfer_id == info.transfer_id)
+        {
+            Some(existing) => *existing = info,
+            None => self.transfers.push(info),
+        }
+    }
+
+    /// Stops showing a file transfer
+    pub fn remove_transfer(&mut self, transfer_id: u64) {
+        self.transfers.retain(|t| t.transfer_id != transfer_id);
+    }
+
+    /// Returns the file transfers shown
+    pub fn transfers(&self) -> &[TransferInfo] {
+        &self.transfers
+    }
+
     /// Shows the overlay
     pub fn show(&self, ui: &mut egui::Ui, stats: &ViewerStats) {
         if !self.config.visible {
@@ -80,8 +115,10 @@ impl StatusOverlay {
 
         let available = ui.available_rect_before_wrap();
         let padding = 10.0;
-        let overlay_width = 150.0;
-        let overlay_height = 100.0;
+        let line_height = self.config.font_size + 4.0;
+        let transfer_height = line_height + PROGRESS_BAR_HEIGHT + 4.0;
+        let overlay_width = if self.transfers.is_empty() { 150.0 } else { 220.0 };
+        let overlay_height = 100.0 + self.transfers.len() as f32 * transfer_height;
 
         // Calculate position based on setting
         let pos = match self.config.position {
@@ -115,7 +152,6 @@ impl StatusOverlay {
 
         // Draw stats text
         let text_color = egui::Color32::WHITE;
-        let line_height = self.config.font_size + 4.0;
         let mut y = pos.y + 8.0;
 
         // FPS
@@ -177,6 +213,55 @@ impl StatusOverlay {
             egui::FontId::proportional(self.config.font_size),
             text_color,
         );
+        y += line_height;
+
+        // File transfers
+        let bar_width = overlay_width - 16.0;
+        for transfer in &self.transfers {
+            ui.painter().text(
+                egui::pos2(pos.x + 8.0, y),
+                egui::Align2::LEFT_TOP,
+                format_transfer(transfer),
+                egui::FontId::proportional(self.config.font_size),
+                text_color,
+            );
+            y += line_height;
+
+            let bar = egui::Rect::from_min_size(
+                egui::pos2(pos.x + 8.0, y),
+                egui::vec2(bar_width, PROGRESS_BAR_HEIGHT),
+            );
+            ui.painter()
+                .rect_filled(bar, egui::Rounding::same(2.0), egui::Color32::DARK_GRAY);
+            let mut done = bar;
+            done.set_width(bar_width * transfer_fraction(transfer));
+            ui.painter()
+                .rect_filled(done, egui::Rounding::same(2.0), egui::Color32::LIGHT_BLUE);
+            y += PROGRESS_BAR_HEIGHT + 4.0;
+        }
+    }
+}
+
+/// Returns how much of a transfer is done, from 0.0 to 1.0
+fn transfer_fraction(transfer: &TransferInfo) -> f32 {
+    if transfer.size == 0 {
+        return 0.0;
+    }
+    (transfer.transferred as f64 / transfer.size as f64).min(1.0) as f32
+}
+
+/// Formats a transfer's name and progress for the overlay
+fn format_transfer(transfer: &TransferInfo) -> String {
+    let name = if transfer.name.chars().count() > MAX_NAME_CHARS {
+        let short: String = transfer.name.chars().take(MAX_NAME_CHARS - 3).collect();
+        format!("{}...", short)
+    } else {
+        transfer.name.clone()
+    };
+
+    match transfer.status {
+        TransferStatus::Offered => format!("{} (waiting)", name),
+        _ => format!("{} {:.0}%", name, transfer_fraction(transfer) * 100.0),
     }
 }
 
@@ -196,6 +281,18 @@ fn format_bandwidth(bps: f64) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clipboard::TransferDirection;
+
+    fn transfer(transfer_id: u64, name: &str, transferred: u64) -> TransferInfo {
+        TransferInfo {
+            transfer_id,
+            direction: TransferDirection::Outgoing,
+            name: name.to_string(),
+            size: 200,
+            transferred,
+            status: TransferStatus::Active,
+        }
+    }
 
     #[test]
     fn test_format_bandwidth() {
@@ -220,4 +317,34 @@ mod tests {
         overlay.set_visible(false);
         assert!(!overlay.is_visible());
     }
+
+    #[test]
+    fn test_overlay_transfers() {
+        let mut overlay = StatusOverlay::default();
+        overlay.set_transfer(transfer(1, "a.txt", 0));
+        overlay.set_transfer(transfer(3, "b.txt", 0));
+        overlay.set_transfer(transfer(1, "a.txt", 100));
+
+        let progress: Vec<(u64, u64)> = overlay
+            .transfers()
+            .iter()
+            .map(|t| (t.transfer_id, t.transferred))
+            .collect();
+        assert_eq!(progress, vec![(1, 100), (3, 0)]);
+
+        overlay.remove_transfer(1);
+        assert_eq!(overlay.transfers().len(), 1);
+    }
+
+    #[test]
+    fn test_format_transfer() {
+        assert_eq!(format_transfer(&transfer(1, "a.txt", 50)), "a.txt 25%");
+
+        let long = transfer(2, "a-very-long-file-name-indeed.tar.gz", 200);
+        assert_eq!(format_transfer(&long), "a-very-long-file-name... 100%");
+
+        let mut offered = transfer(3, "a.txt", 0);
+        offered.status = TransferStatus::Offered;
+        assert_eq!(format_transfer(&offered), "a.txt (waiting)");
+    }
 }
@@ -0,0 +1,349 @@
+//! Terminal panel
+//!
+//! [`TerminalPanel`] runs a shell on the host and shows its output as
+//! text. Keys typed while the panel has focus go to the shell instead of
+//! the remote desktop, and the shell is told the panel's size in
+//! characters whenever it changes.
+
+use std::sync::Arc;
+
+use eframe::egui::{self, Key};
+use tokio::sync::mpsc;
+
+use crate::terminal::{TerminalEvent, TerminalScreen, Terminals};
+
+/// Fewest columns and rows a shell is given
+const MIN_COLS: u16 = 20;
+const MIN_ROWS: u16 = 5;
+
+/// Input for the task running the terminal
+enum Request {
+    Input(Vec<u8>),
+    Resize(u16, u16),
+}
+
+/// News from the task running the terminal
+enum Update {
+    Opened,
+    Event(TerminalEvent),
+    Failed(String),
+}
+
+/// State of the panel's shell
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Status {
+    /// No shell, with the reason the last one ended
+    Stopped(Option<String>),
+    Opening,
+    Running,
+}
+
+/// A shell on the host shown in a panel
+pub struct TerminalPanel {
+    terminals: Arc<Terminals>,
+    /// Runtime the session's terminal service runs on
+    runtime: tokio::runtime::Handle,
+    screen: TerminalScreen,
+    status: Status,
+    /// Input for the running shell
+    requests: Option<mpsc::UnboundedSender<Request>>,
+    /// Updates tagged with the shell they are about
+    updates_tx: mpsc::UnboundedSender<(u64, Update)>,
+    updates_rx: mpsc::UnboundedReceiver<(u64, Update)>,
+    /// Number of shells started, so updates about old ones are ignored
+    generation: u64,
+    /// Size the shell was last given
+    size: (u16, u16),
+    /// Whether typed keys go to the shell
+    focused: bool,
+}
+
+impl TerminalPanel {
+    /// Creates a panel for terminals on the peer
+    ///
+    /// Must be called from within the Tokio runtime the session runs on.
+    pub fn new(terminals: Arc<Terminals>) -> Self {
+        let (updates_tx, updates_rx) = mpsc::unbounded_channel();
+        Self {
+            terminals,
+            runtime: tokio::runtime::Handle::current(),
+            screen: TerminalScreen::new(),
+            status: Status::Stopped(None),
+            requests: None,
+            updates_tx,
+            updates_rx,
+            generation: 0,
+            size: (80, 24),
+            focused: false,
+        }
+    }
+
+    /// Returns true while a shell is starting or running
+    pub fn is_running(&self) -> bool {
+        !matches!(self.status, Status::Stopped(_))
+    }
+
+    /// Returns true if typed keys go to the shell
+    pub fn has_focus(&self) -> bool {
+        self.focused && self.is_running()
+    }
+
+    /// Opens a shell on the host, unless one is running
+    pub fn start(&mut self) {
+        if self.is_running() {
+            return;
+        }
+        self.screen.clear();
+        self.status = Status::Opening;
+        self.generation += 1;
+        let generation = self.generation;
+
+        let (requests_tx, mut requests_rx) = mpsc::unbounded_channel();
+        self.requests = Some(requests_tx);
+        let terminals = Arc::clone(&self.terminals);
+        let updates_tx = self.updates_tx.clone();
+        let report = move |update: Update| {
+            let _ = updates_tx.send((generation, update));
+        };
+        let (cols, rows) = self.size;
+
+        self.runtime.spawn(async move {
+            let mut terminal = match terminals.open(cols, rows).await {
+                Ok(terminal) => terminal,
+                Err(e) => {
+                    report(Update::Failed(e.to_string()));
+                    return;
+                }
+            };
+            report(Update::Opened);
+
+            // Ends when the panel stops the shell or the shell exits; either
+            // way dropping the terminal closes it on the host
+            loop {
+                tokio::select! {
+                    request = requests_rx.recv() => {
+                        let result = match request {
+                            Some(Request::Input(data)) => terminal.write(&data).await,
+                            Some(Request::Resize(cols, rows)) => terminal.resize(cols, rows).await,
+                            None => break,
+                        };
+                        if let Err(e) = result {
+                            report(Update::Failed(e.to_string()));
+                            break;
+                        }
+                    }
+                    event = terminal.recv() => match event {
+                        Some(event) => {
+                            let closed = matches!(event, TerminalEvent::Closed(_));
+                            report(Update::Event(event));
+                            if closed {
+                                break;
+                            }
+                        }
+                        None => {
+                            report(Update::Failed("Session ended".to_string()));
+                            break;
+                        }
+                    },
+                }
+            }
+        });
+    }
+
+    /// Ends the shell
+    pub fn stop(&mut self) {
+        if self.requests.take().is_some() {
+            self.status = Status::Stopped(Some("Closed".to_string()));
+        }
+    }
+
+    /// Picks up the shell's output
+    pub fn poll(&mut self) {
+        while let Ok((generation, update)) = self.updates_rx.try_recv() {
+            if generation != self.generation {
+                continue;
+            }
+            match update {
+                Update::Opened => self.status = Status::Running,
+                Update::Event(TerminalEvent::Output(data)) => self.screen.feed(&data),
+                Update::Event(TerminalEvent::Closed(reason)) | Update::Failed(reason) => {
+                    self.requests = None;
+                    self.status = Status::Stopped(Some(reason));
+                }
+            }
+        }
+    }
+
+    fn send(&self, request: Request) {
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(request);
+        }
+    }
+
+    /// Draws the shell's output and passes typed keys to it
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let mut start = false;
+        let mut stop = false;
+        ui.horizontal(|ui| match &self.status {
+            Status::Stopped(reason) => {
+                if let Some(reason) = reason {
+                    ui.label(reason);
+                }
+                start = ui.button("Open shell").clicked();
+            }
+            Status::Opening => {
+                ui.spinner();
+                ui.label("Opening shell...");
+            }
+            Status::Running => {
+                ui.label(if self.focused {
+                    "Typing goes to the shell"
+                } else {
+                    "Click the terminal to type"
+                });
+                stop = ui.button("Close").clicked();
+            }
+        });
+        if start {
+            self.start();
+        }
+        if stop {
+            self.stop();
+        }
+
+        self.resize_to(ui);
+
+        let output = egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::Label::new(egui::RichText::new(self.screen.text()).monospace())
+                        .wrap(true),
+                );
+            });
+
+        // Focus follows clicks in and out of the terminal
+        let clicked = ui.input(|input| input.pointer.any_pressed());
+        if clicked {
+            self.focused = ui.rect_contains_pointer(output.inner_rect);
+        }
+
+        if self.has_focus() {
+            let events = ui.input(|input| input.events.clone());
+            for event in events {
+                if let Some(data) = event_bytes(&event) {
+                    self.send(Request::Input(data));
+                }
+            }
+        }
+    }
+
+    /// Tells the shell the panel's size in characters, if it changed
+    fn resize_to(&mut self, ui: &egui::Ui) {
+        let font = egui::TextStyle::Monospace.resolve(ui.style());
+        let char_width = ui.fonts(|fonts| fonts.glyph_width(&font, 'M')).max(1.0);
+        let line_height = ui.text_style_height(&egui::TextStyle::Monospace).max(1.0);
+        let available = ui.available_size();
+
+        let cols = ((available.x / char_width) as u16).max(MIN_COLS);
+        let rows = ((available.y / line_height) as u16).max(MIN_ROWS);
+        if (cols, rows) != self.size {
+            self.size = (cols, rows);
+            if self.status == Status::Running {
+                self.send(Request::Resize(cols, rows));
+            }
+        }
+    }
+}
+
+/// Returns the bytes a terminal sends for an input event
+fn event_bytes(event: &egui::Event) -> Option<Vec<u8>> {
+    match event {
+        egui::Event::Text(text) | egui::Event::Paste(text) => Some(text.as_bytes().to_vec()),
+        // Ctrl+C and Ctrl+X arrive as clipboard commands
+        egui::Event::Copy => Some(vec![0x03]),
+        egui::Event::Cut => Some(vec![0x18]),
+        egui::Event::Key {
+            key,
+            pressed: true,
+            modifiers,
+            ..
+        } => key_bytes(*key, *modifiers),
+        _ => None,
+    }
+}
+
+/// Returns the bytes a terminal sends for a key that isn't typed as text
+fn key_bytes(key: Key, modifiers: egui::Modifiers) -> Option<Vec<u8>> {
+    let bytes: &[u8] = match key {
+        Key::Enter => b"\r",
+        Key::Backspace => b"\x7f",
+        Key::Tab => b"\t",
+        Key::Escape => b"\x1b",
+        Key::ArrowUp => b"\x1b[A",
+        Key::ArrowDown => b"\x1b[B",
+        Key::ArrowRight => b"\x1b[C",
+        Key::ArrowLeft => b"\x1b[D",
+        Key::Home => b"\x1b[H",
+        Key::End => b"\x1b[F",
+        Key::Insert => b"\x1b[2~",
+        Key::Delete => b"\x1b[3~",
+        Key::PageUp => b"\x1b[5~",
+        Key::PageDown => b"\x1b[6~",
+        _ if modifiers.ctrl => {
+            // Ctrl with a letter sends its control character
+            let name = key.name().as_bytes();
+            return match name {
+                [letter] if letter.is_ascii_uppercase() => Some(vec![letter & 0x1f]),
+                _ => None,
+            };
+        }
+        _ => return None,
+    };
+    Some(bytes.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_bytes() {
+        let none = egui::Modifiers::NONE;
+        assert_eq!(key_bytes(Key::Enter, none), Some(b"\r".to_vec()));
+        assert_eq!(key_bytes(Key::ArrowLeft, none), Some(b"\x1b[D".to_vec()));
+        assert_eq!(key_bytes(Key::D, egui::Modifiers::CTRL), Some(vec![0x04]));
+        assert_eq!(key_bytes(Key::Z, egui::Modifiers::CTRL), Some(vec![0x1a]));
+        // Letters without Ctrl arrive as text
+        assert_eq!(key_bytes(Key::D, none), None);
+        assert_eq!(key_bytes(Key::Num1, egui::Modifiers::CTRL), None);
+
+        assert_eq!(event_bytes(&egui::Event::Copy), Some(vec![0x03]));
+        assert_eq!(
+            event_bytes(&egui::Event::Text("ls".to_string())),
+            Some(b"ls".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_panel_reports_refusal() {
+        let (host, client) = crate::session::transport::create_loopback_transport();
+        let _host = Terminals::start(host.terminal, None);
+        let mut panel = TerminalPanel::new(Arc::new(Terminals::start(client.terminal, None)));
+
+        panel.start();
+        assert!(panel.is_running());
+        for _ in 0..100 {
+            panel.poll();
+            if !panel.is_running() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            panel.status,
+            Status::Stopped(Some("Peer error: Terminal access is disabled".to_string()))
+        );
+    }
+}
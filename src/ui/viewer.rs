@@ -1,22 +1,42 @@
 //! Viewer window for displaying remote desktop frames
 //!
 //! This module provides an egui-based window that displays received frames
-//! and captures user input to send to the remote host.
+//! and captures user input to send to the remote host. Files dropped onto
+//! the window are offered to the host, with their progress shown in the
+//! status overlay, the host's shared directories can be browsed in a side
+//! panel, a chat panel talks to the person at the host and a terminal panel
+//! runs a shell on it.
 
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
 use eframe::egui;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
 use crate::desktop::{Frame, FrameDecoder};
-use crate::input::{InputEvent, Key, KeyboardEvent, MouseButton, MouseEvent};
-use crate::session::transport::{TransportFrame, TransportInput};
+use crate::files::{
+    FileBrowser, FileTransferEvent, FileTransfers, TransferInfo, TransferStatus,
+};
+use crate::input::{
+    InputEvent, Key, KeyMacro, KeySequence, KeyboardEvent, KeyboardMode, MouseButton, MouseEvent,
+    TextEvent,
+};
+use crate::session::chat::ChatService;
+use crate::terminal::Terminals;
+use crate::session::transport::{ControlMessage, TransportFrame, TransportInput};
+use crate::ui::browser::BrowsePanel;
+use crate::ui::chat::ChatPanel;
+use crate::ui::terminal::TerminalPanel;
 use crate::ui::overlay::StatusOverlay;
 
+/// Key that toggles pointer lock when pressed with Ctrl+Alt
+const POINTER_LOCK_KEY: egui::Key = egui::Key::M;
+
 /// Viewer window configuration
 #[derive(Debug, Clone)]
 pub struct ViewerConfig {
@@ -30,6 +50,10 @@ pub struct ViewerConfig {
     pub show_overlay: bool,
     /// Whether to capture input
     pub capture_input: bool,
+    /// How keyboard input is sent to the host
+    pub keyboard_mode: KeyboardMode,
+    /// Key macros offered in the "Send keys" menu
+    pub macros: Vec<KeyMacro>,
 }
 
 impl Default for ViewerConfig {
@@ -40,6 +64,8 @@ impl Default for ViewerConfig {
             height: 720,
             show_overlay: true,
             capture_input: true,
+            keyboard_mode: KeyboardMode::default(),
+            macros: KeyMacro::builtin(),
         }
     }
 }
@@ -77,6 +103,8 @@ pub struct ViewerWindow {
     frame_rx: Option<mpsc::Receiver<TransportFrame>>,
     /// Input sender channel
     input_tx: Option<mpsc::Sender<TransportInput>>,
+    /// Control sender channel
+    control_tx: Option<mpsc::Sender<ControlMessage>>,
     /// Input sequence counter
     input_sequence: Arc<AtomicU64>,
     /// Status overlay
@@ -91,6 +119,34 @@ pub struct ViewerWindow {
     has_focus: bool,
     /// Mouse position relative to remote screen
     mouse_pos: Option<(f32, f32)>,
+    /// Modifier state last sent to the host
+    modifiers: egui::Modifiers,
+    /// Whether the pointer is locked and sending relative motion
+    pointer_locked: bool,
+    /// Sub-pixel motion not yet sent to the host
+    motion_remainder: egui::Vec2,
+    /// Sends dropped files to the host
+    file_drop: Option<FileDrop>,
+    /// Browses the host's shared directories
+    browse_panel: Option<BrowsePanel>,
+    /// Chats with the person at the host
+    chat_panel: Option<ChatPanel>,
+    /// Whether the chat panel is shown
+    chat_open: bool,
+    /// Runs a shell on the host
+    terminal_panel: Option<TerminalPanel>,
+    /// Whether the terminal panel is shown
+    terminal_open: bool,
+}
+
+/// Sends files dropped onto the viewer to the host
+struct FileDrop {
+    transfers: Arc<FileTransfers>,
+    /// Runtime the session's file transfers run on
+    runtime: tokio::runtime::Handle,
+    progress_tx: mpsc::UnboundedSender<TransferInfo>,
+    /// Progress of dropped files, for the overlay
+    progress_rx: mpsc::UnboundedReceiver<TransferInfo>,
 }
 
 /// Helper struct for FPS calculation
@@ -151,6 +207,7 @@ impl ViewerWindow {
             decoder: FrameDecoder::new(),
             frame_rx: Some(frame_rx),
             input_tx: Some(input_tx),
+            control_tx: None,
             input_sequence: Arc::new(AtomicU64::new(0)),
             overlay: StatusOverlay::default(),
             stats: ViewerStats::default(),
@@ -158,6 +215,15 @@ impl ViewerWindow {
             last_frame_time: None,
             has_focus: true,
             mouse_pos: None,
+            modifiers: egui::Modifiers::NONE,
+            pointer_locked: false,
+            motion_remainder: egui::Vec2::ZERO,
+            file_drop: None,
+            browse_panel: None,
+            chat_panel: None,
+            chat_open: false,
+            terminal_panel: None,
+            terminal_open: false,
         }
     }
 
@@ -169,6 +235,59 @@ impl ViewerWindow {
         Self::new(ViewerConfig::default(), frame_rx, input_tx)
     }
 
+    /// Sets the channel used to send control messages to the host
+    ///
+    /// Needed to ask the host to release held keys when the window loses
+    /// focus.
+    pub fn with_control_sender(mut self, control_tx: mpsc::Sender<ControlMessage>) -> Self {
+        self.control_tx = Some(control_tx);
+        self
+    }
+
+    /// Sets the file transfer service files dropped onto the window are
+    /// sent with
+    ///
+    /// Must be called from within the Tokio runtime the session runs on.
+    pub fn with_file_transfers(mut self, transfers: Arc<FileTransfers>) -> Self {
+        let (progress_tx, progress_rx) = mpsc::unbounded_channel();
+        self.file_drop = Some(FileDrop {
+            transfers,
+            runtime: tokio::runtime::Handle::current(),
+            progress_tx,
+            progress_rx,
+        });
+        self
+    }
+
+    /// Sets the file browser used by the "Files" panel
+    ///
+    /// Downloads are saved with `transfers`. Must be called from within the
+    /// Tokio runtime the session runs on.
+    pub fn with_file_browser(
+        mut self,
+        browser: Arc<FileBrowser>,
+        transfers: Arc<FileTransfers>,
+    ) -> Self {
+        self.browse_panel = Some(BrowsePanel::new(browser, transfers));
+        self
+    }
+
+    /// Sets the chat service used by the "Chat" panel
+    ///
+    /// Must be called from within the Tokio runtime the session runs on.
+    pub fn with_chat(mut self, chat: Arc<ChatService>) -> Self {
+        self.chat_panel = Some(ChatPanel::new(chat));
+        self
+    }
+
+    /// Sets the terminal service used by the "Terminal" panel
+    ///
+    /// Must be called from within the Tokio runtime the session runs on.
+    pub fn with_terminal(mut self, terminals: Arc<Terminals>) -> Self {
+        self.terminal_panel = Some(TerminalPanel::new(terminals));
+        self
+    }
+
     /// Runs the viewer window (blocking)
     pub fn run(self) -> Result<(), eframe::Error> {
         let title = self.config.title.clone();
@@ -186,6 +305,145 @@ impl ViewerWindow {
         )
     }
 
+    /// Returns the current keyboard mode
+    pub fn keyboard_mode(&self) -> KeyboardMode {
+        self.config.keyboard_mode
+    }
+
+    /// Switches between key code and text input for this session
+    pub fn set_keyboard_mode(&mut self, mode: KeyboardMode) {
+        if mode == KeyboardMode::Text && self.modifiers.shift {
+            // Shift is no longer forwarded in text mode, so don't leave it held
+            self.send_input(InputEvent::Keyboard(KeyboardEvent::key_release(Key::Shift)));
+            self.modifiers.shift = false;
+        }
+        self.config.keyboard_mode = mode;
+    }
+
+    /// Returns true if the pointer is locked to the window
+    pub fn is_pointer_locked(&self) -> bool {
+        self.pointer_locked
+    }
+
+    /// Locks or unlocks the pointer
+    ///
+    /// While locked the cursor is hidden and mouse motion is sent as
+    /// relative deltas, which games and 3D applications that grab the
+    /// pointer expect.
+    pub fn set_pointer_lock(&mut self, ctx: &egui::Context, locked: bool) {
+        if self.pointer_locked == locked {
+            return;
+        }
+
+        self.pointer_locked = locked;
+        self.motion_remainder = egui::Vec2::ZERO;
+
+        if locked {
+            // X11 and Windows can only confine the pointer; the lock request
+            // is ignored there and the confinement stays in place
+            ctx.send_viewport_cmd(egui::ViewportCommand::CursorGrab(egui::CursorGrab::Confined));
+            ctx.send_viewport_cmd(egui::ViewportCommand::CursorGrab(egui::CursorGrab::Locked));
+        } else {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CursorGrab(egui::CursorGrab::None));
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::CursorVisible(!locked));
+
+        info!("Pointer lock {}", if locked { "enabled" } else { "disabled" });
+    }
+
+    /// Sends a key sequence to the host as a single batch
+    pub fn send_key_sequence(&mut self, sequence: &KeySequence) {
+        debug!("Sending key sequence {}", sequence);
+        self.send_input(sequence.to_batch());
+    }
+
+    /// Shows the menu bar with key macros, the pointer lock toggle and the
+    /// file browser, chat and terminal toggles
+    fn show_menu_bar(&mut self, ctx: &egui::Context) {
+        let mut selected: Option<KeySequence> = None;
+        let mut pointer_locked = self.pointer_locked;
+        let mut browse_open = self.browse_panel.as_ref().map(BrowsePanel::is_open);
+
+        egui::TopBottomPanel::top("viewer_menu").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                if self.config.capture_input {
+                    ui.menu_button("Send keys", |ui| {
+                        for key_macro in &self.config.macros {
+                            let button = ui
+                                .button(&key_macro.name)
+                                .on_hover_text(key_macro.sequence.to_string());
+                            if button.clicked() {
+                                selected = Some(key_macro.sequence.clone());
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.checkbox(&mut pointer_locked, "Lock pointer (Ctrl+Alt+M)");
+                }
+                if let Some(open) = &mut browse_open {
+                    ui.toggle_value(open, "Files");
+                }
+                if let Some(panel) = &self.chat_panel {
+                    let label = match panel.unread() {
+                        0 => "Chat".to_string(),
+                        unread => format!("Chat ({})", unread),
+                    };
+                    ui.toggle_value(&mut self.chat_open, label);
+                }
+                if let Some(panel) = &mut self.terminal_panel {
+                    let toggle = ui.toggle_value(&mut self.terminal_open, "Terminal");
+                    // Showing the panel opens a shell if none is running
+                    if toggle.clicked() && self.terminal_open {
+                        panel.start();
+                    }
+                }
+            });
+        });
+
+        if let Some(sequence) = selected {
+            self.send_key_sequence(&sequence);
+        }
+        self.set_pointer_lock(ctx, pointer_locked);
+        if let (Some(panel), Some(open)) = (&mut self.browse_panel, browse_open) {
+            panel.set_open(open);
+        }
+    }
+
+    /// Shows the chat panel if it is open
+    fn show_chat_panel(&mut self, ctx: &egui::Context) {
+        let Some(panel) = &mut self.chat_panel else {
+            return;
+        };
+        if !self.chat_open {
+            return;
+        }
+
+        panel.mark_read();
+        egui::TopBottomPanel::bottom("chat_panel")
+            .resizable(true)
+            .default_height(180.0)
+            .show(ctx, |ui| {
+                ui.label(format!("Chat with {}", panel.peer_name()));
+                ui.separator();
+                panel.ui(ui);
+            });
+    }
+
+    /// Shows the terminal panel if it is open
+    fn show_terminal_panel(&mut self, ctx: &egui::Context) {
+        let Some(panel) = &mut self.terminal_panel else {
+            return;
+        };
+        if !self.terminal_open {
+            return;
+        }
+
+        egui::TopBottomPanel::bottom("terminal_panel")
+            .resizable(true)
+            .default_height(240.0)
+            .show(ctx, |ui| panel.ui(ui));
+    }
+
     /// Processes any pending frames
     fn process_pending_frames(&mut self, ctx: &egui::Context) {
         if let Some(ref mut rx) = self.frame_rx {
@@ -253,35 +511,187 @@ impl ViewerWindow {
         }
     }
 
+    /// Offers files dropped onto the window to the host
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let Some(ref file_drop) = self.file_drop else {
+            return;
+        };
+
+        // Dropped data without a path (web builds) can't be sent
+        let paths: Vec<PathBuf> = ctx.input(|input| {
+            input
+                .raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect()
+        });
+
+        for path in paths {
+            debug!("File dropped: {}", path.display());
+            file_drop.runtime.spawn(send_dropped_file(
+                Arc::clone(&file_drop.transfers),
+                path,
+                file_drop.progress_tx.clone(),
+            ));
+        }
+    }
+
+    /// Moves the progress of dropped files into the overlay
+    fn process_transfer_progress(&mut self) {
+        let Some(ref mut file_drop) = self.file_drop else {
+            return;
+        };
+
+        while let Ok(info) = file_drop.progress_rx.try_recv() {
+            if info.status.is_finished() {
+                self.overlay.remove_transfer(info.transfer_id);
+            } else {
+                self.overlay.set_transfer(info);
+            }
+        }
+    }
+
+    /// Shows a hint while files are dragged over the window
+    fn show_drop_hint(&self, ctx: &egui::Context) {
+        if self.file_drop.is_none() || ctx.input(|input| input.raw.hovered_files.is_empty()) {
+            return;
+        }
+
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("file_drop_hint"),
+        ));
+        let screen = ctx.screen_rect();
+        painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+        painter.text(
+            screen.center(),
+            egui::Align2::CENTER_CENTER,
+            "Drop files to send them to the host",
+            egui::FontId::proportional(20.0),
+            egui::Color32::WHITE,
+        );
+    }
+
+    /// Tracks window focus, releasing held input on the host when it is lost
+    ///
+    /// Key and button releases that happen outside the window never reach
+    /// the viewer, so anything held when focus goes would stay pressed.
+    fn update_focus(&mut self, ctx: &egui::Context) {
+        let focused = ctx.input(|input| input.focused);
+        if self.has_focus && !focused {
+            debug!("Viewer lost focus, releasing held input");
+            self.release_all_input();
+            self.set_pointer_lock(ctx, false);
+        }
+        self.has_focus = focused;
+    }
+
+    /// Releases every key and button held on the host
+    fn release_all_input(&mut self) {
+        if !self.config.capture_input {
+            return;
+        }
+
+        for kb_event in modifier_changes(self.modifiers, egui::Modifiers::NONE) {
+            self.send_input(InputEvent::Keyboard(kb_event));
+        }
+        self.modifiers = egui::Modifiers::NONE;
+
+        if let Some(ref tx) = self.control_tx {
+            if tx.try_send(ControlMessage::ReleaseAllInput).is_err() {
+                warn!("Failed to send release-all request");
+            }
+        }
+    }
+
     /// Handles keyboard input
     fn handle_keyboard_input(&mut self, ctx: &egui::Context) {
-        if !self.config.capture_input || !self.has_focus {
+        // Typing into the chat box or the terminal stays in the viewer
+        let terminal_focused = self.terminal_open
+            && self.terminal_panel.as_ref().is_some_and(TerminalPanel::has_focus);
+        if !self.config.capture_input
+            || !self.has_focus
+            || ctx.wants_keyboard_input()
+            || terminal_focused
+        {
             return;
         }
 
+        let text_mode = self.config.keyboard_mode == KeyboardMode::Text;
+        let mut toggle_pointer_lock = false;
+
         ctx.input(|input| {
+            // egui reports modifiers as state rather than key events
+            let mut modifiers = input.modifiers;
+            if text_mode {
+                // The typed text already reflects Shift
+                modifiers.shift = false;
+            }
+            for kb_event in modifier_changes(self.modifiers, modifiers) {
+                self.send_input(InputEvent::Keyboard(kb_event));
+            }
+            self.modifiers = modifiers;
+
             for event in &input.events {
                 match event {
+                    egui::Event::Text(text) if text_mode => {
+                        self.send_input(InputEvent::Text(TextEvent::new(text.clone())));
+                    }
+                    egui::Event::Key {
+                        key: POINTER_LOCK_KEY,
+                        pressed,
+                        repeat,
+                        modifiers,
+                        ..
+                    } if modifiers.ctrl && modifiers.alt => {
+                        // Viewer hotkey, not forwarded to the host
+                        toggle_pointer_lock |= *pressed && !*repeat;
+                    }
                     egui::Event::Key {
                         key,
                         pressed,
-                        modifiers: _,
+                        modifiers,
                         ..
                     } => {
-                        if let Some(our_key) = self.convert_egui_key(*key) {
-                            let kb_event = if *pressed {
-                                KeyboardEvent::key_press(our_key)
-                            } else {
-                                KeyboardEvent::key_release(our_key)
-                            };
-
-                            self.send_input(InputEvent::Keyboard(kb_event));
+                        let Some(our_key) = Key::from_egui(*key) else {
+                            continue;
+                        };
+
+                        if text_mode && !is_shortcut(our_key, *modifiers) {
+                            // Delivered through the matching text event
+                            continue;
+                        }
+
+                        // Shift+arrow and similar still need Shift in text mode
+                        let with_shift = text_mode && *pressed && modifiers.shift;
+                        if with_shift {
+                            self.send_input(InputEvent::Keyboard(KeyboardEvent::key_press(
+                                Key::Shift,
+                            )));
+                        }
+
+                        let kb_event = if *pressed {
+                            KeyboardEvent::key_press(our_key)
+                        } else {
+                            KeyboardEvent::key_release(our_key)
+                        };
+                        self.send_input(InputEvent::Keyboard(kb_event));
+
+                        if with_shift {
+                            self.send_input(InputEvent::Keyboard(KeyboardEvent::key_release(
+                                Key::Shift,
+                            )));
                         }
                     }
                     _ => {}
                 }
             }
         });
+
+        if toggle_pointer_lock {
+            self.set_pointer_lock(ctx, !self.pointer_locked);
+        }
     }
 
     /// Handles mouse input
@@ -291,8 +701,21 @@ impl ViewerWindow {
         }
 
         ctx.input(|input| {
-            // Handle mouse position
-            if let Some(pos) = input.pointer.hover_pos() {
+            if self.pointer_locked {
+                // Send raw motion rather than the (pinned) cursor position
+                for event in &input.events {
+                    if let egui::Event::MouseMoved(delta) = event {
+                        self.motion_remainder += *delta;
+                    }
+                }
+
+                let dx = self.motion_remainder.x.trunc();
+                let dy = self.motion_remainder.y.trunc();
+                if dx != 0.0 || dy != 0.0 {
+                    self.motion_remainder -= egui::vec2(dx, dy);
+                    self.send_input(InputEvent::Mouse(MouseEvent::move_by(dx as i32, dy as i32)));
+                }
+            } else if let Some(pos) = input.pointer.hover_pos() {
                 if image_rect.contains(pos) {
                     // Calculate position relative to the remote screen
                     let rel_x = (pos.x - image_rect.left()) / image_rect.width();
@@ -310,6 +733,22 @@ impl ViewerWindow {
                 }
             }
 
+            // Only presses over the remote screen are sent, so using the
+            // menu bar doesn't click on the host; releases always are
+            let over_image = self.pointer_locked
+                || input
+                    .pointer
+                    .hover_pos()
+                    .is_some_and(|pos| image_rect.contains(pos));
+            if !over_image {
+                for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle] {
+                    if input.pointer.button_released(pointer_button(button)) {
+                        self.send_input(InputEvent::Mouse(MouseEvent::button_release(button)));
+                    }
+                }
+                return;
+            }
+
             // Handle mouse buttons
             if input.pointer.button_pressed(egui::PointerButton::Primary) {
                 self.send_input(InputEvent::Mouse(MouseEvent::button_press(MouseButton::Left)));
@@ -353,93 +792,141 @@ impl ViewerWindow {
         }
     }
 
-    /// Converts egui key to our Key type
-    fn convert_egui_key(&self, key: egui::Key) -> Option<Key> {
-        Some(match key {
-            egui::Key::A => Key::A,
-            egui::Key::B => Key::B,
-            egui::Key::C => Key::C,
-            egui::Key::D => Key::D,
-            egui::Key::E => Key::E,
-            egui::Key::F => Key::F,
-            egui::Key::G => Key::G,
-            egui::Key::H => Key::H,
-            egui::Key::I => Key::I,
-            egui::Key::J => Key::J,
-            egui::Key::K => Key::K,
-            egui::Key::L => Key::L,
-            egui::Key::M => Key::M,
-            egui::Key::N => Key::N,
-            egui::Key::O => Key::O,
-            egui::Key::P => Key::P,
-            egui::Key::Q => Key::Q,
-            egui::Key::R => Key::R,
-            egui::Key::S => Key::S,
-            egui::Key::T => Key::T,
-            egui::Key::U => Key::U,
-            egui::Key::V => Key::V,
-            egui::Key::W => Key::W,
-            egui::Key::X => Key::X,
-            egui::Key::Y => Key::Y,
-            egui::Key::Z => Key::Z,
-            egui::Key::Num0 => Key::Num0,
-            egui::Key::Num1 => Key::Num1,
-            egui::Key::Num2 => Key::Num2,
-            egui::Key::Num3 => Key::Num3,
-            egui::Key::Num4 => Key::Num4,
-            egui::Key::Num5 => Key::Num5,
-            egui::Key::Num6 => Key::Num6,
-            egui::Key::Num7 => Key::Num7,
-            egui::Key::Num8 => Key::Num8,
-            egui::Key::Num9 => Key::Num9,
-            egui::Key::Escape => Key::Escape,
-            egui::Key::Tab => Key::Tab,
-            egui::Key::Backspace => Key::Backspace,
-            egui::Key::Enter => Key::Return,
-            egui::Key::Space => Key::Space,
-            egui::Key::Insert => Key::Insert,
-            egui::Key::Delete => Key::Delete,
-            egui::Key::Home => Key::Home,
-            egui::Key::End => Key::End,
-            egui::Key::PageUp => Key::PageUp,
-            egui::Key::PageDown => Key::PageDown,
-            egui::Key::ArrowLeft => Key::Left,
-            egui::Key::ArrowRight => Key::Right,
-            egui::Key::ArrowUp => Key::Up,
-            egui::Key::ArrowDown => Key::Down,
-            egui::Key::F1 => Key::F1,
-            egui::Key::F2 => Key::F2,
-            egui::Key::F3 => Key::F3,
-            egui::Key::F4 => Key::F4,
-            egui::Key::F5 => Key::F5,
-            egui::Key::F6 => Key::F6,
-            egui::Key::F7 => Key::F7,
-            egui::Key::F8 => Key::F8,
-            egui::Key::F9 => Key::F9,
-            egui::Key::F10 => Key::F10,
-            egui::Key::F11 => Key::F11,
-            egui::Key::F12 => Key::F12,
-            egui::Key::Minus => Key::Minus,
-            egui::Key::Comma => Key::Comma,
-            egui::Key::Period => Key::Period,
-            _ => return None,
-        })
-    }
-
     /// Returns the current statistics
     pub fn stats(&self) -> &ViewerStats {
         &self.stats
     }
 }
 
+/// Returns the key events needed to move from one modifier state to another
+fn modifier_changes(old: egui::Modifiers, new: egui::Modifiers) -> Vec<KeyboardEvent> {
+    [
+        (old.shift, new.shift, Key::Shift),
+        (old.ctrl, new.ctrl, Key::Control),
+        (old.alt, new.alt, Key::Alt),
+        (old.mac_cmd, new.mac_cmd, Key::Meta),
+    ]
+    .into_iter()
+    .filter(|(was, is, _)| was != is)
+    .map(|(_, is, key)| {
+        if is {
+            KeyboardEvent::key_press(key)
+        } else {
+            KeyboardEvent::key_release(key)
+        }
+    })
+    .collect()
+}
+
+/// Offers a dropped file to the host and reports its progress until it ends
+async fn send_dropped_file(
+    transfers: Arc<FileTransfers>,
+    path: PathBuf,
+    progress_tx: mpsc::UnboundedSender<TransferInfo>,
+) {
+    // Subscribe first so no event of the new transfer is missed
+    let mut events = transfers.subscribe();
+
+    let transfer_id = match transfers.send_file(&path).await {
+        Ok(transfer_id) => transfer_id,
+        Err(e) => {
+            warn!("Failed to send {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let Some(mut info) = transfers
+        .list()
+        .await
+        .into_iter()
+        .find(|t| t.transfer_id == transfer_id)
+    else {
+        return;
+    };
+
+    while !info.status.is_finished() {
+        if progress_tx.send(info.clone()).is_err() {
+            // The viewer was closed; the transfer carries on
+            return;
+        }
+
+        loop {
+            match events.recv().await {
+                Ok(FileTransferEvent::Progress {
+                    transfer_id: id,
+                    transferred,
+                    ..
+                }) if id == transfer_id => {
+                    info.status = TransferStatus::Active;
+                    info.transferred = transferred;
+                }
+                Ok(FileTransferEvent::Completed { transfer_id: id, .. }) if id == transfer_id => {
+                    info!("Sent {} to the host", info.name);
+                    info.status = TransferStatus::Completed;
+                    info.transferred = info.size;
+                }
+                Ok(FileTransferEvent::Failed {
+                    transfer_id: id,
+                    message,
+                }) if id == transfer_id => {
+                    info.status = TransferStatus::Failed(message);
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => info.status = TransferStatus::Interrupted,
+            }
+            break;
+        }
+    }
+
+    let _ = progress_tx.send(info);
+}
+
+/// Returns the egui button for one of the standard mouse buttons
+fn pointer_button(button: MouseButton) -> egui::PointerButton {
+    match button {
+        MouseButton::Left => egui::PointerButton::Primary,
+        MouseButton::Right => egui::PointerButton::Secondary,
+        MouseButton::Middle => egui::PointerButton::Middle,
+        MouseButton::Button4 => egui::PointerButton::Extra1,
+        MouseButton::Button5 => egui::PointerButton::Extra2,
+    }
+}
+
+/// Returns true if a key must be sent as a key code in text mode
+///
+/// Printable keys are sent as text unless Control, Alt or Meta is held.
+fn is_shortcut(key: Key, modifiers: egui::Modifiers) -> bool {
+    !key.is_printable() || modifiers.ctrl || modifiers.alt || modifiers.mac_cmd
+}
+
 impl eframe::App for ViewerWindow {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Process any pending frames
         self.process_pending_frames(ctx);
+        self.process_transfer_progress();
+        self.handle_dropped_files(ctx);
 
         // Request continuous repaint for smooth updates
         ctx.request_repaint();
 
+        if let Some(panel) = &mut self.chat_panel {
+            panel.poll();
+        }
+        if let Some(panel) = &mut self.terminal_panel {
+            panel.poll();
+        }
+        if self.config.capture_input
+            || self.browse_panel.is_some()
+            || self.chat_panel.is_some()
+            || self.terminal_panel.is_some()
+        {
+            self.show_menu_bar(ctx);
+        }
+        if let Some(panel) = &mut self.browse_panel {
+            panel.show(ctx);
+        }
+        self.show_chat_panel(ctx);
+        self.show_terminal_panel(ctx);
+
         // Main panel
         egui::CentralPanel::default().show(ctx, |ui| {
             // Calculate image rect for input translation
@@ -469,6 +956,7 @@ impl eframe::App for ViewerWindow {
             };
 
             // Handle input
+            self.update_focus(ctx);
             self.handle_keyboard_input(ctx);
             self.handle_mouse_input(ctx, image_rect);
 
@@ -477,5 +965,7 @@ impl eframe::App for ViewerWindow {
                 self.overlay.show(ui, &self.stats);
             }
         });
+
+        self.show_drop_hint(ctx);
     }
 }
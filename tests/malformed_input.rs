@@ -0,0 +1,224 @@
+//! Property tests for decoding untrusted peer data
+//!
+//! Every message type a peer can send is fed random bytes and corrupted
+//! copies of valid encodings. Decoding must fail cleanly rather than
+//! panic or allocate what the input claims, and whatever does decode must
+//! pass the same checks as a well-formed message.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use remote_desk::desktop::FrameFormat;
+use remote_desk::input::{BatchEvent, InputEvent, Key, KeyboardEvent, MouseButton, MouseEvent};
+use remote_desk::network::codec::{decode, validate_frame};
+use remote_desk::network::{
+    FrameFormat as WireFrameFormat, Message, MessagePayload, MessageType, ScreenFrameData,
+    SessionControlData, TunnelMessage,
+};
+use remote_desk::session::{
+    BrowseMessage, ChannelMessage, ChatMessage, ClipboardMessage, ControlMessage, FileMessage,
+    TerminalMessage, TransportFrame, TransportInput,
+};
+
+/// Inputs tried per message type and strategy
+const CASES: usize = 2000;
+
+fn random_bytes(rng: &mut StdRng) -> Vec<u8> {
+    let len = rng.gen_range(0..256);
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+/// Changes a few bytes of a valid encoding, and sometimes cuts it short
+fn mutate(rng: &mut StdRng, valid: &[u8]) -> Vec<u8> {
+    let mut bytes = valid.to_vec();
+    for _ in 0..rng.gen_range(1..4) {
+        let at = rng.gen_range(0..bytes.len());
+        bytes[at] = match rng.gen_range(0..3) {
+            0 => rng.gen(),
+            1 => 0xff,
+            _ => 0,
+        };
+    }
+    if rng.gen_bool(0.2) {
+        bytes.truncate(rng.gen_range(0..bytes.len()));
+    }
+    bytes
+}
+
+/// Decodes random and corrupted input as `T`, returning what decoded
+fn fuzz<T: Serialize + DeserializeOwned>(seed: u64, samples: &[T]) -> Vec<T> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut decoded = Vec::new();
+
+    for _ in 0..CASES {
+        decoded.extend(decode::<T>(&random_bytes(&mut rng)).ok());
+    }
+    for sample in samples {
+        let valid = bincode::serialize(sample).unwrap();
+        assert!(decode::<T>(&valid).is_ok(), "valid sample fails to decode");
+        for _ in 0..CASES {
+            decoded.extend(decode::<T>(&mutate(&mut rng, &valid)).ok());
+        }
+    }
+    decoded
+}
+
+fn check_message(message: &Message) {
+    if let MessagePayload::ScreenFrame(frame) = &message.payload {
+        let raw = frame.format == WireFrameFormat::Raw;
+        assert!(validate_frame(frame.width, frame.height, raw, frame.data.len()).is_ok());
+    }
+}
+
+#[test]
+fn test_protocol_messages() {
+    let samples = [
+        Message::new(
+            MessageType::ScreenFrame,
+            MessagePayload::ScreenFrame(ScreenFrameData::new(
+                1,
+                2,
+                2,
+                WireFrameFormat::Raw,
+                vec![7; 16],
+            )),
+        ),
+        Message::new(
+            MessageType::SessionControl,
+            MessagePayload::SessionControl(SessionControlData::new(vec![1, 2, 3])),
+        ),
+    ];
+    for message in fuzz(1, &samples) {
+        check_message(&message);
+    }
+    for bytes in [vec![], vec![0xff; 64]] {
+        assert!(Message::from_bytes(&bytes).is_err());
+    }
+}
+
+#[test]
+fn test_transport_frames() {
+    let samples = [
+        TransportFrame::new(1, 2, 2, FrameFormat::Raw, vec![7; 16], 16, 0),
+        TransportFrame::new(2, 640, 480, FrameFormat::Jpeg, vec![1; 32], 1_228_800, 5),
+    ];
+    for frame in fuzz(2, &samples) {
+        assert!(frame.validate().is_ok());
+    }
+}
+
+#[test]
+fn test_session_streams() {
+    let click = InputEvent::Mouse(MouseEvent::button_press(MouseButton::Left));
+    let shortcut = InputEvent::Batch(vec![
+        KeyboardEvent::key_press(Key::Control).into(),
+        MouseEvent::button_press(MouseButton::Left).into(),
+    ]);
+    fuzz(
+        3,
+        &[
+            TransportInput::with_coords(click, 1, 10, 20),
+            TransportInput::new(shortcut, 2),
+        ],
+    );
+    fuzz(
+        4,
+        &[
+            ControlMessage::Ping { timestamp_ms: 1 },
+            ControlMessage::Request {
+                request_id: 7,
+                request: Box::new(ControlMessage::RequestDisplayInfo),
+            },
+        ],
+    );
+    fuzz::<ClipboardMessage>(5, &[]);
+    fuzz::<FileMessage>(6, &[]);
+    fuzz::<BrowseMessage>(7, &[]);
+    fuzz(
+        8,
+        &[ChatMessage {
+            text: "hello".to_string(),
+            timestamp_ms: 1,
+        }],
+    );
+    fuzz(
+        9,
+        &[TerminalMessage::Open {
+            terminal_id: 1,
+            cols: 80,
+            rows: 24,
+        }],
+    );
+    fuzz(
+        10,
+        &[ChannelMessage::Data {
+            channel: "tickets".to_string(),
+            data: vec![1, 2, 3],
+        }],
+    );
+    fuzz(
+        11,
+        &[TunnelMessage::Connect {
+            host: "localhost".to_string(),
+            port: 22,
+        }],
+    );
+}
+
+#[test]
+fn test_huge_lengths_are_refused() {
+    // A chat message whose text claims to be an exabyte long
+    let mut bytes = u64::MAX.to_le_bytes().to_vec();
+    bytes.extend_from_slice(b"hello");
+    assert!(decode::<ChatMessage>(&bytes).is_err());
+
+    // A raw frame claiming the largest dimensions with a few bytes of data
+    let frame = TransportFrame::new(1, 16384, 16384, FrameFormat::Raw, vec![0; 8], 8, 0);
+    assert!(decode::<TransportFrame>(&bincode::serialize(&frame).unwrap()).is_err());
+}
+
+/// Repeats the encoding of `outer` around `inner`, `depth` times
+///
+/// `outer` must end with its encoding of `inner`, which is cut off and
+/// replaced by the next level.
+fn nest(outer: &[u8], inner: &[u8], depth: usize) -> Vec<u8> {
+    let prefix = &outer[..outer.len() - inner.len()];
+    let mut bytes = prefix.repeat(depth);
+    bytes.extend_from_slice(inner);
+    bytes
+}
+
+#[test]
+fn test_nesting_is_refused() {
+    // A request wrapping a request, however deep, fails without recursing
+    let ping = bincode::serialize(&ControlMessage::Ping { timestamp_ms: 1 }).unwrap();
+    let request = bincode::serialize(&ControlMessage::Request {
+        request_id: 7,
+        request: Box::new(ControlMessage::Ping { timestamp_ms: 1 }),
+    })
+    .unwrap();
+    assert!(decode::<ControlMessage>(&request).is_ok());
+    for depth in [2, 3, 1_000_000] {
+        assert!(decode::<ControlMessage>(&nest(&request, &ping, depth)).is_err());
+    }
+
+    // So does a response wrapping a request
+    let response = bincode::serialize(&ControlMessage::Response {
+        request_id: 7,
+        response: Box::new(ControlMessage::Ping { timestamp_ms: 1 }),
+    })
+    .unwrap();
+    let bytes = [&response[..response.len() - ping.len()], &request[..]].concat();
+    assert!(decode::<ControlMessage>(&bytes).is_err());
+
+    // Batches hold only keyboard and mouse events, so can't be nested
+    let press = BatchEvent::Keyboard(KeyboardEvent::key_press(Key::A));
+    let batch = bincode::serialize(&InputEvent::Batch(vec![press.clone()])).unwrap();
+    let press = bincode::serialize(&press).unwrap();
+    assert!(decode::<InputEvent>(&batch).is_ok());
+    for depth in [2, 1_000_000] {
+        assert!(decode::<InputEvent>(&nest(&batch, &press, depth)).is_err());
+    }
+}
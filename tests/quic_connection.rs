@@ -8,14 +8,20 @@
 //! - Protocol handshake
 
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use remote_desk::network::{
     cert, BiStream, ConnectionRole, Message, MessagePayload, MessageType, QuicConfig,
-    QuicConnection, QuicEndpoint, StreamReceiver, StreamSender, CURRENT_PROTOCOL_VERSION,
+    QuicConnection, QuicEndpoint, StreamError, StreamReceiver, StreamSender,
+    CURRENT_PROTOCOL_VERSION, MAX_FRAME_SIZE, MAX_VIDEO_MESSAGE_SIZE,
 };
-use remote_desk::network::protocol::{ConnectionAccept, ConnectionRequest, DesktopInfo};
-use remote_desk::security::DeviceId;
+use remote_desk::config::RateLimitConfig;
+use remote_desk::network::protocol::{
+    ConnectionAccept, ConnectionRequest, DesktopInfo, RejectReason,
+};
+use remote_desk::network::ConnectionListener;
+use remote_desk::security::{DeviceId, RateLimiter};
 use remote_desk::session::{TransportFrame, TransportInput};
 use remote_desk::desktop::FrameFormat;
 use remote_desk::input::{InputEvent, KeyboardEvent, Key};
@@ -422,6 +428,96 @@ async fn test_multiple_streams() {
     server_conn.close("test complete");
 }
 
+#[tokio::test]
+async fn test_large_message_fragmentation() {
+    let (server, _temp1) = create_test_endpoint(17120);
+    let server_addr = server.local_addr();
+
+    let (client, _temp2) = create_test_endpoint(17121);
+
+    // Connect
+    let server_task = tokio::spawn(async move {
+        server.accept().await.unwrap().unwrap()
+    });
+
+    let client_conn = client.connect(server_addr, "localhost").await.unwrap();
+    let server_conn = server_task.await.unwrap();
+
+    let (client_send, _client_recv) = client_conn.open_bi().await.unwrap();
+    let mut sender: StreamSender<TransportFrame> =
+        StreamSender::new(client_send).with_max_message_size(MAX_VIDEO_MESSAGE_SIZE);
+    let send_task = tokio::spawn(async move {
+        // A raw 4K frame is several times the frame size
+        let data: Vec<u8> = (0..3840 * 2160 * 4).map(|i| (i % 251) as u8).collect();
+        let frame = TransportFrame::new(1, 3840, 2160, FrameFormat::Raw, data, 0, 0);
+        sender.send(frame.clone()).await.unwrap();
+        sender.send(frame).await.unwrap();
+        sender
+    });
+
+    let (_server_send, server_recv) = server_conn.accept_bi().await.unwrap();
+    let mut receiver: StreamReceiver<TransportFrame> =
+        StreamReceiver::new(server_recv).with_max_message_size(MAX_VIDEO_MESSAGE_SIZE);
+    let received = receiver.recv().await.unwrap();
+    assert_eq!(received.width, 3840);
+    assert_eq!(received.data.len(), 3840 * 2160 * 4);
+    assert!(received.data.len() > MAX_FRAME_SIZE);
+    assert_eq!(received.data[1000], (1000 % 251) as u8);
+    assert_eq!(receiver.recv().await.unwrap().data, received.data);
+
+    // Sending over the limit fails without touching the stream
+    let mut sender = send_task.await.unwrap().with_max_message_size(1024);
+    let too_large = TransportFrame::new(2, 64, 64, FrameFormat::Raw, vec![0; 2048], 0, 0);
+    assert!(matches!(
+        sender.send(too_large).await,
+        Err(StreamError::MessageTooLarge { .. })
+    ));
+
+    // Cleanup
+    client_conn.close("test complete");
+    server_conn.close("test complete");
+}
+
+#[tokio::test]
+async fn test_oversized_message_is_dropped() {
+    let (server, _temp1) = create_test_endpoint(17122);
+    let server_addr = server.local_addr();
+
+    let (client, _temp2) = create_test_endpoint(17123);
+
+    // Connect
+    let server_task = tokio::spawn(async move {
+        server.accept().await.unwrap().unwrap()
+    });
+
+    let client_conn = client.connect(server_addr, "localhost").await.unwrap();
+    let server_conn = server_task.await.unwrap();
+
+    let (client_send, _client_recv) = client_conn.open_bi().await.unwrap();
+    let mut sender: StreamSender<Vec<u8>> =
+        StreamSender::new(client_send).with_max_message_size(MAX_VIDEO_MESSAGE_SIZE);
+    let send_task = tokio::spawn(async move {
+        sender.send(vec![7; MAX_FRAME_SIZE * 2]).await.unwrap();
+        sender.send(vec![1, 2, 3]).await.unwrap();
+        sender
+    });
+
+    // By default the receiver buffers at most one frame, then carries on
+    // with the stream
+    let (_server_send, server_recv) = server_conn.accept_bi().await.unwrap();
+    let mut receiver: StreamReceiver<Vec<u8>> = StreamReceiver::new(server_recv);
+    assert!(matches!(
+        receiver.recv().await,
+        Err(StreamError::MessageDropped { .. })
+    ));
+    assert_eq!(receiver.recv().await.unwrap(), vec![1, 2, 3]);
+    let _sender = send_task.await.unwrap();
+
+    // Cleanup
+    client_conn.close("test complete");
+    server_conn.close("test complete");
+}
+
 #[tokio::test]
 async fn test_connection_rtt() {
     let (server, _temp1) = create_test_endpoint(17118);
@@ -456,3 +552,101 @@ async fn test_connection_rtt() {
     client_conn.close("test complete");
     server_conn.close("test complete");
 }
+
+/// Connects to `server_addr` and sends a connection request
+async fn send_connection_request(
+    port: u16,
+    server_addr: SocketAddr,
+    host_id: DeviceId,
+) -> (QuicConnection, BiStream<Message>, TempDir) {
+    let (client, temp) = create_test_endpoint(port);
+    let conn = client.connect(server_addr, "localhost").await.unwrap();
+    let (send, recv) = conn.open_bi().await.unwrap();
+    let mut control: BiStream<Message> = BiStream::new(send, recv);
+
+    let request = ConnectionRequest::new(DeviceId::generate(), "Client".to_string(), host_id, None);
+    control
+        .send(Message::new(
+            MessageType::ConnectionRequest,
+            MessagePayload::ConnectionRequest(request),
+        ))
+        .await
+        .unwrap();
+    (conn, control, temp)
+}
+
+#[tokio::test]
+async fn test_unanswered_request_frees_handshake_slot() {
+    let (server, _temp1) = create_test_endpoint(17124);
+    let server_addr = server.local_addr();
+    let host_id = DeviceId::from_u32(123456789).unwrap();
+
+    let rate_limit = RateLimitConfig {
+        max_pending_handshakes: 1,
+        ..RateLimitConfig::default()
+    };
+    let (listener, mut incoming_rx) =
+        ConnectionListener::new(Arc::new(server), host_id, "Test Host".to_string());
+    let listener = listener.with_rate_limiter(Arc::new(Mutex::new(RateLimiter::new(rate_limit))));
+    tokio::spawn(async move { listener.run().await });
+
+    // The first request waits for an answer while the second one arrives
+    let (_conn1, _control1, _temp2) = send_connection_request(17125, server_addr, host_id).await;
+    let (_, first) = tokio::time::timeout(Duration::from_secs(5), incoming_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let (_conn2, _control2, _temp3) = send_connection_request(17126, server_addr, host_id).await;
+    let (incoming, _) = tokio::time::timeout(Duration::from_secs(5), incoming_rx.recv())
+        .await
+        .expect("second request blocked by an unanswered one")
+        .unwrap();
+    assert_eq!(incoming.remote_name, "Client");
+    drop(first);
+}
+
+#[tokio::test]
+async fn test_unanswered_requests_are_capped() {
+    let (server, _temp1) = create_test_endpoint(17127);
+    let server_addr = server.local_addr();
+    let host_id = DeviceId::from_u32(123456789).unwrap();
+
+    let rate_limit = RateLimitConfig {
+        max_pending_requests: 1,
+        ..RateLimitConfig::default()
+    };
+    let (listener, mut incoming_rx) =
+        ConnectionListener::new(Arc::new(server), host_id, "Test Host".to_string());
+    let listener = listener.with_rate_limiter(Arc::new(Mutex::new(RateLimiter::new(rate_limit))));
+    tokio::spawn(async move { listener.run().await });
+
+    // The first request fills the only slot while it waits for an answer
+    let (_conn1, _control1, _temp2) = send_connection_request(17128, server_addr, host_id).await;
+    let (_, first) = tokio::time::timeout(Duration::from_secs(5), incoming_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let (_conn2, mut control2, _temp3) = send_connection_request(17129, server_addr, host_id).await;
+    let response = tokio::time::timeout(Duration::from_secs(5), control2.recv())
+        .await
+        .expect("second request was not answered")
+        .unwrap();
+    match response.payload {
+        MessagePayload::ConnectionReject(reject) => {
+            assert!(matches!(reject.reason, RejectReason::RateLimited { .. }))
+        }
+        other => panic!("expected reject, got {:?}", other),
+    }
+    assert!(incoming_rx.try_recv().is_err());
+
+    // Answering the first request frees its slot
+    first.reject(RejectReason::UserDenied, None).await.unwrap();
+    let (_conn3, _control3, _temp4) = send_connection_request(17130, server_addr, host_id).await;
+    let (incoming, _) = tokio::time::timeout(Duration::from_secs(5), incoming_rx.recv())
+        .await
+        .expect("request blocked after the slot was freed")
+        .unwrap();
+    assert_eq!(incoming.remote_name, "Client");
+}